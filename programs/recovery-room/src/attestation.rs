@@ -0,0 +1,219 @@
+//! Oracle attestations verified through the ed25519 precompile.
+//!
+//! The precompile can't be invoked by CPI, so the client places an ed25519
+//! instruction ahead of ours in the same transaction and we read it back
+//! through the instructions sysvar. A message counts only when its signature,
+//! key and message bytes all live inside that ed25519 instruction, which the
+//! runtime has already verified by the time our instruction runs.
+//!
+//! Prices come from the price verifier rather than an on-chain feed: rugged
+//! tokens rarely have one, and the verifier already tracks them to sign
+//! cost basis.
+
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::{ed25519_program, sysvar::instructions::load_instruction_at_checked};
+
+use crate::RecoveryRoomError;
+
+/// Domain tag prefixed to every rug attestation message
+pub const RUG_ATTESTATION_TAG: &[u8] = b"recovery-room:rug:v1";
+
+/// Domain tag prefixed to every cost-basis attestation message
+pub const COST_BASIS_ATTESTATION_TAG: &[u8] = b"recovery-room:cost-basis:v1";
+
+/// Domain tag prefixed to every price attestation message
+pub const PRICE_ATTESTATION_TAG: &[u8] = b"recovery-room:price:v1";
+
+/// Domain tag prefixed to every loss attestation message
+pub const LOSS_ATTESTATION_TAG: &[u8] = b"recovery-room:loss:v1";
+
+/// Domain tag prefixed to every participation permit a user signs
+pub const PERMIT_TAG: &[u8] = b"recovery-room:permit:v1";
+
+/// Oldest price attestation accepted, in seconds
+pub const MAX_PRICE_ATTESTATION_AGE: i64 = 300;
+
+/// Size of one `Ed25519SignatureOffsets` record
+const OFFSETS_LEN: usize = 14;
+
+/// Start of the offsets records: signature count and a padding byte
+const OFFSETS_START: usize = 2;
+
+/// Instruction index meaning "this instruction" in the offsets records
+const CURRENT_INSTRUCTION: u16 = u16::MAX;
+
+/// How the verifier determined a mint was rugged
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum RugEvidence {
+    LiquidityPulled,
+    PriceCollapse,
+}
+
+/// Verifier's claim that `mint` is rugged, signed as
+/// `RUG_ATTESTATION_TAG || borsh(RugAttestation)`
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+pub struct RugAttestation {
+    pub mint: Pubkey,
+    pub evidence: RugEvidence,
+}
+
+/// Verifier's record of what `user` paid per `mint` token, signed as
+/// `COST_BASIS_ATTESTATION_TAG || borsh(CostBasisAttestation)`
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+pub struct CostBasisAttestation {
+    pub user: Pubkey,
+    pub mint: Pubkey,
+    /// Per-token price, in the same quote units as PriceAttestation::price
+    pub purchase_price: u64,
+    /// When the position was bought
+    pub timestamp: i64,
+}
+
+/// Verifier's current per-token price of `mint`, signed as
+/// `PRICE_ATTESTATION_TAG || borsh(PriceAttestation)`
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+pub struct PriceAttestation {
+    pub mint: Pubkey,
+    pub price: u64,
+    /// When the price was observed
+    pub timestamp: i64,
+}
+
+/// Verifier's figure for what `user` has lost on their `mint` position,
+/// signed as `LOSS_ATTESTATION_TAG || borsh(LossAttestation)`
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+pub struct LossAttestation {
+    pub user: Pubkey,
+    pub mint: Pubkey,
+    /// USD cents
+    pub loss_usd: u64,
+    /// When the loss was valued
+    pub timestamp: i64,
+}
+
+/// A user's authorization for a relayer to enter `entries_hash` (the hash of
+/// the Borsh-encoded entries) into one round, signed as
+/// `PERMIT_TAG || borsh(ParticipationPermit)`
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+pub struct ParticipationPermit {
+    pub user: Pubkey,
+    pub room_id: u32,
+    pub round_id: u64,
+    pub entries_hash: [u8; 32],
+    /// Permit is void after this time
+    pub expires_at: i64,
+}
+
+/// Messages `signer` signed in the transaction's ed25519 instructions
+pub fn signed_messages(instructions: &AccountInfo, signer: &Pubkey) -> Result<Vec<Vec<u8>>> {
+    let mut messages = Vec::new();
+    let mut index = 0;
+    while let Ok(ix) = load_instruction_at_checked(index, instructions) {
+        index += 1;
+        if ix.program_id != ed25519_program::ID {
+            continue;
+        }
+        let data = &ix.data;
+        let count = *data.first().ok_or(RecoveryRoomError::InvalidAttestation)? as usize;
+        for i in 0..count {
+            let start = OFFSETS_START + i * OFFSETS_LEN;
+            let offsets = data
+                .get(start..start + OFFSETS_LEN)
+                .ok_or(RecoveryRoomError::InvalidAttestation)?;
+            let field = |n: usize| u16::from_le_bytes([offsets[2 * n], offsets[2 * n + 1]]);
+
+            // Signature, key and message must all be read from this instruction
+            if field(1) != CURRENT_INSTRUCTION || field(3) != CURRENT_INSTRUCTION || field(6) != CURRENT_INSTRUCTION {
+                continue;
+            }
+            let key = slice(data, field(2) as usize, 32)?;
+            if key != signer.as_ref() {
+                continue;
+            }
+            messages.push(slice(data, field(4) as usize, field(5) as usize)?.to_vec());
+        }
+    }
+    Ok(messages)
+}
+
+/// Fail unless `verifier` attested each of `mints` as rugged in this transaction
+pub fn require_rug_attestations(instructions: &AccountInfo, verifier: &Pubkey, mints: &[Pubkey]) -> Result<()> {
+    let messages = signed_messages(instructions, verifier)?;
+    let attested: Vec<RugAttestation> = decode_tagged(&messages, RUG_ATTESTATION_TAG);
+    for mint in mints {
+        if !attested.iter().any(|a| a.mint == *mint) {
+            msg!("No rug attestation for mint {}", mint);
+            return err!(RecoveryRoomError::MissingRugAttestation);
+        }
+    }
+    Ok(())
+}
+
+/// Fail unless `user` signed an unexpired permit to enter `entries_hash`
+/// into `round_id` of `room_id`
+pub fn require_participation_permit(
+    instructions: &AccountInfo,
+    user: &Pubkey,
+    room_id: u32,
+    round_id: u64,
+    entries_hash: &[u8; 32],
+    now: i64,
+) -> Result<()> {
+    let messages = signed_messages(instructions, user)?;
+    let permit = decode_tagged::<ParticipationPermit>(&messages, PERMIT_TAG)
+        .into_iter()
+        .find(|p| p.user == *user && p.room_id == room_id && p.round_id == round_id && p.entries_hash == *entries_hash)
+        .ok_or(RecoveryRoomError::MissingPermit)?;
+    require!(now <= permit.expires_at, RecoveryRoomError::PermitExpired);
+    Ok(())
+}
+
+/// Loss of `user`'s `mint` position in percent, from the attested cost basis
+/// and a price attested within MAX_PRICE_ATTESTATION_AGE before `now`;
+/// future-dated prices are ignored. A price at or above cost basis is a 0% loss.
+pub fn attested_loss_percentage(messages: &[Vec<u8>], user: &Pubkey, mint: &Pubkey, now: i64) -> Result<u8> {
+    let cost_basis = decode_tagged::<CostBasisAttestation>(messages, COST_BASIS_ATTESTATION_TAG)
+        .into_iter()
+        .filter(|a| a.user == *user && a.mint == *mint)
+        .max_by_key(|a| a.timestamp)
+        .ok_or(RecoveryRoomError::MissingCostBasisAttestation)?;
+    let price = decode_tagged::<PriceAttestation>(messages, PRICE_ATTESTATION_TAG)
+        .into_iter()
+        .filter(|a| a.mint == *mint && a.timestamp <= now && now.saturating_sub(a.timestamp) <= MAX_PRICE_ATTESTATION_AGE)
+        .max_by_key(|a| a.timestamp)
+        .ok_or(RecoveryRoomError::MissingPriceAttestation)?;
+
+    require!(cost_basis.purchase_price > 0, RecoveryRoomError::InvalidAttestation);
+    let drop = cost_basis.purchase_price.saturating_sub(price.price) as u128;
+    Ok((drop * 100 / cost_basis.purchase_price as u128) as u8)
+}
+
+/// `user`'s loss on `mint` in USD cents, from the latest loss attestation
+/// made within MAX_PRICE_ATTESTATION_AGE before `now`, ignoring future-dated ones
+pub fn attested_loss_usd(messages: &[Vec<u8>], user: &Pubkey, mint: &Pubkey, now: i64) -> Result<u64> {
+    let loss = decode_tagged::<LossAttestation>(messages, LOSS_ATTESTATION_TAG)
+        .into_iter()
+        .filter(|a| {
+            a.user == *user
+                && a.mint == *mint
+                && a.timestamp <= now
+                && now.saturating_sub(a.timestamp) <= MAX_PRICE_ATTESTATION_AGE
+        })
+        .max_by_key(|a| a.timestamp)
+        .ok_or(RecoveryRoomError::MissingLossAttestation)?;
+    Ok(loss.loss_usd)
+}
+
+/// Decode every message carrying `tag` as a `T`, skipping malformed ones
+fn decode_tagged<T: AnchorDeserialize>(messages: &[Vec<u8>], tag: &[u8]) -> Vec<T> {
+    messages
+        .iter()
+        .filter_map(|m| m.strip_prefix(tag))
+        .filter_map(|m| T::try_from_slice(m).ok())
+        .collect()
+}
+
+fn slice(data: &[u8], offset: usize, len: usize) -> Result<&[u8]> {
+    data.get(offset..offset + len)
+        .ok_or_else(|| error!(RecoveryRoomError::InvalidAttestation))
+}
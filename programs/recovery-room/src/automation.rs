@@ -0,0 +1,125 @@
+//! Minimal CPI bindings for Clockwork automation threads.
+//!
+//! Threads drive the round lifecycle (start_round, request_randomness) on a
+//! cron schedule. Round PDAs change every round, so each threaded crank
+//! answers with a `ThreadResponse` carrying the instruction for the next run.
+//! Types mirror the thread program's Borsh layout; only what we use is bound.
+
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::{
+    instruction::{AccountMeta, Instruction},
+    program::{invoke_signed, set_return_data},
+    pubkey,
+};
+
+use crate::instruction_discriminator;
+
+/// Clockwork thread program (v2)
+pub const CLOCKWORK_THREAD_PROGRAM_ID: Pubkey = pubkey!("CLoCKyJ6DXBJqqu2VWx9RLbgnwwR6BMHHuyasVmfMzBh");
+
+/// Placeholder the executing worker substitutes with its own signing key
+pub const CLOCKWORK_PAYER: Pubkey = pubkey!("C1ockworkPayer11111111111111111111111111111");
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+pub struct SerializableAccount {
+    pub pubkey: Pubkey,
+    pub is_signer: bool,
+    pub is_writable: bool,
+}
+
+impl SerializableAccount {
+    pub fn mutable(pubkey: Pubkey, is_signer: bool) -> Self {
+        Self { pubkey, is_signer, is_writable: true }
+    }
+
+    pub fn readonly(pubkey: Pubkey, is_signer: bool) -> Self {
+        Self { pubkey, is_signer, is_writable: false }
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+pub struct SerializableInstruction {
+    pub program_id: Pubkey,
+    pub accounts: Vec<SerializableAccount>,
+    pub data: Vec<u8>,
+}
+
+/// Thread triggers; variant order matches the thread program
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+pub enum Trigger {
+    Account { address: Pubkey, offset: u64, size: u64 },
+    Cron { schedule: String, skippable: bool },
+    Now,
+}
+
+/// Return data a threaded instruction may set to steer its thread
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, Default)]
+pub struct ThreadResponse {
+    pub close_to: Option<Pubkey>,
+    pub dynamic_instruction: Option<SerializableInstruction>,
+    pub trigger: Option<Trigger>,
+}
+
+/// Address of the thread `authority` owns under `id`
+pub fn thread_pda(authority: &Pubkey, id: &[u8]) -> Pubkey {
+    Pubkey::find_program_address(
+        &[b"thread", authority.as_ref(), id],
+        &CLOCKWORK_THREAD_PROGRAM_ID,
+    )
+    .0
+}
+
+/// Accounts for thread_create
+pub struct ThreadCreateAccounts<'a, 'info> {
+    pub thread_program: &'a AccountInfo<'info>,
+    pub authority: &'a AccountInfo<'info>,
+    pub payer: &'a AccountInfo<'info>,
+    pub system_program: &'a AccountInfo<'info>,
+    pub thread: &'a AccountInfo<'info>,
+}
+
+/// Create a thread owned by `authority`, funded with `amount` lamports from `payer`
+pub fn thread_create(
+    accounts: &ThreadCreateAccounts<'_, '_>,
+    amount: u64,
+    id: Vec<u8>,
+    instructions: Vec<SerializableInstruction>,
+    trigger: Trigger,
+    signer_seeds: &[&[&[u8]]],
+) -> Result<()> {
+    let mut data = instruction_discriminator("thread_create").to_vec();
+    (amount, id, instructions, trigger).serialize(&mut data)?;
+
+    let ix = Instruction {
+        program_id: CLOCKWORK_THREAD_PROGRAM_ID,
+        accounts: vec![
+            AccountMeta::new_readonly(accounts.authority.key(), true),
+            AccountMeta::new(accounts.payer.key(), true),
+            AccountMeta::new_readonly(accounts.system_program.key(), false),
+            AccountMeta::new(accounts.thread.key(), false),
+        ],
+        data,
+    };
+    invoke_signed(
+        &ix,
+        &[
+            accounts.authority.clone(),
+            accounts.payer.clone(),
+            accounts.system_program.clone(),
+            accounts.thread.clone(),
+            accounts.thread_program.clone(),
+        ],
+        signer_seeds,
+    )
+    .map_err(Into::into)
+}
+
+/// Replace the thread's current instruction with `next` for its next run
+pub fn respond_with(next: SerializableInstruction) -> Result<()> {
+    let response = ThreadResponse {
+        dynamic_instruction: Some(next),
+        ..Default::default()
+    };
+    set_return_data(&response.try_to_vec()?);
+    Ok(())
+}
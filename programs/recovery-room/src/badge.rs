@@ -0,0 +1,122 @@
+//! Soulbound participation badges.
+//!
+//! A badge is one unit of a 0-decimal Token-2022 mint carrying the
+//! NonTransferable extension, so it can never leave the holder's wallet.
+//! Badge mints are program PDAs created on first use by whoever mints the
+//! first badge; the badge authority PDA is the only mint authority.
+
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::program::invoke;
+use anchor_lang::system_program;
+use anchor_spl::associated_token;
+use anchor_spl::token_2022::{
+    self,
+    spl_token_2022::{self, extension::ExtensionType, state::Mint},
+};
+
+/// Accounts for mint_badge
+pub struct BadgeAccounts<'a, 'info> {
+    pub mint: &'a AccountInfo<'info>,
+    pub authority: &'a AccountInfo<'info>,
+    /// Receives the badge and pays for any account created along the way
+    pub holder: &'a AccountInfo<'info>,
+    /// Holder's associated token account for `mint`
+    pub holder_account: &'a AccountInfo<'info>,
+    pub token_program: &'a AccountInfo<'info>,
+    pub associated_token_program: &'a AccountInfo<'info>,
+    pub system_program: &'a AccountInfo<'info>,
+}
+
+/// Mint one badge to the holder, creating the mint and their token account if needed
+pub fn mint_badge(
+    accounts: &BadgeAccounts<'_, '_>,
+    mint_seeds: &[&[u8]],
+    authority_seeds: &[&[u8]],
+) -> Result<()> {
+    if accounts.mint.data_is_empty() {
+        create_mint(accounts, mint_seeds)?;
+    }
+
+    associated_token::create_idempotent(CpiContext::new(
+        accounts.associated_token_program.clone(),
+        associated_token::Create {
+            payer: accounts.holder.clone(),
+            associated_token: accounts.holder_account.clone(),
+            authority: accounts.holder.clone(),
+            mint: accounts.mint.clone(),
+            system_program: accounts.system_program.clone(),
+            token_program: accounts.token_program.clone(),
+        },
+    ))?;
+
+    token_2022::mint_to(
+        CpiContext::new_with_signer(
+            accounts.token_program.clone(),
+            token_2022::MintTo {
+                mint: accounts.mint.clone(),
+                to: accounts.holder_account.clone(),
+                authority: accounts.authority.clone(),
+            },
+            &[authority_seeds],
+        ),
+        1,
+    )
+}
+
+/// Create a NonTransferable badge mint at the PDA `mint_seeds` derive.
+/// Funds, allocates and assigns separately so lamports sent to the address
+/// ahead of time cannot block creation.
+fn create_mint(accounts: &BadgeAccounts<'_, '_>, mint_seeds: &[&[u8]]) -> Result<()> {
+    let space = ExtensionType::try_calculate_account_len::<Mint>(&[ExtensionType::NonTransferable])?;
+    let shortfall = Rent::get()?
+        .minimum_balance(space)
+        .saturating_sub(accounts.mint.lamports());
+    if shortfall > 0 {
+        system_program::transfer(
+            CpiContext::new(
+                accounts.system_program.clone(),
+                system_program::Transfer {
+                    from: accounts.holder.clone(),
+                    to: accounts.mint.clone(),
+                },
+            ),
+            shortfall,
+        )?;
+    }
+    system_program::allocate(
+        CpiContext::new_with_signer(
+            accounts.system_program.clone(),
+            system_program::Allocate {
+                account_to_allocate: accounts.mint.clone(),
+            },
+            &[mint_seeds],
+        ),
+        space as u64,
+    )?;
+    system_program::assign(
+        CpiContext::new_with_signer(
+            accounts.system_program.clone(),
+            system_program::Assign {
+                account_to_assign: accounts.mint.clone(),
+            },
+            &[mint_seeds],
+        ),
+        &token_2022::ID,
+    )?;
+
+    invoke(
+        &spl_token_2022::instruction::initialize_non_transferable_mint(&token_2022::ID, accounts.mint.key)?,
+        std::slice::from_ref(accounts.mint),
+    )?;
+    token_2022::initialize_mint2(
+        CpiContext::new(
+            accounts.token_program.clone(),
+            token_2022::InitializeMint2 {
+                mint: accounts.mint.clone(),
+            },
+        ),
+        0,
+        accounts.authority.key,
+        None,
+    )
+}
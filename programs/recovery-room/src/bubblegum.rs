@@ -0,0 +1,176 @@
+//! Minimal CPI bindings for Metaplex Bubblegum.
+//!
+//! Used to mint compressed NFT receipts of participation into a tree the
+//! program owns. Like `compression`, instructions are built by hand from
+//! Bubblegum's Anchor discriminators; types mirror its Borsh layout and only
+//! what we use is bound.
+
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::{
+    instruction::{AccountMeta, Instruction},
+    program::invoke_signed,
+    pubkey,
+};
+
+use crate::instruction_discriminator;
+
+/// Metaplex Bubblegum program
+pub const BUBBLEGUM_PROGRAM_ID: Pubkey = pubkey!("BGUMAp9Gq7iTEuizy4pqaxsTyUCBK68MDfK752saRPUY");
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug)]
+pub enum TokenStandard {
+    NonFungible,
+    FungibleAsset,
+    Fungible,
+    NonFungibleEdition,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug)]
+pub enum TokenProgramVersion {
+    Original,
+    Token2022,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+pub struct Collection {
+    pub verified: bool,
+    pub key: Pubkey,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug)]
+pub enum UseMethod {
+    Burn,
+    Multiple,
+    Single,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+pub struct Uses {
+    pub use_method: UseMethod,
+    pub remaining: u64,
+    pub total: u64,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+pub struct Creator {
+    pub address: Pubkey,
+    pub verified: bool,
+    pub share: u8,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+pub struct MetadataArgs {
+    pub name: String,
+    pub symbol: String,
+    pub uri: String,
+    pub seller_fee_basis_points: u16,
+    pub primary_sale_happened: bool,
+    pub is_mutable: bool,
+    pub edition_nonce: Option<u8>,
+    pub token_standard: Option<TokenStandard>,
+    pub collection: Option<Collection>,
+    pub uses: Option<Uses>,
+    pub token_program_version: TokenProgramVersion,
+    pub creators: Vec<Creator>,
+}
+
+/// Bubblegum's config PDA for `merkle_tree`
+pub fn tree_config_pda(merkle_tree: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[merkle_tree.as_ref()], &BUBBLEGUM_PROGRAM_ID).0
+}
+
+/// Accounts shared by create_tree and mint_v1
+pub struct BubblegumAccounts<'a, 'info> {
+    pub bubblegum_program: &'a AccountInfo<'info>,
+    pub tree_config: &'a AccountInfo<'info>,
+    pub merkle_tree: &'a AccountInfo<'info>,
+    pub payer: &'a AccountInfo<'info>,
+    /// Tree creator, and the tree delegate that signs mints
+    pub tree_delegate: &'a AccountInfo<'info>,
+    pub noop_program: &'a AccountInfo<'info>,
+    pub compression_program: &'a AccountInfo<'info>,
+    pub system_program: &'a AccountInfo<'info>,
+}
+
+/// Initialize a pre-allocated tree as a private Bubblegum tree owned by `tree_delegate`
+pub fn create_tree(
+    accounts: &BubblegumAccounts<'_, '_>,
+    max_depth: u32,
+    max_buffer_size: u32,
+    signer_seeds: &[&[&[u8]]],
+) -> Result<()> {
+    let mut data = instruction_discriminator("create_tree").to_vec();
+    (max_depth, max_buffer_size, Some(false)).serialize(&mut data)?;
+
+    let ix = Instruction {
+        program_id: BUBBLEGUM_PROGRAM_ID,
+        accounts: vec![
+            AccountMeta::new(accounts.tree_config.key(), false),
+            AccountMeta::new(accounts.merkle_tree.key(), false),
+            AccountMeta::new(accounts.payer.key(), true),
+            AccountMeta::new_readonly(accounts.tree_delegate.key(), true),
+            AccountMeta::new_readonly(accounts.noop_program.key(), false),
+            AccountMeta::new_readonly(accounts.compression_program.key(), false),
+            AccountMeta::new_readonly(accounts.system_program.key(), false),
+        ],
+        data,
+    };
+    invoke_signed(
+        &ix,
+        &[
+            accounts.tree_config.clone(),
+            accounts.merkle_tree.clone(),
+            accounts.payer.clone(),
+            accounts.tree_delegate.clone(),
+            accounts.noop_program.clone(),
+            accounts.compression_program.clone(),
+            accounts.system_program.clone(),
+            accounts.bubblegum_program.clone(),
+        ],
+        signer_seeds,
+    )
+    .map_err(Into::into)
+}
+
+/// Mint a compressed NFT owned and delegated to `leaf_owner`
+pub fn mint_v1<'info>(
+    accounts: &BubblegumAccounts<'_, 'info>,
+    leaf_owner: &AccountInfo<'info>,
+    metadata: MetadataArgs,
+    signer_seeds: &[&[&[u8]]],
+) -> Result<()> {
+    let mut data = instruction_discriminator("mint_v1").to_vec();
+    metadata.serialize(&mut data)?;
+
+    let ix = Instruction {
+        program_id: BUBBLEGUM_PROGRAM_ID,
+        accounts: vec![
+            AccountMeta::new(accounts.tree_config.key(), false),
+            AccountMeta::new_readonly(leaf_owner.key(), false),
+            AccountMeta::new_readonly(leaf_owner.key(), false),
+            AccountMeta::new(accounts.merkle_tree.key(), false),
+            AccountMeta::new(accounts.payer.key(), true),
+            AccountMeta::new_readonly(accounts.tree_delegate.key(), true),
+            AccountMeta::new_readonly(accounts.noop_program.key(), false),
+            AccountMeta::new_readonly(accounts.compression_program.key(), false),
+            AccountMeta::new_readonly(accounts.system_program.key(), false),
+        ],
+        data,
+    };
+    invoke_signed(
+        &ix,
+        &[
+            accounts.tree_config.clone(),
+            leaf_owner.clone(),
+            accounts.merkle_tree.clone(),
+            accounts.payer.clone(),
+            accounts.tree_delegate.clone(),
+            accounts.noop_program.clone(),
+            accounts.compression_program.clone(),
+            accounts.system_program.clone(),
+            accounts.bubblegum_program.clone(),
+        ],
+        signer_seeds,
+    )
+    .map_err(Into::into)
+}
@@ -0,0 +1,99 @@
+//! Minimal CPI bindings for SPL Account Compression.
+//!
+//! Only the three instructions the compressed participation mode needs are
+//! bound here, built by hand from the program's Anchor discriminators so we
+//! don't pull in a second Solana dependency tree alongside Switchboard.
+
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::{
+    instruction::{AccountMeta, Instruction},
+    program::invoke_signed,
+    pubkey,
+};
+
+use crate::instruction_discriminator;
+
+/// SPL Account Compression program
+pub const SPL_ACCOUNT_COMPRESSION_ID: Pubkey = pubkey!("cmtDvXumGCrqC1Age74AVPhSRVXJMd8PJS91L8KbNCK");
+
+/// SPL Noop program, used by account compression as its log wrapper
+pub const SPL_NOOP_ID: Pubkey = pubkey!("noopb9bkMVfRPU8AsbpTUg8AQkHtKwMYZiFUjNRtMmV");
+
+/// Accounts shared by every tree-mutating CPI
+pub struct TreeAccounts<'a, 'info> {
+    pub compression_program: &'a AccountInfo<'info>,
+    pub merkle_tree: &'a AccountInfo<'info>,
+    pub authority: &'a AccountInfo<'info>,
+    pub noop_program: &'a AccountInfo<'info>,
+}
+
+fn invoke_tree_ix<'info>(
+    accounts: &TreeAccounts<'_, 'info>,
+    data: Vec<u8>,
+    proof: &[AccountInfo<'info>],
+    signer_seeds: &[&[&[u8]]],
+) -> Result<()> {
+    let mut metas = vec![
+        AccountMeta::new(accounts.merkle_tree.key(), false),
+        AccountMeta::new_readonly(accounts.authority.key(), true),
+        AccountMeta::new_readonly(accounts.noop_program.key(), false),
+    ];
+    metas.extend(proof.iter().map(|node| AccountMeta::new_readonly(node.key(), false)));
+
+    let mut infos = vec![
+        accounts.merkle_tree.clone(),
+        accounts.authority.clone(),
+        accounts.noop_program.clone(),
+    ];
+    infos.extend(proof.iter().cloned());
+    infos.push(accounts.compression_program.clone());
+
+    let ix = Instruction {
+        program_id: SPL_ACCOUNT_COMPRESSION_ID,
+        accounts: metas,
+        data,
+    };
+    invoke_signed(&ix, &infos, signer_seeds).map_err(Into::into)
+}
+
+/// Initialize a pre-allocated tree with `authority` as its tree authority
+pub fn init_empty_merkle_tree<'info>(
+    accounts: &TreeAccounts<'_, 'info>,
+    max_depth: u32,
+    max_buffer_size: u32,
+    signer_seeds: &[&[&[u8]]],
+) -> Result<()> {
+    let mut data = instruction_discriminator("init_empty_merkle_tree").to_vec();
+    data.extend_from_slice(&max_depth.to_le_bytes());
+    data.extend_from_slice(&max_buffer_size.to_le_bytes());
+    invoke_tree_ix(accounts, data, &[], signer_seeds)
+}
+
+/// Append a leaf to the tree
+pub fn append<'info>(
+    accounts: &TreeAccounts<'_, 'info>,
+    leaf: [u8; 32],
+    signer_seeds: &[&[&[u8]]],
+) -> Result<()> {
+    let mut data = instruction_discriminator("append").to_vec();
+    data.extend_from_slice(&leaf);
+    invoke_tree_ix(accounts, data, &[], signer_seeds)
+}
+
+/// Replace `previous_leaf` at `index`; the CPI fails unless the proof verifies
+pub fn replace_leaf<'info>(
+    accounts: &TreeAccounts<'_, 'info>,
+    root: [u8; 32],
+    previous_leaf: [u8; 32],
+    new_leaf: [u8; 32],
+    index: u32,
+    proof: &[AccountInfo<'info>],
+    signer_seeds: &[&[&[u8]]],
+) -> Result<()> {
+    let mut data = instruction_discriminator("replace_leaf").to_vec();
+    data.extend_from_slice(&root);
+    data.extend_from_slice(&previous_leaf);
+    data.extend_from_slice(&new_leaf);
+    data.extend_from_slice(&index.to_le_bytes());
+    invoke_tree_ix(accounts, data, proof, signer_seeds)
+}
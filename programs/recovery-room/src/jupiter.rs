@@ -0,0 +1,64 @@
+//! Minimal CPI binding for the Jupiter v6 swap aggregator.
+//!
+//! Routes are quoted and serialized off-chain; the program only checks the
+//! instruction is an exact-in route, forwards it with the accounts Jupiter
+//! expects and signs for the wallet selling the tokens. The route's own
+//! slippage setting isn't trusted: callers compare token balances around
+//! the swap instead.
+
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::{
+    instruction::{AccountMeta, Instruction},
+    program::invoke_signed,
+    pubkey,
+};
+
+use crate::{instruction_discriminator, RecoveryRoomError};
+
+/// Jupiter v6 aggregator program
+pub const JUPITER_PROGRAM_ID: Pubkey = pubkey!("JUP6LkbZbjS1jKKwapdHNy74zcZ3tLUZoi5QNyVTaV4");
+
+/// Jupiter instructions that sell an exact input amount
+const EXACT_IN_ROUTES: [&str; 2] = ["route", "shared_accounts_route"];
+
+/// Run the serialized Jupiter route `route_data` over `accounts` (in the
+/// order Jupiter expects), signing for `authority` with `signer_seeds`.
+/// `jupiter_program` must be Jupiter v6 itself; no other program is handed
+/// the signature.
+pub fn swap<'info>(
+    jupiter_program: &AccountInfo<'info>,
+    route_data: &[u8],
+    accounts: &[AccountInfo<'info>],
+    authority: &Pubkey,
+    signer_seeds: &[&[&[u8]]],
+) -> Result<()> {
+    require_keys_eq!(
+        jupiter_program.key(),
+        JUPITER_PROGRAM_ID,
+        RecoveryRoomError::InvalidSwapProgram
+    );
+    let discriminator = route_data.get(..8).ok_or(RecoveryRoomError::InvalidSwapRoute)?;
+    require!(
+        EXACT_IN_ROUTES
+            .iter()
+            .any(|name| instruction_discriminator(name)[..] == *discriminator),
+        RecoveryRoomError::InvalidSwapRoute
+    );
+
+    let ix = Instruction {
+        program_id: JUPITER_PROGRAM_ID,
+        accounts: accounts
+            .iter()
+            .map(|a| AccountMeta {
+                pubkey: a.key(),
+                is_signer: a.is_signer || a.key == authority,
+                is_writable: a.is_writable,
+            })
+            .collect(),
+        data: route_data.to_vec(),
+    };
+    let mut infos = accounts.to_vec();
+    infos.push(jupiter_program.clone());
+
+    invoke_signed(&ix, &infos, signer_seeds).map_err(Into::into)
+}
@@ -1,5 +1,10 @@
 use anchor_lang::prelude::*;
-use anchor_spl::token::{Token, TokenAccount, Mint};
+use anchor_lang::solana_program::{hash::hash, keccak, slot_hashes, sysvar};
+use anchor_lang::system_program;
+use anchor_spl::associated_token::AssociatedToken;
+use anchor_spl::token::{self, Token};
+use anchor_spl::token_2022::{self, Token2022};
+use anchor_spl::token_interface::{self, Burn, CloseAccount, Mint, MintTo, TokenAccount, TokenInterface};
 use switchboard_solana::{
     prelude::*,
     VrfAccountData,
@@ -12,10 +17,60 @@ use switchboard_solana::{
 
 declare_id!("RecovRoomVRF111111111111111111111111111111");
 
+pub mod attestation;
+pub mod automation;
+pub mod badge;
+pub mod bubblegum;
+pub mod compression;
+pub mod jupiter;
+pub mod metadata;
+pub mod money;
+pub mod realloc;
+pub mod transfer;
+
+use money::{mul_div, TokenAmount, UsdCents, BPS_DENOMINATOR};
+
+/// Emit an event indexers depend on. With the `event-cpi` feature it goes
+/// through a self-CPI to the event authority PDA, so it survives RPC log
+/// truncation; otherwise it is a plain `emit!` log. The instruction's
+/// accounts struct must carry `event_cpi` under the same feature.
+macro_rules! emit_indexed {
+    ($ctx:ident, $event:expr) => {{
+        let event = $event;
+        #[cfg(feature = "event-cpi")]
+        {
+            let ctx = &$ctx;
+            emit_cpi!(event);
+        }
+        #[cfg(not(feature = "event-cpi"))]
+        emit!(event);
+    }};
+}
+
+/// `require!` for a bounded value that also logs an `ErrorDetail`, so clients
+/// can say "need >= 80, you provided 42" instead of a bare error name. Both
+/// sides widen to i128, so signed values such as durations compare correctly.
+macro_rules! require_detail {
+    ($error:expr, $field:literal, $provided:expr, at_least $required:expr) => {
+        require_detail!(@check $error, $field, $provided, $required, ">=", >=)
+    };
+    ($error:expr, $field:literal, $provided:expr, at_most $required:expr) => {
+        require_detail!(@check $error, $field, $provided, $required, "<=", <=)
+    };
+    (@check $error:expr, $field:literal, $provided:expr, $required:expr, $op:literal, $cmp:tt) => {{
+        let provided = ($provided) as i128;
+        let required = ($required) as i128;
+        if !(provided $cmp required) {
+            $crate::ErrorDetail { field: $field, op: $op, required, provided }.log($error);
+            return Err(error!($error));
+        }
+    }};
+}
+
 /// Recovery Room Protocol - Verifiable Fair Lottery using Switchboard VRF
 ///
 /// The protocol runs hourly rounds where users submit their "rugged" tokens.
-/// Winner selection uses sqrt-weighted probabilities with on-chain VRF.
+/// Winner selection uses weighted probabilities (sqrt by default) with on-chain VRF.
 
 #[program]
 pub mod recovery_room {
@@ -27,105 +82,536 @@ pub mod recovery_room {
         round_duration: i64,        // Duration in seconds (3600 = 1 hour)
         min_loss_percentage: u8,    // Minimum loss % required (e.g., 80)
         max_tokens_per_user: u8,    // Max tokens per participation (e.g., 3)
+        protocol_fee_bps: u16,      // Fee taken from every payout (e.g., 250 = 2.5%)
     ) -> Result<()> {
-        let protocol = &mut ctx.accounts.protocol_state;
+        ctx.accounts.protocol_state.init_room(
+            ctx.accounts.authority.key(),
+            ctx.bumps.protocol_state,
+            DEFAULT_ROOM_ID,
+            &RoomConfig {
+                round_duration,
+                min_loss_percentage,
+                max_tokens_per_user,
+                protocol_fee_bps,
+            },
+        )?;
+
+        let treasury = &mut ctx.accounts.treasury;
+        treasury.total_fees_collected = 0;
+        treasury.total_withdrawn = 0;
+        treasury.total_bounties_paid = 0;
+        treasury.bump = ctx.bumps.treasury;
+
+        let metrics = &mut ctx.accounts.metrics;
+        metrics.vrf_latencies = [0; LATENCY_WINDOW];
+        metrics.latency_head = 0;
+        metrics.latency_samples = 0;
+        metrics.total_vrf_fulfilled = 0;
+        metrics.max_vrf_latency = 0;
+        metrics.bump = ctx.bumps.metrics;
 
-        protocol.authority = ctx.accounts.authority.key();
-        protocol.round_duration = round_duration;
-        protocol.min_loss_percentage = min_loss_percentage;
-        protocol.max_tokens_per_user = max_tokens_per_user;
-        protocol.current_round = 0;
-        protocol.total_rounds_completed = 0;
-        protocol.bump = ctx.bumps.protocol_state;
+        let jackpot = &mut ctx.accounts.jackpot;
+        jackpot.balance = 0;
+        jackpot.total_rolled_over = 0;
+        jackpot.last_rolled_round = 0;
+        jackpot.bump = ctx.bumps.jackpot;
 
         msg!("Recovery Room Protocol initialized");
         Ok(())
     }
 
+    /// Create an independent room with its own config, rounds, treasury and
+    /// jackpot (default room's authority only). The caller becomes the room's
+    /// authority.
+    pub fn create_room(ctx: Context<CreateRoom>, config: RoomConfig) -> Result<()> {
+        let registry = &mut ctx.accounts.room_registry;
+        if registry.rooms.is_empty() {
+            registry.rooms.push(DEFAULT_ROOM_ID);
+            registry.bump = ctx.bumps.room_registry;
+        }
+        require!(registry.rooms.len() < MAX_ROOMS, RecoveryRoomError::RoomRegistryFull);
+
+        let room_id = registry.last_room_id.checked_add(1).ok_or(RecoveryRoomError::ArithmeticOverflow)?;
+        registry.last_room_id = room_id;
+        registry.rooms.push(room_id);
+
+        ctx.accounts.protocol_state.init_room(
+            ctx.accounts.authority.key(),
+            ctx.bumps.protocol_state,
+            room_id,
+            &config,
+        )?;
+        ctx.accounts.treasury.bump = ctx.bumps.treasury;
+        ctx.accounts.jackpot.bump = ctx.bumps.jackpot;
+
+        emit!(RoomCreated {
+            room_id,
+            protocol_state: ctx.accounts.protocol_state.key(),
+            authority: ctx.accounts.authority.key(),
+        });
+
+        msg!("Room {} created", room_id);
+        Ok(())
+    }
+
+    /// Add a special round to the room's schedule; the first start_round at
+    /// or after `start_time` runs it (authority only)
+    pub fn schedule_round(ctx: Context<ScheduleRound>, round: ScheduledRound) -> Result<()> {
+        let protocol = &mut ctx.accounts.protocol_state;
+        let schedule = &mut ctx.accounts.schedule;
+        schedule.room_id = protocol.room_id;
+        schedule.bump = ctx.bumps.schedule;
+        protocol.has_schedule = true;
+
+        require!(
+            round.start_time > Clock::get()?.unix_timestamp,
+            RecoveryRoomError::InvalidScheduledRound
+        );
+        require_detail!(
+            RecoveryRoomError::InvalidRoundDuration,
+            "round_duration",
+            round.duration,
+            at_least protocol.min_round_duration
+        );
+        require_detail!(
+            RecoveryRoomError::InvalidRoundDuration,
+            "round_duration",
+            round.duration,
+            at_most protocol.max_round_duration
+        );
+        require_detail!(
+            RecoveryRoomError::InvalidScheduledRound,
+            "prize_multiplier_bps",
+            round.prize_multiplier_bps,
+            at_least BPS_DENOMINATOR
+        );
+        require_detail!(
+            RecoveryRoomError::InvalidScheduledRound,
+            "prize_multiplier_bps",
+            round.prize_multiplier_bps,
+            at_most MAX_PRIZE_MULTIPLIER_BPS
+        );
+        require_detail!(
+            RecoveryRoomError::InvalidScheduledRound,
+            "theme_len",
+            round.theme.len(),
+            at_most MAX_SCHEDULE_THEME_LEN
+        );
+        require!(
+            schedule.rounds.len() < MAX_SCHEDULED_ROUNDS,
+            RecoveryRoomError::ScheduleFull
+        );
+        require!(
+            schedule.rounds.iter().all(|r| r.start_time != round.start_time),
+            RecoveryRoomError::InvalidScheduledRound
+        );
+
+        emit!(RoundScheduled {
+            room_id: protocol.room_id,
+            start_time: round.start_time,
+            duration: round.duration,
+            prize_multiplier_bps: round.prize_multiplier_bps,
+            theme: round.theme.clone(),
+        });
+
+        let at = schedule.rounds.partition_point(|r| r.start_time < round.start_time);
+        schedule.rounds.insert(at, round);
+        Ok(())
+    }
+
+    /// Drop the scheduled round starting at `start_time` (authority only)
+    pub fn unschedule_round(ctx: Context<UnscheduleRound>, start_time: i64) -> Result<()> {
+        let schedule = &mut ctx.accounts.schedule;
+        let at = schedule
+            .rounds
+            .iter()
+            .position(|r| r.start_time == start_time)
+            .ok_or(RecoveryRoomError::InvalidScheduledRound)?;
+        schedule.rounds.remove(at);
+
+        msg!("Scheduled round at {} removed from room {}", start_time, schedule.room_id);
+        Ok(())
+    }
+
     /// Start a new round (called hourly by crank/automation)
-    pub fn start_round(ctx: Context<StartRound>) -> Result<()> {
+    ///
+    /// `duration` overrides the protocol's round duration for this round only
+    /// (e.g. a 24-hour weekend round), bounded by the protocol's min/max round
+    /// duration. `max_tokens` likewise overrides max_tokens_per_user, up to
+    /// MAX_TOKENS_PER_USER. Only the authority may pass either override.
+    ///
+    /// When the room's schedule has an event due, the round runs it: its
+    /// duration (unless overridden), theme and prize multiplier. In ticket
+    /// mode the round's ticket mint is created here too, so automation
+    /// threads, which pass none, can't start a ticketed room's rounds.
+    pub fn start_round(
+        ctx: Context<StartRound>,
+        duration: Option<i64>,
+        max_tokens: Option<u8>,
+    ) -> Result<()> {
         let protocol = &mut ctx.accounts.protocol_state;
         let round = &mut ctx.accounts.round_state;
         let clock = Clock::get()?;
 
+        protocol.require_crank(
+            Some(ctx.accounts.payer.key()),
+            ctx.accounts.thread.is_some(),
+            ctx.accounts.keeper_registry.as_deref_mut(),
+            CrankInstruction::StartRound,
+            clock.unix_timestamp,
+        )?;
+        if duration.is_some() || max_tokens.is_some() {
+            require_keys_eq!(
+                ctx.accounts.payer.key(),
+                protocol.authority,
+                RecoveryRoomError::UnauthorizedRoundOverride
+            );
+        }
+
+        // The start thread's instruction predates a schedule created since
+        // its last round; the next one it queues passes it
+        require!(
+            !protocol.has_schedule || ctx.accounts.schedule.is_some() || ctx.accounts.thread.is_some(),
+            RecoveryRoomError::MissingSchedule
+        );
+        let scheduled = ctx
+            .accounts
+            .schedule
+            .as_deref_mut()
+            .and_then(|schedule| schedule.take_due(clock.unix_timestamp));
+
+        let round_duration = match duration {
+            Some(duration) => {
+                require_detail!(
+                    RecoveryRoomError::InvalidRoundDuration,
+                    "round_duration",
+                    duration,
+                    at_least protocol.min_round_duration
+                );
+                require_detail!(
+                    RecoveryRoomError::InvalidRoundDuration,
+                    "round_duration",
+                    duration,
+                    at_most protocol.max_round_duration
+                );
+                duration
+            }
+            None => scheduled.as_ref().map_or(protocol.round_duration, |s| s.duration),
+        };
+
+        let max_tokens_per_user = match max_tokens {
+            Some(max_tokens) => {
+                require_detail!(
+                    RecoveryRoomError::InvalidTokenCount,
+                    "max_tokens_per_user",
+                    max_tokens,
+                    at_least 1
+                );
+                require_detail!(
+                    RecoveryRoomError::InvalidTokenCount,
+                    "max_tokens_per_user",
+                    max_tokens,
+                    at_most MAX_TOKENS_PER_USER
+                );
+                max_tokens
+            }
+            None => protocol.max_tokens_per_user,
+        };
+
         // Verify previous round is complete (if any)
         require!(
-            protocol.current_round == 0 || ctx.accounts.previous_round.is_some(),
+            protocol.current_round == 0
+                || ctx.accounts.previous_round.as_ref().is_some_and(|r| {
+                    r.status == RoundStatus::Complete || r.status == RoundStatus::Cancelled
+                }),
             RecoveryRoomError::PreviousRoundNotComplete
         );
 
-        protocol.current_round += 1;
+        protocol.current_round = protocol.current_round.checked_add(1).ok_or(RecoveryRoomError::ArithmeticOverflow)?;
 
         round.round_id = protocol.current_round;
         round.start_time = clock.unix_timestamp;
-        round.end_time = clock.unix_timestamp + protocol.round_duration;
+        round.end_time = clock.unix_timestamp.checked_add(round_duration).ok_or(RecoveryRoomError::ArithmeticOverflow)?;
         round.total_participants = 0;
         round.total_token_entries = 0;
         round.status = RoundStatus::Active;
         round.vrf_result = None;
         round.winner_token = None;
         round.bump = ctx.bumps.round_state;
+        round.version = ROUND_STATE_VERSION;
+        round.room_id = protocol.room_id;
+        round.sponsor = None;
+        round.metadata_uri = String::new();
+        round.prize_multiplier_bps = BPS_DENOMINATOR as u16;
+        round.theme = String::new();
+        if let Some(scheduled) = scheduled {
+            msg!("Round {} runs scheduled event \"{}\"", round.round_id, scheduled.theme);
+            round.prize_multiplier_bps = scheduled.prize_multiplier_bps;
+            round.theme = scheduled.theme;
+        }
+        round.stable_prize = match protocol.stable_prize {
+            Some(prize) => Some(StablePrize {
+                amount: mul_div(
+                    prize.amount,
+                    (round.prize_multiplier_bps as u64).max(BPS_DENOMINATOR),
+                    BPS_DENOMINATOR,
+                )?,
+                ..prize
+            }),
+            None => None,
+        };
+        round.weighting = protocol.weighting;
+        round.ticketed = protocol.ticket_mode;
+        require!(
+            ctx.accounts.ticket_mint.is_some() == round.ticketed,
+            RecoveryRoomError::MissingTicketAccounts
+        );
+        round.ticket_supply = 0;
+        round.claim_deadline = 0;
+        round.unclaimed_swept = false;
+        round.shard_count = 0;
+        round.pool_entry_count = 0;
+        round.pool_entries_tallied = 0;
+        round.pool_weight = 0;
+        round.fallback_slot = 0;
+        round.slothash_resolved = false;
+        round.randomness_commitment = [0; 32];
+        round.committed_at = 0;
+        round.reveal_salt = [0; 32];
+        round.salt_slot = 0;
+        round.bonus_tickets = 0;
+        round.bonus_ticket = 0;
+        round.bonus_prize = 0;
+        round.bonus_claimed = false;
+        round.wallet_draw = protocol.wallet_draw;
+        round.wallet_ticket = 0;
+        if round.ticketed {
+            // One ticket, one chance
+            round.weighting = WeightingStrategy::Linear;
+        }
+        round.weight_cap = protocol.max_weight_contribution_per_user;
+        round.min_participants = protocol.min_participants;
+        round.max_participants = protocol.max_participants;
+        round.anti_snipe = protocol.anti_snipe;
+        round.total_extension = 0;
+        round.max_tokens_per_user = max_tokens_per_user;
+        round.winner_cooldown_rounds = protocol.winner_cooldown_rounds;
+        round.min_submissions_to_qualify = protocol.min_submissions_to_qualify;
+        round.entry_deposit = protocol.entry_deposit;
+        round.entry_fee = protocol.entry_fee;
+        round.season_id = protocol.season_for(clock.unix_timestamp);
 
-        emit!(RoundStarted {
+        let prize_vault = &mut ctx.accounts.prize_vault;
+        prize_vault.round_id = round.round_id;
+        prize_vault.lamports = 0;
+        prize_vault.bump = ctx.bumps.prize_vault;
+
+        ctx.accounts.token_pool.load_init()?.round_id = round.round_id;
+
+        let started = RoundStarted {
             round_id: round.round_id,
             start_time: round.start_time,
             end_time: round.end_time,
-        });
+        };
+
+        // Roll any accumulated jackpot into this round's prize vault
+        let jackpot = &mut ctx.accounts.jackpot;
+        if jackpot.balance > 0 {
+            let amount = jackpot.balance;
+            move_lamports(&jackpot.to_account_info(), &prize_vault.to_account_info(), amount)?;
+
+            jackpot.balance = 0;
+            jackpot.total_rolled_over = jackpot.total_rolled_over.checked_add(amount).ok_or(RecoveryRoomError::ArithmeticOverflow)?;
+            jackpot.last_rolled_round = round.round_id;
+            prize_vault.lamports = prize_vault.lamports.checked_add(amount).ok_or(RecoveryRoomError::ArithmeticOverflow)?;
+
+            emit!(JackpotRolled {
+                round_id: round.round_id,
+                amount,
+            });
+        }
 
-        msg!("Round {} started", round.round_id);
+        pay_keeper_bounty(
+            &mut ctx.accounts.treasury,
+            &ctx.accounts.payer.to_account_info(),
+            ctx.accounts.protocol_state.keeper_bounties.start_round,
+            CrankInstruction::StartRound,
+            started.round_id,
+        )?;
+
+        // A threaded start queues up the next round's start_round
+        if let Some(thread) = &ctx.accounts.thread {
+            require_keys_eq!(
+                thread.key(),
+                ctx.accounts.protocol_state.start_thread,
+                RecoveryRoomError::UnauthorizedThread
+            );
+            automation::respond_with(start_round_thread_ix(
+                ctx.accounts.protocol_state.key(),
+                ctx.accounts.jackpot.key(),
+                ctx.accounts.treasury.key(),
+                thread.key(),
+                ctx.accounts.protocol_state.room_id,
+                started.round_id.checked_add(1).ok_or(RecoveryRoomError::ArithmeticOverflow)?,
+                ctx.accounts.protocol_state.has_schedule,
+            ))?;
+        }
+
+        msg!("Round {} started", started.round_id);
+        emit_indexed!(ctx, started);
         Ok(())
     }
 
     /// User participates with their losing tokens (max 3)
+    ///
+    /// Remaining accounts are the Metaplex metadata account of each entry's
+    /// mint (or the mint itself, for a Token-2022 mint carrying its own
+    /// metadata), in entry order; tickers are checked against and replaced by the
+    /// on-chain symbol. They are followed by the user's token account for each
+    /// entry's mint, in entry order, which must hold at least the entry's
+    /// holdings, then by the round's PoolEntry address for each entry's mint.
+    ///
+    /// While a rug verifier is set, the transaction must also carry an ed25519
+    /// instruction with the verifier's RugAttestation for every entered mint.
+    /// While a price verifier is set, each entry's loss_percentage is replaced
+    /// by the loss computed from that verifier's cost-basis and price
+    /// attestations, and must still meet min_loss_percentage.
     pub fn participate(
         ctx: Context<Participate>,
         token_entries: Vec<TokenEntry>,
     ) -> Result<()> {
+        let mut token_entries = token_entries;
+        token_entries.iter().try_for_each(TokenEntry::validate)?;
+        check_mint_lists(
+            &ctx.accounts.protocol_state,
+            ctx.accounts.mint_blacklist.as_deref(),
+            ctx.accounts.mint_whitelist.as_deref(),
+            &token_entries,
+        )?;
+        check_access_pass(
+            &ctx.accounts.protocol_state,
+            ctx.accounts.pass_account.as_deref(),
+            ctx.accounts.pass_metadata.as_deref(),
+        )?;
+        check_rug_attestations(
+            &ctx.accounts.protocol_state,
+            ctx.accounts.instructions.as_deref(),
+            &token_entries,
+        )?;
+        attest_losses(
+            &ctx.accounts.protocol_state,
+            ctx.accounts.instructions.as_deref(),
+            &ctx.accounts.user.key(),
+            &mut token_entries,
+        )?;
+        let (metadata_accounts, holding_accounts, pool_entry_accounts) =
+            split_entry_accounts(ctx.remaining_accounts, token_entries.len());
+        canonicalize_tickers(&mut token_entries, metadata_accounts)?;
+        verify_holdings(&ctx.accounts.user.key(), &token_entries, holding_accounts)?;
+        ctx.accounts.user_stats.require_off_cooldown(ctx.accounts.round_state.round_id)?;
+        enforce_rate_limit(
+            &ctx.accounts.protocol_state.rate_limit,
+            &mut ctx.accounts.round_state,
+            &mut ctx.accounts.user_stats,
+            &Clock::get()?,
+        )?;
         let protocol = &ctx.accounts.protocol_state;
         let round = &mut ctx.accounts.round_state;
         let participation = &mut ctx.accounts.participation;
         let clock = Clock::get()?;
 
-        // Validations
-        require!(
-            round.status == RoundStatus::Active,
-            RecoveryRoomError::RoundNotActive
-        );
-        require!(
-            clock.unix_timestamp < round.end_time,
-            RecoveryRoomError::RoundEnded
-        );
-        require!(
-            token_entries.len() > 0 && token_entries.len() <= protocol.max_tokens_per_user as usize,
-            RecoveryRoomError::InvalidTokenCount
-        );
+        let weight_multiplier = match (&ctx.accounts.stake_pool, &ctx.accounts.stake_account) {
+            (Some(pool), Some(stake)) => pool.weight_multiplier(stake, round.end_time),
+            _ => 1,
+        };
+
+        let tickets_before = round.ticket_supply;
+        let (effective_weight, pool_total_weight, wallet_tickets) = record_entries(
+            protocol,
+            round,
+            pool_entry_accounts,
+            ctx.accounts.leaderboard.as_deref_mut(),
+            &token_entries,
+            weight_multiplier,
+            clock.unix_timestamp,
+        )?;
+        mint_tickets(
+            round,
+            ctx.accounts.user.key(),
+            ctx.accounts.ticket_mint.as_ref(),
+            ctx.accounts.user_ticket_account.as_ref(),
+            &ctx.accounts.token_program,
+            round.ticket_supply - tickets_before,
+        )?;
+
+        let entry_fee_paid = collect_entry_fee(
+            round,
+            &ctx.accounts.user.to_account_info(),
+            ctx.accounts.user.key(),
+            &mut ctx.accounts.prize_vault,
+            ctx.accounts.user_fee_account.as_ref(),
+            ctx.accounts.vault_fee_account.as_ref(),
+            ctx.accounts.fee_mint.as_ref(),
+            ctx.accounts.referral.as_mut(),
+            &mut ctx.accounts.user_stats.referrer,
+            protocol.referral_fee_bps,
+            &ctx.accounts.token_program,
+            &ctx.accounts.system_program,
+        )?;
+
+        // Refundable anti-spam deposit, held by the participation account
+        if round.entry_deposit > 0 {
+            system_program::transfer(
+                CpiContext::new(
+                    ctx.accounts.system_program.to_account_info(),
+                    system_program::Transfer {
+                        from: ctx.accounts.user.to_account_info(),
+                        to: participation.to_account_info(),
+                    },
+                ),
+                round.entry_deposit,
+            )?;
+        }
+        participation.deposit = round.entry_deposit;
+        participation.flagged = false;
+        participation.entry_fee_paid = entry_fee_paid;
 
-        // Store participation
+        // Store participation, with the entrant's odds as they stand right now
         participation.user = ctx.accounts.user.key();
         participation.round_id = round.round_id;
         participation.tokens = token_entries.clone();
         participation.timestamp = clock.unix_timestamp;
         participation.bump = ctx.bumps.participation;
+        participation.version = PARTICIPATION_VERSION;
+        participation.recovery_minted = false;
+        participation.weight_multiplier = weight_multiplier;
+        participation.bonus_ticket_start = round.issue_bonus_tickets(token_entries.len())?;
+        participation.losses_attested = protocol.price_verifier.is_some();
+        participation.wallet_tickets = wallet_tickets;
+        participation.effective_weight = effective_weight;
+        participation.pool_total_weight = pool_total_weight;
 
-        // Update round stats
-        round.total_participants += 1;
-        round.total_token_entries += token_entries.len() as u32;
+        let xp = ctx.accounts.user_stats.record_entry(
+            ctx.accounts.user.key(),
+            ctx.bumps.user_stats,
+            round.round_id,
+            &token_entries,
+            &protocol.xp_rates,
+        )?;
+        emit_xp_awarded(&ctx.accounts.user_stats, round.round_id, xp);
+        record_season_entry(round, ctx.accounts.season.as_deref_mut(), &token_entries)?;
 
-        // Update token pool stats (increment submission counts)
-        for entry in &token_entries {
-            // Find or create token pool entry
-            let pool_entry = ctx.accounts.token_pool_entries
-                .iter_mut()
-                .find(|p| p.token_mint == entry.token_mint);
+        if let Some(registry) = &ctx.accounts.ticker_registry {
+            warn_ticker_collisions(&*registry.load()?, round.round_id, &token_entries)?;
+        }
 
-            if let Some(pool) = pool_entry {
-                pool.submission_count += 1;
-            }
-            // Note: In production, you'd use a separate instruction to register tokens
+        let round_id = round.round_id;
+        if ctx.accounts.receipt_config.is_some() {
+            mint_participation_receipt(ctx.accounts, ctx.bumps.receipt_authority, round_id)?;
         }
 
-        emit!(UserParticipated {
-            round_id: round.round_id,
+        emit_indexed!(ctx, UserParticipated {
+            round_id,
             user: ctx.accounts.user.key(),
             token_count: token_entries.len() as u8,
         });
@@ -134,8 +620,401 @@ pub mod recovery_room {
         Ok(())
     }
 
-    /// Request VRF randomness when round ends (called by crank)
+    /// Participate on a user's behalf, for partner programs calling by CPI
+    ///
+    /// `delegate` signs and pays instead of the user. It must be either the
+    /// PDA [PARTICIPATE_FOR_SEED, user] of a registered integrator program,
+    /// which that program signs for with invoke_signed, or the delegate the
+    /// user approved with set_participation_delegate. Entries are checked
+    /// against the user's holdings and attestations exactly as in
+    /// `participate`; no receipt is minted.
+    pub fn participate_for(
+        ctx: Context<ParticipateFor>,
+        token_entries: Vec<TokenEntry>,
+    ) -> Result<()> {
+        check_participation_delegate(
+            &ctx.accounts.user.key(),
+            &ctx.accounts.delegate.key(),
+            ctx.accounts.integrator_registry.as_deref(),
+            ctx.accounts.integrator_program.as_deref(),
+            ctx.accounts.delegation.as_deref(),
+        )?;
+
+        let integrator_program = ctx.accounts.integrator_program.as_ref().map(|p| p.key());
+        participate_on_behalf(ctx, token_entries, integrator_program)
+    }
+
+    /// Participate with the user's signed permit, submitted and paid for by a relayer
+    ///
+    /// The transaction must carry an ed25519 instruction in which the user
+    /// signs `PERMIT_TAG || borsh(ParticipationPermit)` for this room and
+    /// round, committing to the hash of `token_entries` and an expiry. The
+    /// relayer signs as `delegate` and pays every fee and deposit; the entry
+    /// is the user's. A permit can only be used once, as the participation
+    /// account can only be created once.
+    pub fn participate_with_permit(ctx: Context<ParticipateFor>, token_entries: Vec<TokenEntry>) -> Result<()> {
+        let instructions = ctx
+            .accounts
+            .instructions
+            .as_deref()
+            .ok_or(RecoveryRoomError::MissingInstructionsSysvar)?;
+        attestation::require_participation_permit(
+            instructions,
+            &ctx.accounts.user.key(),
+            ctx.accounts.protocol_state.room_id,
+            ctx.accounts.round_state.round_id,
+            &hash(&token_entries.try_to_vec()?).to_bytes(),
+            Clock::get()?.unix_timestamp,
+        )?;
+        participate_on_behalf(ctx, token_entries, None)
+    }
+
+    /// Enter several users' permitted entries in one transaction, for relayers
+    /// batching submissions near round close
+    ///
+    /// Each batch item is one user's entries, signed for in a permit as in
+    /// participate_with_permit. Remaining accounts hold, per item: the user,
+    /// their participation PDA (not yet created), their user stats PDA, then
+    /// one metadata account, one holding token account and one PoolEntry per
+    /// entry, each group in entry order. The
+    /// relayer pays every fee, deposit and account. Rooms gated by an access
+    /// pass and ticketed rounds or rounds with SPL entry fees can't batch.
+    pub fn participate_batch<'info>(
+        ctx: Context<'_, '_, 'info, 'info, ParticipateBatch<'info>>,
+        batch: Vec<Vec<TokenEntry>>,
+    ) -> Result<()> {
+        require!(
+            !batch.is_empty() && batch.len() <= MAX_PARTICIPATE_BATCH,
+            RecoveryRoomError::InvalidParticipateBatch
+        );
+        let protocol = &ctx.accounts.protocol_state;
+        let round = &mut ctx.accounts.round_state;
+        require!(
+            protocol.access_pass.is_none() && round.entry_fee.mint.is_none(),
+            RecoveryRoomError::BatchUnsupported
+        );
+        require!(!round.ticketed, RecoveryRoomError::TicketsUnsupported);
+
+        let instructions = ctx.accounts.instructions.to_account_info();
+        let relayer = ctx.accounts.relayer.to_account_info();
+        let system_program = ctx.accounts.system_program.to_account_info();
+        let round_key = round.key();
+        let round_id = round.round_id;
+        let clock = Clock::get()?;
+        let ticker_registry = match &ctx.accounts.ticker_registry {
+            Some(registry) => Some(registry.load()?),
+            None => None,
+        };
+
+        let mut remaining = ctx.remaining_accounts;
+        for token_entries in batch {
+            let stride = BATCH_ITEM_ACCOUNTS + 3 * token_entries.len();
+            require!(remaining.len() >= stride, RecoveryRoomError::InvalidParticipateBatch);
+            let (item, rest) = remaining.split_at(stride);
+            remaining = rest;
+            let (user_info, participation_info, stats_info) = (&item[0], &item[1], &item[2]);
+            let (metadata_accounts, rest) = item[BATCH_ITEM_ACCOUNTS..].split_at(token_entries.len());
+            let (holding_accounts, pool_entry_accounts) = rest.split_at(token_entries.len());
+            let user = user_info.key();
+
+            attestation::require_participation_permit(
+                &instructions,
+                &user,
+                protocol.room_id,
+                round_id,
+                &hash(&token_entries.try_to_vec()?).to_bytes(),
+                clock.unix_timestamp,
+            )?;
+            let mut token_entries = token_entries;
+            token_entries.iter().try_for_each(TokenEntry::validate)?;
+            check_mint_lists(
+                protocol,
+                ctx.accounts.mint_blacklist.as_deref(),
+                ctx.accounts.mint_whitelist.as_deref(),
+                &token_entries,
+            )?;
+            check_rug_attestations(protocol, Some(&instructions), &token_entries)?;
+            attest_losses(protocol, Some(&instructions), &user, &mut token_entries)?;
+            canonicalize_tickers(&mut token_entries, metadata_accounts)?;
+            verify_holdings(&user, &token_entries, holding_accounts)?;
+
+            let room = room_seed(protocol.room_id);
+            let (stats_key, stats_bump) =
+                Pubkey::find_program_address(&[b"user_stats", &room, user.as_ref()], &crate::ID);
+            require_keys_eq!(stats_info.key(), stats_key, RecoveryRoomError::InvalidBatchAccount);
+            let mut user_stats = if stats_info.data_is_empty() {
+                init_pda::<UserStats>(
+                    stats_info,
+                    &relayer,
+                    &system_program,
+                    UserStats::SIZE,
+                    &[b"user_stats", &room, user.as_ref(), &[stats_bump]],
+                )?
+            } else {
+                Account::<UserStats>::try_from(stats_info)?
+            };
+            user_stats.require_off_cooldown(round_id)?;
+            enforce_rate_limit(&protocol.rate_limit, round, &mut user_stats, &clock)?;
+
+            let (participation_key, participation_bump) = Pubkey::find_program_address(
+                &[PARTICIPATION_SEED, round_key.as_ref(), user.as_ref()],
+                &crate::ID,
+            );
+            require_keys_eq!(
+                participation_info.key(),
+                participation_key,
+                RecoveryRoomError::InvalidBatchAccount
+            );
+            require!(participation_info.data_is_empty(), RecoveryRoomError::AlreadyParticipated);
+            let mut participation = init_pda::<Participation>(
+                participation_info,
+                &relayer,
+                &system_program,
+                Participation::SIZE,
+                &[PARTICIPATION_SEED, round_key.as_ref(), user.as_ref(), &[participation_bump]],
+            )?;
+
+            let (effective_weight, pool_total_weight, wallet_tickets) = record_entries(
+                protocol,
+                round,
+                pool_entry_accounts,
+                ctx.accounts.leaderboard.as_deref_mut(),
+                &token_entries,
+                1,
+                clock.unix_timestamp,
+            )?;
+            let entry_fee_paid = collect_entry_fee(
+                round,
+                &relayer,
+                user,
+                &mut ctx.accounts.prize_vault,
+                None,
+                None,
+                None,
+                None,
+                &mut user_stats.referrer,
+                0,
+                &ctx.accounts.token_program,
+                &ctx.accounts.system_program,
+            )?;
+            if round.entry_deposit > 0 {
+                system_program::transfer(
+                    CpiContext::new(
+                        system_program.clone(),
+                        system_program::Transfer {
+                            from: relayer.clone(),
+                            to: participation_info.clone(),
+                        },
+                    ),
+                    round.entry_deposit,
+                )?;
+            }
+
+            participation.user = user;
+            participation.round_id = round_id;
+            participation.tokens = token_entries.clone();
+            participation.timestamp = clock.unix_timestamp;
+            participation.bump = participation_bump;
+            participation.effective_weight = effective_weight;
+            participation.pool_total_weight = pool_total_weight;
+            participation.deposit = round.entry_deposit;
+            participation.entry_fee_paid = entry_fee_paid;
+            participation.version = PARTICIPATION_VERSION;
+            participation.weight_multiplier = 1;
+            participation.bonus_ticket_start = round.issue_bonus_tickets(token_entries.len())?;
+            participation.losses_attested = protocol.price_verifier.is_some();
+            participation.wallet_tickets = wallet_tickets;
+            participation.exit(&crate::ID)?;
+
+            let xp = user_stats.record_entry(user, stats_bump, round_id, &token_entries, &protocol.xp_rates)?;
+            emit_xp_awarded(&user_stats, round_id, xp);
+            user_stats.exit(&crate::ID)?;
+            record_season_entry(round, ctx.accounts.season.as_deref_mut(), &token_entries)?;
+            if let Some(registry) = &ticker_registry {
+                warn_ticker_collisions(registry, round_id, &token_entries)?;
+            }
+
+            emit!(UserParticipated {
+                round_id,
+                user,
+                token_count: token_entries.len() as u8,
+            });
+        }
+        require!(remaining.is_empty(), RecoveryRoomError::InvalidParticipateBatch);
+
+        msg!("Relayer {} entered a batch of participations", ctx.accounts.relayer.key());
+        Ok(())
+    }
+
+    /// Approve a signer to participate_for the caller, or None to revoke
+    pub fn set_participation_delegate(ctx: Context<SetParticipationDelegate>, delegate: Option<Pubkey>) -> Result<()> {
+        let delegation = &mut ctx.accounts.delegation;
+        delegation.user = ctx.accounts.user.key();
+        delegation.delegate = delegate;
+        delegation.bump = ctx.bumps.delegation;
+
+        msg!("Participation delegate set to {:?}", delegate);
+        Ok(())
+    }
+
+    /// Participate by appending a leaf to the protocol's compressed participation tree
+    ///
+    /// No Participation PDA is created; the leaf commits to (round, user, entries)
+    /// and is redeemed with a merkle proof in claim_prize_compressed. Metadata,
+    /// token and pool entry accounts are passed as in `participate`, and the
+    /// leaf commits to the canonical tickers.
+    pub fn participate_compressed(
+        ctx: Context<ParticipateCompressed>,
+        token_entries: Vec<TokenEntry>,
+    ) -> Result<()> {
+        let mut token_entries = token_entries;
+        token_entries.iter().try_for_each(TokenEntry::validate)?;
+        check_mint_lists(
+            &ctx.accounts.protocol_state,
+            ctx.accounts.mint_blacklist.as_deref(),
+            ctx.accounts.mint_whitelist.as_deref(),
+            &token_entries,
+        )?;
+        check_access_pass(
+            &ctx.accounts.protocol_state,
+            ctx.accounts.pass_account.as_deref(),
+            ctx.accounts.pass_metadata.as_deref(),
+        )?;
+        check_rug_attestations(
+            &ctx.accounts.protocol_state,
+            ctx.accounts.instructions.as_deref(),
+            &token_entries,
+        )?;
+        attest_losses(
+            &ctx.accounts.protocol_state,
+            ctx.accounts.instructions.as_deref(),
+            &ctx.accounts.user.key(),
+            &mut token_entries,
+        )?;
+        let (metadata_accounts, holding_accounts, pool_entry_accounts) =
+            split_entry_accounts(ctx.remaining_accounts, token_entries.len());
+        canonicalize_tickers(&mut token_entries, metadata_accounts)?;
+        verify_holdings(&ctx.accounts.user.key(), &token_entries, holding_accounts)?;
+        ctx.accounts.user_stats.require_off_cooldown(ctx.accounts.round_state.round_id)?;
+        enforce_rate_limit(
+            &ctx.accounts.protocol_state.rate_limit,
+            &mut ctx.accounts.round_state,
+            &mut ctx.accounts.user_stats,
+            &Clock::get()?,
+        )?;
+        let protocol = &ctx.accounts.protocol_state;
+        let round = &mut ctx.accounts.round_state;
+        let clock = Clock::get()?;
+        let user = ctx.accounts.user.key();
+
+        // There is no participation account to hold a deposit
+        require!(
+            round.entry_deposit == 0,
+            RecoveryRoomError::DepositNeedsParticipationAccount
+        );
+        require!(!round.ticketed, RecoveryRoomError::TicketsUnsupported);
+        // Leaves don't record wallet tickets, so no compressed entry could claim
+        require!(!round.wallet_draw, RecoveryRoomError::WalletDrawUnsupported);
+
+        let entry = &mut ctx.accounts.compressed_entry;
+        entry.user = user;
+        entry.round_id = round.round_id;
+        entry.bump = ctx.bumps.compressed_entry;
+
+        // Leaves don't commit to a stake multiplier, so compressed entries count once
+        let (effective_weight, pool_total_weight, _) = record_entries(
+            protocol,
+            round,
+            pool_entry_accounts,
+            ctx.accounts.leaderboard.as_deref_mut(),
+            &token_entries,
+            1,
+            clock.unix_timestamp,
+        )?;
+
+        let entry_fee_paid = collect_entry_fee(
+            round,
+            &ctx.accounts.user.to_account_info(),
+            ctx.accounts.user.key(),
+            &mut ctx.accounts.prize_vault,
+            ctx.accounts.user_fee_account.as_ref(),
+            ctx.accounts.vault_fee_account.as_ref(),
+            ctx.accounts.fee_mint.as_ref(),
+            ctx.accounts.referral.as_mut(),
+            &mut ctx.accounts.user_stats.referrer,
+            protocol.referral_fee_bps,
+            &ctx.accounts.token_program,
+            &ctx.accounts.system_program,
+        )?;
+        ctx.accounts.compressed_entry.entry_fee_paid = entry_fee_paid;
+
+        let leaf = participation_leaf(round.round_id, &user, &token_entries)?;
+        let tree_authority_seeds = &[b"tree_authority".as_ref(), &[ctx.bumps.tree_authority]];
+        compression::append(
+            &compression::TreeAccounts {
+                compression_program: &ctx.accounts.compression_program.to_account_info(),
+                merkle_tree: &ctx.accounts.merkle_tree.to_account_info(),
+                authority: &ctx.accounts.tree_authority.to_account_info(),
+                noop_program: &ctx.accounts.noop_program.to_account_info(),
+            },
+            leaf,
+            &[&tree_authority_seeds[..]],
+        )?;
+
+        let xp = ctx.accounts.user_stats.record_entry(
+            user,
+            ctx.bumps.user_stats,
+            round.round_id,
+            &token_entries,
+            &protocol.xp_rates,
+        )?;
+        emit_xp_awarded(&ctx.accounts.user_stats, round.round_id, xp);
+        record_season_entry(round, ctx.accounts.season.as_deref_mut(), &token_entries)?;
+
+        let protocol = &mut ctx.accounts.protocol_state;
+        let leaf_index = protocol.participation_tree_leaves;
+        protocol.participation_tree_leaves = leaf_index.checked_add(1).ok_or(RecoveryRoomError::ArithmeticOverflow)?;
+
+        emit!(CompressedParticipation {
+            round_id: round.round_id,
+            user,
+            leaf,
+            leaf_index,
+            effective_weight,
+            pool_total_weight,
+        });
+
+        msg!("User participated (compressed leaf {}) with {} tokens", leaf_index, token_entries.len());
+        Ok(())
+    }
+
+    /// Request VRF randomness when round ends (called by crank), once every
+    /// pool entry is tallied. A sharded round passes its shard chain as
+    /// remaining accounts.
     pub fn request_randomness(ctx: Context<RequestRandomness>) -> Result<()> {
+        ctx.accounts.protocol_state.require_crank(
+            Some(ctx.accounts.payer.key()),
+            ctx.accounts.thread.is_some(),
+            ctx.accounts.keeper_registry.as_deref_mut(),
+            CrankInstruction::RequestRandomness,
+            Clock::get()?.unix_timestamp,
+        )?;
+        if let Some(thread) = &ctx.accounts.thread {
+            require_keys_eq!(
+                thread.key(),
+                ctx.accounts.protocol_state.request_thread,
+                RecoveryRoomError::UnauthorizedThread
+            );
+            // Nothing to draw for a cancelled round; move the thread along
+            if ctx.accounts.round_state.status == RoundStatus::Cancelled {
+                return respond_with_next_request(ctx.accounts);
+            }
+        }
+
+        require!(
+            ctx.accounts.protocol_state.randomness_mode == RandomnessMode::Vrf,
+            RecoveryRoomError::WrongRandomnessMode
+        );
         let round = &mut ctx.accounts.round_state;
         let clock = Clock::get()?;
 
@@ -144,21 +1023,80 @@ pub mod recovery_room {
             clock.unix_timestamp >= round.end_time,
             RecoveryRoomError::RoundNotEnded
         );
+        // A request left unanswered for VRF_TIMEOUT_SECS may be retried
+        let is_retry = round.status == RoundStatus::VrfRequested;
         require!(
-            round.status == RoundStatus::Active,
+            round.status == RoundStatus::Active
+                || (is_retry && clock.unix_timestamp >= round.vrf_requested_at.saturating_add(VRF_TIMEOUT_SECS)),
             RecoveryRoomError::InvalidRoundStatus
         );
+        require_detail!(
+            RecoveryRoomError::BelowMinParticipants,
+            "participants",
+            round.total_participants,
+            at_least round.min_participants
+        );
         require!(
             round.total_token_entries > 0,
             RecoveryRoomError::NoParticipants
         );
+        require!(
+            round.pool_entries_tallied == round.pool_entry_count,
+            RecoveryRoomError::PoolEntriesUntallied
+        );
+
+        // Pick the oracle queue: the primary, or the fallback once the primary has
+        // failed FAILOVER_THRESHOLD times in a row and the authority approved failover
+        {
+            let protocol = &mut ctx.accounts.protocol_state;
+            if is_retry && round.oracle_queue == protocol.oracle_queue {
+                protocol.primary_queue_failures = protocol.primary_queue_failures.saturating_add(1);
+            }
+
+            let queue = ctx.accounts.oracle_queue.key();
+            if protocol.oracle_queue != Pubkey::default() && queue != protocol.oracle_queue {
+                require!(
+                    protocol.fallback_oracle_queue == Some(queue)
+                        && protocol.failover_approved
+                        && protocol.primary_queue_failures >= FAILOVER_THRESHOLD,
+                    RecoveryRoomError::OracleQueueNotAllowed
+                );
+                emit!(OracleFailover {
+                    round_id: round.round_id,
+                    oracle_queue: queue,
+                    primary_failures: protocol.primary_queue_failures,
+                });
+            }
+
+            round.oracle_queue = queue;
+            round.vrf_requested_at = clock.unix_timestamp;
+            round.vrf_request_attempts = round.vrf_request_attempts.saturating_add(1);
+        }
+
+        // Commit the fallback slot on the first request, and again on a retry
+        // once the committed slot's hash has aged out of SlotHashes
+        let fallback_expired = clock.slot > round.fallback_slot.saturating_add(slot_hashes::MAX_ENTRIES as u64);
+        if round.fallback_slot == 0 || fallback_expired {
+            round.fallback_slot = clock.slot.saturating_add(VRF_FALLBACK_DELAY_SLOTS);
+        }
 
-        // Update status
+        // Update status, then freeze the weight table and commit to it
         round.status = RoundStatus::VrfRequested;
+        {
+            let mut token_pool = ctx.accounts.token_pool.load_pool_mut()?;
+            token_pool.header.locked = 1;
+            let shards = load_shard_chain(round, ctx.remaining_accounts)?;
+            require!(
+                qualified_token_count(&pool_pages(token_pool.entries(), &shards), round.min_submissions_to_qualify) > 0,
+                RecoveryRoomError::NoQualifyingTokens
+            );
+            round.pool_root = pool_root(token_pool.weight_root(), ctx.remaining_accounts, &shards);
+        }
+        let pool_root = round.pool_root;
 
-        // Request randomness from Switchboard VRF
-        let vrf = ctx.accounts.vrf.load()?;
-        let oracle_queue = ctx.accounts.oracle_queue.load()?;
+        // Request randomness from Switchboard VRF. The request bumps the VRF's
+        // counter; only a result carrying that counter answers this request.
+        round.vrf_counter = ctx.accounts.vrf.load()?.counter.checked_add(1).ok_or(RecoveryRoomError::ArithmeticOverflow)?;
 
         // Build VRF request
         let request_randomness_ctx = VrfRequestRandomness {
@@ -177,8 +1115,10 @@ pub mod recovery_room {
         };
 
         // Sign with protocol PDA
+        let room = room_seed(ctx.accounts.protocol_state.room_id);
         let protocol_seeds = &[
-            b"protocol".as_ref(),
+            PROTOCOL_SEED,
+            &room,
             &[ctx.accounts.protocol_state.bump],
         ];
         let signer_seeds = &[&protocol_seeds[..]];
@@ -188,26 +1128,60 @@ pub mod recovery_room {
             signer_seeds,
         )?;
 
-        emit!(VrfRequested {
-            round_id: round.round_id,
+        let round_id = round.round_id;
+        pay_keeper_bounty(
+            &mut ctx.accounts.treasury,
+            &ctx.accounts.payer.to_account_info(),
+            ctx.accounts.protocol_state.keeper_bounties.request_randomness,
+            CrankInstruction::RequestRandomness,
+            round_id,
+        )?;
+
+        // Set after the Switchboard CPI, which may write return data of its own
+        if ctx.accounts.thread.is_some() {
+            respond_with_next_request(ctx.accounts)?;
+        }
+
+        emit_indexed!(ctx, VrfRequested {
+            round_id,
             timestamp: clock.unix_timestamp,
+            pool_root,
         });
 
-        msg!("VRF randomness requested for round {}", round.round_id);
+        msg!("VRF randomness requested for round {}", round_id);
         Ok(())
     }
 
-    /// Consume VRF result and determine winner (callback from Switchboard)
+    /// Store the round's VRF result and hand it to finalize_round (callback
+    /// from Switchboard). Drawing is left to finalize_round so that walking a
+    /// large weight table gets a compute budget of its own.
     pub fn consume_randomness(ctx: Context<ConsumeRandomness>) -> Result<()> {
+        ctx.accounts.protocol_state.require_crank(
+            ctx.accounts.keeper.as_ref().map(|k| k.key()),
+            false,
+            ctx.accounts.keeper_registry.as_deref_mut(),
+            CrankInstruction::ConsumeRandomness,
+            Clock::get()?.unix_timestamp,
+        )?;
         let round = &mut ctx.accounts.round_state;
 
         require!(
             round.status == RoundStatus::VrfRequested,
             RecoveryRoomError::InvalidRoundStatus
         );
+        // Past the fallback slot its hash is known, so whoever settles first
+        // could pick between it and the VRF result; only the fallback applies
+        require!(
+            round.fallback_slot == 0 || Clock::get()?.slot <= round.fallback_slot,
+            RecoveryRoomError::FallbackWindowOpen
+        );
 
-        // Get VRF result
+        // Get VRF result, rejecting one left over from an earlier request
         let vrf = ctx.accounts.vrf.load()?;
+        require!(
+            vrf.counter == round.vrf_counter,
+            RecoveryRoomError::StaleVrfResult
+        );
         let result_buffer = vrf.get_result()?;
 
         require!(
@@ -215,351 +1189,12581 @@ pub mod recovery_room {
             RecoveryRoomError::VrfNotResolved
         );
 
-        // Convert VRF result to u128 for weighted selection
-        let vrf_value = u128::from_le_bytes(result_buffer[0..16].try_into().unwrap());
-
         // Store VRF result
         round.vrf_result = Some(result_buffer);
+        round.status = RoundStatus::Settling;
 
-        // Calculate winner using sqrt-weighted selection
-        let winner_token = select_winner_sqrt_weighted(
-            &ctx.accounts.token_pool,
-            vrf_value,
-        )?;
+        // A fulfilled primary request clears its failure streak
+        if round.oracle_queue == ctx.accounts.protocol_state.oracle_queue {
+            ctx.accounts.protocol_state.primary_queue_failures = 0;
+        }
 
-        round.winner_token = Some(winner_token);
-        round.status = RoundStatus::Complete;
+        // Track request -> consume latency for the oracle SLO
+        let latency = Clock::get()?.unix_timestamp.saturating_sub(round.vrf_requested_at);
+        ctx.accounts.metrics.record_vrf_latency(latency.clamp(0, u32::MAX as i64) as u32);
 
-        // Update protocol stats
-        let protocol = &mut ctx.accounts.protocol_state;
-        protocol.total_rounds_completed += 1;
+        let round_id = round.round_id;
+        if let Some(keeper) = &ctx.accounts.keeper {
+            pay_keeper_bounty(
+                &mut ctx.accounts.treasury,
+                &keeper.to_account_info(),
+                ctx.accounts.protocol_state.keeper_bounties.consume_randomness,
+                CrankInstruction::ConsumeRandomness,
+                round_id,
+            )?;
+        }
 
-        emit!(RoundComplete {
-            round_id: round.round_id,
-            winner_token,
+        emit_indexed!(ctx, RandomnessConsumed {
+            round_id,
             vrf_result: result_buffer,
         });
 
-        msg!("Round {} complete! Winner: {:?}", round.round_id, winner_token);
+        msg!("Round {} randomness consumed, awaiting finalize", round_id);
         Ok(())
     }
-}
 
-/// Select winner using sqrt-weighted probabilities
-/// Weight = sqrt(submissions), Probability = weight / total_weight
-fn select_winner_sqrt_weighted(
-    token_pool: &Account<TokenPool>,
-    vrf_value: u128,
-) -> Result<Pubkey> {
-    let mut total_weight: f64 = 0.0;
-    let mut weights: Vec<(Pubkey, f64)> = Vec::new();
-
-    // Calculate sqrt weights for each token
-    for entry in &token_pool.entries {
-        if entry.submission_count > 0 {
-            let weight = (entry.submission_count as f64).sqrt();
-            total_weight += weight;
-            weights.push((entry.token_mint, weight));
-        }
-    }
+    /// Commit to keccak(preimage) for a commit-reveal room's active round
+    /// (crank). It must come before the round ends, and the room must have a
+    /// crank authority or keepers, so no entrant can pick the preimage. A
+    /// reveal withheld past REVEAL_TIMEOUT_SECS lets void_round void the round.
+    pub fn commit_randomness(ctx: Context<CommitRandomness>, commitment: [u8; 32]) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        ctx.accounts.protocol_state.require_permissioned_crank()?;
+        ctx.accounts.protocol_state.require_crank(
+            Some(ctx.accounts.crank.key()),
+            false,
+            ctx.accounts.keeper_registry.as_deref_mut(),
+            CrankInstruction::RequestRandomness,
+            now,
+        )?;
+        require!(
+            ctx.accounts.protocol_state.randomness_mode == RandomnessMode::CommitReveal,
+            RecoveryRoomError::WrongRandomnessMode
+        );
+        let round = &mut ctx.accounts.round_state;
+        require!(
+            round.status == RoundStatus::Active,
+            RecoveryRoomError::InvalidRoundStatus
+        );
+        require!(now < round.end_time, RecoveryRoomError::RoundEnded);
+        require!(round.committed_at == 0, RecoveryRoomError::RandomnessAlreadyCommitted);
+        require!(commitment != [0; 32], RecoveryRoomError::InvalidReveal);
 
-    require!(total_weight > 0.0, RecoveryRoomError::NoParticipants);
+        round.randomness_commitment = commitment;
+        round.committed_at = now;
 
-    // Normalize VRF to 0-1 range
-    let normalized = (vrf_value as f64) / (u128::MAX as f64);
-    let target = normalized * total_weight;
+        emit!(RandomnessCommitted {
+            round_id: round.round_id,
+            commitment,
+        });
 
-    // Find winning token
-    let mut accumulated = 0.0;
-    for (token, weight) in weights {
-        accumulated += weight;
-        if target <= accumulated {
-            return Ok(token);
-        }
+        msg!("Round {} randomness committed", round.round_id);
+        Ok(())
     }
 
-    // Fallback to last token (shouldn't happen with proper math)
-    Ok(token_pool.entries.last().unwrap().token_mint)
-}
-
-// ============ Account Structures ============
+    /// Fix the slot whose hash salts an ended, committed round's reveal
+    /// (permissionless). The slot lies SALT_SLOT_DELAY slots ahead, so its
+    /// hash is unknown to the crank holding the preimage, and it can't be
+    /// sealed again.
+    pub fn seal_randomness(ctx: Context<SealRandomness>) -> Result<()> {
+        require!(
+            ctx.accounts.protocol_state.randomness_mode == RandomnessMode::CommitReveal,
+            RecoveryRoomError::WrongRandomnessMode
+        );
+        let clock = Clock::get()?;
+        let round = &mut ctx.accounts.round_state;
+        require!(
+            round.status == RoundStatus::Active,
+            RecoveryRoomError::InvalidRoundStatus
+        );
+        require!(clock.unix_timestamp >= round.end_time, RecoveryRoomError::RoundNotEnded);
+        require!(round.committed_at > 0, RecoveryRoomError::RandomnessNotCommitted);
+        require!(round.salt_slot == 0, RecoveryRoomError::RandomnessAlreadySealed);
 
-#[account]
-pub struct ProtocolState {
-    pub authority: Pubkey,
-    pub round_duration: i64,
-    pub min_loss_percentage: u8,
-    pub max_tokens_per_user: u8,
-    pub current_round: u64,
-    pub total_rounds_completed: u64,
-    pub bump: u8,
-}
+        round.salt_slot = clock
+            .slot
+            .checked_add(SALT_SLOT_DELAY)
+            .ok_or(RecoveryRoomError::ArithmeticOverflow)?;
 
+        emit!(RandomnessSealed {
+            round_id: round.round_id,
+            salt_slot: round.salt_slot,
+        });
+
+        msg!("Round {} reveal salted from slot {}", round.round_id, round.salt_slot);
+        Ok(())
+    }
+
+    /// Reveal the preimage of an ended round's commitment at least
+    /// COMMIT_REVEAL_DELAY_SECS after committing, once its salt slot has
+    /// passed (crank). This freezes the weight table as request_randomness
+    /// would and stores keccak(preimage || slot hash) as the round's result
+    /// for finalize_round. If the salt slot ages out of SlotHashes first, the
+    /// round can only be voided. A sharded round passes its shard chain as
+    /// remaining accounts.
+    pub fn reveal_randomness(ctx: Context<RevealRandomness>, preimage: [u8; 32]) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        ctx.accounts.protocol_state.require_permissioned_crank()?;
+        ctx.accounts.protocol_state.require_crank(
+            Some(ctx.accounts.crank.key()),
+            false,
+            ctx.accounts.keeper_registry.as_deref_mut(),
+            CrankInstruction::ConsumeRandomness,
+            now,
+        )?;
+        require!(
+            ctx.accounts.protocol_state.randomness_mode == RandomnessMode::CommitReveal,
+            RecoveryRoomError::WrongRandomnessMode
+        );
+        let round = &mut ctx.accounts.round_state;
+        require!(
+            round.status == RoundStatus::Active,
+            RecoveryRoomError::InvalidRoundStatus
+        );
+        require!(now >= round.end_time, RecoveryRoomError::RoundNotEnded);
+        require!(
+            round.committed_at > 0 && now >= round.committed_at.saturating_add(COMMIT_REVEAL_DELAY_SECS),
+            RecoveryRoomError::RevealTooEarly
+        );
+        require!(
+            keccak::hash(&preimage).to_bytes() == round.randomness_commitment,
+            RecoveryRoomError::InvalidReveal
+        );
+        require!(
+            round.salt_slot > 0 && Clock::get()?.slot > round.salt_slot,
+            RecoveryRoomError::RevealTooEarly
+        );
+        let (salt_slot, slot_hash) = first_slot_hash_from(&ctx.accounts.slot_hashes, round.salt_slot)?
+            .ok_or(RecoveryRoomError::SaltSlotExpired)?;
+        require_detail!(
+            RecoveryRoomError::BelowMinParticipants,
+            "participants",
+            round.total_participants,
+            at_least round.min_participants
+        );
+        require!(
+            round.total_token_entries > 0,
+            RecoveryRoomError::NoParticipants
+        );
+        require!(
+            round.pool_entries_tallied == round.pool_entry_count,
+            RecoveryRoomError::PoolEntriesUntallied
+        );
+
+        // Freeze the weight table and commit to it
+        {
+            let mut token_pool = ctx.accounts.token_pool.load_pool_mut()?;
+            token_pool.header.locked = 1;
+            let shards = load_shard_chain(round, ctx.remaining_accounts)?;
+            require!(
+                qualified_token_count(&pool_pages(token_pool.entries(), &shards), round.min_submissions_to_qualify) > 0,
+                RecoveryRoomError::NoQualifyingTokens
+            );
+            round.pool_root = pool_root(token_pool.weight_root(), ctx.remaining_accounts, &shards);
+        }
+
+        round.reveal_salt = slot_hash;
+        let result = keccak::hashv(&[&preimage, &round.reveal_salt]).to_bytes();
+        round.vrf_result = Some(result);
+        round.status = RoundStatus::Settling;
+
+        emit!(RandomnessRevealed {
+            round_id: round.round_id,
+            preimage,
+            salt_slot,
+            slot_hash,
+            result,
+            pool_root: round.pool_root,
+        });
+
+        msg!("Round {} randomness revealed, awaiting finalize", round.round_id);
+        Ok(())
+    }
+
+    /// Settle a round whose VRF request went unanswered past its fallback slot
+    /// from that slot's hash instead (permissionless)
+    ///
+    /// The hash of the first block at or after the slot committed at
+    /// request_randomness stands in for the VRF result, so finalize_round
+    /// draws as usual. A slot hash is weaker randomness than VRF, as the
+    /// slot's leader has some influence over it, so this is only for oracle
+    /// outages. Once the slot passes, consume_randomness rejects the VRF
+    /// result, so a settler can't choose between the two. If the slot has
+    /// aged out of SlotHashes, a request_randomness retry commits a new one.
+    pub fn resolve_with_slothash(ctx: Context<ResolveWithSlothash>) -> Result<()> {
+        let round = &mut ctx.accounts.round_state;
+        require!(
+            round.status == RoundStatus::VrfRequested,
+            RecoveryRoomError::InvalidRoundStatus
+        );
+        let slot = Clock::get()?.slot;
+        require!(
+            round.fallback_slot > 0 && slot > round.fallback_slot,
+            RecoveryRoomError::FallbackSlotNotReached
+        );
+        let (hashed_slot, slot_hash) = first_slot_hash_from(&ctx.accounts.slot_hashes, round.fallback_slot)?
+            .ok_or(RecoveryRoomError::FallbackSlotExpired)?;
+
+        let result = keccak::hashv(&[b"slothash-fallback", &slot_hash, &round.round_id.to_le_bytes()]).to_bytes();
+        round.vrf_result = Some(result);
+        round.slothash_resolved = true;
+        round.status = RoundStatus::Settling;
+
+        emit!(SlotHashFallbackResolved {
+            round_id: round.round_id,
+            slot: hashed_slot,
+            slot_hash,
+            result,
+        });
+
+        msg!("Round {} randomness resolved from slot {}", round.round_id, hashed_slot);
+        Ok(())
+    }
+
+    /// Draw a settling round's winners from its stored VRF result and freeze
+    /// the payouts (permissionless; the result is already fixed)
+    pub fn finalize_round(ctx: Context<FinalizeRound>) -> Result<()> {
+        let round = &mut ctx.accounts.round_state;
+
+        require!(
+            round.status == RoundStatus::Settling,
+            RecoveryRoomError::InvalidRoundStatus
+        );
+        let result_buffer = round.vrf_result.ok_or(RecoveryRoomError::VrfNotResolved)?;
+
+        let tier_bps = ctx.accounts.protocol_state.current_rules(round).tier_bps;
+        let token_pool = ctx.accounts.token_pool.load_pool()?;
+        let shards = load_shard_chain(round, ctx.remaining_accounts)?;
+        require!(
+            pool_root(token_pool.weight_root(), ctx.remaining_accounts, &shards) == round.pool_root,
+            RecoveryRoomError::PoolRootMismatch
+        );
+        let pages = pool_pages(token_pool.entries(), &shards);
+        let winners = draw_winners(
+            &pages,
+            round.weighting,
+            round.min_submissions_to_qualify,
+            &tier_bps,
+            &result_buffer,
+        )?;
+        let winner_token = winners[0];
+
+        round.winner_token = Some(winner_token);
+        round.status = RoundStatus::Complete;
+        round.rules = ctx.accounts.protocol_state.current_rules(round);
+        let claim_window = ctx.accounts.protocol_state.claim_window_secs;
+        if claim_window > 0 {
+            round.claim_deadline = Clock::get()?
+                .unix_timestamp
+                .checked_add(claim_window)
+                .ok_or(RecoveryRoomError::ArithmeticOverflow)?;
+        }
+
+        let prize_pool = match round.stable_prize {
+            // A stable round pays its fixed prize; SOL in its vault carries
+            // over to the next round through the jackpot
+            Some(stable) => {
+                let prize_vault = &mut ctx.accounts.prize_vault;
+                let carried = prize_vault.lamports;
+                if carried > 0 {
+                    move_lamports(&prize_vault.to_account_info(), &ctx.accounts.jackpot.to_account_info(), carried)?;
+                    prize_vault.lamports = 0;
+                    ctx.accounts.jackpot.balance = ctx.accounts.jackpot.balance.checked_add(carried).ok_or(RecoveryRoomError::ArithmeticOverflow)?;
+                }
+                stable.amount
+            }
+            None => {
+                // A scheduled event's multiplier tops the prize up from the treasury
+                if round.prize_multiplier_bps as u64 > BPS_DENOMINATOR {
+                    boost_prize(
+                        &mut ctx.accounts.treasury,
+                        &mut ctx.accounts.prize_vault,
+                        round.prize_multiplier_bps,
+                        round.round_id,
+                    )?;
+                }
+                ctx.accounts.prize_vault.lamports
+            }
+        };
+
+        // A SOL prize sets the bonus share aside for one participant ticket,
+        // drawn independently of the token draw
+        if round.stable_prize.is_none() && round.bonus_tickets > 0 {
+            round.bonus_prize = mul_div(
+                prize_pool,
+                ctx.accounts.protocol_state.bonus_prize_bps as u64,
+                BPS_DENOMINATOR,
+            )?;
+            round.bonus_ticket = bonus_draw(&result_buffer, round.bonus_tickets);
+        }
+        let prize_pool = prize_pool.checked_sub(round.bonus_prize).ok_or(RecoveryRoomError::ArithmeticOverflow)?;
+
+        // Freeze the payout basis: each tier's prize is split across every
+        // submission of that tier's token. Shares of undrawn tiers are
+        // redistributed across the drawn ones.
+        let drawn_bps: u64 = tier_bps[..winners.len()].iter().map(|b| *b as u64).sum();
+        round.prize_pool = prize_pool;
+        round.winner_count = winners.len() as u8;
+        for (tier, winner) in winners.iter().enumerate() {
+            round.winner_tokens[tier] = *winner;
+            round.tier_prizes[tier] = mul_div(prize_pool, tier_bps[tier] as u64, drawn_bps)?;
+            round.tier_submissions[tier] = pages
+                .iter()
+                .flat_map(|p| p.iter())
+                .find(|p| p.token_mint == *winner)
+                .map_or(0, |p| p.submission_count);
+        }
+
+        // Second stage: one cent of the winning token's attested total loss,
+        // whose entry's wallet takes the prize
+        if round.wallet_draw {
+            let total_loss_usd = pages
+                .iter()
+                .flat_map(|p| p.iter())
+                .find(|p| p.token_mint == winner_token)
+                .map_or(0, |p| p.total_loss_usd);
+            round.wallet_ticket = wallet_draw(&result_buffer, total_loss_usd);
+            emit!(WalletTicketDrawn {
+                round_id: round.round_id,
+                winner_token,
+                ticket: round.wallet_ticket,
+                total_loss_usd,
+            });
+        }
+
+        // Update protocol stats
+        let protocol = &mut ctx.accounts.protocol_state;
+        protocol.total_rounds_completed = protocol.total_rounds_completed.checked_add(1).ok_or(RecoveryRoomError::ArithmeticOverflow)?;
+
+        let round_id = round.round_id;
+        emit_indexed!(ctx, RoundComplete {
+            round_id,
+            winner_token,
+            vrf_result: result_buffer,
+            tier_winners: winners,
+        });
+
+        msg!("Round {} complete! Winner: {:?}", round_id, winner_token);
+        Ok(())
+    }
+
+    /// finalize_round and start_round in one instruction, so the next round
+    /// opens the moment the previous one settles. The next round runs the
+    /// protocol's defaults (or a due scheduled event).
+    pub fn settle_and_start<'info>(ctx: Context<'_, '_, '_, 'info, SettleAndStart<'info>>) -> Result<()> {
+        let accounts = ctx.accounts;
+        // The round being started must follow the one being settled
+        require!(
+            accounts
+                .start
+                .previous_round
+                .as_ref()
+                .is_some_and(|r| r.key() == accounts.finalize.round_state.key()),
+            RecoveryRoomError::PreviousRoundNotComplete
+        );
+
+        finalize_round(Context::new(
+            ctx.program_id,
+            &mut accounts.finalize,
+            ctx.remaining_accounts,
+            ctx.bumps.finalize,
+        ))?;
+
+        // Accounts both halves share were deserialized before the settle;
+        // persist it and pick up what it changed
+        accounts.finalize.exit(ctx.program_id)?;
+        accounts.start.protocol_state.reload()?;
+        accounts.start.treasury.reload()?;
+        accounts.start.jackpot.reload()?;
+        if let Some(previous_round) = accounts.start.previous_round.as_mut() {
+            previous_round.reload()?;
+        }
+
+        start_round(
+            Context::new(ctx.program_id, &mut accounts.start, ctx.remaining_accounts, ctx.bumps.start),
+            None,
+            None,
+        )
+    }
+
+    /// Cancel an ended round that drew no entries, rolling its prize into the jackpot
+    pub fn cancel_round(ctx: Context<CancelRound>) -> Result<()> {
+        let round = &mut ctx.accounts.round_state;
+        let prize_vault = &mut ctx.accounts.prize_vault;
+        let jackpot = &mut ctx.accounts.jackpot;
+        let clock = Clock::get()?;
+
+        require!(
+            clock.unix_timestamp >= round.end_time,
+            RecoveryRoomError::RoundNotEnded
+        );
+        require!(
+            round.status == RoundStatus::Active,
+            RecoveryRoomError::InvalidRoundStatus
+        );
+        require!(
+            round.total_token_entries == 0,
+            RecoveryRoomError::RoundHasParticipants
+        );
+
+        round.status = RoundStatus::Cancelled;
+
+        let amount = prize_vault.lamports;
+        if amount > 0 {
+            move_lamports(&prize_vault.to_account_info(), &jackpot.to_account_info(), amount)?;
+            prize_vault.lamports = 0;
+            jackpot.balance = jackpot.balance.checked_add(amount).ok_or(RecoveryRoomError::ArithmeticOverflow)?;
+        }
+
+        emit!(RoundCancelled {
+            round_id: round.round_id,
+            rolled_to_jackpot: amount,
+        });
+
+        msg!("Round {} cancelled, {} lamports rolled into jackpot", round.round_id, amount);
+        Ok(())
+    }
+
+    /// Void an ended round that drew fewer than its minimum participants, in
+    /// which no token reached min_submissions_to_qualify, or whose randomness
+    /// commitment went unrevealed for REVEAL_TIMEOUT_SECS past its end. The
+    /// qualifying check needs the round's token pool, and a sharded round its
+    /// shard chain as remaining accounts.
+    ///
+    /// No VRF is requested and no prize can be claimed. Entry fees stay in
+    /// the vault for entrants to reclaim with claim_void_refund, SOL in its
+    /// lamports and SPL in its fee token account; the rest of its lamports
+    /// roll into the jackpot. Burned SPL fees can't be refunded.
+    pub fn void_round(ctx: Context<CancelRound>) -> Result<()> {
+        let round = &mut ctx.accounts.round_state;
+        let prize_vault = &mut ctx.accounts.prize_vault;
+        let jackpot = &mut ctx.accounts.jackpot;
+        let clock = Clock::get()?;
+
+        require!(
+            clock.unix_timestamp >= round.end_time,
+            RecoveryRoomError::RoundNotEnded
+        );
+        require!(
+            round.status == RoundStatus::Active,
+            RecoveryRoomError::InvalidRoundStatus
+        );
+        let unqualified = match &ctx.accounts.token_pool {
+            Some(token_pool) => {
+                let token_pool = token_pool.load_pool()?;
+                let shards = load_shard_chain(round, ctx.remaining_accounts)?;
+                let pages = pool_pages(token_pool.entries(), &shards);
+                qualified_token_count(&pages, round.min_submissions_to_qualify) == 0
+            }
+            None => false,
+        };
+        let reveal_withheld = round.committed_at > 0
+            && clock.unix_timestamp >= round.end_time.saturating_add(REVEAL_TIMEOUT_SECS);
+        require!(
+            round.total_participants < round.min_participants || unqualified || reveal_withheld,
+            RecoveryRoomError::MinParticipantsReached
+        );
+
+        round.status = RoundStatus::Cancelled;
+        round.refunds_outstanding = if round.entry_fee.mint.is_some() && round.entry_fee.burn {
+            0
+        } else {
+            round.entry_fees_collected
+        };
+        let held_lamports = match round.entry_fee.mint {
+            Some(_) => 0,
+            None => round.refunds_outstanding,
+        };
+
+        let amount = prize_vault
+            .lamports
+            .checked_sub(held_lamports)
+            .ok_or(RecoveryRoomError::ArithmeticOverflow)?;
+        if amount > 0 {
+            move_lamports(&prize_vault.to_account_info(), &jackpot.to_account_info(), amount)?;
+            prize_vault.lamports = held_lamports;
+            jackpot.balance = jackpot.balance.checked_add(amount).ok_or(RecoveryRoomError::ArithmeticOverflow)?;
+        }
+
+        emit!(RoundVoided {
+            round_id: round.round_id,
+            participants: round.total_participants,
+            rolled_to_jackpot: amount,
+            refundable: round.refunds_outstanding,
+        });
+
+        msg!(
+            "Round {} voided with {} of {} participants, {} lamports rolled into jackpot",
+            round.round_id,
+            round.total_participants,
+            round.min_participants,
+            amount
+        );
+        Ok(())
+    }
+
+    /// Refund a participation's entry fee once its round was voided
+    /// (permissionless; the user receives it). An SPL fee is paid to the
+    /// user's token account for the fee mint.
+    pub fn claim_void_refund(ctx: Context<ClaimVoidRefund>) -> Result<()> {
+        let participation = &mut ctx.accounts.participation;
+        refund_entry_fee(
+            &mut ctx.accounts.round_state,
+            &mut ctx.accounts.prize_vault,
+            &ctx.accounts.user.to_account_info(),
+            ctx.accounts.user_fee_account.as_ref(),
+            ctx.accounts.vault_fee_account.as_ref(),
+            ctx.accounts.fee_mint.as_ref(),
+            ctx.accounts.token_program.as_ref(),
+            participation.entry_fee_paid,
+        )?;
+        participation.entry_fee_paid = 0;
+        Ok(())
+    }
+
+    /// Refund a compressed entry's entry fee once its round was voided
+    /// (permissionless; the user receives it), as claim_void_refund does
+    pub fn claim_void_refund_compressed(ctx: Context<ClaimVoidRefundCompressed>) -> Result<()> {
+        let entry = &mut ctx.accounts.compressed_entry;
+        refund_entry_fee(
+            &mut ctx.accounts.round_state,
+            &mut ctx.accounts.prize_vault,
+            &ctx.accounts.user.to_account_info(),
+            ctx.accounts.user_fee_account.as_ref(),
+            ctx.accounts.vault_fee_account.as_ref(),
+            ctx.accounts.fee_mint.as_ref(),
+            ctx.accounts.token_program.as_ref(),
+            entry.entry_fee_paid,
+        )?;
+        entry.entry_fee_paid = 0;
+        Ok(())
+    }
+
+    /// Permissionless deposit of SOL and/or SPL tokens into a round's prize vault
+    ///
+    /// A Token-2022 mint's transfer hook accounts are passed as remaining
+    /// accounts. The sponsor is credited what the vault received, net of any
+    /// transfer fee.
+    pub fn fund_prize_pool<'info>(
+        ctx: Context<'_, '_, '_, 'info, FundPrizePool<'info>>,
+        lamports: u64,
+        token_amount: u64,
+        memo: Option<String>,
+    ) -> Result<()> {
+        let round = &ctx.accounts.round_state;
+
+        require!(
+            round.status == RoundStatus::Active || round.status == RoundStatus::VrfRequested,
+            RecoveryRoomError::InvalidRoundStatus
+        );
+        require!(
+            lamports > 0 || token_amount > 0,
+            RecoveryRoomError::ZeroFundingAmount
+        );
+        if let Some(memo) = &memo {
+            require_detail!(
+                RecoveryRoomError::MemoTooLong,
+                "memo_len",
+                memo.len(),
+                at_most MAX_SPONSOR_MEMO_LEN
+            );
+        }
+
+        if lamports > 0 {
+            system_program::transfer(
+                CpiContext::new(
+                    ctx.accounts.system_program.to_account_info(),
+                    system_program::Transfer {
+                        from: ctx.accounts.sponsor.to_account_info(),
+                        to: ctx.accounts.prize_vault.to_account_info(),
+                    },
+                ),
+                lamports,
+            )?;
+            ctx.accounts.prize_vault.lamports = ctx.accounts.prize_vault.lamports.checked_add(lamports).ok_or(RecoveryRoomError::ArithmeticOverflow)?;
+        }
+
+        let mut token_mint = None;
+        let mut tokens_received = 0;
+        if token_amount > 0 {
+            let (Some(from), Some(to), Some(mint)) = (
+                &ctx.accounts.sponsor_token_account,
+                &ctx.accounts.vault_token_account,
+                &ctx.accounts.token_mint,
+            ) else {
+                return err!(RecoveryRoomError::MissingTokenAccounts);
+            };
+            require_keys_eq!(from.mint, mint.key(), RecoveryRoomError::MintMismatch);
+            require_keys_eq!(to.mint, mint.key(), RecoveryRoomError::MintMismatch);
+
+            tokens_received = transfer::transfer_checked(
+                &ctx.accounts.token_program.to_account_info(),
+                &from.to_account_info(),
+                &mint.to_account_info(),
+                &to.to_account_info(),
+                &ctx.accounts.sponsor.to_account_info(),
+                ctx.remaining_accounts,
+                token_amount,
+                mint.decimals,
+                &[],
+            )?;
+            token_mint = Some(from.mint);
+        }
+
+        let sponsor = &mut ctx.accounts.sponsor_record;
+        if sponsor.sponsor == Pubkey::default() {
+            sponsor.sponsor = ctx.accounts.sponsor.key();
+            sponsor.round_id = round.round_id;
+            sponsor.bump = ctx.bumps.sponsor_record;
+        }
+        if let Some(mint) = token_mint {
+            // A sponsor record tracks a single SPL mint per round
+            require!(
+                sponsor.token_mint.is_none_or(|m| m == mint),
+                RecoveryRoomError::MintMismatch
+            );
+            sponsor.token_mint = Some(mint);
+            sponsor.tokens_funded = sponsor.tokens_funded.checked_add(tokens_received).ok_or(RecoveryRoomError::ArithmeticOverflow)?;
+        }
+        sponsor.lamports_funded = sponsor.lamports_funded.checked_add(lamports).ok_or(RecoveryRoomError::ArithmeticOverflow)?;
+        if let Some(memo) = memo {
+            sponsor.memo = memo;
+        }
+
+        emit!(PrizePoolFunded {
+            round_id: round.round_id,
+            sponsor: sponsor.sponsor,
+            lamports,
+            token_mint,
+            token_amount: tokens_received,
+        });
+
+        msg!("Round {} prize pool funded by {}", round.round_id, sponsor.sponsor);
+        Ok(())
+    }
+
+    /// Brand a round for one of its prize sponsors, with a metadata URI
+    /// front-ends render it by (authority only)
+    pub fn brand_round(ctx: Context<BrandRound>, metadata_uri: String) -> Result<()> {
+        let round = &mut ctx.accounts.round_state;
+
+        require!(
+            round.status == RoundStatus::Active || round.status == RoundStatus::VrfRequested,
+            RecoveryRoomError::InvalidRoundStatus
+        );
+        require_detail!(
+            RecoveryRoomError::MetadataUriTooLong,
+            "metadata_uri_len",
+            metadata_uri.len(),
+            at_most MAX_ROUND_METADATA_URI_LEN
+        );
+
+        let sponsor = ctx.accounts.sponsor_record.sponsor;
+        round.sponsor = Some(sponsor);
+        round.metadata_uri = metadata_uri;
+
+        emit!(RoundBranded {
+            round_id: round.round_id,
+            sponsor,
+            metadata_uri: round.metadata_uri.clone(),
+        });
+
+        msg!("Round {} branded for sponsor {}", round.round_id, sponsor);
+        Ok(())
+    }
+
+    /// Winner claims their share of the round prize, net of the protocol fee
+    ///
+    /// `splits` optionally divides the payout across recipient wallets passed
+    /// as remaining accounts (bps each, summing to 10_000); empty pays the user.
+    /// A stable prize takes no splits; its remaining accounts are the stable
+    /// mint's transfer hook accounts, if it has a hook.
+    pub fn claim_prize<'info>(ctx: Context<'_, '_, '_, 'info, ClaimPrize<'info>>, splits: Vec<u16>) -> Result<()> {
+        let protocol = &ctx.accounts.protocol_state;
+        let round = &mut ctx.accounts.round_state;
+        let participation = &mut ctx.accounts.participation;
+
+        require!(
+            round.status == RoundStatus::Complete,
+            RecoveryRoomError::InvalidRoundStatus
+        );
+        let now = Clock::get()?.unix_timestamp;
+        require!(round.claims_open(now), RecoveryRoomError::ClaimDeadlinePassed);
+        require!(!participation.claimed, RecoveryRoomError::AlreadyClaimed);
+
+        let (gross, tier_entries) = prize_share(
+            round,
+            &participation.tokens,
+            &participation.wallet_tickets,
+            participation.weight_multiplier,
+        )?;
+        let gross = match (round.stable_prize, &ctx.accounts.stable_mint) {
+            (None, _) => gross,
+            (Some(stable), Some(mint)) => {
+                require_keys_eq!(mint.key(), stable.mint, RecoveryRoomError::MintMismatch);
+                TokenAmount::new(stable.mint, mint.decimals, gross.amount)?
+            }
+            (Some(_), None) => return err!(RecoveryRoomError::MissingTokenAccounts),
+        };
+        let (fee, payout) = gross.split_bps(round.rules.protocol_fee_bps)?;
+
+        let prize_vault_info = ctx.accounts.prize_vault.to_account_info();
+        if round.stable_prize.is_some() {
+            // Paid from the treasury's vault, which keeps the fee
+            require!(splits.is_empty(), RecoveryRoomError::InvalidClaimSplit);
+            let (Some(from), Some(to), Some(mint), Some(token_program)) = (
+                &ctx.accounts.treasury_stable_account,
+                &ctx.accounts.user_stable_account,
+                &ctx.accounts.stable_mint,
+                &ctx.accounts.token_program,
+            ) else {
+                return err!(RecoveryRoomError::MissingTokenAccounts);
+            };
+            require_keys_eq!(from.mint, mint.key(), RecoveryRoomError::MintMismatch);
+            require_keys_eq!(to.mint, mint.key(), RecoveryRoomError::MintMismatch);
+
+            let room = room_seed(protocol.room_id);
+            let treasury_seeds = &[b"treasury".as_ref(), &room, &[ctx.accounts.treasury.bump]];
+            transfer::transfer_checked(
+                &token_program.to_account_info(),
+                &from.to_account_info(),
+                &mint.to_account_info(),
+                &to.to_account_info(),
+                &ctx.accounts.treasury.to_account_info(),
+                ctx.remaining_accounts,
+                payout.amount,
+                mint.decimals,
+                &[&treasury_seeds[..]],
+            )?;
+        } else if splits.is_empty() {
+            move_lamports(&prize_vault_info, &ctx.accounts.user.to_account_info(), payout.amount)?;
+        } else {
+            require!(
+                splits.len() <= MAX_CLAIM_RECIPIENTS && splits.len() == ctx.remaining_accounts.len(),
+                RecoveryRoomError::InvalidClaimSplit
+            );
+            let total_bps: u64 = splits.iter().map(|b| *b as u64).sum();
+            require!(total_bps == BPS_DENOMINATOR, RecoveryRoomError::InvalidClaimSplit);
+
+            // The last recipient takes the rounding remainder so nothing is left behind
+            let mut remaining = payout;
+            for (i, (bps, recipient)) in splits.iter().zip(ctx.remaining_accounts).enumerate() {
+                require!(recipient.is_writable, RecoveryRoomError::InvalidClaimSplit);
+                let share = if i + 1 == splits.len() { remaining } else { payout.bps(*bps)? };
+                remaining = remaining.checked_sub(share)?;
+
+                move_lamports(&prize_vault_info, recipient, share.amount)?;
+                emit!(PrizeSplitPaid {
+                    round_id: round.round_id,
+                    user: participation.user,
+                    recipient: recipient.key(),
+                    amount: share.amount,
+                });
+            }
+            require!(remaining.is_zero(), RecoveryRoomError::InvalidClaimSplit);
+        }
+        if round.stable_prize.is_none() {
+            collect_protocol_fee(
+                &prize_vault_info,
+                &mut ctx.accounts.treasury,
+                ctx.accounts.consolation_pool.as_mut(),
+                ctx.accounts.rewards_vault.as_mut(),
+                fee.amount,
+                protocol,
+            )?;
+            ctx.accounts.prize_vault.lamports = ctx.accounts.prize_vault.lamports.checked_sub(gross.amount).ok_or(RecoveryRoomError::ArithmeticOverflow)?;
+        }
+
+        let year = year_from_unix(now);
+        let ledger = &mut ctx.accounts.payout_ledger;
+        if ledger.wallet == Pubkey::default() {
+            ledger.wallet = participation.user;
+            ledger.bump = ctx.bumps.payout_ledger;
+        }
+        if ledger.needs_new_bucket(year, &payout.mint) {
+            realloc::grow(
+                &ledger.to_account_info(),
+                PayoutLedger::space(ledger.entries.len() + 1),
+                PayoutLedger::SIZE,
+                &ctx.accounts.user.to_account_info(),
+                &ctx.accounts.system_program.to_account_info(),
+            )?;
+        }
+        ledger.record(year, payout, fee.amount)?;
+
+        // Winnings are tallied in lamports
+        ctx.accounts.user_stats.record_win(
+            participation.user,
+            ctx.bumps.user_stats,
+            if round.stable_prize.is_some() { 0 } else { payout.amount },
+            protocol.current_round.saturating_add(protocol.wallet_cooldown_rounds as u64),
+        );
+
+        refund_deposit(participation, &ctx.accounts.user.to_account_info(), round.round_id)?;
+        participation.claimed = true;
+        round.record_claim(&tier_entries)?;
+
+        emit!(PrizeClaimed {
+            round_id: round.round_id,
+            user: participation.user,
+            amount: payout.amount,
+            fee: fee.amount,
+        });
+
+        msg!("Round {} prize claimed: {} base units of {}", round.round_id, payout.amount, payout.mint);
+        Ok(())
+    }
+
+    /// Claim the bonus prize held by the participation whose ticket range
+    /// covers the round's bonus ticket. Paid in SOL less the protocol fee,
+    /// and independent of claim_prize.
+    pub fn claim_bonus_prize(ctx: Context<ClaimBonusPrize>) -> Result<()> {
+        let protocol = &ctx.accounts.protocol_state;
+        let round = &mut ctx.accounts.round_state;
+        let participation = &ctx.accounts.participation;
+
+        require!(
+            round.status == RoundStatus::Complete,
+            RecoveryRoomError::InvalidRoundStatus
+        );
+        let now = Clock::get()?.unix_timestamp;
+        require!(round.claims_open(now), RecoveryRoomError::ClaimDeadlinePassed);
+        require!(round.bonus_prize > 0, RecoveryRoomError::NotBonusWinner);
+        require!(!round.bonus_claimed, RecoveryRoomError::AlreadyClaimed);
+        let tickets = participation.bonus_ticket_start as u64
+            ..participation.bonus_ticket_start as u64 + participation.tokens.len() as u64;
+        require!(
+            tickets.contains(&(round.bonus_ticket as u64)),
+            RecoveryRoomError::NotBonusWinner
+        );
+
+        let (fee, payout) = TokenAmount::lamports(round.bonus_prize).split_bps(round.rules.protocol_fee_bps)?;
+        let prize_vault_info = ctx.accounts.prize_vault.to_account_info();
+        move_lamports(&prize_vault_info, &ctx.accounts.user.to_account_info(), payout.amount)?;
+        collect_protocol_fee(
+            &prize_vault_info,
+            &mut ctx.accounts.treasury,
+            ctx.accounts.consolation_pool.as_mut(),
+            ctx.accounts.rewards_vault.as_mut(),
+            fee.amount,
+            protocol,
+        )?;
+        ctx.accounts.prize_vault.lamports = ctx.accounts.prize_vault.lamports.checked_sub(round.bonus_prize).ok_or(RecoveryRoomError::ArithmeticOverflow)?;
+
+        let ledger = &mut ctx.accounts.payout_ledger;
+        if ledger.wallet == Pubkey::default() {
+            ledger.wallet = participation.user;
+            ledger.bump = ctx.bumps.payout_ledger;
+        }
+        let year = year_from_unix(now);
+        if ledger.needs_new_bucket(year, &payout.mint) {
+            realloc::grow(
+                &ledger.to_account_info(),
+                PayoutLedger::space(ledger.entries.len() + 1),
+                PayoutLedger::SIZE,
+                &ctx.accounts.user.to_account_info(),
+                &ctx.accounts.system_program.to_account_info(),
+            )?;
+        }
+        ledger.record(year, payout, fee.amount)?;
+
+        round.bonus_claimed = true;
+
+        emit!(BonusPrizeClaimed {
+            round_id: round.round_id,
+            user: participation.user,
+            ticket: round.bonus_ticket,
+            amount: payout.amount,
+            fee: fee.amount,
+        });
+
+        msg!("Round {} bonus prize claimed: {} lamports", round.round_id, payout.amount);
+        Ok(())
+    }
+
+    /// Claim the user's prizes from several rounds of one room at once
+    ///
+    /// Remaining accounts are (round, prize vault, participation) triples,
+    /// all writable. Rounds the user didn't win, already claimed or whose
+    /// claim deadline passed are skipped, as are stable-prize rounds (claimed
+    /// with claim_prize); the whole prize is paid to the user.
+    pub fn claim_many<'info>(ctx: Context<'_, '_, 'info, 'info, ClaimMany<'info>>) -> Result<()> {
+        let protocol = &ctx.accounts.protocol_state;
+        let user = ctx.accounts.user.key();
+        let user_info = ctx.accounts.user.to_account_info();
+        let room = room_seed(protocol.room_id);
+        let now = Clock::get()?.unix_timestamp;
+        let year = year_from_unix(now);
+
+        let batch = ctx.remaining_accounts;
+        require!(
+            !batch.is_empty()
+                && batch.len().is_multiple_of(CLAIM_BATCH_STRIDE)
+                && batch.len() / CLAIM_BATCH_STRIDE <= MAX_CLAIM_BATCH,
+            RecoveryRoomError::InvalidClaimBatch
+        );
+
+        let ledger = &mut ctx.accounts.payout_ledger;
+        if ledger.wallet == Pubkey::default() {
+            ledger.wallet = user;
+            ledger.bump = ctx.bumps.payout_ledger;
+        }
+
+        let mut claimed: u8 = 0;
+        for accounts in batch.chunks(CLAIM_BATCH_STRIDE) {
+            require!(
+                accounts.iter().all(|a| a.is_writable),
+                RecoveryRoomError::InvalidClaimAccount
+            );
+            let mut round = Account::<RoundState>::try_from(&accounts[0])?;
+            let mut prize_vault = Account::<PrizeVault>::try_from(&accounts[1])?;
+            let mut participation = Account::<Participation>::try_from(&accounts[2])?;
+
+            let round_id = round.round_id.to_le_bytes();
+            require_pda(&round, &[ROUND_SEED, &room, &round_id], round.bump)?;
+            require_pda(&prize_vault, &[b"prize_vault", &room, &round_id], prize_vault.bump)?;
+            require_pda(
+                &participation,
+                &[PARTICIPATION_SEED, round.key().as_ref(), user.as_ref()],
+                participation.bump,
+            )?;
+
+            let won = if round.wallet_draw {
+                holds_wallet_ticket(&round, &participation.tokens, &participation.wallet_tickets)
+            } else {
+                round.winner_tokens[..round.winner_count as usize]
+                    .iter()
+                    .any(|winner| participation.tokens.iter().any(|t| t.token_mint == *winner))
+            };
+            if round.status != RoundStatus::Complete
+                || !round.claims_open(now)
+                || participation.claimed
+                || !won
+                || round.stable_prize.is_some()
+            {
+                continue;
+            }
+
+            let (gross, tier_entries) = prize_share(
+                &round,
+                &participation.tokens,
+                &participation.wallet_tickets,
+                participation.weight_multiplier,
+            )?;
+            let (fee, payout) = gross.split_bps(round.rules.protocol_fee_bps)?;
+
+            let prize_vault_info = prize_vault.to_account_info();
+            move_lamports(&prize_vault_info, &user_info, payout.amount)?;
+            collect_protocol_fee(
+                &prize_vault_info,
+                &mut ctx.accounts.treasury,
+                ctx.accounts.consolation_pool.as_mut(),
+                ctx.accounts.rewards_vault.as_mut(),
+                fee.amount,
+                protocol,
+            )?;
+            prize_vault.lamports = prize_vault.lamports.checked_sub(gross.amount).ok_or(RecoveryRoomError::ArithmeticOverflow)?;
+
+            if ledger.needs_new_bucket(year, &payout.mint) {
+                realloc::grow(
+                    &ledger.to_account_info(),
+                    PayoutLedger::space(ledger.entries.len() + 1),
+                    PayoutLedger::SIZE,
+                    &user_info,
+                    &ctx.accounts.system_program.to_account_info(),
+                )?;
+            }
+            ledger.record(year, payout, fee.amount)?;
+
+            ctx.accounts.user_stats.record_win(
+                user,
+                ctx.bumps.user_stats,
+                payout.amount,
+                protocol.current_round.saturating_add(protocol.wallet_cooldown_rounds as u64),
+            );
+
+            refund_deposit(&mut participation, &user_info, round.round_id)?;
+            participation.claimed = true;
+            round.record_claim(&tier_entries)?;
+
+            emit!(PrizeClaimed {
+                round_id: round.round_id,
+                user,
+                amount: payout.amount,
+                fee: fee.amount,
+            });
+
+            round.exit(ctx.program_id)?;
+            prize_vault.exit(ctx.program_id)?;
+            participation.exit(ctx.program_id)?;
+            claimed += 1;
+        }
+
+        msg!("Claimed prizes from {} of {} rounds", claimed, batch.len() / CLAIM_BATCH_STRIDE);
+        Ok(())
+    }
+
+    /// Claim a prize for a compressed participation by proving its leaf
+    ///
+    /// The leaf is replaced with its "claimed" hash, which both verifies the
+    /// proof (passed as remaining accounts) and prevents a second claim.
+    pub fn claim_prize_compressed<'info>(
+        ctx: Context<'_, '_, '_, 'info, ClaimPrizeCompressed<'info>>,
+        root: [u8; 32],
+        leaf_index: u32,
+        token_entries: Vec<TokenEntry>,
+    ) -> Result<()> {
+        let protocol = &ctx.accounts.protocol_state;
+        let round = &mut ctx.accounts.round_state;
+        let user = ctx.accounts.user.key();
+
+        require!(
+            round.status == RoundStatus::Complete,
+            RecoveryRoomError::InvalidRoundStatus
+        );
+        require!(
+            round.claims_open(Clock::get()?.unix_timestamp),
+            RecoveryRoomError::ClaimDeadlinePassed
+        );
+        require!(round.stable_prize.is_none(), RecoveryRoomError::StablePrizeRound);
+
+        let (gross, tier_entries) = prize_share(round, &token_entries, &[], 1)?;
+        let (fee, payout) = gross.split_bps(round.rules.protocol_fee_bps)?;
+
+        let leaf = participation_leaf(round.round_id, &user, &token_entries)?;
+        let tree_authority_seeds = &[b"tree_authority".as_ref(), &[ctx.bumps.tree_authority]];
+        compression::replace_leaf(
+            &compression::TreeAccounts {
+                compression_program: &ctx.accounts.compression_program.to_account_info(),
+                merkle_tree: &ctx.accounts.merkle_tree.to_account_info(),
+                authority: &ctx.accounts.tree_authority.to_account_info(),
+                noop_program: &ctx.accounts.noop_program.to_account_info(),
+            },
+            root,
+            leaf,
+            claimed_leaf(&leaf),
+            leaf_index,
+            ctx.remaining_accounts,
+            &[&tree_authority_seeds[..]],
+        )?;
+
+        let prize_vault_info = ctx.accounts.prize_vault.to_account_info();
+        move_lamports(&prize_vault_info, &ctx.accounts.user.to_account_info(), payout.amount)?;
+        collect_protocol_fee(
+            &prize_vault_info,
+            &mut ctx.accounts.treasury,
+            ctx.accounts.consolation_pool.as_mut(),
+            ctx.accounts.rewards_vault.as_mut(),
+            fee.amount,
+            protocol,
+        )?;
+        ctx.accounts.prize_vault.lamports = ctx.accounts.prize_vault.lamports.checked_sub(gross.amount).ok_or(RecoveryRoomError::ArithmeticOverflow)?;
+        round.record_claim(&tier_entries)?;
+
+        let year = year_from_unix(Clock::get()?.unix_timestamp);
+        let ledger = &mut ctx.accounts.payout_ledger;
+        if ledger.wallet == Pubkey::default() {
+            ledger.wallet = user;
+            ledger.bump = ctx.bumps.payout_ledger;
+        }
+        if ledger.needs_new_bucket(year, &payout.mint) {
+            realloc::grow(
+                &ledger.to_account_info(),
+                PayoutLedger::space(ledger.entries.len() + 1),
+                PayoutLedger::SIZE,
+                &ctx.accounts.user.to_account_info(),
+                &ctx.accounts.system_program.to_account_info(),
+            )?;
+        }
+        ledger.record(year, payout, fee.amount)?;
+
+        ctx.accounts.user_stats.record_win(
+            user,
+            ctx.bumps.user_stats,
+            payout.amount,
+            protocol.current_round.saturating_add(protocol.wallet_cooldown_rounds as u64),
+        );
+
+        emit!(PrizeClaimed {
+            round_id: round.round_id,
+            user,
+            amount: payout.amount,
+            fee: fee.amount,
+        });
+
+        msg!("Round {} compressed prize claimed: {} lamports", round.round_id, payout.amount);
+        Ok(())
+    }
+
+    /// Non-winning participant of a completed round claims the fixed
+    /// consolation reward while its claim window is open. In a wallet draw,
+    /// every entrant but the drawn wallet counts as non-winning.
+    pub fn claim_consolation(ctx: Context<ClaimConsolation>) -> Result<()> {
+        let round = &ctx.accounts.round_state;
+        let participation = &mut ctx.accounts.participation;
+        let pool = &mut ctx.accounts.consolation_pool;
+        let reward = ctx.accounts.protocol_state.consolation_reward;
+
+        require!(
+            round.status == RoundStatus::Complete,
+            RecoveryRoomError::InvalidRoundStatus
+        );
+        require!(round.claims_open(Clock::get()?.unix_timestamp), RecoveryRoomError::ClaimDeadlinePassed);
+        require!(!participation.claimed, RecoveryRoomError::AlreadyClaimed);
+        require!(!participation.flagged, RecoveryRoomError::ParticipationFlagged);
+        require!(reward > 0, RecoveryRoomError::ConsolationDisabled);
+        let won = if round.wallet_draw {
+            holds_wallet_ticket(round, &participation.tokens, &participation.wallet_tickets)
+        } else {
+            round.winner_tokens[..round.winner_count as usize]
+                .iter()
+                .any(|winner| participation.tokens.iter().any(|t| t.token_mint == *winner))
+        };
+        require!(!won, RecoveryRoomError::NotConsolationEligible);
+        require_detail!(
+            RecoveryRoomError::ConsolationPoolDepleted,
+            "lamports",
+            reward,
+            at_most pool.balance
+        );
+
+        move_lamports(&pool.to_account_info(), &ctx.accounts.user.to_account_info(), reward)?;
+        pool.balance = pool.balance.checked_sub(reward).ok_or(RecoveryRoomError::ArithmeticOverflow)?;
+        pool.total_paid = pool.total_paid.checked_add(reward).ok_or(RecoveryRoomError::ArithmeticOverflow)?;
+        refund_deposit(participation, &ctx.accounts.user.to_account_info(), round.round_id)?;
+        participation.claimed = true;
+
+        emit!(ConsolationClaimed {
+            round_id: round.round_id,
+            user: participation.user,
+            amount: reward,
+        });
+
+        msg!("Round {} consolation claimed: {} lamports", round.round_id, reward);
+        Ok(())
+    }
+
+    /// Settle a participation's deposit once its round is over (permissionless):
+    /// refunded to the user, or forfeited to the treasury if the entry was flagged
+    pub fn settle_deposit(ctx: Context<SettleDeposit>) -> Result<()> {
+        let round = &ctx.accounts.round_state;
+        let participation = &mut ctx.accounts.participation;
+        require!(
+            round.status == RoundStatus::Complete || round.status == RoundStatus::Cancelled,
+            RecoveryRoomError::InvalidRoundStatus
+        );
+        require!(participation.deposit > 0, RecoveryRoomError::NoDeposit);
+
+        if !participation.flagged {
+            return refund_deposit(participation, &ctx.accounts.user.to_account_info(), round.round_id);
+        }
+
+        let amount = participation.deposit;
+        let treasury = &mut ctx.accounts.treasury;
+        move_lamports(&participation.to_account_info(), &treasury.to_account_info(), amount)?;
+        treasury.total_fees_collected = treasury.total_fees_collected.checked_add(amount).ok_or(RecoveryRoomError::ArithmeticOverflow)?;
+        participation.deposit = 0;
+
+        emit!(DepositSettled {
+            round_id: round.round_id,
+            user: participation.user,
+            amount,
+            forfeited: true,
+        });
+
+        msg!("Round {} deposit of {} lamports forfeited", round.round_id, amount);
+        Ok(())
+    }
+
+    /// Flag an invalid entry so its deposit is forfeited rather than refunded (authority only)
+    pub fn flag_entry(ctx: Context<FlagEntry>) -> Result<()> {
+        let participation = &mut ctx.accounts.participation;
+        require!(participation.deposit > 0, RecoveryRoomError::NoDeposit);
+        participation.flagged = true;
+
+        msg!("Round {} entry of {} flagged", participation.round_id, participation.user);
+        Ok(())
+    }
+
+    /// Mint the participant's soulbound badge for this round
+    pub fn mint_badge(ctx: Context<MintBadge>) -> Result<()> {
+        let participation = &mut ctx.accounts.participation;
+        require!(!participation.badge_minted, RecoveryRoomError::BadgeAlreadyMinted);
+
+        let room = room_seed(ctx.accounts.round_state.room_id);
+        let round_id = ctx.accounts.round_state.round_id.to_le_bytes();
+        let mint_seeds = &[b"round_badge".as_ref(), &room, &round_id, &[ctx.bumps.badge_mint]];
+        mint_soulbound_badge(
+            &ctx.accounts.badge_mint,
+            &ctx.accounts.badge_authority,
+            ctx.bumps.badge_authority,
+            &ctx.accounts.user,
+            &ctx.accounts.user_badge_account,
+            &ctx.accounts.token_program,
+            &ctx.accounts.associated_token_program,
+            &ctx.accounts.system_program,
+            mint_seeds,
+        )?;
+        participation.badge_minted = true;
+
+        emit!(BadgeMinted {
+            user: participation.user,
+            mint: ctx.accounts.badge_mint.key(),
+            round_id: Some(participation.round_id),
+            milestone: None,
+        });
+
+        msg!("Round {} badge minted", participation.round_id);
+        Ok(())
+    }
+
+    /// Mint the tiered badge for entering `rounds` rounds of room `room_id`
+    /// (one of BADGE_MILESTONES); each room has its own badge mints
+    pub fn mint_milestone_badge(ctx: Context<MintMilestoneBadge>, rounds: u32, room_id: u32) -> Result<()> {
+        let tier = BADGE_MILESTONES
+            .iter()
+            .position(|m| *m == rounds)
+            .ok_or(RecoveryRoomError::InvalidBadgeMilestone)?;
+        let stats = &mut ctx.accounts.user_stats;
+        require_detail!(
+            RecoveryRoomError::BadgeMilestoneNotReached,
+            "rounds_entered",
+            stats.rounds_entered,
+            at_least rounds
+        );
+        require!(
+            stats.milestone_badges & (1 << tier) == 0,
+            RecoveryRoomError::BadgeAlreadyMinted
+        );
+
+        let room = room_seed(room_id);
+        let milestone = rounds.to_le_bytes();
+        let mint_seeds = &[b"milestone_badge".as_ref(), &room, &milestone, &[ctx.bumps.badge_mint]];
+        mint_soulbound_badge(
+            &ctx.accounts.badge_mint,
+            &ctx.accounts.badge_authority,
+            ctx.bumps.badge_authority,
+            &ctx.accounts.user,
+            &ctx.accounts.user_badge_account,
+            &ctx.accounts.token_program,
+            &ctx.accounts.associated_token_program,
+            &ctx.accounts.system_program,
+            mint_seeds,
+        )?;
+        stats.milestone_badges |= 1 << tier;
+
+        emit!(BadgeMinted {
+            user: stats.user,
+            mint: ctx.accounts.badge_mint.key(),
+            round_id: None,
+            milestone: Some(rounds),
+        });
+
+        msg!("{}-round milestone badge minted", rounds);
+        Ok(())
+    }
+
+    /// Sell SPL tokens held by a round's prize vault through a Jupiter route
+    /// (authority only)
+    ///
+    /// Proceeds land either in a wrapped-SOL account of the prize vault, which
+    /// is unwrapped into the round's prize (or the jackpot, once the round has
+    /// settled), or in the treasury's vault for the stable prize mint. The swap
+    /// may spend at most `amount_in` and must return at least `min_out`.
+    /// Remaining accounts are the route's accounts in Jupiter's order.
+    pub fn liquidate_escrow<'info>(
+        ctx: Context<'_, '_, '_, 'info, LiquidateEscrow<'info>>,
+        amount_in: u64,
+        min_out: u64,
+        route_data: Vec<u8>,
+    ) -> Result<()> {
+        require!(amount_in > 0 && min_out > 0, RecoveryRoomError::InvalidSwapRoute);
+
+        let to_stable = match (&ctx.accounts.wsol_account, &ctx.accounts.treasury_stable_account) {
+            (Some(_), None) => false,
+            (None, Some(stable)) => {
+                let stable_mint = ctx.accounts.protocol_state.stable_prize.map(|p| p.mint);
+                require!(stable_mint == Some(stable.mint), RecoveryRoomError::MintMismatch);
+                true
+            }
+            _ => return err!(RecoveryRoomError::MissingTokenAccounts),
+        };
+
+        let round = &ctx.accounts.round_state;
+        let room = room_seed(round.room_id);
+        let round_id_bytes = round.round_id.to_le_bytes();
+        let vault_seeds = &[b"prize_vault".as_ref(), &room, &round_id_bytes, &[ctx.accounts.prize_vault.bump]];
+        let signer_seeds = &[&vault_seeds[..]];
+
+        let source_before = ctx.accounts.source_token_account.amount;
+        let out_before = ctx
+            .accounts
+            .wsol_account
+            .as_ref()
+            .or(ctx.accounts.treasury_stable_account.as_ref())
+            .map_or(0, |out| out.amount);
+
+        jupiter::swap(
+            &ctx.accounts.jupiter_program.to_account_info(),
+            &route_data,
+            ctx.remaining_accounts,
+            &ctx.accounts.prize_vault.key(),
+            signer_seeds,
+        )?;
+
+        ctx.accounts.source_token_account.reload()?;
+        let spent = source_before.saturating_sub(ctx.accounts.source_token_account.amount);
+        require_detail!(
+            RecoveryRoomError::SlippageExceeded,
+            "swap_in",
+            spent,
+            at_most amount_in
+        );
+        let out = match ctx.accounts.wsol_account.as_mut() {
+            Some(wsol) => wsol,
+            None => ctx
+                .accounts
+                .treasury_stable_account
+                .as_mut()
+                .ok_or(RecoveryRoomError::MissingTokenAccounts)?,
+        };
+        out.reload()?;
+        let (out_mint, out_after) = (out.mint, out.amount);
+        let received = out_after.saturating_sub(out_before);
+        require_detail!(
+            RecoveryRoomError::SlippageExceeded,
+            "swap_out",
+            received,
+            at_least min_out
+        );
+
+        // Unwrap SOL proceeds: closing hands the prize vault every lamport of
+        // the wrapped account. The token balance is credited and the rent
+        // passed on to the authority.
+        let mut to_jackpot = false;
+        if let Some(wsol) = &ctx.accounts.wsol_account {
+            let vault_lamports_before = ctx.accounts.prize_vault.to_account_info().lamports();
+            token_interface::close_account(CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                CloseAccount {
+                    account: wsol.to_account_info(),
+                    destination: ctx.accounts.prize_vault.to_account_info(),
+                    authority: ctx.accounts.prize_vault.to_account_info(),
+                },
+                signer_seeds,
+            ))?;
+
+            let status = ctx.accounts.round_state.status.clone();
+            let prize_vault = &mut ctx.accounts.prize_vault;
+            let rent = prize_vault
+                .to_account_info()
+                .lamports()
+                .checked_sub(vault_lamports_before)
+                .and_then(|unwrapped| unwrapped.checked_sub(out_after))
+                .ok_or(RecoveryRoomError::ArithmeticOverflow)?;
+            if rent > 0 {
+                move_lamports(&prize_vault.to_account_info(), &ctx.accounts.authority.to_account_info(), rent)?;
+            }
+            if status == RoundStatus::Active || status == RoundStatus::VrfRequested {
+                prize_vault.lamports = prize_vault.lamports.checked_add(out_after).ok_or(RecoveryRoomError::ArithmeticOverflow)?;
+            } else {
+                // Settled prizes are frozen; later rounds get the proceeds
+                let jackpot = &mut ctx.accounts.jackpot;
+                move_lamports(&prize_vault.to_account_info(), &jackpot.to_account_info(), out_after)?;
+                jackpot.balance = jackpot.balance.checked_add(out_after).ok_or(RecoveryRoomError::ArithmeticOverflow)?;
+                to_jackpot = true;
+            }
+        }
+        let credited = if to_stable { received } else { out_after };
+
+        emit!(EscrowLiquidated {
+            round_id: ctx.accounts.round_state.round_id,
+            input_mint: ctx.accounts.source_token_account.mint,
+            amount_in: spent,
+            output_mint: out_mint,
+            amount_out: credited,
+            to_stable_vault: to_stable,
+            to_jackpot,
+        });
+
+        msg!(
+            "Round {} vault sold {} of {} for {} of {}",
+            ctx.accounts.round_state.round_id,
+            spent,
+            ctx.accounts.source_token_account.mint,
+            received,
+            out_mint
+        );
+        Ok(())
+    }
+
+    /// Burn everything a settled round's prize vault holds of the round's
+    /// winning token (permissionless)
+    pub fn burn_escrow(ctx: Context<BurnEscrow>) -> Result<()> {
+        let round = &ctx.accounts.round_state;
+        require!(
+            round.status == RoundStatus::Complete,
+            RecoveryRoomError::InvalidRoundStatus
+        );
+
+        let amount = ctx.accounts.vault_token_account.amount;
+        require!(amount > 0, RecoveryRoomError::NothingToBurn);
+
+        let room = room_seed(round.room_id);
+        let round_id_bytes = round.round_id.to_le_bytes();
+        let vault_seeds = &[b"prize_vault".as_ref(), &room, &round_id_bytes, &[ctx.accounts.prize_vault.bump]];
+        token_interface::burn(
+            CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                Burn {
+                    mint: ctx.accounts.winning_mint.to_account_info(),
+                    from: ctx.accounts.vault_token_account.to_account_info(),
+                    authority: ctx.accounts.prize_vault.to_account_info(),
+                },
+                &[&vault_seeds[..]],
+            ),
+            amount,
+        )?;
+
+        let mint = ctx.accounts.winning_mint.key();
+        emit!(TokensBurned {
+            round_id: round.round_id,
+            mint,
+            amount,
+        });
+
+        msg!("Round {} burned {} of winning token {}", round.round_id, amount, mint);
+        Ok(())
+    }
+
+    /// Reclaim the prizes winners left unclaimed past a round's claim deadline
+    /// (permissionless)
+    ///
+    /// The prize vault's SOL rolls into the jackpot for later rounds. A stable
+    /// prize is paid from the treasury's vault, so what wasn't claimed simply
+    /// stays in the treasury. Either way the round's claims close for good and
+    /// finalize_vault may close its vault.
+    pub fn sweep_unclaimed(ctx: Context<CancelRound>) -> Result<()> {
+        let round = &mut ctx.accounts.round_state;
+        let prize_vault = &mut ctx.accounts.prize_vault;
+        let jackpot = &mut ctx.accounts.jackpot;
+
+        require!(
+            round.status == RoundStatus::Complete,
+            RecoveryRoomError::InvalidRoundStatus
+        );
+        require!(!round.unclaimed_swept, RecoveryRoomError::AlreadySwept);
+        require!(
+            round.claim_deadline > 0 && !round.claims_open(Clock::get()?.unix_timestamp),
+            RecoveryRoomError::ClaimWindowOpen
+        );
+
+        round.unclaimed_swept = true;
+
+        let amount = prize_vault.lamports;
+        if amount > 0 {
+            move_lamports(&prize_vault.to_account_info(), &jackpot.to_account_info(), amount)?;
+            prize_vault.lamports = 0;
+            jackpot.balance = jackpot.balance.checked_add(amount).ok_or(RecoveryRoomError::ArithmeticOverflow)?;
+        }
+
+        emit!(UnclaimedPrizesSwept {
+            round_id: round.round_id,
+            claimed_count: round.claimed_count,
+            rolled_to_jackpot: amount,
+        });
+
+        msg!("Round {} unclaimed prizes swept, {} lamports rolled into jackpot", round.round_id, amount);
+        Ok(())
+    }
+
+    /// Close a settled round's prize vault once every prize share is claimed or
+    /// swept (or the round was cancelled), returning rent and leftover dust to
+    /// the treasury
+    ///
+    /// Remaining accounts are (vault token account, treasury token account, mint)
+    /// triples. Any balance left in a vault token account is swept to its
+    /// treasury account before the vault account is closed. Vaults may mix SPL
+    /// Token and Token-2022 mints; each triple uses its mint's program. Dust of
+    /// a mint with a transfer hook is burned rather than swept, and withheld
+    /// transfer fees are harvested to the mint so the account can close.
+    pub fn finalize_vault<'info>(ctx: Context<'_, '_, 'info, 'info, FinalizeVault<'info>>) -> Result<()> {
+        let round = &ctx.accounts.round_state;
+        require!(
+            round.status == RoundStatus::Cancelled
+                || (round.status == RoundStatus::Complete && (round.all_prizes_claimed() || round.unclaimed_swept)),
+            RecoveryRoomError::PrizesOutstanding
+        );
+        require!(round.refunds_outstanding == 0, RecoveryRoomError::RefundsOutstanding);
+        require!(
+            ctx.remaining_accounts.len().is_multiple_of(3),
+            RecoveryRoomError::MissingTokenAccounts
+        );
+
+        let room = room_seed(round.room_id);
+        let round_id_bytes = round.round_id.to_le_bytes();
+        let vault_seeds = &[b"prize_vault".as_ref(), &room, &round_id_bytes, &[ctx.accounts.prize_vault.bump]];
+        let signer_seeds = &[&vault_seeds[..]];
+        let prize_vault_info = ctx.accounts.prize_vault.to_account_info();
+        let treasury_info = ctx.accounts.treasury.to_account_info();
+        let token_program_info = ctx.accounts.token_program.to_account_info();
+        let token_2022_program_info = ctx.accounts.token_2022_program.to_account_info();
+
+        for triple in ctx.remaining_accounts.chunks(3) {
+            let vault_token = InterfaceAccount::<TokenAccount>::try_from(&triple[0])?;
+            let treasury_token = InterfaceAccount::<TokenAccount>::try_from(&triple[1])?;
+            let mint = InterfaceAccount::<Mint>::try_from(&triple[2])?;
+            require_keys_eq!(vault_token.owner, prize_vault_info.key(), RecoveryRoomError::InvalidVaultTokenAccount);
+            require_keys_eq!(treasury_token.owner, treasury_info.key(), RecoveryRoomError::InvalidVaultTokenAccount);
+            require_keys_eq!(vault_token.mint, mint.key(), RecoveryRoomError::MintMismatch);
+            require_keys_eq!(treasury_token.mint, mint.key(), RecoveryRoomError::MintMismatch);
+
+            let program = if *triple[2].owner == token_2022::ID {
+                token_2022_program_info.clone()
+            } else {
+                token_program_info.clone()
+            };
+            let extensions = transfer::mint_extensions(&triple[2])?;
+            if vault_token.amount > 0 && extensions.transfer_hook {
+                token_interface::burn(
+                    CpiContext::new_with_signer(
+                        program.clone(),
+                        Burn {
+                            mint: triple[2].clone(),
+                            from: triple[0].clone(),
+                            authority: prize_vault_info.clone(),
+                        },
+                        signer_seeds,
+                    ),
+                    vault_token.amount,
+                )?;
+            } else if vault_token.amount > 0 {
+                transfer::transfer_checked(
+                    &program,
+                    &triple[0],
+                    &triple[2],
+                    &triple[1],
+                    &prize_vault_info,
+                    &[],
+                    vault_token.amount,
+                    mint.decimals,
+                    signer_seeds,
+                )?;
+            }
+            if extensions.transfer_fee {
+                transfer::harvest_withheld(&program, &triple[2], &triple[0])?;
+            }
+            token_interface::close_account(CpiContext::new_with_signer(
+                program,
+                CloseAccount {
+                    account: triple[0].clone(),
+                    destination: treasury_info.clone(),
+                    authority: prize_vault_info.clone(),
+                },
+                signer_seeds,
+            ))?;
+        }
+
+        // The vault itself is closed into the treasury on exit
+        emit!(RoundFinalized {
+            round_id: round.round_id,
+            lamports_returned: prize_vault_info.lamports(),
+            token_accounts_closed: (ctx.remaining_accounts.len() / 3) as u8,
+        });
+
+        msg!("Round {} vault finalized", round.round_id);
+        Ok(())
+    }
+
+    /// Create the recovery token mint, a program PDA that is its own mint
+    /// authority (authority only)
+    pub fn init_recovery_mint(_ctx: Context<InitRecoveryMint>) -> Result<()> {
+        msg!("Recovery token mint created");
+        Ok(())
+    }
+
+    /// Set how many recovery token base units losers of rounds in this room
+    /// get per USD of verified loss; 0 stops minting (authority only)
+    pub fn set_recovery_rate(ctx: Context<UpdateProtocol>, tokens_per_usd: u64) -> Result<()> {
+        ConfigChange::RecoveryRate(tokens_per_usd).apply_directly(&mut ctx.accounts.protocol_state)?;
+
+        msg!("Recovery rate set to {} per USD", tokens_per_usd);
+        Ok(())
+    }
+
+    /// Mint the participant recovery tokens for the losing entries of a
+    /// completed round, pro rata to their verified USD losses. Only entries
+    /// made while the room had a price verifier count, and at most
+    /// MAX_RECOVERABLE_LOSS_USD_CENTS of them per participation.
+    pub fn mint_recovery_tokens(ctx: Context<MintRecoveryTokens>) -> Result<()> {
+        let round = &ctx.accounts.round_state;
+        let participation = &mut ctx.accounts.participation;
+        let rate = ctx.accounts.protocol_state.recovery_tokens_per_usd;
+
+        require!(
+            round.status == RoundStatus::Complete,
+            RecoveryRoomError::InvalidRoundStatus
+        );
+        require!(rate > 0, RecoveryRoomError::RecoveryMintingDisabled);
+        require!(!participation.recovery_minted, RecoveryRoomError::RecoveryAlreadyMinted);
+        require!(!participation.flagged, RecoveryRoomError::ParticipationFlagged);
+        require!(participation.losses_attested, RecoveryRoomError::LossNotAttested);
+
+        let winners = &round.winner_tokens[..round.winner_count as usize];
+        let mut losses = UsdCents::ZERO;
+        for entry in participation.tokens.iter().filter(|t| !winners.contains(&t.token_mint)) {
+            losses = losses.checked_add(entry.loss_usd())?;
+        }
+        let losses = UsdCents(losses.cents().min(MAX_RECOVERABLE_LOSS_USD_CENTS));
+        let amount = mul_div(losses.cents(), rate, money::CENTS_PER_DOLLAR)?;
+        require!(amount > 0, RecoveryRoomError::NoRecoveryTokensOwed);
+
+        let mint_seeds = &[b"recovery_mint".as_ref(), &[ctx.bumps.recovery_mint]];
+        token::mint_to(
+            CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                token::MintTo {
+                    mint: ctx.accounts.recovery_mint.to_account_info(),
+                    to: ctx.accounts.user_token_account.to_account_info(),
+                    authority: ctx.accounts.recovery_mint.to_account_info(),
+                },
+                &[&mint_seeds[..]],
+            ),
+            amount,
+        )?;
+        participation.recovery_minted = true;
+
+        emit!(RecoveryTokensMinted {
+            round_id: round.round_id,
+            user: participation.user,
+            losses_usd_cents: losses.cents(),
+            amount,
+        });
+
+        msg!("Round {} recovery tokens minted: {}", round.round_id, amount);
+        Ok(())
+    }
+
+    /// Create the recovery token staking pool, its vault and the stakers'
+    /// rewards vault (authority only)
+    ///
+    /// Every `tokens_per_boost` recovery tokens staked add one to the weight
+    /// multiplier of the staker's entries, up to MAX_STAKE_MULTIPLIER. Each
+    /// stake locks the whole balance for `lockup_secs`. Fees routed to the
+    /// rewards vault during one `epoch_secs` epoch stream out to stakers over
+    /// the next.
+    pub fn init_stake_pool(
+        ctx: Context<InitStakePool>,
+        tokens_per_boost: u64,
+        lockup_secs: i64,
+        epoch_secs: i64,
+    ) -> Result<()> {
+        require!(
+            tokens_per_boost > 0 && lockup_secs >= 0 && epoch_secs > 0,
+            RecoveryRoomError::InvalidStakeConfig
+        );
+        let pool = &mut ctx.accounts.stake_pool;
+        pool.tokens_per_boost = tokens_per_boost;
+        pool.lockup_secs = lockup_secs;
+        pool.total_staked = 0;
+        pool.bump = ctx.bumps.stake_pool;
+
+        let rewards = &mut ctx.accounts.rewards_vault;
+        rewards.epoch = 0;
+        rewards.epoch_secs = epoch_secs;
+        rewards.epoch_start = Clock::get()?.unix_timestamp;
+        rewards.pending = 0;
+        rewards.streaming = 0;
+        rewards.streamed = 0;
+        rewards.reward_per_token = 0;
+        rewards.total_funded = 0;
+        rewards.total_claimed = 0;
+        rewards.bump = ctx.bumps.rewards_vault;
+
+        msg!(
+            "Stake pool created: {} tokens per boost, {}s lockup, {}s reward epochs",
+            tokens_per_boost,
+            lockup_secs,
+            epoch_secs
+        );
+        Ok(())
+    }
+
+    /// Set the share of protocol fees paid to stakers (authority only)
+    pub fn set_staking_fee(ctx: Context<SetStakingFee>, staking_fee_bps: u16) -> Result<()> {
+        ConfigChange::StakingFee(staking_fee_bps).apply_directly(&mut ctx.accounts.protocol_state)?;
+
+        msg!("Staking fee set to {} bps of protocol fees", staking_fee_bps);
+        Ok(())
+    }
+
+    /// Pay the staker the fees their stake has earned so far
+    pub fn claim_staking_rewards(ctx: Context<ClaimStakingRewards>) -> Result<()> {
+        let rewards = &mut ctx.accounts.rewards_vault;
+        let stake = &mut ctx.accounts.stake_account;
+        advance_reward_epoch(rewards, ctx.accounts.stake_pool.total_staked, Clock::get()?.unix_timestamp)?;
+        stake.settle_rewards(rewards.reward_per_token)?;
+
+        let amount = stake.rewards_owed;
+        require!(amount > 0, RecoveryRoomError::NoStakingRewards);
+        move_lamports(&rewards.to_account_info(), &ctx.accounts.user.to_account_info(), amount)?;
+        stake.rewards_owed = 0;
+        rewards.total_claimed = rewards.total_claimed.checked_add(amount).ok_or(RecoveryRoomError::ArithmeticOverflow)?;
+
+        emit!(StakingRewardsClaimed {
+            user: stake.owner,
+            amount,
+            epoch: rewards.epoch,
+        });
+
+        msg!("Claimed {} lamports of staking rewards", amount);
+        Ok(())
+    }
+
+    /// Stake recovery tokens, restarting the lockup on the whole balance
+    pub fn stake(ctx: Context<Stake>, amount: u64) -> Result<()> {
+        require!(amount > 0, RecoveryRoomError::ZeroStakeAmount);
+
+        token::transfer(
+            CpiContext::new(
+                ctx.accounts.token_program.to_account_info(),
+                token::Transfer {
+                    from: ctx.accounts.user_token_account.to_account_info(),
+                    to: ctx.accounts.stake_vault.to_account_info(),
+                    authority: ctx.accounts.user.to_account_info(),
+                },
+            ),
+            amount,
+        )?;
+
+        let now = Clock::get()?.unix_timestamp;
+        let pool = &mut ctx.accounts.stake_pool;
+        let stake = &mut ctx.accounts.stake_account;
+        let rewards = &mut ctx.accounts.rewards_vault;
+        advance_reward_epoch(rewards, pool.total_staked, now)?;
+        stake.settle_rewards(rewards.reward_per_token)?;
+
+        stake.owner = ctx.accounts.user.key();
+        stake.bump = ctx.bumps.stake_account;
+        stake.amount = stake.amount.checked_add(amount).ok_or(RecoveryRoomError::ArithmeticOverflow)?;
+        stake.locked_until = now.saturating_add(pool.lockup_secs);
+        pool.total_staked = pool.total_staked.checked_add(amount).ok_or(RecoveryRoomError::ArithmeticOverflow)?;
+
+        emit!(Staked {
+            user: stake.owner,
+            amount,
+            total: stake.amount,
+            locked_until: stake.locked_until,
+        });
+
+        msg!("Staked {} recovery tokens, locked until {}", amount, stake.locked_until);
+        Ok(())
+    }
+
+    /// Withdraw staked recovery tokens once their lockup has passed
+    pub fn unstake(ctx: Context<Unstake>, amount: u64) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        let stake = &mut ctx.accounts.stake_account;
+        require!(amount > 0, RecoveryRoomError::ZeroStakeAmount);
+        require!(now >= stake.locked_until, RecoveryRoomError::StakeLocked);
+        require_detail!(
+            RecoveryRoomError::InsufficientStake,
+            "stake",
+            amount,
+            at_most stake.amount
+        );
+
+        let pool_seeds = &[b"stake_pool".as_ref(), &[ctx.accounts.stake_pool.bump]];
+        token::transfer(
+            CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                token::Transfer {
+                    from: ctx.accounts.stake_vault.to_account_info(),
+                    to: ctx.accounts.user_token_account.to_account_info(),
+                    authority: ctx.accounts.stake_pool.to_account_info(),
+                },
+                &[&pool_seeds[..]],
+            ),
+            amount,
+        )?;
+
+        let rewards = &mut ctx.accounts.rewards_vault;
+        advance_reward_epoch(rewards, ctx.accounts.stake_pool.total_staked, now)?;
+        stake.settle_rewards(rewards.reward_per_token)?;
+
+        stake.amount -= amount;
+        let pool = &mut ctx.accounts.stake_pool;
+        pool.total_staked = pool.total_staked.saturating_sub(amount);
+
+        emit!(Unstaked {
+            user: stake.owner,
+            amount,
+            total: stake.amount,
+        });
+
+        msg!("Unstaked {} recovery tokens", amount);
+        Ok(())
+    }
+
+    /// Open a referral account for the signer, who can then be passed as the
+    /// referrer of other users' participations
+    pub fn register_referrer(ctx: Context<RegisterReferrer>) -> Result<()> {
+        let referral = &mut ctx.accounts.referral;
+        referral.referrer = ctx.accounts.referrer.key();
+        referral.claimable = 0;
+        referral.total_earned = 0;
+        referral.referred_entries = 0;
+        referral.referred_fees = 0;
+        referral.registered_at = Clock::get()?.unix_timestamp;
+        referral.bump = ctx.bumps.referral;
+
+        emit!(ReferrerRegistered {
+            referrer: referral.referrer,
+        });
+
+        msg!("Referrer {} registered", referral.referrer);
+        Ok(())
+    }
+
+    /// Withdraw the referrer's accumulated share of referred entry fees
+    pub fn claim_referral_fees(ctx: Context<ClaimReferralFees>) -> Result<()> {
+        let referral = &mut ctx.accounts.referral;
+        let amount = referral.claimable;
+        require!(amount > 0, RecoveryRoomError::NoReferralFees);
+
+        move_lamports(&referral.to_account_info(), &ctx.accounts.referrer.to_account_info(), amount)?;
+        referral.claimable = 0;
+
+        emit!(ReferralFeesClaimed {
+            referrer: referral.referrer,
+            amount,
+        });
+
+        msg!("Referrer claimed {} lamports", amount);
+        Ok(())
+    }
+
+    /// Set the share of referred users' SOL entry fees paid to referrers (authority only)
+    pub fn set_referral_fee(ctx: Context<UpdateProtocol>, referral_fee_bps: u16) -> Result<()> {
+        ConfigChange::ReferralFee(referral_fee_bps).apply_directly(&mut ctx.accounts.protocol_state)?;
+
+        msg!("Referral fee set to {} bps of entry fees", referral_fee_bps);
+        Ok(())
+    }
+
+    /// Create the all-time most-submitted tokens leaderboard (authority only)
+    pub fn init_leaderboard(ctx: Context<InitLeaderboard>) -> Result<()> {
+        let leaderboard = &mut ctx.accounts.leaderboard;
+        leaderboard.entries = Vec::new();
+        leaderboard.updated_at = Clock::get()?.unix_timestamp;
+        leaderboard.bump = ctx.bumps.leaderboard;
+
+        msg!("Leaderboard initialized");
+        Ok(())
+    }
+
+    /// Create the consolation pool fed by a slice of protocol fees (authority only)
+    pub fn init_consolation_pool(ctx: Context<InitConsolationPool>) -> Result<()> {
+        let pool = &mut ctx.accounts.consolation_pool;
+        pool.balance = 0;
+        pool.total_funded = 0;
+        pool.total_paid = 0;
+        pool.bump = ctx.bumps.consolation_pool;
+
+        msg!("Consolation pool initialized");
+        Ok(())
+    }
+
+    /// Freeze the current leaderboard into a per-season snapshot (authority only)
+    pub fn snapshot_leaderboard(ctx: Context<SnapshotLeaderboard>, season_id: u32) -> Result<()> {
+        let snapshot = &mut ctx.accounts.snapshot;
+        snapshot.season_id = season_id;
+        snapshot.entries = ctx.accounts.leaderboard.entries.clone();
+        snapshot.taken_at = Clock::get()?.unix_timestamp;
+        snapshot.bump = ctx.bumps.snapshot;
+
+        msg!("Leaderboard snapshot taken for season {}", season_id);
+        Ok(())
+    }
+
+    /// Open the next season; rounds started before `end_time` are stamped with it (authority only)
+    pub fn start_season(ctx: Context<StartSeason>, end_time: i64) -> Result<()> {
+        let protocol = &mut ctx.accounts.protocol_state;
+        let now = Clock::get()?.unix_timestamp;
+
+        require!(!protocol.season_active, RecoveryRoomError::SeasonActive);
+        require!(end_time > now, RecoveryRoomError::InvalidSeasonEnd);
+
+        protocol.current_season = protocol.current_season.checked_add(1).ok_or(RecoveryRoomError::ArithmeticOverflow)?;
+        protocol.season_active = true;
+        protocol.season_end_time = end_time;
+
+        let season = &mut ctx.accounts.season;
+        season.season_id = protocol.current_season;
+        season.start_time = now;
+        season.end_time = end_time;
+        season.ended_at = 0;
+        season.leaderboard = Vec::new();
+        season.bump = ctx.bumps.season;
+
+        emit!(SeasonStarted {
+            season_id: season.season_id,
+            start_time: now,
+            end_time,
+        });
+
+        msg!("Season {} started", season.season_id);
+        Ok(())
+    }
+
+    /// Close the current season, possibly ahead of schedule (authority only)
+    ///
+    /// Rounds already stamped with the season keep counting toward it.
+    pub fn end_season(ctx: Context<EndSeason>) -> Result<()> {
+        let protocol = &mut ctx.accounts.protocol_state;
+        require!(protocol.season_active, RecoveryRoomError::NoActiveSeason);
+        protocol.season_active = false;
+
+        let season = &mut ctx.accounts.season;
+        season.ended_at = Clock::get()?.unix_timestamp;
+
+        emit!(SeasonEnded {
+            season_id: season.season_id,
+            ended_at: season.ended_at,
+            rounds: season.rounds,
+            participants: season.participants,
+            total_loss_usd: season.total_loss_usd,
+        });
+
+        msg!("Season {} ended", season.season_id);
+        Ok(())
+    }
+
+    /// Create the global ticker registry (authority only)
+    pub fn init_ticker_registry(ctx: Context<InitTickerRegistry>) -> Result<()> {
+        let mut registry = ctx.accounts.ticker_registry.load_init()?;
+        registry.len = 0;
+
+        msg!("Ticker registry initialized");
+        Ok(())
+    }
+
+    /// Create a mint's global registry entry holding its ticker, color and
+    /// lifetime stats; done once per mint, before it can enter any round
+    ///
+    /// The first mint registered under a ticker becomes its canonical mint. A
+    /// different mint reusing the ticker is still accepted but flags the ticker
+    /// and emits TickerCollision so frontends can warn entrants. The
+    /// registrant pays TOKEN_REGISTRATION_FEE_LAMPORTS into the default
+    /// room's treasury, since the registry spans every room, so
+    /// the registry's ticker slots can't be filled for the price of rent.
+    pub fn register_token_global(
+        ctx: Context<RegisterTokenGlobal>,
+        ticker: String,
+        color: String,
+    ) -> Result<()> {
+        let symbol = metadata::read_symbol(&ctx.accounts.metadata, &ctx.accounts.mint.key())?;
+        require_ticker_matches(&ticker, &symbol)?;
+        let ticker = normalize_ticker(&symbol)?;
+        require!(
+            color.len() == 7 && color.starts_with('#') && color[1..].chars().all(|c| c.is_ascii_hexdigit()),
+            RecoveryRoomError::InvalidColor
+        );
+
+        let mint = ctx.accounts.mint.key();
+        let token = &mut ctx.accounts.token_registry;
+        token.mint = mint;
+        token.ticker = ticker;
+        token.color = fixed_bytes(&color);
+        token.total_submissions = 0;
+        token.total_wins = 0;
+        token.last_won_round = 0;
+        token.registered_at = Clock::get()?.unix_timestamp;
+        token.bump = ctx.bumps.token_registry;
+
+        system_program::transfer(
+            CpiContext::new(
+                ctx.accounts.system_program.to_account_info(),
+                system_program::Transfer {
+                    from: ctx.accounts.registrant.to_account_info(),
+                    to: ctx.accounts.treasury.to_account_info(),
+                },
+            ),
+            TOKEN_REGISTRATION_FEE_LAMPORTS,
+        )?;
+        let treasury = &mut ctx.accounts.treasury;
+        treasury.total_fees_collected = treasury
+            .total_fees_collected
+            .checked_add(TOKEN_REGISTRATION_FEE_LAMPORTS)
+            .ok_or(RecoveryRoomError::ArithmeticOverflow)?;
+
+        let mut registry = ctx.accounts.ticker_registry.load_mut()?;
+        if let Some(canonical_mint) = registry.register(ticker, mint)? {
+            emit!(TickerCollision {
+                round_id: None,
+                ticker,
+                canonical_mint,
+                colliding_mint: mint,
+            });
+        }
+
+        msg!("Token {} registered globally", mint);
+        Ok(())
+    }
+
+    /// Make room for POOL_GROWTH_TOKENS more tokens in an active round's pool
+    /// (permissionless; payer, typically the next registrant, funds the rent)
+    ///
+    /// Pools start with INITIAL_POOL_TOKENS entries so rent follows actual
+    /// use; register_token fails with TokenPoolFull until the pool is grown.
+    pub fn expand_token_pool(ctx: Context<ExpandTokenPool>) -> Result<()> {
+        let round = &ctx.accounts.round_state;
+        require!(
+            round.status == RoundStatus::Active,
+            RecoveryRoomError::RoundNotActive
+        );
+        ctx.accounts.token_pool.load()?.require_unlocked()?;
+
+        let info = ctx.accounts.token_pool.to_account_info();
+        let capacity = TokenPool::capacity(info.data_len());
+        require_detail!(
+            RecoveryRoomError::TokenPoolFull,
+            "pool_capacity",
+            capacity,
+            at_most MAX_POOL_TOKENS - 1
+        );
+        let new_capacity = (capacity + POOL_GROWTH_TOKENS).min(MAX_POOL_TOKENS);
+        realloc::grow(
+            &info,
+            TokenPool::space(new_capacity),
+            TokenPool::space(MAX_POOL_TOKENS),
+            &ctx.accounts.payer.to_account_info(),
+            &ctx.accounts.system_program.to_account_info(),
+        )?;
+
+        msg!("Round {} token pool expanded to {} tokens", round.round_id, new_capacity);
+        Ok(())
+    }
+
+    /// Chain a new shard onto an active round's pool once the pool is at
+    /// MAX_POOL_TOKENS and its last shard is full (permissionless; payer funds
+    /// the rent)
+    ///
+    /// A sharded round settles with its shard chain passed as remaining
+    /// accounts to request_randomness, finalize_round and verify_winner, so
+    /// it is cranked by hand rather than by the automation thread.
+    pub fn add_token_pool_shard(ctx: Context<AddTokenPoolShard>) -> Result<()> {
+        let round = &mut ctx.accounts.round_state;
+        require!(
+            round.status == RoundStatus::Active,
+            RecoveryRoomError::RoundNotActive
+        );
+        let token_pool = ctx.accounts.token_pool.load_pool()?;
+        token_pool.require_unlocked()?;
+        require_detail!(
+            RecoveryRoomError::TokenPoolFull,
+            "shard_count",
+            round.shard_count,
+            at_most MAX_POOL_SHARDS - 1
+        );
+        require!(
+            token_pool.entries().len() == MAX_POOL_TOKENS,
+            RecoveryRoomError::PoolShardNotNeeded
+        );
+
+        let shard_key = ctx.accounts.shard.key();
+        match (&ctx.accounts.tail_shard, round.shard_count) {
+            (None, 0) => {}
+            (Some(tail), count) if count > 0 => {
+                let mut tail = tail.load_mut()?;
+                require!(
+                    tail.len as usize == POOL_SHARD_TOKENS,
+                    RecoveryRoomError::PoolShardNotNeeded
+                );
+                tail.next_shard = shard_key;
+            }
+            _ => return err!(RecoveryRoomError::InvalidPoolShard),
+        }
+
+        let mut shard = ctx.accounts.shard.load_init()?;
+        shard.round_id = round.round_id;
+        shard.index = round.shard_count;
+        shard.bump = ctx.bumps.shard;
+        round.shard_count += 1;
+
+        msg!("Round {} token pool shard {} added", round.round_id, shard.index);
+        Ok(())
+    }
+
+    /// Add a globally registered token to the active round's pool. Tokens that
+    /// won within the round's winner cooldown are kept out of the pool.
+    ///
+    /// Creates the token's PoolEntry, which entries for it count into.
+    ///
+    /// Once the round has pool shards the token goes on the last one, and the
+    /// whole shard chain is passed as remaining accounts so the mint can be
+    /// checked against every page.
+    pub fn register_token(ctx: Context<RegisterToken>) -> Result<()> {
+        let round = &mut ctx.accounts.round_state;
+        require!(
+            round.status == RoundStatus::Active,
+            RecoveryRoomError::RoundNotActive
+        );
+        require!(
+            !ctx.accounts
+                .token_registry
+                .on_cooldown(round.round_id, round.winner_cooldown_rounds),
+            RecoveryRoomError::TokenOnCooldown
+        );
+
+        let mint = ctx.accounts.token_registry.mint;
+        let mut token_pool = ctx.accounts.token_pool.load_pool_mut()?;
+        token_pool.require_unlocked()?;
+        let entry = TokenPoolEntry {
+            token_mint: mint,
+            submission_count: 0,
+            total_loss_usd: 0,
+            stats_synced: 0,
+            _padding: [0; 3],
+        };
+        if round.shard_count == 0 {
+            require!(
+                !token_pool.entries().iter().any(|e| e.token_mint == mint),
+                RecoveryRoomError::TokenAlreadyRegistered
+            );
+            token_pool.push(entry)?;
+        } else {
+            {
+                let shards = load_shard_chain(round, ctx.remaining_accounts)?;
+                require!(
+                    !pool_pages(token_pool.entries(), &shards)
+                        .iter()
+                        .any(|p| p.iter().any(|e| e.token_mint == mint)),
+                    RecoveryRoomError::TokenAlreadyRegistered
+                );
+            }
+            let tail = &ctx.remaining_accounts[round.shard_count as usize - 1];
+            load_shard_mut(tail)?.push(entry)?;
+        }
+
+        let pool_entry = &mut ctx.accounts.pool_entry;
+        pool_entry.round_id = round.round_id;
+        pool_entry.token_mint = mint;
+        pool_entry.bump = ctx.bumps.pool_entry;
+        round.pool_entry_count = round.pool_entry_count.checked_add(1).ok_or(RecoveryRoomError::ArithmeticOverflow)?;
+
+        msg!("Token {} registered for round {}", mint, round.round_id);
+        Ok(())
+    }
+
+    /// Copy an ended round's pool entries, passed as remaining accounts, into
+    /// its token pool's weight table (permissionless, once per entry).
+    /// request_randomness waits until every entry is tallied; entries of
+    /// tokens on a pool shard are tallied by a call that passes that shard.
+    pub fn tally_pool_entries(ctx: Context<TallyPoolEntries>) -> Result<()> {
+        let round = &mut ctx.accounts.round_state;
+        require!(
+            round.status == RoundStatus::Active,
+            RecoveryRoomError::InvalidRoundStatus
+        );
+        // Entries close at end_time, so the counts are final
+        require!(
+            Clock::get()?.unix_timestamp >= round.end_time,
+            RecoveryRoomError::RoundNotEnded
+        );
+
+        let mut token_pool = ctx.accounts.token_pool.load_pool_mut()?;
+        token_pool.require_unlocked()?;
+        let mut shard = ctx.accounts.token_pool_shard.as_ref().map(|s| s.load_mut()).transpose()?;
+        let mut tallied: u32 = 0;
+        for info in ctx.remaining_accounts {
+            require_keys_eq!(*info.owner, crate::ID, RecoveryRoomError::InvalidPoolEntry);
+            let mut entry = PoolEntry::try_deserialize(&mut &info.try_borrow_data()?[..])?;
+            require_keys_eq!(
+                info.key(),
+                pool_entry_address(round, &entry.token_mint),
+                RecoveryRoomError::InvalidPoolEntry
+            );
+            if entry.tallied {
+                continue;
+            }
+            let slot = token_pool
+                .entries_mut()
+                .iter_mut()
+                .chain(shard.as_deref_mut().map(TokenPoolShard::entries_mut).unwrap_or_default())
+                .find(|e| e.token_mint == entry.token_mint)
+                .ok_or(RecoveryRoomError::TokenNotInPool)?;
+            slot.submission_count = entry.submission_count;
+            slot.total_loss_usd = entry.total_loss_usd;
+            entry.tallied = true;
+            entry.try_serialize(&mut &mut info.try_borrow_mut_data()?[..])?;
+            tallied += 1;
+        }
+        round.pool_entries_tallied = round.pool_entries_tallied.checked_add(tallied).ok_or(RecoveryRoomError::ArithmeticOverflow)?;
+
+        msg!(
+            "Tallied {} pool entries for round {} ({}/{})",
+            tallied,
+            round.round_id,
+            round.pool_entries_tallied,
+            round.pool_entry_count
+        );
+        Ok(())
+    }
+
+    /// Fold a settled round's pool stats into the lifetime TokenRegistry
+    /// accounts passed as remaining accounts (permissionless, once per token).
+    /// Tokens on a pool shard sync through the call that passes that shard.
+    pub fn sync_token_stats<'info>(ctx: Context<'_, '_, 'info, 'info, SyncTokenStats<'info>>) -> Result<()> {
+        let round = &ctx.accounts.round_state;
+        require!(
+            round.status == RoundStatus::Complete || round.status == RoundStatus::Cancelled,
+            RecoveryRoomError::InvalidRoundStatus
+        );
+
+        let winners = &round.winner_tokens[..round.winner_count as usize];
+        let mut token_pool = ctx.accounts.token_pool.load_pool_mut()?;
+        let mut shard = ctx.accounts.token_pool_shard.as_ref().map(|s| s.load_mut()).transpose()?;
+        let mut synced: u32 = 0;
+        for info in ctx.remaining_accounts {
+            let mut token = Account::<TokenRegistry>::try_from(info)?;
+            let Some(entry) = token_pool
+                .entries_mut()
+                .iter_mut()
+                .chain(shard.as_deref_mut().map(TokenPoolShard::entries_mut).unwrap_or_default())
+                .find(|e| e.token_mint == token.mint)
+            else {
+                continue;
+            };
+            if entry.stats_synced != 0 {
+                continue;
+            }
+
+            token.total_submissions = token.total_submissions.saturating_add(entry.submission_count as u64);
+            if winners.contains(&token.mint) {
+                token.total_wins = token.total_wins.saturating_add(1);
+                token.last_won_round = token.last_won_round.max(round.round_id);
+            }
+            entry.stats_synced = 1;
+            token.exit(&crate::ID)?;
+            synced += 1;
+        }
+
+        msg!("Synced {} token registries for round {}", synced, round.round_id);
+        Ok(())
+    }
+
+    /// Pick the canonical mint for a ticker, e.g. after a collision (authority only)
+    pub fn set_canonical_mint(
+        ctx: Context<SetCanonicalMint>,
+        ticker: String,
+        canonical_mint: Pubkey,
+    ) -> Result<()> {
+        let ticker = normalize_ticker(&ticker)?;
+        let mut registry = ctx.accounts.ticker_registry.load_mut()?;
+        let record = registry
+            .find_mut(&ticker)
+            .ok_or(RecoveryRoomError::TickerNotRegistered)?;
+        record.canonical_mint = canonical_mint;
+
+        msg!("Canonical mint for ticker set to {}", canonical_mint);
+        Ok(())
+    }
+
+    /// Create the compressed participation tree under the program's tree authority
+    /// (authority only). The tree account must be pre-allocated by the caller.
+    pub fn init_participation_tree(
+        ctx: Context<InitParticipationTree>,
+        max_depth: u32,
+        max_buffer_size: u32,
+    ) -> Result<()> {
+        let tree_authority_seeds = &[b"tree_authority".as_ref(), &[ctx.bumps.tree_authority]];
+        compression::init_empty_merkle_tree(
+            &compression::TreeAccounts {
+                compression_program: &ctx.accounts.compression_program.to_account_info(),
+                merkle_tree: &ctx.accounts.merkle_tree.to_account_info(),
+                authority: &ctx.accounts.tree_authority.to_account_info(),
+                noop_program: &ctx.accounts.noop_program.to_account_info(),
+            },
+            max_depth,
+            max_buffer_size,
+            &[&tree_authority_seeds[..]],
+        )?;
+
+        let protocol = &mut ctx.accounts.protocol_state;
+        protocol.participation_tree = ctx.accounts.merkle_tree.key();
+        protocol.participation_tree_leaves = 0;
+
+        msg!("Participation tree {} initialized", protocol.participation_tree);
+        Ok(())
+    }
+
+    /// Create the Bubblegum tree participation receipts are minted into (authority only)
+    ///
+    /// The tree account must be pre-allocated and owned by the compression program.
+    pub fn init_receipt_tree(
+        ctx: Context<InitReceiptTree>,
+        max_depth: u32,
+        max_buffer_size: u32,
+        uri: String,
+    ) -> Result<()> {
+        require_detail!(
+            RecoveryRoomError::ReceiptUriTooLong,
+            "uri_len",
+            uri.len(),
+            at_most MAX_RECEIPT_URI_LEN
+        );
+
+        let receipt_authority_seeds = &[b"receipt_authority".as_ref(), &[ctx.bumps.receipt_authority]];
+        bubblegum::create_tree(
+            &bubblegum::BubblegumAccounts {
+                bubblegum_program: &ctx.accounts.bubblegum_program.to_account_info(),
+                tree_config: &ctx.accounts.tree_config.to_account_info(),
+                merkle_tree: &ctx.accounts.merkle_tree.to_account_info(),
+                payer: &ctx.accounts.authority.to_account_info(),
+                tree_delegate: &ctx.accounts.receipt_authority.to_account_info(),
+                noop_program: &ctx.accounts.noop_program.to_account_info(),
+                compression_program: &ctx.accounts.compression_program.to_account_info(),
+                system_program: &ctx.accounts.system_program.to_account_info(),
+            },
+            max_depth,
+            max_buffer_size,
+            &[&receipt_authority_seeds[..]],
+        )?;
+
+        let config = &mut ctx.accounts.receipt_config;
+        config.merkle_tree = ctx.accounts.merkle_tree.key();
+        config.uri = uri;
+        config.bump = ctx.bumps.receipt_config;
+
+        msg!("Receipt tree {} initialized", config.merkle_tree);
+        Ok(())
+    }
+
+    /// Create the automation threads that run start_round and request_randomness
+    /// on cron schedules (authority only)
+    ///
+    /// Remaining accounts are the request thread's static Switchboard accounts in
+    /// RequestRandomness order: vrf, oracle_queue, queue_authority, data_buffer,
+    /// permission, escrow, payer_wallet, recent_blockhashes,
+    /// switchboard_program_state, switchboard_program. The request thread pays
+    /// VRF fees itself, so `payer_wallet` must be owned by the request thread.
+    pub fn init_automation<'info>(
+        ctx: Context<'_, '_, '_, 'info, InitAutomation<'info>>,
+        start_schedule: String,
+        request_schedule: String,
+        thread_fund_lamports: u64,
+    ) -> Result<()> {
+        // Each room gets its own pair of threads under the shared thread authority
+        let room_id = ctx.accounts.protocol_state.room_id;
+        let start_id = [START_ROUND_THREAD_ID, &room_seed(room_id)].concat();
+        let request_id = [REQUEST_RANDOMNESS_THREAD_ID, &room_seed(room_id)].concat();
+        let thread_authority = ctx.accounts.thread_authority.key();
+        let start_thread = automation::thread_pda(&thread_authority, &start_id);
+        let request_thread = automation::thread_pda(&thread_authority, &request_id);
+        require_keys_eq!(ctx.accounts.start_thread.key(), start_thread, RecoveryRoomError::UnauthorizedThread);
+        require_keys_eq!(ctx.accounts.request_thread.key(), request_thread, RecoveryRoomError::UnauthorizedThread);
+        require_detail!(
+            RecoveryRoomError::MissingAutomationAccounts,
+            "account_count",
+            ctx.remaining_accounts.len(),
+            at_least REQUEST_THREAD_SWITCHBOARD_ACCOUNTS
+        );
+        let switchboard: Vec<Pubkey> = ctx.remaining_accounts[..REQUEST_THREAD_SWITCHBOARD_ACCOUNTS]
+            .iter()
+            .map(|a| a.key())
+            .collect();
+
+        let protocol_key = ctx.accounts.protocol_state.key();
+        let current_round = ctx.accounts.protocol_state.current_round;
+        let room = room_seed(ctx.accounts.protocol_state.room_id);
+        let jackpot = Pubkey::find_program_address(&[b"jackpot", &room], &crate::ID).0;
+        let treasury = Pubkey::find_program_address(&[b"treasury", &room], &crate::ID).0;
+
+        let thread_authority_seeds = &[b"thread_authority".as_ref(), &[ctx.bumps.thread_authority]];
+        let threads = [
+            (
+                ctx.accounts.start_thread.to_account_info(),
+                start_id,
+                start_round_thread_ix(
+                    protocol_key,
+                    jackpot,
+                    treasury,
+                    start_thread,
+                    room_id,
+                    current_round + 1,
+                    ctx.accounts.protocol_state.has_schedule,
+                ),
+                start_schedule,
+            ),
+            (
+                ctx.accounts.request_thread.to_account_info(),
+                request_id,
+                request_randomness_thread_ix(
+                    protocol_key,
+                    room_id,
+                    current_round.max(1),
+                    &switchboard,
+                    treasury,
+                    request_thread,
+                ),
+                request_schedule,
+            ),
+        ];
+        for (thread, id, ix, schedule) in threads {
+            automation::thread_create(
+                &automation::ThreadCreateAccounts {
+                    thread_program: &ctx.accounts.thread_program.to_account_info(),
+                    authority: &ctx.accounts.thread_authority.to_account_info(),
+                    payer: &ctx.accounts.authority.to_account_info(),
+                    system_program: &ctx.accounts.system_program.to_account_info(),
+                    thread: &thread,
+                },
+                thread_fund_lamports,
+                id,
+                vec![ix],
+                automation::Trigger::Cron { schedule, skippable: true },
+                &[&thread_authority_seeds[..]],
+            )?;
+        }
+
+        let protocol = &mut ctx.accounts.protocol_state;
+        protocol.start_thread = start_thread;
+        protocol.request_thread = request_thread;
+
+        msg!("Automation threads created: start {} / request {}", start_thread, request_thread);
+        Ok(())
+    }
+
+    /// Return rolling p50/p95 VRF latency over the last LATENCY_WINDOW rounds
+    pub fn get_vrf_latency(ctx: Context<GetVrfLatency>) -> Result<VrfLatencyStats> {
+        Ok(ctx.accounts.metrics.vrf_latency_stats())
+    }
+
+    /// Return a summary of protocol-wide state
+    pub fn get_protocol_info(ctx: Context<GetProtocolInfo>) -> Result<ProtocolInfo> {
+        let protocol = &ctx.accounts.protocol_state;
+        Ok(ProtocolInfo {
+            room_id: protocol.room_id,
+            current_round: protocol.current_round,
+            total_rounds_completed: protocol.total_rounds_completed,
+            round_duration: protocol.round_duration,
+            min_loss_percentage: protocol.min_loss_percentage,
+            max_tokens_per_user: protocol.max_tokens_per_user,
+            protocol_fee_bps: protocol.protocol_fee_bps,
+            entry_fee: protocol.entry_fee,
+            current_season: protocol.season_for(Clock::get()?.unix_timestamp),
+            access_pass: protocol.access_pass,
+        })
+    }
+
+    /// Return a summary of a round, including seconds left to enter
+    pub fn get_round_info(ctx: Context<GetRoundInfo>) -> Result<RoundInfo> {
+        let round = &ctx.accounts.round_state;
+        let time_remaining = if round.status == RoundStatus::Active {
+            (round.end_time - Clock::get()?.unix_timestamp).max(0)
+        } else {
+            0
+        };
+        Ok(RoundInfo {
+            room_id: round.room_id,
+            round_id: round.round_id,
+            status: round.status.clone(),
+            start_time: round.start_time,
+            end_time: round.end_time,
+            time_remaining,
+            total_participants: round.total_participants,
+            total_token_entries: round.total_token_entries,
+            prize_pool: ctx.accounts.prize_vault.as_ref().map_or(round.prize_pool, |v| v.lamports),
+            stable_prize: round.stable_prize,
+            winner_token: round.winner_token,
+            sponsor: round.sponsor,
+            metadata_uri: round.metadata_uri.clone(),
+            ticketed: round.ticketed,
+            ticket_supply: round.ticket_supply,
+            claim_deadline: round.claim_deadline,
+            max_participants: round.max_participants,
+            shard_count: round.shard_count,
+            pool_entry_count: round.pool_entry_count,
+            pool_entries_tallied: round.pool_entries_tallied,
+        })
+    }
+
+    /// Return a deterministic permutation of a settled round's pool entries,
+    /// derived from its VRF result, as indices into the token pool. Partner
+    /// games can build on the same verified entropy without a new draw.
+    pub fn derive_shuffle(ctx: Context<DeriveShuffle>) -> Result<Vec<u16>> {
+        let round = &ctx.accounts.round_state;
+        require!(
+            round.status == RoundStatus::Complete,
+            RecoveryRoomError::InvalidRoundStatus
+        );
+        let vrf_result = round.vrf_result.ok_or(RecoveryRoomError::InvalidRoundStatus)?;
+
+        let token_pool = ctx.accounts.token_pool.load_pool()?;
+        let shards = load_shard_chain(round, ctx.remaining_accounts)?;
+        let len = pool_pages(token_pool.entries(), &shards).iter().map(|p| p.len()).sum();
+        Ok(derive_shuffle_indices(&vrf_result, len))
+    }
+
+    /// Re-draw a settled round from its stored VRF result and committed weight
+    /// table, and check the result matches the recorded winners (permissionless)
+    pub fn verify_winner(ctx: Context<VerifyWinner>) -> Result<()> {
+        let round = &ctx.accounts.round_state;
+        require!(
+            round.status == RoundStatus::Complete,
+            RecoveryRoomError::InvalidRoundStatus
+        );
+        let vrf_result = round.vrf_result.ok_or(RecoveryRoomError::InvalidRoundStatus)?;
+
+        let token_pool = ctx.accounts.token_pool.load_pool()?;
+        let shards = load_shard_chain(round, ctx.remaining_accounts)?;
+        require!(
+            pool_root(token_pool.weight_root(), ctx.remaining_accounts, &shards) == round.pool_root,
+            RecoveryRoomError::PoolRootMismatch
+        );
+        let pages = pool_pages(token_pool.entries(), &shards);
+        let winners = draw_winners(
+            &pages,
+            round.weighting,
+            round.min_submissions_to_qualify,
+            &round.rules.tier_bps,
+            &vrf_result,
+        )?;
+        require!(
+            Some(winners[0]) == round.winner_token
+                && winners[..] == round.winner_tokens[..round.winner_count as usize],
+            RecoveryRoomError::WinnerMismatch
+        );
+
+        emit!(WinnerVerified {
+            round_id: round.round_id,
+            winner_token: winners[0],
+            tier_winners: winners,
+            pool_root: round.pool_root,
+            vrf_result,
+        });
+
+        msg!("Round {} winner verified", round.round_id);
+        Ok(())
+    }
+
+    /// Return the tier winners a draw seeded with `seed` would pick from the
+    /// round's current pool, first tier first. Read-only; lets front-ends
+    /// sample live odds through the same math as consume_randomness.
+    pub fn simulate_winner(ctx: Context<SimulateWinner>, seed: [u8; 32]) -> Result<Vec<Pubkey>> {
+        let round = &ctx.accounts.round_state;
+        let token_pool = ctx.accounts.token_pool.load_pool()?;
+        let shards = load_shard_chain(round, ctx.remaining_accounts)?;
+        let pages = pool_pages(token_pool.entries(), &shards);
+        draw_winners(
+            &pages,
+            round.weighting,
+            round.min_submissions_to_qualify,
+            &ctx.accounts.protocol_state.current_rules(round).tier_bps,
+            &seed,
+        )
+    }
+
+    /// Return a wallet's recorded payout totals for `year` (one entry per mint).
+    /// Buckets evicted from a full ledger survive only in PayoutBucketEvicted
+    /// events.
+    pub fn get_payout_totals(ctx: Context<GetPayoutTotals>, year: u16) -> Result<Vec<YearlyPayout>> {
+        Ok(ctx.accounts.payout_ledger
+            .entries
+            .iter()
+            .filter(|e| e.year == year)
+            .cloned()
+            .collect())
+    }
+
+    /// Update the protocol fee taken from payouts (authority only)
+    pub fn set_protocol_fee(ctx: Context<UpdateProtocol>, protocol_fee_bps: u16) -> Result<()> {
+        ConfigChange::ProtocolFee(protocol_fee_bps).apply_directly(&mut ctx.accounts.protocol_state)?;
+
+        msg!("Protocol fee set to {} bps", protocol_fee_bps);
+        Ok(())
+    }
+
+    /// Set the prize split between 1st/2nd/3rd winning tokens (authority only)
+    pub fn set_tier_split(ctx: Context<UpdateProtocol>, tier_bps: [u16; MAX_WINNER_TIERS]) -> Result<()> {
+        ConfigChange::TierSplit(tier_bps).apply_directly(&mut ctx.accounts.protocol_state)?;
+
+        msg!("Tier split set to {:?} bps", tier_bps);
+        Ok(())
+    }
+
+    /// Set the default weighting strategy for new rounds (authority only)
+    pub fn set_weighting_strategy(
+        ctx: Context<UpdateProtocol>,
+        weighting: WeightingStrategy,
+    ) -> Result<()> {
+        ConfigChange::Weighting(weighting).apply_directly(&mut ctx.accounts.protocol_state)?;
+
+        msg!("Weighting strategy set to {:?}", weighting);
+        Ok(())
+    }
+
+    /// Cap the submissions one wallet can add to any single token (0 = unlimited, authority only)
+    pub fn set_max_weight_contribution(
+        ctx: Context<UpdateProtocol>,
+        max_weight_contribution_per_user: u32,
+    ) -> Result<()> {
+        ConfigChange::MaxWeightContribution(max_weight_contribution_per_user)
+            .apply_directly(&mut ctx.accounts.protocol_state)?;
+
+        msg!("Per-user weight cap set to {}", max_weight_contribution_per_user);
+        Ok(())
+    }
+
+    /// Set the participants rounds started from now on need to be drawn (authority only)
+    pub fn set_min_participants(ctx: Context<UpdateProtocol>, min_participants: u32) -> Result<()> {
+        ConfigChange::MinParticipants(min_participants).apply_directly(&mut ctx.accounts.protocol_state)?;
+
+        msg!("Minimum participants set to {}", min_participants);
+        Ok(())
+    }
+
+    /// Cap the participants rounds started from now on admit, bounding what
+    /// settlement has to process (0 = unlimited, authority only)
+    pub fn set_max_participants(ctx: Context<UpdateProtocol>, max_participants: u32) -> Result<()> {
+        ConfigChange::MaxParticipants(max_participants).apply_directly(&mut ctx.accounts.protocol_state)?;
+
+        msg!("Maximum participants set to {}", max_participants);
+        Ok(())
+    }
+
+    /// Choose where rounds get their randomness (authority only)
+    pub fn set_randomness_mode(ctx: Context<UpdateProtocol>, randomness_mode: RandomnessMode) -> Result<()> {
+        ConfigChange::RandomnessMode(randomness_mode).apply_directly(&mut ctx.accounts.protocol_state)?;
+
+        msg!("Randomness mode set to {:?}", randomness_mode);
+        Ok(())
+    }
+
+    /// Set how many submissions a token needs to be drawn (authority only).
+    /// Tokens below it stay in the pool but carry no weight, so one stray
+    /// entry of an unrelated mint can't take the pot.
+    pub fn set_min_submissions_to_qualify(ctx: Context<UpdateProtocol>, min_submissions_to_qualify: u32) -> Result<()> {
+        ConfigChange::MinSubmissionsToQualify(min_submissions_to_qualify)
+            .apply_directly(&mut ctx.accounts.protocol_state)?;
+
+        msg!("Tokens now qualify for the draw at {} submissions", min_submissions_to_qualify);
+        Ok(())
+    }
+
+    /// Set the share of the prize pool paid to the bonus participant draw
+    /// (authority only). Applies to rounds finalized from now on.
+    pub fn set_bonus_prize(ctx: Context<UpdateProtocol>, bonus_prize_bps: u16) -> Result<()> {
+        ConfigChange::BonusPrize(bonus_prize_bps).apply_directly(&mut ctx.accounts.protocol_state)?;
+
+        msg!("Bonus prize set to {} bps of the prize pool", bonus_prize_bps);
+        Ok(())
+    }
+
+    /// Turn the two-stage draw on or off for rounds started from now on
+    /// (authority only). When on, the winning token's prize goes whole to one
+    /// of its submitters, drawn with odds proportional to the loss the price
+    /// verifier attested for them.
+    pub fn set_wallet_draw(ctx: Context<UpdateProtocol>, wallet_draw: bool) -> Result<()> {
+        ConfigChange::WalletDraw(wallet_draw).apply_directly(&mut ctx.accounts.protocol_state)?;
+
+        msg!("Wallet draw {}", if wallet_draw { "enabled" } else { "disabled" });
+        Ok(())
+    }
+
+    /// Set how many rounds a winning token sits out after its win (authority only).
+    /// Wins count once sync_token_stats has folded the round into the registry.
+    pub fn set_winner_cooldown(ctx: Context<UpdateProtocol>, winner_cooldown_rounds: u32) -> Result<()> {
+        ConfigChange::WinnerCooldown(winner_cooldown_rounds).apply_directly(&mut ctx.accounts.protocol_state)?;
+
+        msg!("Winning-token cooldown set to {} rounds", winner_cooldown_rounds);
+        Ok(())
+    }
+
+    /// Set how many rounds a wallet sits out after claiming a prize (authority only).
+    /// Applies to claims made from now on.
+    pub fn set_wallet_cooldown(ctx: Context<UpdateProtocol>, wallet_cooldown_rounds: u32) -> Result<()> {
+        ctx.accounts.protocol_state.wallet_cooldown_rounds = wallet_cooldown_rounds;
+
+        msg!("Winner wallet cooldown set to {} rounds", wallet_cooldown_rounds);
+        Ok(())
+    }
+
+    /// Set the duration of rounds started without an override (authority only).
+    /// It must fall within the current duration bounds.
+    pub fn set_round_duration(ctx: Context<UpdateProtocol>, round_duration: i64) -> Result<()> {
+        ConfigChange::RoundDuration(round_duration).apply_directly(&mut ctx.accounts.protocol_state)?;
+
+        msg!("Round duration set to {}s", round_duration);
+        Ok(())
+    }
+
+    /// Queue a parameter change to take effect after the room's config delay
+    /// (authority only; payer funds the pending account until it's applied)
+    ///
+    /// Only one change may be pending per room. Queueing a ConfigDelay is how
+    /// the delay itself is set.
+    pub fn queue_config(ctx: Context<QueueConfig>, change: ConfigChange) -> Result<()> {
+        let protocol = &ctx.accounts.protocol_state;
+        change.validate(protocol)?;
+        // Fees can only be routed to stakers once the vault exists
+        if let ConfigChange::StakingFee(bps) = change {
+            require!(
+                bps == 0 || ctx.accounts.rewards_vault.is_some(),
+                RecoveryRoomError::MissingRewardsVault
+            );
+        }
+
+        let now = Clock::get()?.unix_timestamp;
+        let pending = &mut ctx.accounts.pending_config;
+        pending.room_id = protocol.room_id;
+        pending.change = change;
+        pending.queued_at = now;
+        pending.eta = now.checked_add(protocol.config_delay_secs).ok_or(RecoveryRoomError::ArithmeticOverflow)?;
+        pending.rent_payer = ctx.accounts.payer.key();
+        pending.bump = ctx.bumps.pending_config;
+
+        emit!(ConfigQueued {
+            room_id: pending.room_id,
+            change,
+            eta: pending.eta,
+        });
+
+        msg!("Config change {:?} queued, applies at {}", change, pending.eta);
+        Ok(())
+    }
+
+    /// Apply a queued parameter change once its delay has passed (permissionless)
+    pub fn apply_config(ctx: Context<ApplyConfig>) -> Result<()> {
+        let pending = &ctx.accounts.pending_config;
+        let now = Clock::get()?.unix_timestamp;
+        require_detail!(
+            RecoveryRoomError::ConfigNotReady,
+            "now",
+            now,
+            at_least pending.eta
+        );
+        let change = pending.change;
+        change.apply(&mut ctx.accounts.protocol_state)?;
+
+        emit!(ConfigApplied {
+            room_id: pending.room_id,
+            change,
+        });
+
+        msg!("Config change {:?} applied", change);
+        Ok(())
+    }
+
+    /// Drop the pending parameter change before it applies (authority only)
+    pub fn cancel_pending_config(ctx: Context<CancelPendingConfig>) -> Result<()> {
+        let pending = &ctx.accounts.pending_config;
+
+        emit!(ConfigCancelled {
+            room_id: pending.room_id,
+            change: pending.change,
+        });
+
+        msg!("Config change {:?} cancelled", pending.change);
+        Ok(())
+    }
+
+    /// Set the room's emergency admins and how many must sign an emergency
+    /// withdrawal (authority only; payer funds the account on first use)
+    ///
+    /// `threshold` (at least MIN_ADMIN_THRESHOLD) of the new admins co-sign,
+    /// and once a set exists, replacing it also takes `threshold` of its
+    /// current admins. Both sign as remaining accounts.
+    pub fn set_admin_set(ctx: Context<SetAdminSet>, admins: Vec<Pubkey>, threshold: u8) -> Result<()> {
+        AdminSet::validate(&admins, threshold)?;
+
+        let admin_set = &mut ctx.accounts.admin_set;
+        if admin_set.threshold > 0 {
+            admin_set.require_threshold(ctx.remaining_accounts)?;
+        }
+        admin_set.room_id = ctx.accounts.protocol_state.room_id;
+        admin_set.admins = admins;
+        admin_set.threshold = threshold;
+        admin_set.bump = ctx.bumps.admin_set;
+        admin_set.require_threshold(ctx.remaining_accounts)?;
+
+        emit!(AdminSetUpdated {
+            room_id: admin_set.room_id,
+            admins: admin_set.admins.clone(),
+            threshold,
+        });
+
+        msg!("Admin set updated: {} of {}", threshold, admin_set.admins.len());
+        Ok(())
+    }
+
+    /// Queue a withdrawal of a round's prize vault funds to `destination`,
+    /// executable after EMERGENCY_WITHDRAW_DELAY_SECS (admin threshold; payer
+    /// funds the pending account)
+    ///
+    /// The admins sign as remaining accounts. One withdrawal may be pending per
+    /// round; any single admin can cancel it during the delay.
+    pub fn queue_emergency_withdraw(
+        ctx: Context<QueueEmergencyWithdraw>,
+        lamports: u64,
+        token_amount: u64,
+    ) -> Result<()> {
+        let approvals = ctx.accounts.admin_set.require_threshold(ctx.remaining_accounts)?;
+        require!(
+            lamports > 0 || token_amount > 0,
+            RecoveryRoomError::ZeroFundingAmount
+        );
+
+        let (token_account, token_destination) = if token_amount > 0 {
+            let (Some(from), Some(to)) = (
+                &ctx.accounts.vault_token_account,
+                &ctx.accounts.destination_token_account,
+            ) else {
+                return err!(RecoveryRoomError::MissingTokenAccounts);
+            };
+            require_keys_eq!(to.mint, from.mint, RecoveryRoomError::MintMismatch);
+            (Some(from.key()), Some(to.key()))
+        } else {
+            (None, None)
+        };
+
+        let round = &ctx.accounts.round_state;
+        let now = Clock::get()?.unix_timestamp;
+        let withdrawal = &mut ctx.accounts.emergency_withdrawal;
+        withdrawal.room_id = round.room_id;
+        withdrawal.round_id = round.round_id;
+        withdrawal.destination = ctx.accounts.destination.key();
+        withdrawal.lamports = lamports;
+        withdrawal.token_account = token_account;
+        withdrawal.token_destination = token_destination;
+        withdrawal.token_amount = token_amount;
+        withdrawal.approvals = approvals;
+        withdrawal.queued_at = now;
+        withdrawal.eta = now
+            .checked_add(EMERGENCY_WITHDRAW_DELAY_SECS)
+            .ok_or(RecoveryRoomError::ArithmeticOverflow)?;
+        withdrawal.rent_payer = ctx.accounts.payer.key();
+        withdrawal.bump = ctx.bumps.emergency_withdrawal;
+
+        emit!(EmergencyWithdrawQueued {
+            room_id: withdrawal.room_id,
+            round_id: withdrawal.round_id,
+            destination: withdrawal.destination,
+            lamports,
+            token_account,
+            token_amount,
+            approvals,
+            eta: withdrawal.eta,
+        });
+
+        msg!(
+            "Emergency withdrawal from round {} queued with {} approvals, executes at {}",
+            withdrawal.round_id,
+            approvals,
+            withdrawal.eta
+        );
+        Ok(())
+    }
+
+    /// Carry out a queued emergency withdrawal once its delay has passed
+    /// (permissionless; the destination was fixed when it was queued)
+    ///
+    /// A Token-2022 mint's transfer hook accounts are passed as remaining accounts.
+    pub fn execute_emergency_withdraw<'info>(
+        ctx: Context<'_, '_, '_, 'info, ExecuteEmergencyWithdraw<'info>>,
+    ) -> Result<()> {
+        let withdrawal = &ctx.accounts.emergency_withdrawal;
+        let now = Clock::get()?.unix_timestamp;
+        require_detail!(
+            RecoveryRoomError::EmergencyWithdrawNotReady,
+            "now",
+            now,
+            at_least withdrawal.eta
+        );
+
+        let round = &ctx.accounts.round_state;
+        let prize_vault_info = ctx.accounts.prize_vault.to_account_info();
+        if withdrawal.lamports > 0 {
+            require_detail!(
+                RecoveryRoomError::InsufficientVaultBalance,
+                "lamports",
+                withdrawal.lamports,
+                at_most ctx.accounts.prize_vault.lamports
+            );
+            move_lamports(&prize_vault_info, &ctx.accounts.destination.to_account_info(), withdrawal.lamports)?;
+            ctx.accounts.prize_vault.lamports = ctx.accounts.prize_vault.lamports.checked_sub(withdrawal.lamports).ok_or(RecoveryRoomError::ArithmeticOverflow)?;
+        }
+
+        let mut tokens_received = 0;
+        if withdrawal.token_amount > 0 {
+            let (Some(from), Some(to), Some(mint), Some(token_program)) = (
+                &ctx.accounts.vault_token_account,
+                &ctx.accounts.destination_token_account,
+                &ctx.accounts.token_mint,
+                &ctx.accounts.token_program,
+            ) else {
+                return err!(RecoveryRoomError::MissingTokenAccounts);
+            };
+            require!(
+                withdrawal.token_account == Some(from.key()) && withdrawal.token_destination == Some(to.key()),
+                RecoveryRoomError::InvalidVaultTokenAccount
+            );
+            require_keys_eq!(from.mint, mint.key(), RecoveryRoomError::MintMismatch);
+
+            let room = room_seed(round.room_id);
+            let round_id_bytes = round.round_id.to_le_bytes();
+            let vault_seeds = &[b"prize_vault".as_ref(), &room, &round_id_bytes, &[ctx.accounts.prize_vault.bump]];
+            tokens_received = transfer::transfer_checked(
+                &token_program.to_account_info(),
+                &from.to_account_info(),
+                &mint.to_account_info(),
+                &to.to_account_info(),
+                &prize_vault_info,
+                ctx.remaining_accounts,
+                withdrawal.token_amount,
+                mint.decimals,
+                &[&vault_seeds[..]],
+            )?;
+        }
+
+        emit!(EmergencyWithdrawExecuted {
+            room_id: withdrawal.room_id,
+            round_id: withdrawal.round_id,
+            destination: withdrawal.destination,
+            lamports: withdrawal.lamports,
+            token_account: withdrawal.token_account,
+            token_amount: tokens_received,
+        });
+
+        msg!("Emergency withdrawal from round {} executed", withdrawal.round_id);
+        Ok(())
+    }
+
+    /// Drop a queued emergency withdrawal during its delay (any one admin)
+    pub fn cancel_emergency_withdraw(ctx: Context<CancelEmergencyWithdraw>) -> Result<()> {
+        let withdrawal = &ctx.accounts.emergency_withdrawal;
+
+        emit!(EmergencyWithdrawCancelled {
+            room_id: withdrawal.room_id,
+            round_id: withdrawal.round_id,
+            cancelled_by: ctx.accounts.admin.key(),
+        });
+
+        msg!("Emergency withdrawal from round {} cancelled", withdrawal.round_id);
+        Ok(())
+    }
+
+    /// Nominate a new protocol authority, e.g. a Realms governance PDA, or
+    /// None to withdraw a nomination (authority only)
+    ///
+    /// The handover completes when the nominee signs accept_authority, so a
+    /// mistyped key can't lock the room. The config setters and queue_config
+    /// take the authority as their only signer and never as a payer, so a DAO
+    /// proposal can execute them directly. Instructions that create accounts
+    /// (create_room, schedule_round, the keeper, integrator and mint lists,
+    /// seasons, leaderboard snapshots and the init_* setup) have the authority
+    /// pay their rent, so a governance authority needs lamports of its own.
+    pub fn propose_authority(ctx: Context<UpdateProtocol>, new_authority: Option<Pubkey>) -> Result<()> {
+        ctx.accounts.protocol_state.pending_authority = new_authority;
+
+        msg!("Pending authority set to {:?}", new_authority);
+        Ok(())
+    }
+
+    /// Take over as protocol authority after being nominated
+    pub fn accept_authority(ctx: Context<AcceptAuthority>) -> Result<()> {
+        let protocol = &mut ctx.accounts.protocol_state;
+        let previous = protocol.authority;
+        protocol.authority = ctx.accounts.new_authority.key();
+        protocol.pending_authority = None;
+
+        emit!(AuthorityTransferred {
+            room_id: protocol.room_id,
+            previous,
+            new_authority: protocol.authority,
+        });
+
+        msg!("Protocol authority transferred from {} to {}", previous, protocol.authority);
+        Ok(())
+    }
+
+    /// Set the bounds a start_round duration override must fall within (authority only)
+    pub fn set_round_duration_bounds(
+        ctx: Context<UpdateProtocol>,
+        min_round_duration: i64,
+        max_round_duration: i64,
+    ) -> Result<()> {
+        require!(
+            min_round_duration > 0 && min_round_duration <= max_round_duration,
+            RecoveryRoomError::InvalidRoundDuration
+        );
+        let protocol = &mut ctx.accounts.protocol_state;
+        protocol.min_round_duration = min_round_duration;
+        protocol.max_round_duration = max_round_duration;
+
+        msg!("Round duration bounds set: {}s to {}s", min_round_duration, max_round_duration);
+        Ok(())
+    }
+
+    /// Set the per-wallet and per-slot participation caps (authority only)
+    pub fn set_rate_limit(ctx: Context<UpdateProtocol>, rate_limit: RateLimit) -> Result<()> {
+        rate_limit.validate()?;
+        ctx.accounts.protocol_state.rate_limit = rate_limit;
+
+        msg!(
+            "Rate limit set: {} per wallet per {}s, {} per slot",
+            rate_limit.max_per_window,
+            rate_limit.window_secs,
+            rate_limit.max_per_slot
+        );
+        Ok(())
+    }
+
+    /// Restrict start_round, request_randomness and consume_randomness to one
+    /// signer, or None to open them to anyone (authority only). Automation
+    /// threads keep working either way.
+    pub fn set_crank_authority(ctx: Context<UpdateProtocol>, crank_authority: Option<Pubkey>) -> Result<()> {
+        ctx.accounts.protocol_state.crank_authority = crank_authority;
+
+        msg!("Crank authority set to {:?}", crank_authority);
+        Ok(())
+    }
+
+    /// Register a keeper, or update its permissions: a bitmask of
+    /// CrankInstruction::permission bits (authority only)
+    pub fn add_keeper(ctx: Context<ManageKeepers>, keeper: Pubkey, permissions: u8) -> Result<()> {
+        require!(
+            permissions != 0 && permissions & !CrankInstruction::ALL_PERMISSIONS == 0,
+            RecoveryRoomError::InvalidKeeperPermissions
+        );
+        let registry = &mut ctx.accounts.keeper_registry;
+        registry.bump = ctx.bumps.keeper_registry;
+        match registry.keepers.iter_mut().find(|k| k.key == keeper) {
+            Some(existing) => existing.permissions = permissions,
+            None => {
+                require_detail!(
+                    RecoveryRoomError::KeeperRegistryFull,
+                    "keeper_count",
+                    registry.keepers.len() + 1,
+                    at_most MAX_KEEPERS
+                );
+                registry.keepers.push(Keeper {
+                    key: keeper,
+                    permissions,
+                    last_active: 0,
+                });
+            }
+        }
+        ctx.accounts.protocol_state.keepers_registered = true;
+
+        msg!("Keeper {} permitted {:#05b}", keeper, permissions);
+        Ok(())
+    }
+
+    /// Approve a partner program to participate_for users (authority only)
+    pub fn add_integrator(ctx: Context<ManageIntegrators>, program: Pubkey) -> Result<()> {
+        let registry = &mut ctx.accounts.integrator_registry;
+        registry.bump = ctx.bumps.integrator_registry;
+        if !registry.programs.contains(&program) {
+            require_detail!(
+                RecoveryRoomError::IntegratorRegistryFull,
+                "integrator_count",
+                registry.programs.len() + 1,
+                at_most MAX_INTEGRATORS
+            );
+            registry.programs.push(program);
+        }
+
+        msg!("Integrator program {} approved", program);
+        Ok(())
+    }
+
+    /// Revoke a partner program's participate_for approval (authority only)
+    pub fn remove_integrator(ctx: Context<ManageIntegrators>, program: Pubkey) -> Result<()> {
+        let registry = &mut ctx.accounts.integrator_registry;
+        let index = registry
+            .programs
+            .iter()
+            .position(|p| *p == program)
+            .ok_or(RecoveryRoomError::UnknownIntegrator)?;
+        registry.programs.swap_remove(index);
+
+        msg!("Integrator program {} removed", program);
+        Ok(())
+    }
+
+    /// Deregister a keeper; cranking reopens once none remain and no crank
+    /// authority is set (authority only)
+    pub fn remove_keeper(ctx: Context<ManageKeepers>, keeper: Pubkey) -> Result<()> {
+        let registry = &mut ctx.accounts.keeper_registry;
+        let index = registry
+            .keepers
+            .iter()
+            .position(|k| k.key == keeper)
+            .ok_or(RecoveryRoomError::KeeperNotFound)?;
+        registry.keepers.swap_remove(index);
+        ctx.accounts.protocol_state.keepers_registered = !registry.keepers.is_empty();
+
+        msg!("Keeper {} removed", keeper);
+        Ok(())
+    }
+
+    /// Set the anti-snipe rule for rounds started from now on (authority only)
+    pub fn set_anti_snipe(ctx: Context<UpdateProtocol>, anti_snipe: AntiSnipe) -> Result<()> {
+        ConfigChange::AntiSnipe(anti_snipe).apply_directly(&mut ctx.accounts.protocol_state)?;
+
+        msg!(
+            "Anti-snipe set: +{}s for entries in the last {}s, up to {}s total",
+            anti_snipe.extension_secs,
+            anti_snipe.window_secs,
+            anti_snipe.max_extension_secs
+        );
+        Ok(())
+    }
+
+    /// Set the entry fee charged by rounds started from now on (authority only)
+    ///
+    /// With `mint` set the fee is paid in that SPL token (e.g. a partner
+    /// community's token) and may be burned on entry; otherwise it is paid in
+    /// lamports. Either way it lands in the round's prize vault.
+    pub fn set_entry_fee(ctx: Context<UpdateProtocol>, entry_fee: EntryFee) -> Result<()> {
+        ConfigChange::EntryFee(entry_fee).apply_directly(&mut ctx.accounts.protocol_state)?;
+
+        msg!("Entry fee set to {} (mint {:?}, burn {})", entry_fee.amount, entry_fee.mint, entry_fee.burn);
+        Ok(())
+    }
+
+    /// Pay rounds started from now on a fixed stablecoin prize out of the
+    /// treasury's token account for its mint, or None to go back to SOL
+    /// prizes (authority only). The treasury must be funded to cover claims.
+    pub fn set_stable_prize(ctx: Context<UpdateProtocol>, stable_prize: Option<StablePrize>) -> Result<()> {
+        ConfigChange::StablePrize(stable_prize).apply_directly(&mut ctx.accounts.protocol_state)?;
+
+        msg!("Stable prize set to {:?}", stable_prize);
+        Ok(())
+    }
+
+    /// Set the refundable lamport deposit taken per participation in rounds
+    /// started from now on (authority only)
+    pub fn set_entry_deposit(ctx: Context<UpdateProtocol>, lamports: u64) -> Result<()> {
+        ConfigChange::EntryDeposit(lamports).apply_directly(&mut ctx.accounts.protocol_state)?;
+
+        msg!("Entry deposit set to {} lamports", lamports);
+        Ok(())
+    }
+
+    /// Set the lamport bounty paid to the caller of each crank instruction (authority only)
+    pub fn set_keeper_bounties(ctx: Context<UpdateProtocol>, bounties: KeeperBounties) -> Result<()> {
+        ConfigChange::KeeperBounties(bounties).apply_directly(&mut ctx.accounts.protocol_state)?;
+
+        msg!(
+            "Keeper bounties set: start {} / request {} / consume {}",
+            bounties.start_round,
+            bounties.request_randomness,
+            bounties.consume_randomness
+        );
+        Ok(())
+    }
+
+    /// Set the XP rates applied to new participations (authority only)
+    pub fn set_xp_rates(ctx: Context<UpdateProtocol>, rates: XpRates) -> Result<()> {
+        ctx.accounts.protocol_state.xp_rates = rates;
+
+        msg!(
+            "XP rates set: {} per entry / {} per token / {} per $ lost (capped at ${})",
+            rates.per_participation,
+            rates.per_token,
+            rates.per_loss_dollar,
+            rates.max_loss_dollars
+        );
+        Ok(())
+    }
+
+    /// Replace the list of mints barred from participation (authority only)
+    pub fn set_mint_blacklist(ctx: Context<SetMintBlacklist>, mints: Vec<Pubkey>) -> Result<()> {
+        require_detail!(
+            RecoveryRoomError::MintListFull,
+            "mint_count",
+            mints.len(),
+            at_most MAX_MINT_LIST_LEN
+        );
+        ctx.accounts.protocol_state.blacklist_enabled = !mints.is_empty();
+
+        let list = &mut ctx.accounts.mint_blacklist;
+        list.mints = mints;
+        list.bump = ctx.bumps.mint_blacklist;
+
+        msg!("Mint blacklist set: {} mints", list.mints.len());
+        Ok(())
+    }
+
+    /// Set the oracle whose ed25519 rug attestations entries require, or None to
+    /// stop requiring them (authority only)
+    pub fn set_rug_verifier(ctx: Context<UpdateProtocol>, rug_verifier: Option<Pubkey>) -> Result<()> {
+        ConfigChange::RugVerifier(rug_verifier).apply_directly(&mut ctx.accounts.protocol_state)?;
+
+        msg!("Rug verifier set to {:?}", rug_verifier);
+        Ok(())
+    }
+
+    /// Set the oracle whose cost-basis and price attestations determine entries'
+    /// loss percentages, or None to trust self-reported losses (authority only)
+    pub fn set_price_verifier(ctx: Context<UpdateProtocol>, price_verifier: Option<Pubkey>) -> Result<()> {
+        ConfigChange::PriceVerifier(price_verifier).apply_directly(&mut ctx.accounts.protocol_state)?;
+
+        msg!("Price verifier set to {:?}", price_verifier);
+        Ok(())
+    }
+
+    /// Make the room members-only by requiring a token or NFT pass to
+    /// participate, or None to open it to everyone (authority only)
+    pub fn set_access_pass(ctx: Context<UpdateProtocol>, access_pass: Option<AccessPass>) -> Result<()> {
+        if let Some(pass) = &access_pass {
+            pass.validate()?;
+        }
+        ctx.accounts.protocol_state.access_pass = access_pass;
+
+        msg!("Access pass set to {:?}", access_pass);
+        Ok(())
+    }
+
+    /// Replace the list of mints allowed in whitelist-only mode, and toggle that mode (authority only)
+    pub fn set_mint_whitelist(
+        ctx: Context<SetMintWhitelist>,
+        mints: Vec<Pubkey>,
+        whitelist_only: bool,
+    ) -> Result<()> {
+        require_detail!(
+            RecoveryRoomError::MintListFull,
+            "mint_count",
+            mints.len(),
+            at_most MAX_MINT_LIST_LEN
+        );
+        ctx.accounts.protocol_state.whitelist_only = whitelist_only;
+
+        let list = &mut ctx.accounts.mint_whitelist;
+        list.mints = mints;
+        list.bump = ctx.bumps.mint_whitelist;
+
+        msg!("Mint whitelist set: {} mints, whitelist-only {}", list.mints.len(), whitelist_only);
+        Ok(())
+    }
+
+    /// Set the fee slice routed to the consolation pool and the per-claim reward (authority only)
+    pub fn set_consolation(ctx: Context<UpdateProtocol>, fee_bps: u16, reward_lamports: u64) -> Result<()> {
+        ConfigChange::Consolation(fee_bps, reward_lamports).apply_directly(&mut ctx.accounts.protocol_state)?;
+
+        msg!("Consolation set: {} bps of fees, {} lamports per claim", fee_bps, reward_lamports);
+        Ok(())
+    }
+
+    /// Make rounds started from now on raffle-ticketed (authority only)
+    ///
+    /// A ticketed round draws with Linear weighting, so each submission is
+    /// one ticket, and mints every entrant one ticket token per submission
+    /// they add. Ticket balances are fixed at entry: transferring tickets
+    /// doesn't move odds. start_round creates each round's ticket mint, and
+    /// entering creates the entrant's ticket account.
+    pub fn set_ticket_mode(ctx: Context<UpdateProtocol>, enabled: bool) -> Result<()> {
+        ConfigChange::TicketMode(enabled).apply_directly(&mut ctx.accounts.protocol_state)?;
+
+        msg!("Ticket mode set to {}", enabled);
+        Ok(())
+    }
+
+    /// Permanently lock the room's rules (authority only)
+    ///
+    /// Every config setter, the config queue, the keeper, integrator and mint
+    /// lists, round scheduling, branding and seeding, seasons, moderation,
+    /// canonical mints, escrow liquidation and emergency and treasury
+    /// withdrawals are refused from then on, and a pending authority
+    /// nomination is dropped. There is no way back. Only one-time account
+    /// setup carries on; the oracle fallback stays as approved when
+    /// finalizing.
+    pub fn finalize_protocol(ctx: Context<UpdateProtocol>) -> Result<()> {
+        let protocol = &mut ctx.accounts.protocol_state;
+        protocol.finalized = true;
+        protocol.pending_authority = None;
+
+        emit!(ProtocolFinalized {
+            room_id: protocol.room_id,
+            finalized_at: Clock::get()?.unix_timestamp,
+        });
+
+        msg!("Room {} finalized; its rules can no longer change", protocol.room_id);
+        Ok(())
+    }
+
+    /// Set how long winners of rounds settled from now on have to claim; 0
+    /// lets claims run forever (authority only)
+    pub fn set_claim_window(ctx: Context<UpdateProtocol>, claim_window_secs: i64) -> Result<()> {
+        ConfigChange::ClaimWindow(claim_window_secs).apply_directly(&mut ctx.accounts.protocol_state)?;
+
+        msg!("Claim window set to {} seconds", claim_window_secs);
+        Ok(())
+    }
+
+    /// Override the weighting strategy of a round before it takes entries
+    /// (authority only). The override can't wait out a timelock, so a room
+    /// with a config delay changes weighting through set_weighting_strategy.
+    pub fn set_round_weighting(
+        ctx: Context<SetRoundWeighting>,
+        weighting: WeightingStrategy,
+    ) -> Result<()> {
+        let protocol = &ctx.accounts.protocol_state;
+        require!(protocol.config_delay_secs == 0, RecoveryRoomError::ConfigTimelocked);
+        ConfigChange::Weighting(weighting).validate(protocol)?;
+        let round = &mut ctx.accounts.round_state;
+
+        require!(
+            round.status == RoundStatus::Active && round.total_token_entries == 0,
+            RecoveryRoomError::InvalidRoundStatus
+        );
+        require!(!round.ticketed, RecoveryRoomError::TicketedRoundWeighting);
+        round.weighting = weighting;
+
+        msg!("Round {} weighting set to {:?}", round.round_id, weighting);
+        Ok(())
+    }
+
+    /// Override an active round's participant cap, e.g. for a small exclusive
+    /// round; it can't drop below the entrants it already has (0 = unlimited,
+    /// authority only)
+    pub fn set_round_max_participants(ctx: Context<SetRoundWeighting>, max_participants: u32) -> Result<()> {
+        let round = &mut ctx.accounts.round_state;
+
+        require!(
+            round.status == RoundStatus::Active,
+            RecoveryRoomError::InvalidRoundStatus
+        );
+        if max_participants > 0 {
+            require_detail!(
+                RecoveryRoomError::InvalidMaxParticipants,
+                "max_participants",
+                max_participants,
+                at_least round.min_participants.max(round.total_participants)
+            );
+        }
+        round.max_participants = max_participants;
+
+        msg!("Round {} capped at {} participants", round.round_id, max_participants);
+        Ok(())
+    }
+
+    /// Configure the primary and optional fallback Switchboard queues (authority only)
+    pub fn set_oracle_queues(
+        ctx: Context<UpdateProtocol>,
+        oracle_queue: Pubkey,
+        fallback_oracle_queue: Option<Pubkey>,
+    ) -> Result<()> {
+        let protocol = &mut ctx.accounts.protocol_state;
+
+        require!(
+            fallback_oracle_queue != Some(oracle_queue),
+            RecoveryRoomError::OracleQueueNotAllowed
+        );
+        protocol.oracle_queue = oracle_queue;
+        protocol.fallback_oracle_queue = fallback_oracle_queue;
+        protocol.failover_approved = false;
+        protocol.primary_queue_failures = 0;
+
+        msg!("Oracle queues set: primary {}, fallback {:?}", oracle_queue, fallback_oracle_queue);
+        Ok(())
+    }
+
+    /// Bind the protocol to the Switchboard VRF account every draw must use
+    /// (authority only). The VRF's authority must be the protocol PDA, which
+    /// signs its randomness requests.
+    pub fn init_vrf(ctx: Context<InitVrf>) -> Result<()> {
+        let protocol_key = ctx.accounts.protocol_state.key();
+        require_keys_eq!(
+            ctx.accounts.vrf.load()?.authority,
+            protocol_key,
+            RecoveryRoomError::VrfAuthorityMismatch
+        );
+        let vrf = ctx.accounts.vrf.key();
+        ctx.accounts.protocol_state.vrf = vrf;
+
+        msg!("VRF account set to {}", vrf);
+        Ok(())
+    }
+
+    /// Approve or revoke use of the fallback oracle queue (authority only)
+    pub fn approve_failover(ctx: Context<UpdateProtocol>, approved: bool) -> Result<()> {
+        let protocol = &mut ctx.accounts.protocol_state;
+
+        require!(
+            !approved || protocol.fallback_oracle_queue.is_some(),
+            RecoveryRoomError::OracleQueueNotAllowed
+        );
+        protocol.failover_approved = approved;
+
+        msg!("Oracle failover approved: {}", approved);
+        Ok(())
+    }
+
+    /// Withdraw collected fees from the treasury (authority only)
+    ///
+    /// A Token-2022 mint's transfer hook accounts are passed as remaining accounts.
+    pub fn withdraw_treasury<'info>(
+        ctx: Context<'_, '_, '_, 'info, WithdrawTreasury<'info>>,
+        lamports: u64,
+        token_amount: u64,
+    ) -> Result<()> {
+        let treasury_info = ctx.accounts.treasury.to_account_info();
+
+        if lamports > 0 {
+            let rent_floor = Rent::get()?.minimum_balance(treasury_info.data_len());
+            require_detail!(
+                RecoveryRoomError::InsufficientVaultBalance,
+                "lamports",
+                lamports,
+                at_most treasury_info.lamports().saturating_sub(rent_floor)
+            );
+            move_lamports(&treasury_info, &ctx.accounts.destination.to_account_info(), lamports)?;
+        }
+
+        if token_amount > 0 {
+            let (Some(from), Some(to), Some(mint)) = (
+                &ctx.accounts.treasury_token_account,
+                &ctx.accounts.destination_token_account,
+                &ctx.accounts.token_mint,
+            ) else {
+                return err!(RecoveryRoomError::MissingTokenAccounts);
+            };
+            require_keys_eq!(from.mint, mint.key(), RecoveryRoomError::MintMismatch);
+            require_keys_eq!(to.mint, mint.key(), RecoveryRoomError::MintMismatch);
+
+            let room = room_seed(ctx.accounts.protocol_state.room_id);
+            let treasury_seeds = &[b"treasury".as_ref(), &room, &[ctx.accounts.treasury.bump]];
+            transfer::transfer_checked(
+                &ctx.accounts.token_program.to_account_info(),
+                &from.to_account_info(),
+                &mint.to_account_info(),
+                &to.to_account_info(),
+                &treasury_info,
+                ctx.remaining_accounts,
+                token_amount,
+                mint.decimals,
+                &[&treasury_seeds[..]],
+            )?;
+        }
+
+        let treasury = &mut ctx.accounts.treasury;
+        treasury.total_withdrawn = treasury.total_withdrawn.checked_add(lamports).ok_or(RecoveryRoomError::ArithmeticOverflow)?;
+
+        emit!(TreasuryWithdrawn {
+            destination: ctx.accounts.destination.key(),
+            lamports,
+            token_amount,
+        });
+
+        msg!("Treasury withdrawal: {} lamports, {} tokens", lamports, token_amount);
+        Ok(())
+    }
+
+    /// Bulk-populate an active round with synthetic pool entries (devnet load testing)
+    ///
+    /// Fails with TestHarnessDisabled unless built with the `test-harness`
+    /// feature, which compiles the seeding in. The pool must first be grown
+    /// with expand_token_pool to fit `token_count`.
+    pub fn seed_test_round(
+        ctx: Context<SeedTestRound>,
+        token_count: u16,
+        submissions_per_token: u32,
+        participants: u32,
+    ) -> Result<()> {
+        #[cfg(feature = "test-harness")]
+        return seed_synthetic_pool(ctx, token_count, submissions_per_token, participants);
+
+        #[cfg(not(feature = "test-harness"))]
+        {
+            let _ = (ctx, token_count, submissions_per_token, participants);
+            err!(RecoveryRoomError::TestHarnessDisabled)
+        }
+    }
+
+    /// Close stale Complete/Cancelled rounds passed as remaining accounts (keeper)
+    ///
+    /// Remaining accounts are (round, prize vault) pairs; the vault is only
+    /// read, to check it is empty or closed. The caller keeps GC_REWARD_BPS of
+    /// the reclaimed rent; the rest feeds the jackpot.
+    pub fn gc_rounds(ctx: Context<GcRounds>) -> Result<()> {
+        let protocol = &ctx.accounts.protocol_state;
+        let clock = Clock::get()?;
+
+        let pairs = ctx.remaining_accounts.len() / 2;
+        require!(
+            pairs > 0 && pairs <= MAX_GC_ROUNDS && ctx.remaining_accounts.len().is_multiple_of(2),
+            RecoveryRoomError::InvalidGcBatch
+        );
+
+        let mut reclaimed: u64 = 0;
+        for pair in ctx.remaining_accounts.chunks(2) {
+            collectable_round(protocol, &pair[0], &pair[1], clock.unix_timestamp)?;
+            reclaimed += close_program_account(&pair[0])?;
+        }
+
+        let caller_reward = share_reclaimed_rent(
+            reclaimed,
+            &ctx.accounts.caller.to_account_info(),
+            &mut ctx.accounts.jackpot,
+        )?;
+
+        emit!(RoundsCollected {
+            rounds_closed: pairs as u8,
+            rent_reclaimed: reclaimed,
+            caller_reward,
+        });
+
+        msg!("Collected {} rounds, {} lamports reclaimed", pairs, reclaimed);
+        Ok(())
+    }
+
+    /// Close an expired round's token pool, and the pool shards and pool
+    /// entries passed as remaining accounts, once its claim deadline has
+    /// passed or it was cancelled (permissionless). The caller keeps
+    /// GC_REWARD_BPS of the reclaimed rent; the rest feeds the jackpot. The
+    /// RoundState itself is left for gc_rounds or archive_round.
+    ///
+    /// Pool and shard stats must already be folded into the token registries
+    /// by sync_token_stats.
+    pub fn gc_round(ctx: Context<GcRound>) -> Result<()> {
+        let round = &ctx.accounts.round_state;
+        let now = Clock::get()?.unix_timestamp;
+        require!(
+            round.status == RoundStatus::Cancelled
+                || (round.status == RoundStatus::Complete && !round.claims_open(now)),
+            RecoveryRoomError::RoundNotCollectable
+        );
+
+        let mut reclaimed: u64 = 0;
+        for info in ctx.remaining_accounts {
+            require!(info.is_writable, RecoveryRoomError::InvalidGcAccount);
+            require_keys_eq!(*info.owner, crate::ID, RecoveryRoomError::InvalidGcAccount);
+            let is_shard = info.try_borrow_data()?.get(..8)
+                == Some(&<TokenPoolShard as anchor_lang::Discriminator>::DISCRIMINATOR[..]);
+            if is_shard {
+                let shard = load_shard(info)?;
+                require!(shard.round_id == round.round_id, RecoveryRoomError::InvalidGcAccount);
+                require_keys_eq!(
+                    info.key(),
+                    shard_address(round, shard.index, shard.bump)?,
+                    RecoveryRoomError::InvalidGcAccount
+                );
+                require!(
+                    shard.entries().iter().all(|e| e.stats_synced != 0),
+                    RecoveryRoomError::TokenStatsNotSynced
+                );
+            } else {
+                let entry = PoolEntry::try_deserialize(&mut &info.try_borrow_data()?[..])?;
+                require_keys_eq!(
+                    info.key(),
+                    pool_entry_address(round, &entry.token_mint),
+                    RecoveryRoomError::InvalidGcAccount
+                );
+            }
+            reclaimed += close_program_account(info)?;
+        }
+        let mut accounts_closed = ctx.remaining_accounts.len() as u32;
+
+        if let Some(token_pool) = &ctx.accounts.token_pool {
+            require!(
+                token_pool.load_pool()?.entries().iter().all(|e| e.stats_synced != 0),
+                RecoveryRoomError::TokenStatsNotSynced
+            );
+            reclaimed += close_program_account(&token_pool.to_account_info())?;
+            accounts_closed += 1;
+        }
+
+        let caller_reward = share_reclaimed_rent(
+            reclaimed,
+            &ctx.accounts.caller.to_account_info(),
+            &mut ctx.accounts.jackpot,
+        )?;
+
+        emit!(RoundAccountsCollected {
+            round_id: round.round_id,
+            accounts_closed,
+            rent_reclaimed: reclaimed,
+            caller_reward,
+        });
+
+        msg!("Round {}: collected {} accounts, {} lamports reclaimed", round.round_id, accounts_closed, reclaimed);
+        Ok(())
+    }
+
+    /// Append a collectable round's summary to the room's RoundArchive, then
+    /// close its RoundState as gc_rounds would (permissionless; the caller
+    /// funds the archive's growth and keeps GC_REWARD_BPS of the round's rent,
+    /// the rest feeds the jackpot)
+    pub fn archive_round(ctx: Context<ArchiveRound>) -> Result<()> {
+        let protocol = &ctx.accounts.protocol_state;
+        let round_info = ctx.accounts.round_state.to_account_info();
+        let round = collectable_round(
+            protocol,
+            &round_info,
+            &ctx.accounts.prize_vault,
+            Clock::get()?.unix_timestamp,
+        )?;
+
+        let record = ArchivedRound {
+            round_id: round.round_id,
+            prize_pool: round.prize_pool,
+            end_time: round.end_time,
+            winner_token: round.winner_token.unwrap_or_default(),
+            vrf_result_hash: round.vrf_result.map_or([0; 32], |r| keccak::hash(&r).to_bytes()),
+            total_participants: round.total_participants,
+            total_token_entries: round.total_token_entries,
+            status: round.status as u8,
+            _padding: [0; 7],
+        };
+
+        let archive = &mut ctx.accounts.archive;
+        archive.room_id = protocol.room_id;
+        archive.bump = ctx.bumps.archive;
+        let archive_info = archive.to_account_info();
+        let offset = RoundArchive::space(archive.len as usize);
+        realloc::grow(
+            &archive_info,
+            offset + RoundArchive::RECORD_LEN,
+            RoundArchive::space(MAX_ARCHIVED_ROUNDS),
+            &ctx.accounts.caller.to_account_info(),
+            &ctx.accounts.system_program.to_account_info(),
+        )?;
+        archive_info.try_borrow_mut_data()?[offset..offset + RoundArchive::RECORD_LEN]
+            .copy_from_slice(bytemuck::bytes_of(&record));
+        let index = archive.len;
+        archive.len += 1;
+
+        let reclaimed = close_program_account(&round_info)?;
+        let caller_reward = share_reclaimed_rent(
+            reclaimed,
+            &ctx.accounts.caller.to_account_info(),
+            &mut ctx.accounts.jackpot,
+        )?;
+
+        emit!(RoundArchived {
+            round_id: record.round_id,
+            index,
+            rent_reclaimed: reclaimed,
+            caller_reward,
+        });
+
+        msg!("Round {} archived at index {}", record.round_id, index);
+        Ok(())
+    }
+
+    /// Grow the protocol account to the current layout and stamp its version
+    /// (authority only; payer funds rent)
+    pub fn migrate_protocol(ctx: Context<MigrateProtocol>, _room_id: u32) -> Result<()> {
+        let info = ctx.accounts.protocol_state.to_account_info();
+        let mut protocol = realloc::upgrade::<ProtocolState>(
+            &info,
+            ProtocolState::SIZE,
+            &ctx.accounts.payer.to_account_info(),
+            &ctx.accounts.system_program.to_account_info(),
+        )?;
+        require_keys_eq!(
+            protocol.authority,
+            ctx.accounts.authority.key(),
+            RecoveryRoomError::InvalidMigrationAccount
+        );
+        let from_version = protocol.version;
+        require!(from_version < PROTOCOL_STATE_VERSION, RecoveryRoomError::AlreadyMigrated);
+
+        protocol.version = PROTOCOL_STATE_VERSION;
+        realloc::store(&info, &protocol)?;
+
+        emit!(AccountMigrated {
+            account: info.key(),
+            from_version,
+            to_version: PROTOCOL_STATE_VERSION,
+        });
+        Ok(())
+    }
+
+    /// Grow a round to the current layout and stamp its version (permissionless; payer funds rent)
+    pub fn migrate_round(ctx: Context<MigrateRound>, _room_id: u32, _round_id: u64) -> Result<()> {
+        let info = ctx.accounts.round_state.to_account_info();
+        let mut round = realloc::upgrade::<RoundState>(
+            &info,
+            RoundState::SIZE,
+            &ctx.accounts.payer.to_account_info(),
+            &ctx.accounts.system_program.to_account_info(),
+        )?;
+        let from_version = round.version;
+        require!(from_version < ROUND_STATE_VERSION, RecoveryRoomError::AlreadyMigrated);
+
+        round.version = ROUND_STATE_VERSION;
+        realloc::store(&info, &round)?;
+
+        emit!(AccountMigrated {
+            account: info.key(),
+            from_version,
+            to_version: ROUND_STATE_VERSION,
+        });
+        Ok(())
+    }
+
+    /// Grow a participation to the current layout and stamp its version
+    /// (permissionless; payer funds rent)
+    pub fn migrate_participation(
+        ctx: Context<MigrateParticipation>,
+        _room_id: u32,
+        _round_id: u64,
+        _user: Pubkey,
+    ) -> Result<()> {
+        let info = ctx.accounts.participation.to_account_info();
+        let mut participation = realloc::upgrade::<Participation>(
+            &info,
+            Participation::SIZE,
+            &ctx.accounts.payer.to_account_info(),
+            &ctx.accounts.system_program.to_account_info(),
+        )?;
+        let from_version = participation.version;
+        require!(from_version < PARTICIPATION_VERSION, RecoveryRoomError::AlreadyMigrated);
+
+        participation.version = PARTICIPATION_VERSION;
+        realloc::store(&info, &participation)?;
+
+        emit!(AccountMigrated {
+            account: info.key(),
+            from_version,
+            to_version: PARTICIPATION_VERSION,
+        });
+        Ok(())
+    }
+}
+
+/// PDA seed of the ProtocolState singleton
+#[constant]
+pub const PROTOCOL_SEED: &[u8] = b"protocol";
+
+/// PDA seed of a RoundState, followed by the round id (u64 LE)
+#[constant]
+pub const ROUND_SEED: &[u8] = b"round";
+
+/// PDA seed of a Participation, followed by the round and user keys. The
+/// round key already scopes it to a room.
+#[constant]
+pub const PARTICIPATION_SEED: &[u8] = b"participation";
+
+/// Room created by initialize_protocol; its PDAs carry no room seed
+#[constant]
+pub const DEFAULT_ROOM_ID: u32 = 0;
+
+/// Max rooms one deployment can hold, the default room included
+pub const MAX_ROOMS: usize = 32;
+
+/// Seed scoping a room's protocol, round, per-round and per-room PDAs
+/// (treasury, jackpot, leaderboard, consolation pool, user stats), placed
+/// right after their tag. Empty for the default room, so its accounts keep the
+/// addresses they had before rooms existed.
+pub fn room_seed(room_id: u32) -> Vec<u8> {
+    if room_id == DEFAULT_ROOM_ID {
+        Vec::new()
+    } else {
+        room_id.to_le_bytes().to_vec()
+    }
+}
+
+/// Number of prize tiers drawn per round (1st/2nd/3rd token)
+pub const MAX_WINNER_TIERS: usize = 3;
+
+/// Seconds a VRF request may stay unanswered before it can be retried
+pub const VRF_TIMEOUT_SECS: i64 = 300;
+
+/// Shortest time between commit_randomness and reveal_randomness
+pub const COMMIT_REVEAL_DELAY_SECS: i64 = 60;
+
+/// Slots between seal_randomness and the slot whose hash salts the reveal
+pub const SALT_SLOT_DELAY: u64 = 10;
+
+/// Seconds past a committed round's end after which, still unrevealed, it
+/// can be voided, so withholding the reveal can't hold its entries hostage
+pub const REVEAL_TIMEOUT_SECS: i64 = 3600;
+
+/// Slots after a round's first VRF request whose hash resolve_with_slothash
+/// may settle it from (~1 hour)
+pub const VRF_FALLBACK_DELAY_SLOTS: u64 = 9_000;
+
+/// Consecutive unanswered primary-queue requests before failover is allowed
+pub const FAILOVER_THRESHOLD: u8 = 3;
+
+/// Max recipients a single prize claim can be split across
+pub const MAX_CLAIM_RECIPIENTS: usize = 5;
+
+/// Max rounds a single claim_many call will settle
+pub const MAX_CLAIM_BATCH: usize = 8;
+
+/// Accounts claim_many takes per round: round, prize vault, participation
+pub const CLAIM_BATCH_STRIDE: usize = 3;
+
+/// Upper bound on the protocol fee (10%)
+#[constant]
+pub const MAX_PROTOCOL_FEE_BPS: u16 = 1_000;
+
+/// How long a settled round is kept before gc_rounds may close it
+pub const ROUND_RETENTION_SECS: i64 = 7 * 24 * 60 * 60;
+
+/// Max round accounts a single gc_rounds call will close
+pub const MAX_GC_ROUNDS: usize = 10;
+
+/// Current account layout versions. Bump one when its struct gains fields
+/// (always appended), so migrate_* can bring older accounts up to date.
+pub const PROTOCOL_STATE_VERSION: u8 = 18;
+pub const ROUND_STATE_VERSION: u8 = 16;
+pub const PARTICIPATION_VERSION: u8 = 7;
+
+/// Most rounds a room's RoundArchive holds
+pub const MAX_ARCHIVED_ROUNDS: usize = 50_000;
+
+/// Share of reclaimed rent paid to the gc_rounds, gc_round and archive_round caller
+pub const GC_REWARD_BPS: u64 = 1_000;
+
+/// Anchor instruction discriminator: sha256("global:<name>")[..8]
+pub(crate) fn instruction_discriminator(name: &str) -> [u8; 8] {
+    let preimage = format!("global:{}", name);
+    let mut out = [0u8; 8];
+    out.copy_from_slice(&hash(preimage.as_bytes()).to_bytes()[..8]);
+    out
+}
+
+/// Thread ids under the thread authority PDA
+pub const START_ROUND_THREAD_ID: &[u8] = b"rr-start-round";
+pub const REQUEST_RANDOMNESS_THREAD_ID: &[u8] = b"rr-request-vrf";
+
+/// Static Switchboard accounts the request thread replays every round
+pub const REQUEST_THREAD_SWITCHBOARD_ACCOUNTS: usize = 10;
+
+fn round_pda(room_id: u32, round_id: u64) -> Pubkey {
+    Pubkey::find_program_address(&[ROUND_SEED, &room_seed(room_id), &round_id.to_le_bytes()], &crate::ID).0
+}
+
+fn token_pool_pda(room_id: u32, round_id: u64) -> Pubkey {
+    Pubkey::find_program_address(&[b"token_pool", &room_seed(room_id), &round_id.to_le_bytes()], &crate::ID).0
+}
+
+fn schedule_pda(room_id: u32) -> Pubkey {
+    Pubkey::find_program_address(&[b"schedule", &room_seed(room_id)], &crate::ID).0
+}
+
+fn prize_vault_pda(room_id: u32, round_id: u64) -> Pubkey {
+    Pubkey::find_program_address(&[b"prize_vault", &room_seed(room_id), &round_id.to_le_bytes()], &crate::ID).0
+}
+
+/// Accounts `event_cpi` appends to a threaded instruction
+fn event_cpi_accounts() -> Vec<automation::SerializableAccount> {
+    #[cfg(feature = "event-cpi")]
+    {
+        let event_authority = Pubkey::find_program_address(&[b"__event_authority"], &crate::ID).0;
+        vec![
+            automation::SerializableAccount::readonly(event_authority, false),
+            automation::SerializableAccount::readonly(crate::ID, false),
+        ]
+    }
+    #[cfg(not(feature = "event-cpi"))]
+    vec![]
+}
+
+/// start_round for `room_id`'s round `round_id` as run by the start thread;
+/// the worker pays rent. `scheduled` passes the room's schedule.
+fn start_round_thread_ix(
+    protocol: Pubkey,
+    jackpot: Pubkey,
+    treasury: Pubkey,
+    thread: Pubkey,
+    room_id: u32,
+    round_id: u64,
+    scheduled: bool,
+) -> automation::SerializableInstruction {
+    use automation::SerializableAccount as Meta;
+
+    // Absent optional accounts are passed as the program id
+    let previous_round = if round_id > 1 { round_pda(room_id, round_id - 1) } else { crate::ID };
+    let schedule = if scheduled { schedule_pda(room_id) } else { crate::ID };
+    let mut accounts = vec![
+        Meta::mutable(protocol, false),
+        Meta::mutable(round_pda(room_id, round_id), false),
+        Meta::mutable(prize_vault_pda(room_id, round_id), false),
+        Meta::mutable(token_pool_pda(room_id, round_id), false),
+        Meta::mutable(jackpot, false),
+        Meta::mutable(treasury, false),
+        Meta::readonly(previous_round, false),
+        Meta::mutable(automation::CLOCKWORK_PAYER, true),
+        Meta::readonly(thread, true),
+        Meta::readonly(crate::ID, false),
+        Meta::mutable(schedule, false),
+        Meta::readonly(crate::ID, false),
+        Meta::readonly(crate::ID, false),
+        Meta::readonly(system_program::ID, false),
+    ];
+    accounts.extend(event_cpi_accounts());
+
+    // Threaded rounds run the protocol's defaults, or a scheduled event's
+    let mut data = instruction_discriminator("start_round").to_vec();
+    data.push(0); // duration: None
+    data.push(0); // max_tokens: None
+
+    automation::SerializableInstruction {
+        program_id: crate::ID,
+        accounts,
+        data,
+    }
+}
+
+/// request_randomness for `round` as run by the request thread, which also
+/// acts as the VRF payer. `switchboard` holds the static accounts in
+/// RequestRandomness order, payer excluded.
+fn request_randomness_thread_ix(
+    protocol: Pubkey,
+    room_id: u32,
+    round_id: u64,
+    switchboard: &[Pubkey],
+    treasury: Pubkey,
+    thread: Pubkey,
+) -> automation::SerializableInstruction {
+    use automation::SerializableAccount as Meta;
+
+    let &[vrf, oracle_queue, queue_authority, data_buffer, permission, escrow, payer_wallet, recent_blockhashes, sb_state, sb_program] =
+        switchboard
+    else {
+        unreachable!("caller passes REQUEST_THREAD_SWITCHBOARD_ACCOUNTS keys");
+    };
+    let mut accounts = vec![
+        Meta::mutable(protocol, false),
+        Meta::mutable(round_pda(room_id, round_id), false),
+        Meta::mutable(token_pool_pda(room_id, round_id), false),
+        Meta::mutable(vrf, false),
+        Meta::mutable(oracle_queue, false),
+        Meta::readonly(queue_authority, false),
+        Meta::mutable(data_buffer, false),
+        Meta::readonly(permission, false),
+        Meta::mutable(escrow, false),
+        Meta::mutable(payer_wallet, false),
+        Meta::mutable(thread, true),
+        Meta::readonly(recent_blockhashes, false),
+        Meta::readonly(sb_state, false),
+        Meta::readonly(sb_program, false),
+        Meta::mutable(treasury, false),
+        Meta::readonly(thread, true),
+        Meta::readonly(crate::ID, false),
+        Meta::readonly(token::ID, false),
+        Meta::readonly(system_program::ID, false),
+    ];
+    accounts.extend(event_cpi_accounts());
+
+    automation::SerializableInstruction {
+        program_id: crate::ID,
+        accounts,
+        data: instruction_discriminator("request_randomness").to_vec(),
+    }
+}
+
+/// Point the request thread at the round after the one it just handled
+fn respond_with_next_request(accounts: &RequestRandomness) -> Result<()> {
+    let switchboard = [
+        accounts.vrf.key(),
+        accounts.oracle_queue.key(),
+        accounts.queue_authority.key(),
+        accounts.data_buffer.key(),
+        accounts.permission.key(),
+        accounts.escrow.key(),
+        accounts.payer_wallet.key(),
+        accounts.recent_blockhashes.key(),
+        accounts.switchboard_program_state.key(),
+        accounts.switchboard_program.key(),
+    ];
+    automation::respond_with(request_randomness_thread_ix(
+        accounts.protocol_state.key(),
+        accounts.protocol_state.room_id,
+        accounts.round_state.round_id.checked_add(1).ok_or(RecoveryRoomError::ArithmeticOverflow)?,
+        &switchboard,
+        accounts.treasury.key(),
+        accounts.payer.key(),
+    ))
+}
+
+/// Upper bound on the per-participation deposit (0.1 SOL)
+#[constant]
+pub const MAX_ENTRY_DEPOSIT_LAMPORTS: u64 = 100_000_000;
+
+/// Upper bound on a single crank bounty (0.01 SOL)
+pub const MAX_KEEPER_BOUNTY_LAMPORTS: u64 = 10_000_000;
+
+/// Pay a crank caller from the treasury. Never fails the crank for lack of
+/// funds: pays whatever is above the treasury's rent floor, up to `amount`.
+fn pay_keeper_bounty<'info>(
+    treasury: &mut Account<'info, Treasury>,
+    keeper: &AccountInfo<'info>,
+    amount: u64,
+    crank: CrankInstruction,
+    round_id: u64,
+) -> Result<()> {
+    let treasury_info = treasury.to_account_info();
+    let rent_floor = Rent::get()?.minimum_balance(treasury_info.data_len());
+    let amount = amount.min(treasury_info.lamports().saturating_sub(rent_floor));
+    if amount == 0 {
+        return Ok(());
+    }
+
+    move_lamports(&treasury_info, keeper, amount)?;
+    treasury.total_bounties_paid = treasury.total_bounties_paid.checked_add(amount).ok_or(RecoveryRoomError::ArithmeticOverflow)?;
+
+    emit!(KeeperBountyPaid {
+        keeper: keeper.key(),
+        crank,
+        round_id,
+        amount,
+    });
+    Ok(())
+}
+
+/// Add `multiplier_bps` of the prize vault's balance, less the balance
+/// itself, from the treasury, as far as the treasury can cover it above rent
+fn boost_prize<'info>(
+    treasury: &mut Account<'info, Treasury>,
+    prize_vault: &mut Account<'info, PrizeVault>,
+    multiplier_bps: u16,
+    round_id: u64,
+) -> Result<()> {
+    let wanted = mul_div(
+        prize_vault.lamports,
+        (multiplier_bps as u64).saturating_sub(BPS_DENOMINATOR),
+        BPS_DENOMINATOR,
+    )?;
+    let treasury_info = treasury.to_account_info();
+    let rent_floor = Rent::get()?.minimum_balance(treasury_info.data_len());
+    let amount = wanted.min(treasury_info.lamports().saturating_sub(rent_floor));
+    if amount == 0 {
+        return Ok(());
+    }
+
+    move_lamports(&treasury_info, &prize_vault.to_account_info(), amount)?;
+    prize_vault.lamports = prize_vault.lamports.saturating_add(amount);
+
+    emit!(PrizeBoosted {
+        round_id,
+        multiplier_bps,
+        amount,
+    });
+    Ok(())
+}
+
+/// seed_test_round's synthetic pool entries, only compiled into test-harness builds
+#[cfg(feature = "test-harness")]
+fn seed_synthetic_pool(
+    ctx: Context<SeedTestRound>,
+    token_count: u16,
+    submissions_per_token: u32,
+    participants: u32,
+) -> Result<()> {
+    let round = &mut ctx.accounts.round_state;
+    let mut token_pool = ctx.accounts.token_pool.load_pool_mut()?;
+
+    require!(
+        round.status == RoundStatus::Active,
+        RecoveryRoomError::RoundNotActive
+    );
+    token_pool.require_unlocked()?;
+    require!(
+        token_count > 0 && submissions_per_token > 0,
+        RecoveryRoomError::InvalidTokenCount
+    );
+
+    let offset = token_pool.header.len;
+    for i in 0..token_count as u32 {
+        let index = offset + i;
+        let mint = keccak::hashv(&[
+            b"synthetic",
+            &round.round_id.to_le_bytes(),
+            &index.to_le_bytes(),
+        ]);
+        token_pool.push(TokenPoolEntry {
+            token_mint: Pubkey::new_from_array(mint.to_bytes()),
+            submission_count: submissions_per_token,
+            total_loss_usd: submissions_per_token as u64 * SYNTHETIC_LOSS_CENTS,
+            stats_synced: 0,
+            _padding: [0; 3],
+        })?;
+    }
+
+    round.total_participants = round.total_participants.checked_add(participants).ok_or(RecoveryRoomError::ArithmeticOverflow)?;
+    round.total_token_entries = (token_count as u32)
+        .checked_mul(submissions_per_token)
+        .and_then(|entries| round.total_token_entries.checked_add(entries))
+        .ok_or(RecoveryRoomError::ArithmeticOverflow)?;
+
+    msg!(
+        "Seeded round {} with {} synthetic tokens",
+        round.round_id,
+        token_count
+    );
+    Ok(())
+}
+
+/// Return an unflagged participation's deposit to its user
+fn refund_deposit(participation: &mut Account<Participation>, user: &AccountInfo, round_id: u64) -> Result<()> {
+    let amount = participation.deposit;
+    if amount == 0 || participation.flagged {
+        return Ok(());
+    }
+    move_lamports(&participation.to_account_info(), user, amount)?;
+    participation.deposit = 0;
+
+    emit!(DepositSettled {
+        round_id,
+        user: participation.user,
+        amount,
+        forfeited: false,
+    });
+    Ok(())
+}
+
+/// Pay `amount` of a voided round's held entry fees back to `user`: from
+/// the vault's lamports for a SOL fee, or from its fee token account into the
+/// user's for an SPL one
+#[allow(clippy::too_many_arguments)]
+fn refund_entry_fee<'info>(
+    round: &mut RoundState,
+    prize_vault: &mut Account<'info, PrizeVault>,
+    user: &AccountInfo<'info>,
+    user_fee_account: Option<&InterfaceAccount<'info, TokenAccount>>,
+    vault_fee_account: Option<&InterfaceAccount<'info, TokenAccount>>,
+    fee_mint: Option<&InterfaceAccount<'info, Mint>>,
+    token_program: Option<&Interface<'info, TokenInterface>>,
+    amount: u64,
+) -> Result<()> {
+    require!(
+        round.status == RoundStatus::Cancelled,
+        RecoveryRoomError::InvalidRoundStatus
+    );
+    require!(amount > 0, RecoveryRoomError::NoRefundOwed);
+
+    let mint = match round.entry_fee.mint {
+        None => {
+            move_lamports(&prize_vault.to_account_info(), user, amount)?;
+            prize_vault.lamports = prize_vault
+                .lamports
+                .checked_sub(amount)
+                .ok_or(RecoveryRoomError::ArithmeticOverflow)?;
+            token::spl_token::native_mint::ID
+        }
+        Some(mint) => {
+            let (Some(to), Some(from), Some(fee_mint), Some(token_program)) =
+                (user_fee_account, vault_fee_account, fee_mint, token_program)
+            else {
+                return err!(RecoveryRoomError::MissingTokenAccounts);
+            };
+            require_keys_eq!(fee_mint.key(), mint, RecoveryRoomError::MintMismatch);
+            require_keys_eq!(from.mint, mint, RecoveryRoomError::MintMismatch);
+            require_keys_eq!(to.mint, mint, RecoveryRoomError::MintMismatch);
+
+            let room = room_seed(round.room_id);
+            let round_id_bytes = round.round_id.to_le_bytes();
+            let vault_seeds = &[b"prize_vault".as_ref(), &room, &round_id_bytes, &[prize_vault.bump]];
+            transfer::transfer_checked(
+                &token_program.to_account_info(),
+                &from.to_account_info(),
+                &fee_mint.to_account_info(),
+                &to.to_account_info(),
+                &prize_vault.to_account_info(),
+                &[],
+                amount,
+                fee_mint.decimals,
+                &[&vault_seeds[..]],
+            )?;
+            mint
+        }
+    };
+    round.refunds_outstanding = round
+        .refunds_outstanding
+        .checked_sub(amount)
+        .ok_or(RecoveryRoomError::ArithmeticOverflow)?;
+
+    emit!(EntryFeeRefunded {
+        round_id: round.round_id,
+        user: user.key(),
+        mint,
+        amount,
+    });
+
+    msg!("Round {} entry fee of {} refunded", round.round_id, amount);
+    Ok(())
+}
+
+/// Fail unless `account` is the program PDA `seeds` and `bump` derive
+fn require_pda(account: &impl Key, seeds: &[&[u8]], bump: u8) -> Result<()> {
+    let expected = Pubkey::create_program_address(&[seeds, &[&[bump]]].concat(), &crate::ID)
+        .map_err(|_| error!(RecoveryRoomError::InvalidClaimAccount))?;
+    require_keys_eq!(account.key(), expected, RecoveryRoomError::InvalidClaimAccount);
+    Ok(())
+}
+
+/// Decode a round account, checking it is one of this room's settled rounds
+/// past ROUND_RETENTION_SECS with an empty (or closed) `prize_vault`, so
+/// gc_rounds or archive_round may close it
+fn collectable_round(
+    protocol: &ProtocolState,
+    info: &AccountInfo,
+    prize_vault: &AccountInfo,
+    now: i64,
+) -> Result<RoundState> {
+    require!(info.is_writable, RecoveryRoomError::InvalidGcAccount);
+    require_keys_eq!(*info.owner, crate::ID, RecoveryRoomError::InvalidGcAccount);
+
+    let round = {
+        let data = info.try_borrow_data()?;
+        RoundState::try_deserialize(&mut &data[..])?
+    };
+
+    let (expected, _) = Pubkey::find_program_address(
+        &[ROUND_SEED, &room_seed(protocol.room_id), &round.round_id.to_le_bytes()],
+        &crate::ID,
+    );
+    require_keys_eq!(info.key(), expected, RecoveryRoomError::InvalidGcAccount);
+    require!(
+        round.round_id < protocol.current_round,
+        RecoveryRoomError::RoundNotCollectable
+    );
+    // A settled round stays until its claims are over: every prize paid or
+    // the unclaimed ones swept, and the claim window shut; a voided one until
+    // its entry fees are refunded
+    require!(
+        (round.status == RoundStatus::Cancelled && round.refunds_outstanding == 0)
+            || (round.status == RoundStatus::Complete
+                && (round.all_prizes_claimed() || round.unclaimed_swept)
+                && !round.claims_open(now)),
+        RecoveryRoomError::RoundNotCollectable
+    );
+    require!(
+        now >= round.end_time.saturating_add(ROUND_RETENTION_SECS),
+        RecoveryRoomError::RoundNotCollectable
+    );
+
+    let (vault, _) = Pubkey::find_program_address(
+        &[b"prize_vault", &room_seed(protocol.room_id), &round.round_id.to_le_bytes()],
+        &crate::ID,
+    );
+    require_keys_eq!(prize_vault.key(), vault, RecoveryRoomError::InvalidGcAccount);
+    if !prize_vault.data_is_empty() {
+        let vault = PrizeVault::try_deserialize(&mut &prize_vault.try_borrow_data()?[..])?;
+        require!(vault.lamports == 0, RecoveryRoomError::RoundNotCollectable);
+    }
+    Ok(round)
+}
+
+/// Close an account the program owns, returning the rent it held
+fn close_program_account(info: &AccountInfo) -> Result<u64> {
+    let lamports = info.lamports();
+    **info.try_borrow_mut_lamports()? = 0;
+    info.assign(&system_program::ID);
+    info.realloc(0, false)?;
+    Ok(lamports)
+}
+
+/// Pay GC_REWARD_BPS of `reclaimed` rent to `caller` and the rest into the
+/// jackpot, returning the caller's share
+fn share_reclaimed_rent(reclaimed: u64, caller: &AccountInfo, jackpot: &mut Account<Jackpot>) -> Result<u64> {
+    let caller_reward = mul_div(reclaimed, GC_REWARD_BPS, BPS_DENOMINATOR)?;
+    let to_jackpot = reclaimed - caller_reward;
+
+    **caller.try_borrow_mut_lamports()? += caller_reward;
+    **jackpot.to_account_info().try_borrow_mut_lamports()? += to_jackpot;
+    jackpot.balance = jackpot.balance.checked_add(to_jackpot).ok_or(RecoveryRoomError::ArithmeticOverflow)?;
+    Ok(caller_reward)
+}
+
+/// Move lamports between two program-owned accounts
+fn move_lamports(from: &AccountInfo, to: &AccountInfo, amount: u64) -> Result<()> {
+    let from_balance = from.lamports();
+    require_detail!(
+        RecoveryRoomError::InsufficientVaultBalance,
+        "lamports",
+        amount,
+        at_most from_balance
+    );
+
+    **from.try_borrow_mut_lamports()? = from_balance - amount;
+    **to.try_borrow_mut_lamports()? += amount;
+    Ok(())
+}
+
+/// Mint a participation receipt cNFT to the user; every receipt account must be passed
+fn mint_participation_receipt(accounts: &Participate<'_>, receipt_authority_bump: u8, round_id: u64) -> Result<()> {
+    let (
+        Some(config),
+        Some(receipt_authority),
+        Some(tree_config),
+        Some(merkle_tree),
+        Some(bubblegum_program),
+        Some(compression_program),
+        Some(noop_program),
+    ) = (
+        &accounts.receipt_config,
+        &accounts.receipt_authority,
+        &accounts.receipt_tree_config,
+        &accounts.receipt_tree,
+        &accounts.bubblegum_program,
+        &accounts.compression_program,
+        &accounts.noop_program,
+    )
+    else {
+        return err!(RecoveryRoomError::MissingReceiptAccounts);
+    };
+    require_keys_eq!(merkle_tree.key(), config.merkle_tree, RecoveryRoomError::WrongReceiptTree);
+
+    let receipt_authority_seeds = &[b"receipt_authority".as_ref(), &[receipt_authority_bump]];
+    bubblegum::mint_v1(
+        &bubblegum::BubblegumAccounts {
+            bubblegum_program: &bubblegum_program.to_account_info(),
+            tree_config: &tree_config.to_account_info(),
+            merkle_tree: &merkle_tree.to_account_info(),
+            payer: &accounts.user.to_account_info(),
+            tree_delegate: &receipt_authority.to_account_info(),
+            noop_program: &noop_program.to_account_info(),
+            compression_program: &compression_program.to_account_info(),
+            system_program: &accounts.system_program.to_account_info(),
+        },
+        &accounts.user.to_account_info(),
+        bubblegum::MetadataArgs {
+            name: format!("Recovery Room #{}", round_id),
+            symbol: RECEIPT_SYMBOL.to_string(),
+            uri: config.uri.clone(),
+            seller_fee_basis_points: 0,
+            primary_sale_happened: true,
+            is_mutable: false,
+            edition_nonce: None,
+            token_standard: Some(bubblegum::TokenStandard::NonFungible),
+            collection: None,
+            uses: None,
+            token_program_version: bubblegum::TokenProgramVersion::Original,
+            creators: Vec::new(),
+        },
+        &[&receipt_authority_seeds[..]],
+    )
+}
+
+#[allow(clippy::too_many_arguments)]
+fn mint_soulbound_badge<'info>(
+    badge_mint: &UncheckedAccount<'info>,
+    badge_authority: &UncheckedAccount<'info>,
+    badge_authority_bump: u8,
+    user: &Signer<'info>,
+    user_badge_account: &UncheckedAccount<'info>,
+    token_program: &Program<'info, Token2022>,
+    associated_token_program: &Program<'info, AssociatedToken>,
+    system_program: &Program<'info, System>,
+    mint_seeds: &[&[u8]],
+) -> Result<()> {
+    badge::mint_badge(
+        &badge::BadgeAccounts {
+            mint: &badge_mint.to_account_info(),
+            authority: &badge_authority.to_account_info(),
+            holder: &user.to_account_info(),
+            holder_account: &user_badge_account.to_account_info(),
+            token_program: &token_program.to_account_info(),
+            associated_token_program: &associated_token_program.to_account_info(),
+            system_program: &system_program.to_account_info(),
+        },
+        mint_seeds,
+        &[b"badge_authority".as_ref(), &[badge_authority_bump]],
+    )
+}
+
+/// Move a claim's protocol fee out of the prize vault, routing the protocol's
+/// consolation and staking slices to their pools and the rest to the treasury
+fn collect_protocol_fee(
+    prize_vault: &AccountInfo,
+    treasury: &mut Account<Treasury>,
+    consolation_pool: Option<&mut Account<ConsolationPool>>,
+    rewards_vault: Option<&mut Account<RewardsVault>>,
+    fee: u64,
+    protocol: &ProtocolState,
+) -> Result<()> {
+    let consolation = mul_div(fee, protocol.consolation_fee_bps as u64, BPS_DENOMINATOR)?;
+    let staking = mul_div(fee, protocol.staking_fee_bps as u64, BPS_DENOMINATOR)?;
+    let treasury_fee = fee - consolation - staking;
+
+    if consolation > 0 {
+        let consolation_pool = consolation_pool.ok_or(RecoveryRoomError::MissingConsolationPool)?;
+        move_lamports(prize_vault, &consolation_pool.to_account_info(), consolation)?;
+        consolation_pool.balance = consolation_pool.balance.checked_add(consolation).ok_or(RecoveryRoomError::ArithmeticOverflow)?;
+        consolation_pool.total_funded = consolation_pool.total_funded.checked_add(consolation).ok_or(RecoveryRoomError::ArithmeticOverflow)?;
+    }
+
+    if staking > 0 {
+        let rewards_vault = rewards_vault.ok_or(RecoveryRoomError::MissingRewardsVault)?;
+        move_lamports(prize_vault, &rewards_vault.to_account_info(), staking)?;
+        rewards_vault.pending = rewards_vault.pending.checked_add(staking).ok_or(RecoveryRoomError::ArithmeticOverflow)?;
+        rewards_vault.total_funded = rewards_vault.total_funded.checked_add(staking).ok_or(RecoveryRoomError::ArithmeticOverflow)?;
+    }
+
+    move_lamports(prize_vault, &treasury.to_account_info(), treasury_fee)?;
+    treasury.total_fees_collected = treasury.total_fees_collected.checked_add(treasury_fee).ok_or(RecoveryRoomError::ArithmeticOverflow)?;
+    Ok(())
+}
+
+/// Create the program-owned PDA `account` (signed for by `seeds`) with
+/// `space` bytes for a `T`, rent paid by `payer`, and load it zeroed. For
+/// PDAs passed as remaining accounts, which `init` can't declare.
+fn init_pda<'info, T>(
+    account: &'info AccountInfo<'info>,
+    payer: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+    space: usize,
+    seeds: &[&[u8]],
+) -> Result<Account<'info, T>>
+where
+    T: AccountSerialize + AccountDeserialize + Owner + anchor_lang::Discriminator + Clone,
+{
+    let rent = Rent::get()?.minimum_balance(space);
+    let signer = &[seeds];
+    let funded = account.lamports();
+    if funded == 0 {
+        system_program::create_account(
+            CpiContext::new_with_signer(
+                system_program.clone(),
+                system_program::CreateAccount {
+                    from: payer.clone(),
+                    to: account.clone(),
+                },
+                signer,
+            ),
+            rent,
+            space as u64,
+            &crate::ID,
+        )?;
+    } else {
+        // Someone sent lamports to the address first; build the account around them
+        if funded < rent {
+            system_program::transfer(
+                CpiContext::new(
+                    system_program.clone(),
+                    system_program::Transfer {
+                        from: payer.clone(),
+                        to: account.clone(),
+                    },
+                ),
+                rent - funded,
+            )?;
+        }
+        system_program::allocate(
+            CpiContext::new_with_signer(
+                system_program.clone(),
+                system_program::Allocate {
+                    account_to_allocate: account.clone(),
+                },
+                signer,
+            ),
+            space as u64,
+        )?;
+        system_program::assign(
+            CpiContext::new_with_signer(
+                system_program.clone(),
+                system_program::Assign {
+                    account_to_assign: account.clone(),
+                },
+                signer,
+            ),
+            &crate::ID,
+        )?;
+    }
+
+    account.try_borrow_mut_data()?[..8].copy_from_slice(&T::discriminator());
+    Account::try_from(account)
+}
+
+/// Record `token_entries` for `ctx.accounts.user`, with the delegate paying.
+/// The delegate's authority to act for the user is checked by the caller.
+fn participate_on_behalf(
+    ctx: Context<ParticipateFor>,
+    token_entries: Vec<TokenEntry>,
+    integrator_program: Option<Pubkey>,
+) -> Result<()> {
+    let user = ctx.accounts.user.key();
+    let delegate = ctx.accounts.delegate.key();
+    let mut token_entries = token_entries;
+    token_entries.iter().try_for_each(TokenEntry::validate)?;
+    check_mint_lists(
+        &ctx.accounts.protocol_state,
+        ctx.accounts.mint_blacklist.as_deref(),
+        ctx.accounts.mint_whitelist.as_deref(),
+        &token_entries,
+    )?;
+    check_access_pass(
+        &ctx.accounts.protocol_state,
+        ctx.accounts.pass_account.as_deref(),
+        ctx.accounts.pass_metadata.as_deref(),
+    )?;
+    check_rug_attestations(
+        &ctx.accounts.protocol_state,
+        ctx.accounts.instructions.as_deref(),
+        &token_entries,
+    )?;
+    attest_losses(
+        &ctx.accounts.protocol_state,
+        ctx.accounts.instructions.as_deref(),
+        &user,
+        &mut token_entries,
+    )?;
+    let (metadata_accounts, holding_accounts, pool_entry_accounts) =
+        split_entry_accounts(ctx.remaining_accounts, token_entries.len());
+    canonicalize_tickers(&mut token_entries, metadata_accounts)?;
+    verify_holdings(&user, &token_entries, holding_accounts)?;
+    ctx.accounts.user_stats.require_off_cooldown(ctx.accounts.round_state.round_id)?;
+    enforce_rate_limit(
+        &ctx.accounts.protocol_state.rate_limit,
+        &mut ctx.accounts.round_state,
+        &mut ctx.accounts.user_stats,
+        &Clock::get()?,
+    )?;
+    let protocol = &ctx.accounts.protocol_state;
+    let round = &mut ctx.accounts.round_state;
+    let participation = &mut ctx.accounts.participation;
+    let clock = Clock::get()?;
+
+    let weight_multiplier = match (&ctx.accounts.stake_pool, &ctx.accounts.stake_account) {
+        (Some(pool), Some(stake)) => pool.weight_multiplier(stake, round.end_time),
+        _ => 1,
+    };
+
+    let tickets_before = round.ticket_supply;
+    let (effective_weight, pool_total_weight, wallet_tickets) = record_entries(
+        protocol,
+        round,
+        pool_entry_accounts,
+        ctx.accounts.leaderboard.as_deref_mut(),
+        &token_entries,
+        weight_multiplier,
+        clock.unix_timestamp,
+    )?;
+    mint_tickets(
+        round,
+        user,
+        ctx.accounts.ticket_mint.as_ref(),
+        ctx.accounts.user_ticket_account.as_ref(),
+        &ctx.accounts.token_program,
+        round.ticket_supply - tickets_before,
+    )?;
+
+    let entry_fee_paid = collect_entry_fee(
+        round,
+        &ctx.accounts.delegate.to_account_info(),
+        user,
+        &mut ctx.accounts.prize_vault,
+        ctx.accounts.delegate_fee_account.as_ref(),
+        ctx.accounts.vault_fee_account.as_ref(),
+        ctx.accounts.fee_mint.as_ref(),
+        ctx.accounts.referral.as_mut(),
+        &mut ctx.accounts.user_stats.referrer,
+        protocol.referral_fee_bps,
+        &ctx.accounts.token_program,
+        &ctx.accounts.system_program,
+    )?;
+
+    // The delegate funds the deposit; the refund goes to the user
+    if round.entry_deposit > 0 {
+        system_program::transfer(
+            CpiContext::new(
+                ctx.accounts.system_program.to_account_info(),
+                system_program::Transfer {
+                    from: ctx.accounts.delegate.to_account_info(),
+                    to: participation.to_account_info(),
+                },
+            ),
+            round.entry_deposit,
+        )?;
+    }
+    participation.deposit = round.entry_deposit;
+    participation.flagged = false;
+    participation.entry_fee_paid = entry_fee_paid;
+
+    participation.user = user;
+    participation.round_id = round.round_id;
+    participation.tokens = token_entries.clone();
+    participation.timestamp = clock.unix_timestamp;
+    participation.bump = ctx.bumps.participation;
+    participation.version = PARTICIPATION_VERSION;
+    participation.recovery_minted = false;
+    participation.weight_multiplier = weight_multiplier;
+    participation.bonus_ticket_start = round.issue_bonus_tickets(token_entries.len())?;
+    participation.losses_attested = protocol.price_verifier.is_some();
+    participation.wallet_tickets = wallet_tickets;
+    participation.effective_weight = effective_weight;
+    participation.pool_total_weight = pool_total_weight;
+
+    let xp = ctx.accounts.user_stats.record_entry(
+        user,
+        ctx.bumps.user_stats,
+        round.round_id,
+        &token_entries,
+        &protocol.xp_rates,
+    )?;
+    emit_xp_awarded(&ctx.accounts.user_stats, round.round_id, xp);
+    record_season_entry(round, ctx.accounts.season.as_deref_mut(), &token_entries)?;
+
+    if let Some(registry) = &ctx.accounts.ticker_registry {
+        warn_ticker_collisions(&*registry.load()?, round.round_id, &token_entries)?;
+    }
+
+    let round_id = round.round_id;
+    emit_indexed!(ctx, UserParticipated {
+        round_id,
+        user,
+        token_count: token_entries.len() as u8,
+    });
+    emit!(ParticipatedFor {
+        round_id,
+        user,
+        delegate,
+        integrator_program,
+    });
+
+    msg!("Delegate {} entered {} tokens for {}", delegate, token_entries.len(), user);
+    Ok(())
+}
+
+/// Fail unless `delegate` may participate for `user`: either the
+/// participate_for PDA of a registered integrator program, or the user's
+/// approved delegate
+fn check_participation_delegate(
+    user: &Pubkey,
+    delegate: &Pubkey,
+    registry: Option<&IntegratorRegistry>,
+    integrator_program: Option<&AccountInfo>,
+    delegation: Option<&ParticipationDelegate>,
+) -> Result<()> {
+    if let Some(program) = integrator_program {
+        let registry = registry.ok_or(RecoveryRoomError::UnknownIntegrator)?;
+        require!(
+            registry.programs.contains(program.key),
+            RecoveryRoomError::UnknownIntegrator
+        );
+        let (expected, _) = Pubkey::find_program_address(&[PARTICIPATE_FOR_SEED, user.as_ref()], program.key);
+        require_keys_eq!(*delegate, expected, RecoveryRoomError::UnauthorizedDelegate);
+        return Ok(());
+    }
+    match delegation {
+        Some(delegation) if delegation.delegate == Some(*delegate) => Ok(()),
+        _ => err!(RecoveryRoomError::UnauthorizedDelegate),
+    }
+}
+
+/// Mint `tickets` of a ticketed round's ticket mint to `user`'s ticket account
+fn mint_tickets<'info>(
+    round: &RoundState,
+    user: Pubkey,
+    ticket_mint: Option<&InterfaceAccount<'info, Mint>>,
+    ticket_account: Option<&InterfaceAccount<'info, TokenAccount>>,
+    token_program: &Interface<'info, TokenInterface>,
+    tickets: u64,
+) -> Result<()> {
+    if !round.ticketed || tickets == 0 {
+        return Ok(());
+    }
+    let (Some(mint), Some(to)) = (ticket_mint, ticket_account) else {
+        return err!(RecoveryRoomError::MissingTicketAccounts);
+    };
+    let room = room_seed(round.room_id);
+    let round_id = round.round_id.to_le_bytes();
+    let (expected, bump) = Pubkey::find_program_address(&[b"ticket_mint", &room, &round_id], &crate::ID);
+    require_keys_eq!(mint.key(), expected, RecoveryRoomError::MissingTicketAccounts);
+    require_keys_eq!(to.mint, expected, RecoveryRoomError::MintMismatch);
+
+    let mint_seeds = &[b"ticket_mint".as_ref(), &room, &round_id, &[bump]];
+    token_interface::mint_to(
+        CpiContext::new_with_signer(
+            token_program.to_account_info(),
+            MintTo {
+                mint: mint.to_account_info(),
+                to: to.to_account_info(),
+                authority: mint.to_account_info(),
+            },
+            &[&mint_seeds[..]],
+        ),
+        tickets,
+    )?;
+
+    emit!(TicketsMinted {
+        round_id: round.round_id,
+        user,
+        tickets,
+    });
+    Ok(())
+}
+
+/// Accrue streamed staking rewards up to `now`, rolling the epoch if it ended
+fn advance_reward_epoch(rewards: &mut RewardsVault, total_staked: u64, now: i64) -> Result<()> {
+    if let Some(streaming) = rewards.accrue(total_staked, now)? {
+        emit!(RewardEpochAdvanced {
+            epoch: rewards.epoch,
+            streaming,
+            total_staked,
+        });
+    }
+    Ok(())
+}
+
+fn emit_xp_awarded(stats: &UserStats, round_id: u64, points: u64) {
+    if points > 0 {
+        emit!(XpAwarded {
+            user: stats.user,
+            round_id,
+            points,
+            total_xp: stats.xp,
+        });
+    }
+}
+
+/// Fold a participation into its round's season, if the round belongs to one
+fn record_season_entry(
+    round: &RoundState,
+    season: Option<&mut Season>,
+    token_entries: &[TokenEntry],
+) -> Result<()> {
+    if round.season_id == 0 {
+        return Ok(());
+    }
+    let season = season.ok_or(RecoveryRoomError::SeasonAccountMissing)?;
+    season.record_entry(round.total_participants == 1, token_entries)
+}
+
+/// Count a participation against the per-slot and per-wallet caps
+fn enforce_rate_limit(
+    limit: &RateLimit,
+    round: &mut RoundState,
+    stats: &mut UserStats,
+    clock: &Clock,
+) -> Result<()> {
+    if limit.max_per_slot > 0 {
+        if round.last_entry_slot != clock.slot {
+            round.last_entry_slot = clock.slot;
+            round.slot_entries = 0;
+        }
+        round.slot_entries = round.slot_entries.saturating_add(1);
+        require_detail!(
+            RecoveryRoomError::RateLimited,
+            "slot_entries",
+            round.slot_entries,
+            at_most limit.max_per_slot
+        );
+    }
+    if limit.max_per_window > 0 {
+        if clock.unix_timestamp.saturating_sub(stats.window_start) >= limit.window_secs as i64 {
+            stats.window_start = clock.unix_timestamp;
+            stats.window_entries = 0;
+        }
+        stats.window_entries = stats.window_entries.saturating_add(1);
+        require_detail!(
+            RecoveryRoomError::RateLimited,
+            "window_entries",
+            stats.window_entries,
+            at_most limit.max_per_window
+        );
+    }
+    Ok(())
+}
+
+/// Validate a set of entries and fold them into the round, its per-mint
+/// pool entries, passed one per entry, and the leaderboard, if passed, each
+/// counting as `weight_multiplier` submissions. Returns the entrant's
+/// (effective weight, pool total weight) after entry, and each entry's
+/// wallet draw tickets.
+///
+/// Per-mint tallies live on the pool entries, so entries for different mints
+/// only share the round's counters; an entrant who leaves the leaderboard
+/// out doesn't lock it either.
+fn record_entries(
+    protocol: &ProtocolState,
+    round: &mut RoundState,
+    pool_entries: &[AccountInfo],
+    mut leaderboard: Option<&mut Leaderboard>,
+    token_entries: &[TokenEntry],
+    weight_multiplier: u8,
+    now: i64,
+) -> Result<(u64, u64, Vec<WalletTickets>)> {
+    // Validations
+    require!(
+        round.status == RoundStatus::Active,
+        RecoveryRoomError::RoundNotActive
+    );
+    require!(
+        now < round.end_time,
+        RecoveryRoomError::RoundEnded
+    );
+    require_detail!(
+        RecoveryRoomError::InvalidTokenCount,
+        "token_count",
+        token_entries.len(),
+        at_least 1
+    );
+    require_detail!(
+        RecoveryRoomError::InvalidTokenCount,
+        "token_count",
+        token_entries.len(),
+        at_most round.max_tokens_per_user
+    );
+    // Loss-weighted draws may only count losses the price verifier attested
+    if round.weighting == WeightingStrategy::SqrtLossUsd || round.wallet_draw {
+        require!(protocol.price_verifier.is_some(), RecoveryRoomError::LossNotAttested);
+    }
+    for entry in token_entries {
+        entry.require_min_loss(protocol.min_loss_percentage)?;
+    }
+
+    // Late entries push the close back so the round can't be sniped
+    let extension = round.anti_snipe.extension(now, round.end_time, round.total_extension);
+    if extension > 0 {
+        round.end_time = round.end_time.checked_add(extension as i64).ok_or(RecoveryRoomError::ArithmeticOverflow)?;
+        round.total_extension = round.total_extension.checked_add(extension).ok_or(RecoveryRoomError::ArithmeticOverflow)?;
+        emit!(RoundExtended {
+            round_id: round.round_id,
+            end_time: round.end_time,
+            total_extension: round.total_extension,
+        });
+    }
+
+    // Update round stats
+    if round.max_participants > 0 {
+        require!(
+            round.total_participants < round.max_participants,
+            RecoveryRoomError::RoundFull
+        );
+    }
+    round.total_participants = round.total_participants.checked_add(1).ok_or(RecoveryRoomError::ArithmeticOverflow)?;
+    if round.max_participants > 0 && round.total_participants == round.max_participants {
+        emit!(RoundFilled {
+            round_id: round.round_id,
+            participants: round.total_participants,
+        });
+    }
+    round.total_token_entries = round
+        .total_token_entries
+        .checked_add(token_entries.len() as u32)
+        .ok_or(RecoveryRoomError::ArithmeticOverflow)?;
+
+    // Update pool entry stats (increment submission counts), counting at most
+    // max_weight_contribution_per_user submissions of any one token per wallet
+    require!(
+        pool_entries.len() == token_entries.len(),
+        RecoveryRoomError::MissingPoolEntryAccounts
+    );
+    let weight_cap = round.weight_cap;
+    let mut wallet_tickets = vec![WalletTickets::default(); token_entries.len()];
+    for (i, (entry, info)) in token_entries.iter().zip(pool_entries).enumerate() {
+        let prior = token_entries[..i]
+            .iter()
+            .filter(|e| e.token_mint == entry.token_mint)
+            .count() as u32;
+        if weight_cap > 0 && prior >= weight_cap {
+            continue;
+        }
+
+        // Tokens enter the pool through register_token
+        let Some(mut pool) = load_pool_entry(round, &entry.token_mint, info)? else {
+            continue;
+        };
+        let weight_before = scaled_weight(round.weighting, &pool.as_pool_entry());
+        pool.submission_count = pool
+            .submission_count
+            .checked_add(weight_multiplier as u32)
+            .ok_or(RecoveryRoomError::ArithmeticOverflow)?;
+        let loss = entry
+            .loss_amount_usd
+            .checked_mul(weight_multiplier as u64)
+            .ok_or(RecoveryRoomError::ArithmeticOverflow)?;
+        wallet_tickets[i] = WalletTickets {
+            start: pool.total_loss_usd,
+            len: loss,
+        };
+        pool.total_loss_usd = UsdCents(pool.total_loss_usd)
+            .checked_add(UsdCents(loss))?
+            .cents();
+        round.ticket_supply = round
+            .ticket_supply
+            .checked_add(weight_multiplier as u64)
+            .ok_or(RecoveryRoomError::ArithmeticOverflow)?;
+        if let Some(leaderboard) = leaderboard.as_deref_mut() {
+            leaderboard.record(entry.token_mint);
+        }
+        round.pool_weight = round
+            .pool_weight
+            .saturating_sub(weight_before)
+            .checked_add(scaled_weight(round.weighting, &pool.as_pool_entry()))
+            .ok_or(RecoveryRoomError::ArithmeticOverflow)?;
+        pool.try_serialize(&mut &mut info.try_borrow_mut_data()?[..])?;
+    }
+    if let Some(leaderboard) = leaderboard {
+        leaderboard.updated_at = now;
+    }
+
+    // The entrant's weight is the combined weight of every token they submitted
+    let mut effective_weight: u64 = 0;
+    for (i, (entry, info)) in token_entries.iter().zip(pool_entries).enumerate() {
+        if token_entries[..i].iter().any(|e| e.token_mint == entry.token_mint) {
+            continue;
+        }
+        if let Some(pool) = load_pool_entry(round, &entry.token_mint, info)? {
+            effective_weight = effective_weight.saturating_add(scaled_weight(round.weighting, &pool.as_pool_entry()));
+        }
+    }
+    Ok((effective_weight, round.pool_weight, wallet_tickets))
+}
+
+/// The SlotHashes entry of the first slot at or after `slot` (skipped slots
+/// have none), or None once the sysvar no longer reaches back to `slot`
+fn first_slot_hash_from(slot_hashes: &AccountInfo, slot: u64) -> Result<Option<(u64, [u8; 32])>> {
+    const ENTRY_LEN: usize = 8 + 32;
+    let data = slot_hashes.try_borrow_data()?;
+    let len = data
+        .get(..8)
+        .map_or(0, |b| u64::from_le_bytes(b.try_into().unwrap())) as usize;
+
+    // Entries run newest first
+    let mut found = None;
+    for entry in data[8..].chunks_exact(ENTRY_LEN).take(len) {
+        let entry_slot = u64::from_le_bytes(entry[..8].try_into().unwrap());
+        if entry_slot < slot {
+            return Ok(found);
+        }
+        found = Some((entry_slot, entry[8..].try_into().unwrap()));
+    }
+    // Ran out of entries before passing `slot`: only an exact match is certain
+    Ok(found.filter(|(entry_slot, _)| *entry_slot == slot))
+}
+
+/// Address of `round`'s pool entry for `mint`
+fn pool_entry_address(round: &RoundState, mint: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(
+        &[
+            b"pool_entry",
+            &room_seed(round.room_id),
+            &round.round_id.to_le_bytes(),
+            mint.as_ref(),
+        ],
+        &crate::ID,
+    )
+    .0
+}
+
+/// `round`'s pool entry for `mint` from `info`, which must be its address;
+/// None when the mint was never registered for the round
+fn load_pool_entry(round: &RoundState, mint: &Pubkey, info: &AccountInfo) -> Result<Option<PoolEntry>> {
+    require_keys_eq!(info.key(), pool_entry_address(round, mint), RecoveryRoomError::InvalidPoolEntry);
+    if info.data_is_empty() {
+        return Ok(None);
+    }
+    require_keys_eq!(*info.owner, crate::ID, RecoveryRoomError::InvalidPoolEntry);
+    Ok(Some(PoolEntry::try_deserialize(&mut &info.try_borrow_data()?[..])?))
+}
+
+/// Charge a round's entry fee into its prize vault, burning it when configured.
+/// `payer` pays on behalf of `user`, the entrant. A named referrer is
+/// credited referral_fee_bps of a SOL fee, and the first one a wallet names
+/// is bound to it in `bound_referrer`. An SPL fee counts what the vault
+/// received net of any transfer fee; fee mints with a transfer hook aren't
+/// supported, as the entry's remaining accounts carry no hook accounts.
+///
+/// Returns the fee the vault kept, in the fee's mint, which a voided round
+/// refunds (0 for a burned SPL fee).
+#[allow(clippy::too_many_arguments)]
+fn collect_entry_fee<'info>(
+    round: &mut RoundState,
+    payer: &AccountInfo<'info>,
+    user: Pubkey,
+    prize_vault: &mut Account<'info, PrizeVault>,
+    user_fee_account: Option<&InterfaceAccount<'info, TokenAccount>>,
+    vault_fee_account: Option<&InterfaceAccount<'info, TokenAccount>>,
+    fee_mint: Option<&InterfaceAccount<'info, Mint>>,
+    referral: Option<&mut Account<'info, Referral>>,
+    bound_referrer: &mut Option<Pubkey>,
+    referral_fee_bps: u16,
+    token_program: &Interface<'info, TokenInterface>,
+    system_program: &Program<'info, System>,
+) -> Result<u64> {
+    let fee = round.entry_fee;
+    if let Some(referral) = &referral {
+        require_keys_neq!(referral.referrer, user, RecoveryRoomError::SelfReferral);
+        match bound_referrer {
+            Some(bound) => require_keys_eq!(referral.referrer, *bound, RecoveryRoomError::ReferrerMismatch),
+            None => *bound_referrer = Some(referral.referrer),
+        }
+    }
+    if fee.amount == 0 {
+        if let Some(referral) = referral {
+            referral.referred_entries = referral.referred_entries.checked_add(1).ok_or(RecoveryRoomError::ArithmeticOverflow)?;
+        }
+        return Ok(0);
+    }
+
+    let Some(mint) = fee.mint else {
+        // The referrer's cut of a SOL fee goes straight to their Referral account
+        let referral_share = match &referral {
+            Some(_) => mul_div(fee.amount, referral_fee_bps as u64, BPS_DENOMINATOR)?,
+            None => 0,
+        };
+        let vault_share = fee.amount - referral_share;
+        system_program::transfer(
+            CpiContext::new(
+                system_program.to_account_info(),
+                system_program::Transfer {
+                    from: payer.clone(),
+                    to: prize_vault.to_account_info(),
+                },
+            ),
+            vault_share,
+        )?;
+        prize_vault.lamports = prize_vault.lamports.checked_add(vault_share).ok_or(RecoveryRoomError::ArithmeticOverflow)?;
+        round.entry_fees_collected = round.entry_fees_collected.checked_add(vault_share).ok_or(RecoveryRoomError::ArithmeticOverflow)?;
+
+        if let Some(referral) = referral {
+            if referral_share > 0 {
+                system_program::transfer(
+                    CpiContext::new(
+                        system_program.to_account_info(),
+                        system_program::Transfer {
+                            from: payer.clone(),
+                            to: referral.to_account_info(),
+                        },
+                    ),
+                    referral_share,
+                )?;
+                referral.claimable = referral.claimable.checked_add(referral_share).ok_or(RecoveryRoomError::ArithmeticOverflow)?;
+                referral.total_earned = referral.total_earned.checked_add(referral_share).ok_or(RecoveryRoomError::ArithmeticOverflow)?;
+
+                emit!(ReferralFeePaid {
+                    referrer: referral.referrer,
+                    user,
+                    round_id: round.round_id,
+                    amount: referral_share,
+                });
+            }
+            referral.referred_entries = referral.referred_entries.checked_add(1).ok_or(RecoveryRoomError::ArithmeticOverflow)?;
+            referral.referred_fees = referral.referred_fees.checked_add(fee.amount).ok_or(RecoveryRoomError::ArithmeticOverflow)?;
+        }
+
+        emit!(EntryFeePaid {
+            round_id: round.round_id,
+            user,
+            mint: token::spl_token::native_mint::ID,
+            amount: fee.amount,
+            burned: false,
+        });
+        return Ok(vault_share);
+    };
+
+    let (Some(from), Some(to), Some(fee_mint)) = (user_fee_account, vault_fee_account, fee_mint) else {
+        return err!(RecoveryRoomError::MissingTokenAccounts);
+    };
+    require_keys_eq!(fee_mint.key(), mint, RecoveryRoomError::MintMismatch);
+    require_keys_eq!(from.mint, mint, RecoveryRoomError::MintMismatch);
+    require_keys_eq!(to.mint, mint, RecoveryRoomError::MintMismatch);
+
+    let received = transfer::transfer_checked(
+        &token_program.to_account_info(),
+        &from.to_account_info(),
+        &fee_mint.to_account_info(),
+        &to.to_account_info(),
+        payer,
+        &[],
+        fee.amount,
+        fee_mint.decimals,
+        &[],
+    )?;
+    // SPL fees aren't shared; the referral only counts the entry
+    if let Some(referral) = referral {
+        referral.referred_entries = referral.referred_entries.checked_add(1).ok_or(RecoveryRoomError::ArithmeticOverflow)?;
+    }
+
+    if fee.burn {
+        let room = room_seed(round.room_id);
+        let round_id_bytes = round.round_id.to_le_bytes();
+        let vault_seeds = &[b"prize_vault".as_ref(), &room, &round_id_bytes, &[prize_vault.bump]];
+        token_interface::burn(
+            CpiContext::new_with_signer(
+                token_program.to_account_info(),
+                Burn {
+                    mint: fee_mint.to_account_info(),
+                    from: to.to_account_info(),
+                    authority: prize_vault.to_account_info(),
+                },
+                &[&vault_seeds[..]],
+            ),
+            received,
+        )?;
+    }
+    round.entry_fees_collected = round.entry_fees_collected.checked_add(received).ok_or(RecoveryRoomError::ArithmeticOverflow)?;
+
+    emit!(EntryFeePaid {
+        round_id: round.round_id,
+        user,
+        mint,
+        amount: fee.amount,
+        burned: fee.burn,
+    });
+    Ok(if fee.burn { 0 } else { received })
+}
+
+/// Gross prize owed for a set of entries in a settled round, summed across
+/// every tier whose winning token the entries include
+fn prize_share(
+    round: &RoundState,
+    tokens: &[TokenEntry],
+    wallet_tickets: &[WalletTickets],
+    weight_multiplier: u8,
+) -> Result<(TokenAmount, [u32; MAX_WINNER_TIERS])> {
+    let mut tier_entries = [0u32; MAX_WINNER_TIERS];
+    // A wallet draw pays the single prize whole, settling every winning entry
+    if round.wallet_draw {
+        require!(
+            holds_wallet_ticket(round, tokens, wallet_tickets),
+            RecoveryRoomError::NotAWinner
+        );
+        tier_entries[0] = round.tier_submissions[0];
+        return Ok((TokenAmount::lamports(round.tier_prizes[0]), tier_entries));
+    }
+
+    let mut gross = TokenAmount::lamports(0);
+    let mut winning_entries: u64 = 0;
+    for (tier, tier_entry) in tier_entries.iter_mut().enumerate().take(round.winner_count as usize) {
+        let tier_token = round.winner_tokens[tier];
+        let mut entries = tokens
+            .iter()
+            .filter(|t| t.token_mint == tier_token)
+            .count() as u64;
+        // Only capped entries were counted into the pool, each as
+        // weight_multiplier submissions
+        if round.weight_cap > 0 {
+            entries = entries.min(round.weight_cap as u64);
+        }
+        entries = entries.checked_mul(weight_multiplier.max(1) as u64).ok_or(RecoveryRoomError::ArithmeticOverflow)?;
+        if entries == 0 {
+            continue;
+        }
+        winning_entries = winning_entries.checked_add(entries).ok_or(RecoveryRoomError::ArithmeticOverflow)?;
+        *tier_entry = entries as u32;
+        gross = gross.checked_add(TokenAmount::lamports(mul_div(
+            round.tier_prizes[tier],
+            entries,
+            round.tier_submissions[tier] as u64,
+        )?))?;
+    }
+    require!(winning_entries > 0, RecoveryRoomError::NotAWinner);
+    Ok((gross, tier_entries))
+}
+
+/// Leaf committed to the participation tree for a compressed entry
+fn participation_leaf(round_id: u64, user: &Pubkey, token_entries: &[TokenEntry]) -> Result<[u8; 32]> {
+    let entries = token_entries.to_vec().try_to_vec()?;
+    Ok(keccak::hashv(&[
+        PARTICIPATION_SEED,
+        &round_id.to_le_bytes(),
+        user.as_ref(),
+        &entries,
+    ])
+    .to_bytes())
+}
+
+/// Replacement for a leaf whose prize has been claimed
+fn claimed_leaf(leaf: &[u8; 32]) -> [u8; 32] {
+    keccak::hashv(&[b"claimed", leaf]).to_bytes()
+}
+
+/// Gregorian calendar year of a unix timestamp (UTC)
+fn year_from_unix(timestamp: i64) -> u16 {
+    // Days-to-civil conversion (Howard Hinnant's algorithm)
+    let days = timestamp.div_euclid(86_400) + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days - era * 146_097;
+    let year_of_era = (day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let year = year_of_era + era * 400 + if month_index >= 10 { 1 } else { 0 };
+    year as u16
+}
+
+/// Fixed-point scale for weights persisted on-chain (6 decimal places)
+pub const WEIGHT_SCALE: f64 = 1_000_000.0;
+
+/// A pool token's draw weight, scaled by WEIGHT_SCALE
+fn scaled_weight(weighting: WeightingStrategy, entry: &TokenPoolEntry) -> u64 {
+    (weighting.weight(entry) * WEIGHT_SCALE) as u64
+}
+
+/// Draw value for a prize tier. Tier 0 uses the raw VRF bytes; later tiers
+/// use keccak(vrf_result || tier) so every draw is independent yet reproducible.
+fn tier_draw_value(vrf_result: &[u8; 32], tier: usize) -> u128 {
+    let bytes = if tier == 0 {
+        *vrf_result
+    } else {
+        keccak::hashv(&[vrf_result, &[tier as u8]]).to_bytes()
+    };
+    u128::from_le_bytes(bytes[0..16].try_into().unwrap())
+}
+
+/// Bonus ticket out of `tickets`, drawn from keccak(vrf_result || "user")
+/// so it is independent of every tier's token draw. Modulo bias is at most
+/// tickets / 2^64 and is ignored.
+fn bonus_draw(vrf_result: &[u8; 32], tickets: u32) -> u32 {
+    let hash = keccak::hashv(&[vrf_result, b"user"]).to_bytes();
+    (u64::from_le_bytes(hash[0..8].try_into().unwrap()) % tickets as u64) as u32
+}
+
+/// Cent of `total_loss_usd` drawn from keccak(vrf_result || "wallet"),
+/// independent of the token and bonus draws. Modulo bias is ignored as in
+/// bonus_draw.
+fn wallet_draw(vrf_result: &[u8; 32], total_loss_usd: u64) -> u64 {
+    let hash = keccak::hashv(&[vrf_result, b"wallet"]).to_bytes();
+    u64::from_le_bytes(hash[0..8].try_into().unwrap()) % total_loss_usd.max(1)
+}
+
+/// Whether an entry in `tokens` holds a wallet-draw round's winning ticket
+fn holds_wallet_ticket(round: &RoundState, tokens: &[TokenEntry], wallet_tickets: &[WalletTickets]) -> bool {
+    tokens
+        .iter()
+        .zip(wallet_tickets)
+        .any(|(t, l)| Some(t.token_mint) == round.winner_token && l.contains(round.wallet_ticket))
+}
+
+/// Fisher-Yates over a keccak hash chain seeded by the VRF result: step i
+/// swaps position i with one drawn from hash_i, where hash_0 =
+/// keccak(vrf_result || "shuffle") and hash_k+1 = keccak(hash_k).
+/// Modulo bias is at most len / 2^64 and is ignored.
+fn derive_shuffle_indices(vrf_result: &[u8; 32], len: usize) -> Vec<u16> {
+    let mut order: Vec<u16> = (0..len as u16).collect();
+    let mut hash = keccak::hashv(&[vrf_result, b"shuffle"]).to_bytes();
+    for i in (1..len).rev() {
+        let draw = u64::from_le_bytes(hash[0..8].try_into().unwrap());
+        let j = (draw % (i as u64 + 1)) as usize;
+        order.swap(i, j);
+        hash = keccak::hash(&hash).to_bytes();
+    }
+    order
+}
+
+/// Draw each configured tier from the same VRF result, removing earlier
+/// winners from the weight table between draws
+fn draw_winners(
+    pages: &[&[TokenPoolEntry]],
+    weighting: WeightingStrategy,
+    min_submissions: u32,
+    tier_bps: &[u16; MAX_WINNER_TIERS],
+    vrf_result: &[u8; 32],
+) -> Result<Vec<Pubkey>> {
+    let mut winners: Vec<Pubkey> = Vec::with_capacity(MAX_WINNER_TIERS);
+    for (tier, &bps) in tier_bps.iter().enumerate() {
+        if tier > 0 && (bps == 0 || winners.len() == qualified_token_count(pages, min_submissions)) {
+            break;
+        }
+        let winner = select_winner(
+            pages,
+            weighting,
+            min_submissions,
+            tier_draw_value(vrf_result, tier),
+            &winners,
+        )?;
+        winners.push(winner);
+    }
+    Ok(winners)
+}
+
+/// Whether `entry` has the submissions to be drawn: at least one, and at
+/// least the round's `min_submissions`
+fn qualifies(entry: &TokenPoolEntry, min_submissions: u32) -> bool {
+    entry.submission_count > 0 && entry.submission_count >= min_submissions
+}
+
+/// Number of pool tokens that qualify for the draw
+fn qualified_token_count(pages: &[&[TokenPoolEntry]], min_submissions: u32) -> usize {
+    pages.iter().flat_map(|p| p.iter()).filter(|e| qualifies(e, min_submissions)).count()
+}
+
+/// Select winner using weighted probabilities
+/// Weight = f(submissions) per the round's strategy, Probability = weight / total_weight
+/// Tokens in `excluded` (earlier tier winners) or short of `min_submissions`
+/// carry no weight. Walks the pages twice rather than buffering weights, so
+/// sharded pools fit the heap.
+fn select_winner(
+    pages: &[&[TokenPoolEntry]],
+    weighting: WeightingStrategy,
+    min_submissions: u32,
+    vrf_value: u128,
+    excluded: &[Pubkey],
+) -> Result<Pubkey> {
+    let weighted = || {
+        pages
+            .iter()
+            .flat_map(|p| p.iter())
+            .filter(|e| qualifies(e, min_submissions) && !excluded.contains(&e.token_mint))
+    };
+
+    // Calculate weights for each token
+    let total_weight: f64 = weighted().map(|e| weighting.weight(e)).sum();
+    require!(total_weight > 0.0, RecoveryRoomError::NoParticipants);
+
+    // Normalize VRF to 0-1 range
+    let normalized = (vrf_value as f64) / (u128::MAX as f64);
+    let target = normalized * total_weight;
+
+    // Find winning token, falling back to the last weighted token
+    // (shouldn't happen with proper math)
+    let mut accumulated = 0.0;
+    let mut winner = Pubkey::default();
+    for entry in weighted() {
+        accumulated += weighting.weight(entry);
+        winner = entry.token_mint;
+        if target <= accumulated {
+            break;
+        }
+    }
+
+    Ok(winner)
+}
+
+/// Address of `round`'s pool shard `index`
+fn shard_address(round: &RoundState, index: u32, bump: u8) -> Result<Pubkey> {
+    Pubkey::create_program_address(
+        &[
+            b"token_pool_shard",
+            &room_seed(round.room_id),
+            &round.round_id.to_le_bytes(),
+            &index.to_le_bytes(),
+            &[bump],
+        ],
+        &crate::ID,
+    )
+    .map_err(|_| error!(RecoveryRoomError::InvalidPoolShard))
+}
+
+fn load_shard<'a>(info: &'a AccountInfo) -> Result<std::cell::Ref<'a, TokenPoolShard>> {
+    require_keys_eq!(*info.owner, crate::ID, RecoveryRoomError::InvalidPoolShard);
+    let data = info.try_borrow_data()?;
+    require!(
+        data.len() >= TokenPoolShard::SIZE && data[..8] == <TokenPoolShard as anchor_lang::Discriminator>::DISCRIMINATOR,
+        RecoveryRoomError::InvalidPoolShard
+    );
+    Ok(std::cell::Ref::map(data, |d| bytemuck::from_bytes(&d[8..TokenPoolShard::SIZE])))
+}
+
+fn load_shard_mut<'a>(info: &'a AccountInfo) -> Result<std::cell::RefMut<'a, TokenPoolShard>> {
+    drop(load_shard(info)?);
+    Ok(std::cell::RefMut::map(info.try_borrow_mut_data()?, |d| {
+        bytemuck::from_bytes_mut(&mut d[8..TokenPoolShard::SIZE])
+    }))
+}
+
+/// Borrow `round`'s pool shards from `accounts`, which must be its whole
+/// chain in order: shard 0 at its address, then each shard's `next_shard`
+fn load_shard_chain<'a>(
+    round: &RoundState,
+    accounts: &'a [AccountInfo],
+) -> Result<Vec<std::cell::Ref<'a, TokenPoolShard>>> {
+    require_detail!(
+        RecoveryRoomError::InvalidPoolShard,
+        "shard_count",
+        accounts.len(),
+        at_least round.shard_count
+    );
+    let accounts = &accounts[..round.shard_count as usize];
+    let mut shards: Vec<std::cell::Ref<TokenPoolShard>> = Vec::with_capacity(accounts.len());
+    for (i, info) in accounts.iter().enumerate() {
+        let shard = load_shard(info)?;
+        require!(
+            shard.round_id == round.round_id && shard.index == i as u32,
+            RecoveryRoomError::InvalidPoolShard
+        );
+        let expected = match shards.last() {
+            None => shard_address(round, 0, shard.bump)?,
+            Some(previous) => previous.next_shard,
+        };
+        require_keys_eq!(info.key(), expected, RecoveryRoomError::InvalidPoolShard);
+        shards.push(shard);
+    }
+    if let Some(last) = shards.last() {
+        require!(last.next_shard == Pubkey::default(), RecoveryRoomError::InvalidPoolShard);
+    }
+    Ok(shards)
+}
+
+/// The pages a sharded pool's entries live on, main pool first
+fn pool_pages<'a>(main: &'a [TokenPoolEntry], shards: &'a [std::cell::Ref<TokenPoolShard>]) -> Vec<&'a [TokenPoolEntry]> {
+    std::iter::once(main).chain(shards.iter().map(|s| s.entries())).collect()
+}
+
+/// Root request_randomness commits to: the main pool's weight root, chained
+/// for a sharded round with each shard's address and length as
+/// root = keccak(root || shard || len_le). Shard entries are covered by the
+/// main pool's lock, which every write to them checks.
+fn pool_root(main_root: [u8; 32], shard_accounts: &[AccountInfo], shards: &[std::cell::Ref<TokenPoolShard>]) -> [u8; 32] {
+    shard_accounts
+        .iter()
+        .zip(shards)
+        .fold(main_root, |root, (info, shard)| {
+            keccak::hashv(&[&root, info.key.as_ref(), &shard.len.to_le_bytes()]).to_bytes()
+        })
+}
+
+// ============ Account Structures ============
+
+#[account]
+#[derive(InitSpace)]
+pub struct ProtocolState {
+    pub authority: Pubkey,
+    pub round_duration: i64,
+    pub min_loss_percentage: u8,
+    pub max_tokens_per_user: u8,
+    pub current_round: u64,
+    pub total_rounds_completed: u64,
+    pub bump: u8,
+    pub protocol_fee_bps: u16,
+    /// Prize share per winner tier in bps; sums to 10_000
+    pub tier_bps: [u16; MAX_WINNER_TIERS],
+    /// Default weighting for new rounds
+    pub weighting: WeightingStrategy,
+    /// Max submissions one wallet may add to a single token per round (0 = unlimited)
+    pub max_weight_contribution_per_user: u32,
+    /// Primary Switchboard queue (default = unrestricted)
+    pub oracle_queue: Pubkey,
+    pub fallback_oracle_queue: Option<Pubkey>,
+    /// Authority sign-off required before the fallback queue may be used
+    pub failover_approved: bool,
+    /// Consecutive primary requests that timed out
+    pub primary_queue_failures: u8,
+    /// Concurrent merkle tree for compressed participations (default = disabled)
+    pub participation_tree: Pubkey,
+    pub participation_tree_leaves: u64,
+    /// Entry fee new rounds charge per participation
+    pub entry_fee: EntryFee,
+    /// Lamports paid from the treasury to crank callers
+    pub keeper_bounties: KeeperBounties,
+    /// Points granted per participation (all zero = XP disabled)
+    pub xp_rates: XpRates,
+    /// Share of each protocol fee routed to the consolation pool
+    pub consolation_fee_bps: u16,
+    /// Lamports a non-winning participant may claim per round (0 = disabled)
+    pub consolation_reward: u64,
+    /// Automation threads allowed to run the lifecycle cranks (default = none)
+    pub start_thread: Pubkey,
+    pub request_thread: Pubkey,
+    /// Latest season opened (0 = none yet)
+    pub current_season: u32,
+    pub season_active: bool,
+    /// Rounds started at or after this time are not stamped with the season
+    pub season_end_time: i64,
+    /// Participations must pass the mint blacklist, which is non-empty
+    pub blacklist_enabled: bool,
+    /// Only whitelisted mints may be entered
+    pub whitelist_only: bool,
+    /// Participants a round needs to be drawn; smaller rounds are voided (0 = any)
+    pub min_participants: u32,
+    /// Late-entry extension applied to new rounds
+    pub anti_snipe: AntiSnipe,
+    /// Bounds on a per-round duration override, in seconds
+    pub min_round_duration: i64,
+    pub max_round_duration: i64,
+    /// Rounds a winning token sits out of new rounds' pools (0 = no cooldown)
+    pub winner_cooldown_rounds: u32,
+    /// Rounds a wallet sits out after claiming a prize (0 = no cooldown)
+    pub wallet_cooldown_rounds: u32,
+    /// Oracle key whose rug attestation every entered mint needs (None = not required)
+    pub rug_verifier: Option<Pubkey>,
+    /// Oracle key whose cost-basis and price attestations set each entry's
+    /// loss_percentage (None = self-reported)
+    pub price_verifier: Option<Pubkey>,
+    /// Refundable anti-spam deposit per participation, in lamports (0 = none)
+    pub entry_deposit: u64,
+    /// Caps on participations per wallet and per slot
+    pub rate_limit: RateLimit,
+    /// The only Switchboard VRF account draws may use, set by init_vrf
+    pub vrf: Pubkey,
+    /// Signer allowed to crank the round lifecycle besides registered keepers
+    /// (None, with no keepers registered = anyone)
+    pub crank_authority: Option<Pubkey>,
+    /// The keeper registry is non-empty, so cranking is permissioned
+    pub keepers_registered: bool,
+    /// Layout version; see PROTOCOL_STATE_VERSION
+    pub version: u8,
+    /// Room this config runs (DEFAULT_ROOM_ID = the original deployment)
+    pub room_id: u32,
+    /// Pass a wallet must hold to participate (None = open room)
+    pub access_pass: Option<AccessPass>,
+    /// Fixed prize paid from the treasury's vault for rounds started from
+    /// now on (None = rounds pay out their SOL prize vault)
+    pub stable_prize: Option<StablePrize>,
+    /// Recovery token base units minted per USD of losing entries (0 = off)
+    pub recovery_tokens_per_usd: u64,
+    /// Share of each protocol fee routed to the stakers' rewards vault
+    pub staking_fee_bps: u16,
+    /// Authority nominated by propose_authority, until it accepts
+    pub pending_authority: Option<Pubkey>,
+    /// Seconds a queued ConfigChange waits before apply_config (0 = changes
+    /// apply directly through their setters)
+    pub config_delay_secs: i64,
+    /// Share of a referred user's SOL entry fee credited to their referrer
+    pub referral_fee_bps: u16,
+    /// Rounds started from now on mint raffle tickets and draw linearly over them
+    pub ticket_mode: bool,
+    /// Seconds winners of rounds settled from now on have to claim before
+    /// sweep_unclaimed may reclaim their prizes (0 = claims never expire)
+    pub claim_window_secs: i64,
+    /// Set by finalize_protocol: config and emergency withdrawals are locked for good
+    pub finalized: bool,
+    /// Participants rounds started from now on admit (0 = unlimited)
+    pub max_participants: u32,
+    /// Where rounds get their randomness
+    pub randomness_mode: RandomnessMode,
+    /// Submissions a token needs to be drawn in rounds started from now on
+    /// (0 = any)
+    pub min_submissions_to_qualify: u32,
+    /// Share of a SOL prize pool set aside for the bonus participant draw
+    pub bonus_prize_bps: u16,
+    /// Rounds started from now on pay their whole prize to one wallet,
+    /// drawn among the winning token's submitters by attested loss
+    pub wallet_draw: bool,
+    /// schedule_round has created the room's schedule, so start_round must
+    /// be passed it
+    pub has_schedule: bool,
+}
+
+impl ProtocolState {
+    pub const SIZE: usize = 8 + Self::INIT_SPACE;
+
+    /// Set up a freshly created room with `config` and default settings
+    pub fn init_room(&mut self, authority: Pubkey, bump: u8, room_id: u32, config: &RoomConfig) -> Result<()> {
+        require_detail!(
+            RecoveryRoomError::FeeTooHigh,
+            "protocol_fee_bps",
+            config.protocol_fee_bps,
+            at_most MAX_PROTOCOL_FEE_BPS
+        );
+        require_detail!(
+            RecoveryRoomError::InvalidTokenCount,
+            "max_tokens_per_user",
+            config.max_tokens_per_user,
+            at_most MAX_TOKENS_PER_USER
+        );
+
+        self.authority = authority;
+        self.round_duration = config.round_duration;
+        self.min_loss_percentage = config.min_loss_percentage;
+        self.max_tokens_per_user = config.max_tokens_per_user;
+        self.current_round = 0;
+        self.total_rounds_completed = 0;
+        self.bump = bump;
+        self.version = PROTOCOL_STATE_VERSION;
+        self.protocol_fee_bps = config.protocol_fee_bps;
+        self.tier_bps = [BPS_DENOMINATOR as u16, 0, 0];
+        self.weighting = WeightingStrategy::Sqrt;
+        self.max_weight_contribution_per_user = 0;
+        self.entry_fee = EntryFee::default();
+        self.keeper_bounties = KeeperBounties::default();
+        self.xp_rates = XpRates::default();
+        self.consolation_fee_bps = 0;
+        self.consolation_reward = 0;
+        self.blacklist_enabled = false;
+        self.whitelist_only = false;
+        self.min_participants = 0;
+        self.anti_snipe = AntiSnipe::default();
+        self.min_round_duration = config.round_duration;
+        self.max_round_duration = config.round_duration;
+        self.winner_cooldown_rounds = 0;
+        self.wallet_cooldown_rounds = 0;
+        self.rug_verifier = None;
+        self.price_verifier = None;
+        self.entry_deposit = 0;
+        self.rate_limit = RateLimit::default();
+        self.vrf = Pubkey::default();
+        self.crank_authority = None;
+        self.keepers_registered = false;
+        self.room_id = room_id;
+        self.access_pass = None;
+        self.stable_prize = None;
+        self.recovery_tokens_per_usd = 0;
+        self.staking_fee_bps = 0;
+        self.pending_authority = None;
+        self.config_delay_secs = 0;
+        self.referral_fee_bps = 0;
+        self.ticket_mode = false;
+        self.claim_window_secs = 0;
+        self.finalized = false;
+        self.max_participants = 0;
+        self.randomness_mode = RandomnessMode::Vrf;
+        self.min_submissions_to_qualify = 0;
+        self.bonus_prize_bps = 0;
+        self.wallet_draw = false;
+        self.has_schedule = false;
+        Ok(())
+    }
+
+    /// Season a round starting at `now` belongs to (0 = none)
+    pub fn season_for(&self, now: i64) -> u32 {
+        if self.season_active && now < self.season_end_time {
+            self.current_season
+        } else {
+            0
+        }
+    }
+
+    /// Require a lifecycle crank to come from the crank authority or a keeper
+    /// permitted to run `instruction`, while either is configured. Threaded
+    /// calls are checked against the protocol's threads instead. A keeper's
+    /// activity is recorded whenever the registry is passed.
+    pub fn require_crank(
+        &self,
+        signer: Option<Pubkey>,
+        threaded: bool,
+        registry: Option<&mut KeeperRegistry>,
+        instruction: CrankInstruction,
+        now: i64,
+    ) -> Result<()> {
+        let is_keeper = match (signer, registry) {
+            (Some(signer), Some(registry)) => registry.record_activity(&signer, instruction, now),
+            _ => false,
+        };
+        let permissioned = self.crank_authority.is_some() || self.keepers_registered;
+        if permissioned && !threaded && !is_keeper {
+            require!(
+                signer.is_some() && signer == self.crank_authority,
+                RecoveryRoomError::UnauthorizedCrank
+            );
+        }
+        Ok(())
+    }
+
+    /// Require a crank authority or registered keepers, without which anyone
+    /// could crank, and so choose, a commit-reveal room's randomness
+    pub fn require_permissioned_crank(&self) -> Result<()> {
+        require!(
+            self.crank_authority.is_some() || self.keepers_registered,
+            RecoveryRoomError::CommitRevealNeedsCrank
+        );
+        Ok(())
+    }
+
+    /// Rules a round is settled under, recorded verbatim in the round
+    pub fn current_rules(&self, round: &RoundState) -> RoundRules {
+        RoundRules {
+            weighting: round.weighting,
+            min_loss_percentage: self.min_loss_percentage,
+            max_tokens_per_user: round.max_tokens_per_user,
+            protocol_fee_bps: self.protocol_fee_bps,
+            tier_bps: if round.wallet_draw {
+                // The one drawn wallet takes the whole prize
+                let mut single = [0; MAX_WINNER_TIERS];
+                single[0] = BPS_DENOMINATOR as u16;
+                single
+            } else {
+                self.tier_bps
+            },
+            max_weight_contribution_per_user: round.weight_cap,
+        }
+    }
+}
+
+/// Prize of a fixed amount of a stablecoin (e.g. USDC), paid from the
+/// treasury's token account for that mint
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug, InitSpace)]
+pub struct StablePrize {
+    pub mint: Pubkey,
+    /// Prize per round, in base units of the mint
+    pub amount: u64,
+}
+
+/// Per-participation entry fee, in lamports or a configured SPL mint
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default, PartialEq, Eq, Debug, InitSpace)]
+pub struct EntryFee {
+    /// Fee mint (None = lamports)
+    pub mint: Option<Pubkey>,
+    /// Fee in base units of the mint (0 = free entry)
+    pub amount: u64,
+    /// Burn SPL fees from the vault instead of adding them to the prize
+    pub burn: bool,
+}
+
+impl EntryFee {
+    pub fn validate(&self) -> Result<()> {
+        // Lamports can't be burned
+        require!(!self.burn || self.mint.is_some(), RecoveryRoomError::InvalidEntryFee);
+        Ok(())
+    }
+}
+
+/// Lamport bounty per crank instruction (0 = unpaid)
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default, PartialEq, Eq, Debug, InitSpace)]
+pub struct KeeperBounties {
+    pub start_round: u64,
+    pub request_randomness: u64,
+    pub consume_randomness: u64,
+}
+
+impl KeeperBounties {
+    pub fn max(&self) -> u64 {
+        self.start_round
+            .max(self.request_randomness)
+            .max(self.consume_randomness)
+    }
+}
+
+/// XP earned per participation: a flat amount, plus per token submitted,
+/// plus per whole dollar of loss up to `max_loss_dollars`
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default, PartialEq, Eq, Debug, InitSpace)]
+pub struct XpRates {
+    pub per_participation: u32,
+    pub per_token: u32,
+    pub per_loss_dollar: u32,
+    /// Loss counted toward XP per participation, in whole dollars
+    pub max_loss_dollars: u32,
+}
+
+impl XpRates {
+    pub fn points(&self, tokens: &[TokenEntry]) -> Result<u64> {
+        let loss = tokens
+            .iter()
+            .try_fold(UsdCents::ZERO, |total, t| total.checked_add(t.loss_usd()))?;
+        let loss_dollars = (loss.cents() / money::CENTS_PER_DOLLAR).min(self.max_loss_dollars as u64);
+
+        Ok((self.per_participation as u64)
+            .saturating_add(self.per_token as u64 * tokens.len() as u64)
+            .saturating_add(self.per_loss_dollar as u64 * loss_dollars))
+    }
+}
+
+/// Entries landing in the last `window_secs` of a round extend it by
+/// `extension_secs`, at most `max_extension_secs` in total (zeroed = off)
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default, PartialEq, Eq, Debug, InitSpace)]
+pub struct AntiSnipe {
+    pub window_secs: u32,
+    pub extension_secs: u32,
+    pub max_extension_secs: u32,
+}
+
+impl AntiSnipe {
+    pub fn validate(&self) -> Result<()> {
+        require!(
+            self.extension_secs <= self.max_extension_secs,
+            RecoveryRoomError::InvalidAntiSnipe
+        );
+        Ok(())
+    }
+
+    /// Seconds to add to `end_time` for an entry at `now`
+    pub fn extension(&self, now: i64, end_time: i64, total_extension: u32) -> u32 {
+        if now < end_time.saturating_sub(self.window_secs as i64) {
+            return 0;
+        }
+        self.extension_secs
+            .min(self.max_extension_secs.saturating_sub(total_extension))
+    }
+}
+
+/// At most `max_per_window` participations per wallet every `window_secs`,
+/// and at most `max_per_slot` participations per slot (zero = no cap)
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default, PartialEq, Eq, Debug, InitSpace)]
+pub struct RateLimit {
+    pub window_secs: u32,
+    pub max_per_window: u16,
+    pub max_per_slot: u16,
+}
+
+impl RateLimit {
+    pub fn validate(&self) -> Result<()> {
+        require!(
+            self.max_per_window == 0 || self.window_secs > 0,
+            RecoveryRoomError::InvalidRateLimit
+        );
+        Ok(())
+    }
+}
+
+/// Membership pass gating participation in a private room
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug, InitSpace)]
+pub enum AccessPass {
+    /// At least `min_amount` base units of an SPL mint
+    Token { mint: Pubkey, min_amount: u64 },
+    /// Any NFT verified as part of a Metaplex collection
+    Collection { collection: Pubkey },
+}
+
+impl AccessPass {
+    pub fn validate(&self) -> Result<()> {
+        if let AccessPass::Token { min_amount, .. } = self {
+            require!(*min_amount > 0, RecoveryRoomError::InvalidAccessPass);
+        }
+        Ok(())
+    }
+}
+
+/// Source of a room's round randomness
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default, PartialEq, Eq, Debug, InitSpace)]
+pub enum RandomnessMode {
+    /// Switchboard VRF through request_randomness
+    #[default]
+    Vrf,
+    /// The crank's commit_randomness and reveal_randomness, salted by a slot
+    /// hash fixed at seal_randomness, for clusters without Switchboard
+    /// (localnet, private validators). Needs a crank authority or keepers;
+    /// a withheld reveal can only void the round.
+    CommitReveal,
+}
+
+/// Crank instructions that earn a keeper bounty
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum CrankInstruction {
+    StartRound,
+    RequestRandomness,
+    ConsumeRandomness,
+}
+
+impl CrankInstruction {
+    pub const ALL_PERMISSIONS: u8 = 0b111;
+
+    /// Bit granting a keeper this instruction
+    pub fn permission(self) -> u8 {
+        1 << self as u8
+    }
+}
+
+/// Shortest claim window a room may give its winners
+#[constant]
+pub const MIN_CLAIM_WINDOW_SECS: i64 = 7 * 24 * 60 * 60;
+
+/// Longest delay a room may put on its parameter changes
+#[constant]
+pub const MAX_CONFIG_DELAY_SECS: i64 = 30 * 24 * 60 * 60;
+
+/// A protocol parameter change that can sit in the timelock queue
+///
+/// Every setter that moves funds or shifts the odds goes through here,
+/// including ticket mode, which forces Linear weighting, and the randomness
+/// source and verifiers the draw rests on. The setters left out are
+/// deliberately exempt: operational ones that must react at once (oracle
+/// queues, crank authority, rate limit, access pass, mint lists, duration
+/// bounds) and ones that only touch standing or rewards outside the prize
+/// pool (wallet cooldown, XP rates).
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug, InitSpace)]
+pub enum ConfigChange {
+    ProtocolFee(u16),
+    TierSplit([u16; MAX_WINNER_TIERS]),
+    Weighting(WeightingStrategy),
+    RoundDuration(i64),
+    EntryFee(EntryFee),
+    ConfigDelay(i64),
+    KeeperBounties(KeeperBounties),
+    ClaimWindow(i64),
+    StablePrize(Option<StablePrize>),
+    EntryDeposit(u64),
+    RecoveryRate(u64),
+    StakingFee(u16),
+    ReferralFee(u16),
+    /// Consolation fee slice in bps, and lamports paid per consolation claim
+    Consolation(u16, u64),
+    MaxWeightContribution(u32),
+    MinParticipants(u32),
+    MaxParticipants(u32),
+    MinSubmissionsToQualify(u32),
+    BonusPrize(u16),
+    WalletDraw(bool),
+    TicketMode(bool),
+    RandomnessMode(RandomnessMode),
+    RugVerifier(Option<Pubkey>),
+    PriceVerifier(Option<Pubkey>),
+    WinnerCooldown(u32),
+    AntiSnipe(AntiSnipe),
+}
+
+impl ConfigChange {
+    pub fn validate(&self, protocol: &ProtocolState) -> Result<()> {
+        match self {
+            ConfigChange::ProtocolFee(bps) => require_detail!(
+                RecoveryRoomError::FeeTooHigh,
+                "protocol_fee_bps",
+                *bps,
+                at_most MAX_PROTOCOL_FEE_BPS
+            ),
+            ConfigChange::TierSplit(tier_bps) => {
+                let total: u64 = tier_bps.iter().map(|b| *b as u64).sum();
+                require!(
+                    total == BPS_DENOMINATOR && tier_bps[0] > 0,
+                    RecoveryRoomError::InvalidTierSplit
+                );
+                // Tiers must be contiguous: a zero tier ends the draw
+                require!(
+                    tier_bps.windows(2).all(|w| w[0] > 0 || w[1] == 0),
+                    RecoveryRoomError::InvalidTierSplit
+                );
+            }
+            ConfigChange::Weighting(weighting) => weighting.validate()?,
+            ConfigChange::RoundDuration(duration) => {
+                require_detail!(
+                    RecoveryRoomError::InvalidRoundDuration,
+                    "round_duration",
+                    *duration,
+                    at_least protocol.min_round_duration
+                );
+                require_detail!(
+                    RecoveryRoomError::InvalidRoundDuration,
+                    "round_duration",
+                    *duration,
+                    at_most protocol.max_round_duration
+                );
+            }
+            ConfigChange::EntryFee(entry_fee) => entry_fee.validate()?,
+            ConfigChange::ConfigDelay(delay) => {
+                require!(*delay >= 0, RecoveryRoomError::InvalidConfigDelay);
+                require_detail!(
+                    RecoveryRoomError::InvalidConfigDelay,
+                    "config_delay_secs",
+                    *delay,
+                    at_most MAX_CONFIG_DELAY_SECS
+                );
+            }
+            ConfigChange::KeeperBounties(bounties) => require_detail!(
+                RecoveryRoomError::BountyTooHigh,
+                "lamports",
+                bounties.max(),
+                at_most MAX_KEEPER_BOUNTY_LAMPORTS
+            ),
+            ConfigChange::ClaimWindow(window) => {
+                require!(*window >= 0, RecoveryRoomError::InvalidClaimWindow);
+                if *window != 0 {
+                    require_detail!(
+                        RecoveryRoomError::InvalidClaimWindow,
+                        "claim_window_secs",
+                        *window,
+                        at_least MIN_CLAIM_WINDOW_SECS
+                    );
+                }
+            }
+            ConfigChange::StablePrize(stable_prize) => {
+                if let Some(prize) = stable_prize {
+                    require!(prize.amount > 0, RecoveryRoomError::InvalidStablePrize);
+                }
+            }
+            ConfigChange::EntryDeposit(lamports) => require_detail!(
+                RecoveryRoomError::DepositTooHigh,
+                "lamports",
+                *lamports,
+                at_most MAX_ENTRY_DEPOSIT_LAMPORTS
+            ),
+            ConfigChange::StakingFee(bps) => require_detail!(
+                RecoveryRoomError::FeeTooHigh,
+                "staking_fee_bps",
+                *bps,
+                at_most BPS_DENOMINATOR as u16 - protocol.consolation_fee_bps
+            ),
+            ConfigChange::ReferralFee(bps) => require_detail!(
+                RecoveryRoomError::FeeTooHigh,
+                "referral_fee_bps",
+                *bps,
+                at_most MAX_REFERRAL_FEE_BPS
+            ),
+            ConfigChange::Consolation(fee_bps, _) => require_detail!(
+                RecoveryRoomError::FeeTooHigh,
+                "consolation_fee_bps",
+                *fee_bps,
+                at_most BPS_DENOMINATOR as u16 - protocol.staking_fee_bps
+            ),
+            ConfigChange::MinParticipants(min) => {
+                if protocol.max_participants > 0 {
+                    require_detail!(
+                        RecoveryRoomError::InvalidMaxParticipants,
+                        "min_participants",
+                        *min,
+                        at_most protocol.max_participants
+                    );
+                }
+            }
+            ConfigChange::MaxParticipants(max) => {
+                if *max > 0 {
+                    require_detail!(
+                        RecoveryRoomError::InvalidMaxParticipants,
+                        "max_participants",
+                        *max,
+                        at_least protocol.min_participants
+                    );
+                }
+            }
+            ConfigChange::BonusPrize(bps) => require_detail!(
+                RecoveryRoomError::BonusPrizeTooHigh,
+                "bonus_prize_bps",
+                *bps,
+                at_most MAX_BONUS_PRIZE_BPS
+            ),
+            ConfigChange::RandomnessMode(mode) => {
+                if *mode == RandomnessMode::CommitReveal {
+                    protocol.require_permissioned_crank()?;
+                }
+            }
+            ConfigChange::AntiSnipe(anti_snipe) => anti_snipe.validate()?,
+            ConfigChange::RecoveryRate(_)
+            | ConfigChange::MaxWeightContribution(_)
+            | ConfigChange::MinSubmissionsToQualify(_)
+            | ConfigChange::WalletDraw(_)
+            | ConfigChange::TicketMode(_)
+            | ConfigChange::RugVerifier(_)
+            | ConfigChange::PriceVerifier(_)
+            | ConfigChange::WinnerCooldown(_) => {}
+        }
+        Ok(())
+    }
+
+    /// Validate against the current config, then write the change
+    pub fn apply(&self, protocol: &mut ProtocolState) -> Result<()> {
+        self.validate(protocol)?;
+        match *self {
+            ConfigChange::ProtocolFee(bps) => protocol.protocol_fee_bps = bps,
+            ConfigChange::TierSplit(tier_bps) => protocol.tier_bps = tier_bps,
+            ConfigChange::Weighting(weighting) => protocol.weighting = weighting,
+            ConfigChange::RoundDuration(duration) => protocol.round_duration = duration,
+            ConfigChange::EntryFee(entry_fee) => protocol.entry_fee = entry_fee,
+            ConfigChange::ConfigDelay(delay) => protocol.config_delay_secs = delay,
+            ConfigChange::KeeperBounties(bounties) => protocol.keeper_bounties = bounties,
+            ConfigChange::ClaimWindow(window) => protocol.claim_window_secs = window,
+            ConfigChange::StablePrize(stable_prize) => protocol.stable_prize = stable_prize,
+            ConfigChange::EntryDeposit(lamports) => protocol.entry_deposit = lamports,
+            ConfigChange::RecoveryRate(tokens_per_usd) => protocol.recovery_tokens_per_usd = tokens_per_usd,
+            ConfigChange::StakingFee(bps) => protocol.staking_fee_bps = bps,
+            ConfigChange::ReferralFee(bps) => protocol.referral_fee_bps = bps,
+            ConfigChange::Consolation(fee_bps, reward_lamports) => {
+                protocol.consolation_fee_bps = fee_bps;
+                protocol.consolation_reward = reward_lamports;
+            }
+            ConfigChange::MaxWeightContribution(cap) => protocol.max_weight_contribution_per_user = cap,
+            ConfigChange::MinParticipants(min) => protocol.min_participants = min,
+            ConfigChange::MaxParticipants(max) => protocol.max_participants = max,
+            ConfigChange::MinSubmissionsToQualify(min) => protocol.min_submissions_to_qualify = min,
+            ConfigChange::BonusPrize(bps) => protocol.bonus_prize_bps = bps,
+            ConfigChange::WalletDraw(wallet_draw) => protocol.wallet_draw = wallet_draw,
+            ConfigChange::TicketMode(enabled) => protocol.ticket_mode = enabled,
+            ConfigChange::RandomnessMode(mode) => protocol.randomness_mode = mode,
+            ConfigChange::RugVerifier(verifier) => protocol.rug_verifier = verifier,
+            ConfigChange::PriceVerifier(verifier) => protocol.price_verifier = verifier,
+            ConfigChange::WinnerCooldown(rounds) => protocol.winner_cooldown_rounds = rounds,
+            ConfigChange::AntiSnipe(anti_snipe) => protocol.anti_snipe = anti_snipe,
+        }
+        Ok(())
+    }
+
+    /// Apply through a direct setter, which only rooms without a config
+    /// delay allow
+    pub fn apply_directly(&self, protocol: &mut ProtocolState) -> Result<()> {
+        require!(protocol.config_delay_secs == 0, RecoveryRoomError::ConfigTimelocked);
+        self.apply(protocol)
+    }
+}
+
+/// A room's queued parameter change, applied by apply_config once `eta` passes
+#[account]
+#[derive(InitSpace)]
+pub struct PendingConfig {
+    pub room_id: u32,
+    pub change: ConfigChange,
+    pub queued_at: i64,
+    pub eta: i64,
+    /// Refunded the account's rent when it's applied or cancelled
+    pub rent_payer: Pubkey,
+    pub bump: u8,
+}
+
+impl PendingConfig {
+    pub const SIZE: usize = 8 + Self::INIT_SPACE;
+}
+
+/// Max admins in a room's admin set
+pub const MAX_ADMINS: usize = 10;
+
+/// Fewest admins that must sign for an admin set, so no single key can move
+/// a room's prize vaults
+#[constant]
+pub const MIN_ADMIN_THRESHOLD: u8 = 2;
+
+/// Wait between queueing an emergency withdrawal and executing it
+#[constant]
+pub const EMERGENCY_WITHDRAW_DELAY_SECS: i64 = 48 * 60 * 60;
+
+/// A room's emergency admins, `threshold` of whom must sign an emergency withdrawal
+#[account]
+#[derive(InitSpace)]
+pub struct AdminSet {
+    pub room_id: u32,
+    #[max_len(MAX_ADMINS)]
+    pub admins: Vec<Pubkey>,
+    pub threshold: u8,
+    pub bump: u8,
+}
+
+impl AdminSet {
+    pub const SIZE: usize = 8 + Self::INIT_SPACE;
+
+    pub fn validate(admins: &[Pubkey], threshold: u8) -> Result<()> {
+        require_detail!(
+            RecoveryRoomError::InvalidAdminSet,
+            "admin_count",
+            admins.len(),
+            at_most MAX_ADMINS
+        );
+        require!(
+            threshold >= MIN_ADMIN_THRESHOLD && threshold as usize <= admins.len(),
+            RecoveryRoomError::InvalidAdminSet
+        );
+        let mut unique = admins.to_vec();
+        unique.sort();
+        unique.dedup();
+        require!(unique.len() == admins.len(), RecoveryRoomError::InvalidAdminSet);
+        Ok(())
+    }
+
+    /// Admins among `signers` that signed the transaction, failing below threshold
+    pub fn require_threshold(&self, signers: &[AccountInfo]) -> Result<u8> {
+        let approvals = self
+            .admins
+            .iter()
+            .filter(|admin| signers.iter().any(|s| s.is_signer && s.key == *admin))
+            .count();
+        require_detail!(
+            RecoveryRoomError::InsufficientAdminApprovals,
+            "approvals",
+            approvals,
+            at_least self.threshold
+        );
+        Ok(approvals as u8)
+    }
+}
+
+/// A queued withdrawal of one round's prize vault funds, executable once `eta` passes
+#[account]
+#[derive(InitSpace)]
+pub struct EmergencyWithdrawal {
+    pub room_id: u32,
+    pub round_id: u64,
+    /// Receives the lamports
+    pub destination: Pubkey,
+    pub lamports: u64,
+    /// Prize vault token account drained of token_amount, and where it goes
+    pub token_account: Option<Pubkey>,
+    pub token_destination: Option<Pubkey>,
+    pub token_amount: u64,
+    /// Admins that signed the queueing
+    pub approvals: u8,
+    pub queued_at: i64,
+    pub eta: i64,
+    /// Refunded the account's rent when it's executed or cancelled
+    pub rent_payer: Pubkey,
+    pub bump: u8,
+}
+
+impl EmergencyWithdrawal {
+    pub const SIZE: usize = 8 + Self::INIT_SPACE;
+}
+
+/// Curve applied to submission counts to get draw weights
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default, PartialEq, Eq, Debug, InitSpace)]
+pub enum WeightingStrategy {
+    /// sqrt(n): dampens large communities (the original behaviour)
+    #[default]
+    Sqrt,
+    /// n: one submission, one ticket
+    Linear,
+    /// n^2: rewards coordinated communities
+    Quadratic,
+    /// sqrt(min(n, max_submissions)): sqrt with a hard ceiling per token
+    CappedSqrt { max_submissions: u32 },
+    /// sqrt(total loss in USD): bigger verified losses beat dust spam. Entries
+    /// need the room's price verifier to attest their losses.
+    SqrtLossUsd,
+}
+
+impl WeightingStrategy {
+    /// Draw weight of a single pool token
+    pub fn weight(&self, entry: &TokenPoolEntry) -> f64 {
+        let n = entry.submission_count as f64;
+        match self {
+            WeightingStrategy::Sqrt => n.sqrt(),
+            WeightingStrategy::Linear => n,
+            WeightingStrategy::Quadratic => n * n,
+            WeightingStrategy::CappedSqrt { max_submissions } => {
+                (entry.submission_count.min(*max_submissions) as f64).sqrt()
+            }
+            WeightingStrategy::SqrtLossUsd => {
+                (entry.total_loss_usd as f64 / money::CENTS_PER_DOLLAR as f64).sqrt()
+            }
+        }
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        if let WeightingStrategy::CappedSqrt { max_submissions } = self {
+            require!(*max_submissions > 0, RecoveryRoomError::InvalidWeightingStrategy);
+        }
+        Ok(())
+    }
+}
+
+/// The exact parameters a round was settled under, so disputes can be
+/// resolved from the round account alone
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default, PartialEq, Eq, InitSpace)]
+pub struct RoundRules {
+    pub weighting: WeightingStrategy,
+    pub min_loss_percentage: u8,
+    pub max_tokens_per_user: u8,
+    pub protocol_fee_bps: u16,
+    pub tier_bps: [u16; MAX_WINNER_TIERS],
+    pub max_weight_contribution_per_user: u32,
+}
+
+
+#[account]
+#[derive(InitSpace)]
+pub struct RoundState {
+    pub round_id: u64,
+    pub start_time: i64,
+    pub end_time: i64,
+    pub total_participants: u32,
+    pub total_token_entries: u32,
+    pub status: RoundStatus,
+    pub vrf_result: Option<[u8; 32]>,
+    pub winner_token: Option<Pubkey>,
+    pub bump: u8,
+    /// Prize vault lamports frozen at settlement
+    pub prize_pool: u64,
+    pub claimed_count: u32,
+    /// Winning tokens by tier (1st, 2nd, 3rd); only `winner_count` are set
+    pub winner_tokens: [Pubkey; MAX_WINNER_TIERS],
+    pub winner_count: u8,
+    /// Lamports allotted to each tier at settlement
+    pub tier_prizes: [u64; MAX_WINNER_TIERS],
+    /// Pool submissions of each tier's token at settlement
+    pub tier_submissions: [u32; MAX_WINNER_TIERS],
+    /// Rules in force when the round settled
+    pub rules: RoundRules,
+    /// Weighting used for this round's draw (protocol default unless overridden)
+    pub weighting: WeightingStrategy,
+    /// Per-user weight cap snapshotted at start_round (0 = unlimited)
+    pub weight_cap: u32,
+    /// Oracle queue used for the latest VRF request
+    pub oracle_queue: Pubkey,
+    pub vrf_requested_at: i64,
+    pub vrf_request_attempts: u8,
+    /// Entry fee snapshotted at start_round
+    pub entry_fee: EntryFee,
+    /// Entry fees taken this round, in the fee's base units (burned fees included)
+    pub entry_fees_collected: u64,
+    /// Winning entries paid out so far, per tier
+    pub claimed_tier_entries: [u32; MAX_WINNER_TIERS],
+    /// Season the round counts toward (0 = none), stamped at start_round
+    pub season_id: u32,
+    /// Participants needed for a draw, snapshotted at start_round
+    pub min_participants: u32,
+    /// Anti-snipe rule snapshotted at start_round
+    pub anti_snipe: AntiSnipe,
+    /// Seconds end_time has been pushed back by late entries
+    pub total_extension: u32,
+    /// Token cap per participation, snapshotted or overridden at start_round
+    pub max_tokens_per_user: u8,
+    /// Winning-token cooldown snapshotted at start_round
+    pub winner_cooldown_rounds: u32,
+    /// Refundable deposit per participation snapshotted at start_round
+    pub entry_deposit: u64,
+    /// Slot of the latest participation, and participations in that slot
+    pub last_entry_slot: u64,
+    pub slot_entries: u16,
+    /// pool_root committed at request_randomness
+    pub pool_root: [u8; 32],
+    /// VRF counter the latest request will be answered under
+    pub vrf_counter: u128,
+    /// Layout version; see ROUND_STATE_VERSION
+    pub version: u8,
+    /// Room the round belongs to, copied from its protocol at start_round
+    pub room_id: u32,
+    /// Sponsor the round is branded for, set by brand_round
+    pub sponsor: Option<Pubkey>,
+    /// Off-chain JSON describing the sponsored round's branding
+    #[max_len(MAX_ROUND_METADATA_URI_LEN)]
+    pub metadata_uri: String,
+    /// Theme of the scheduled event the round was started for (empty = none)
+    #[max_len(MAX_SCHEDULE_THEME_LEN)]
+    pub theme: String,
+    /// Prize multiplier of that event, in bps (BPS_DENOMINATOR or less = none)
+    pub prize_multiplier_bps: u16,
+    /// Stable prize assigned at start_round, multiplier included; the tier
+    /// prizes are then denominated in its mint
+    pub stable_prize: Option<StablePrize>,
+    /// Entrants are minted a raffle ticket per submission they add
+    pub ticketed: bool,
+    /// Submissions added to the pool, stake multipliers included; for a
+    /// ticketed round, the tickets minted
+    pub ticket_supply: u64,
+    /// Last moment prizes can be claimed, stamped at settlement (0 = none)
+    pub claim_deadline: i64,
+    /// Unclaimed prizes were reclaimed by sweep_unclaimed
+    pub unclaimed_swept: bool,
+    /// Participants the round admits, snapshotted or overridden (0 = unlimited)
+    pub max_participants: u32,
+    /// Pool shards chained after the round's token pool
+    pub shard_count: u32,
+    /// PoolEntry accounts register_token created, and how many
+    /// tally_pool_entries has folded into the token pool
+    pub pool_entry_count: u32,
+    pub pool_entries_tallied: u32,
+    /// Combined draw weight of the pool entries, scaled by WEIGHT_SCALE
+    pub pool_weight: u64,
+    /// Slot committed at request time whose hash resolve_with_slothash can
+    /// settle the round from if VRF never answers (0 = none)
+    pub fallback_slot: u64,
+    /// The round was settled from a slot hash rather than VRF
+    pub slothash_resolved: bool,
+    /// keccak(preimage) committed by commit_randomness, and when
+    /// (0 = not committed)
+    pub randomness_commitment: [u8; 32],
+    pub committed_at: i64,
+    /// Hash of the salt slot, mixed into a commit-reveal result at
+    /// reveal_randomness
+    pub reveal_salt: [u8; 32],
+    /// Submissions a token needs to be drawn, snapshotted at start_round
+    pub min_submissions_to_qualify: u32,
+    /// Bonus draw tickets handed out, one per entry of a participation account
+    pub bonus_tickets: u32,
+    /// Ticket drawn for the bonus prize at finalize_round
+    pub bonus_ticket: u32,
+    /// Lamports set aside for the bonus ticket's holder (0 = no bonus draw)
+    pub bonus_prize: u64,
+    pub bonus_claimed: bool,
+    /// Two-stage draw snapshotted at start_round: the prize goes to the
+    /// holder of `wallet_ticket`, a cent of the winning token's attested
+    /// total loss
+    pub wallet_draw: bool,
+    pub wallet_ticket: u64,
+    /// Entry fees void_round left in the prize vault for entrants to
+    /// reclaim, in the round's fee mint, less those refunded so far
+    pub refunds_outstanding: u64,
+    /// Slot fixed by seal_randomness whose hash salts a commit-reveal result
+    /// (0 = not sealed)
+    pub salt_slot: u64,
+}
+
+impl RoundState {
+    pub const SIZE: usize = 8 + Self::INIT_SPACE;
+
+    /// Count one claim and the winning entries it covered
+    pub fn record_claim(&mut self, tier_entries: &[u32; MAX_WINNER_TIERS]) -> Result<()> {
+        self.claimed_count = self.claimed_count.checked_add(1).ok_or(RecoveryRoomError::ArithmeticOverflow)?;
+        for (claimed, entries) in self.claimed_tier_entries.iter_mut().zip(tier_entries) {
+            *claimed = claimed.checked_add(*entries).ok_or(RecoveryRoomError::ArithmeticOverflow)?;
+        }
+        Ok(())
+    }
+
+    /// Whether prizes can still be claimed at `now`
+    pub fn claims_open(&self, now: i64) -> bool {
+        self.claim_deadline == 0 || now <= self.claim_deadline
+    }
+
+    /// Whether every tier's winning entries, and any bonus prize, have been paid out
+    pub fn all_prizes_claimed(&self) -> bool {
+        (0..self.winner_count as usize)
+            .all(|tier| self.claimed_tier_entries[tier] >= self.tier_submissions[tier])
+            && (self.bonus_prize == 0 || self.bonus_claimed)
+    }
+
+    /// Hand out one bonus draw ticket per entry, returning the first
+    pub fn issue_bonus_tickets(&mut self, entries: usize) -> Result<u32> {
+        let first = self.bonus_tickets;
+        self.bonus_tickets = first
+            .checked_add(entries as u32)
+            .ok_or(RecoveryRoomError::ArithmeticOverflow)?;
+        Ok(first)
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, InitSpace)]
+pub enum RoundStatus {
+    Active,
+    VrfRequested,
+    Complete,
+    Cancelled,
+    /// VRF result stored; waiting on finalize_round to draw winners
+    Settling,
+}
+
+/// Holds a round's SOL prize; `lamports` excludes the account's own rent
+#[account]
+#[derive(InitSpace)]
+pub struct PrizeVault {
+    pub round_id: u64,
+    pub lamports: u64,
+    pub bump: u8,
+}
+
+impl PrizeVault {
+    pub const SIZE: usize = 8 + Self::INIT_SPACE;
+}
+
+/// Maximum length of a sponsored round's metadata URI
+#[constant]
+pub const MAX_ROUND_METADATA_URI_LEN: usize = 200;
+
+/// Maximum length of a sponsor attribution memo
+pub const MAX_SPONSOR_MEMO_LEN: usize = 64;
+
+/// Attribution for everything one sponsor deposited into a round's prize vault
+#[account]
+#[derive(InitSpace)]
+pub struct Sponsor {
+    pub sponsor: Pubkey,
+    pub round_id: u64,
+    pub lamports_funded: u64,
+    pub token_mint: Option<Pubkey>,
+    pub tokens_funded: u64,
+    #[max_len(MAX_SPONSOR_MEMO_LEN)]
+    pub memo: String,
+    pub bump: u8,
+}
+
+impl Sponsor {
+    pub const SIZE: usize = 8 + Self::INIT_SPACE;
+}
+
+/// Protocol fee sink; SOL fees sit on the account, SPL fees in token accounts it owns
+#[account]
+#[derive(InitSpace)]
+pub struct Treasury {
+    pub total_fees_collected: u64,
+    pub total_withdrawn: u64,
+    pub bump: u8,
+    /// Lamports paid out to crank callers
+    pub total_bounties_paid: u64,
+}
+
+impl Treasury {
+    pub const SIZE: usize = 8 + Self::INIT_SPACE;
+}
+
+/// Max (year, mint) buckets kept per wallet; the oldest is evicted when
+/// full, its totals logged in a PayoutBucketEvicted event
+pub const MAX_LEDGER_ENTRIES: usize = 10;
+
+/// Prize totals paid to one wallet in one calendar year for one mint
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, InitSpace)]
+pub struct YearlyPayout {
+    pub year: u16,
+    pub mint: Pubkey,
+    pub amount: u64,
+    pub fees: u64,
+    pub claims: u32,
+}
+
+
+/// Append-only log of a room's closed rounds at ["round_archive", room],
+/// written by archive_round. This header is followed in the account by `len`
+/// ArchivedRound records in archive order.
+#[account]
+#[derive(InitSpace)]
+pub struct RoundArchive {
+    pub room_id: u32,
+    pub len: u32,
+    pub bump: u8,
+}
+
+impl RoundArchive {
+    const HEADER_LEN: usize = 8 + Self::INIT_SPACE;
+    const RECORD_LEN: usize = std::mem::size_of::<ArchivedRound>();
+
+    /// Account size for an archive of `len` records
+    pub const fn space(len: usize) -> usize {
+        Self::HEADER_LEN + len * Self::RECORD_LEN
+    }
+}
+
+/// Compact summary of a closed round (104 bytes)
+#[zero_copy]
+pub struct ArchivedRound {
+    pub round_id: u64,
+    pub prize_pool: u64,
+    pub end_time: i64,
+    /// First-tier winner (default = none)
+    pub winner_token: Pubkey,
+    /// keccak of the round's VRF result (zeroes = none)
+    pub vrf_result_hash: [u8; 32],
+    pub total_participants: u32,
+    pub total_token_entries: u32,
+    /// RoundStatus discriminant: Complete or Cancelled
+    pub status: u8,
+    pub _padding: [u8; 7],
+}
+
+/// Per-wallet payout history for tax reporting
+#[account]
+#[derive(InitSpace)]
+pub struct PayoutLedger {
+    pub wallet: Pubkey,
+    #[max_len(MAX_LEDGER_ENTRIES)]
+    pub entries: Vec<YearlyPayout>,
+    pub bump: u8,
+}
+
+impl PayoutLedger {
+    /// Size at capacity; the account starts empty and grows one bucket at a time
+    pub const SIZE: usize = 8 + Self::INIT_SPACE;
+
+    pub const fn space(entries: usize) -> usize {
+        Self::SIZE - YearlyPayout::INIT_SPACE * (MAX_LEDGER_ENTRIES - entries)
+    }
+
+    /// Whether recording (year, mint) would append a bucket rather than reuse or evict one
+    pub fn needs_new_bucket(&self, year: u16, mint: &Pubkey) -> bool {
+        self.entries.len() < MAX_LEDGER_ENTRIES
+            && !self.entries.iter().any(|e| e.year == year && e.mint == *mint)
+    }
+
+    /// Add a net payout (and the fee withheld from it) to the year's bucket,
+    /// evicting the oldest bucket of an earlier year if a new one doesn't
+    /// fit. A year's own buckets are never evicted, so its totals stay whole.
+    pub fn record(&mut self, year: u16, payout: TokenAmount, fee: u64) -> Result<()> {
+        if let Some(entry) = self.entries
+            .iter_mut()
+            .find(|e| e.year == year && e.mint == payout.mint)
+        {
+            entry.amount = entry.amount.saturating_add(payout.amount);
+            entry.fees = entry.fees.saturating_add(fee);
+            entry.claims = entry.claims.saturating_add(1);
+            return Ok(());
+        }
+
+        if self.entries.len() == MAX_LEDGER_ENTRIES {
+            let oldest = self
+                .entries
+                .iter()
+                .enumerate()
+                .filter(|(_, e)| e.year < year)
+                .min_by_key(|(_, e)| e.year)
+                .map(|(i, _)| i)
+                .ok_or(RecoveryRoomError::PayoutLedgerFull)?;
+            let evicted = self.entries.remove(oldest);
+            emit!(PayoutBucketEvicted {
+                wallet: self.wallet,
+                year: evicted.year,
+                mint: evicted.mint,
+                amount: evicted.amount,
+                fees: evicted.fees,
+                claims: evicted.claims,
+            });
+        }
+        self.entries.push(YearlyPayout {
+            year,
+            mint: payout.mint,
+            amount: payout.amount,
+            fees: fee,
+            claims: 1,
+        });
+        Ok(())
+    }
+}
+
+/// Lifetime activity of one wallet, kept so front-ends need not scan history
+#[account]
+#[derive(InitSpace)]
+pub struct UserStats {
+    pub user: Pubkey,
+    pub rounds_entered: u32,
+    pub tokens_submitted: u32,
+    /// Sum of submitted losses in USD cents
+    pub total_loss_usd: u64,
+    /// Net lamports claimed across all rounds
+    pub total_winnings: u64,
+    /// Consecutive rounds entered, ending at `last_round_entered`
+    pub current_streak: u32,
+    pub last_round_entered: u64,
+    pub bump: u8,
+    /// Participation points earned to date
+    pub xp: u64,
+    /// Bit i set once the BADGE_MILESTONES[i] badge is minted
+    pub milestone_badges: u8,
+    /// Last round the wallet is barred from after claiming a prize
+    pub cooldown_until_round: u64,
+    /// Start of the wallet's current rate-limit window, and participations in it
+    pub window_start: i64,
+    pub window_entries: u16,
+    /// Referrer named by the wallet's first referred entry; later entries
+    /// may only name this one
+    pub referrer: Option<Pubkey>,
+}
+
+impl UserStats {
+    pub const SIZE: usize = 8 + Self::INIT_SPACE;
+
+    fn init_if_new(&mut self, user: Pubkey, bump: u8) {
+        if self.user == Pubkey::default() {
+            self.user = user;
+            self.bump = bump;
+        }
+    }
+
+    /// Record a participation; returns the XP it earned
+    pub fn record_entry(
+        &mut self,
+        user: Pubkey,
+        bump: u8,
+        round_id: u64,
+        tokens: &[TokenEntry],
+        xp_rates: &XpRates,
+    ) -> Result<u64> {
+        self.init_if_new(user, bump);
+
+        let points = xp_rates.points(tokens)?;
+        self.xp = self.xp.saturating_add(points);
+
+        let loss = tokens
+            .iter()
+            .try_fold(UsdCents::ZERO, |total, t| total.checked_add(t.loss_usd()))?;
+        self.total_loss_usd = UsdCents(self.total_loss_usd).checked_add(loss)?.cents();
+        self.tokens_submitted = self.tokens_submitted.saturating_add(tokens.len() as u32);
+
+        if self.rounds_entered == 0 || self.last_round_entered != round_id {
+            self.rounds_entered = self.rounds_entered.saturating_add(1);
+            self.current_streak = if self.rounds_entered > 1 && self.last_round_entered + 1 == round_id {
+                self.current_streak.saturating_add(1)
+            } else {
+                1
+            };
+            self.last_round_entered = round_id;
+        }
+        Ok(points)
+    }
+
+    /// Record a claimed prize, barring the wallet from rounds up to `cooldown_until_round`
+    pub fn record_win(&mut self, user: Pubkey, bump: u8, payout: u64, cooldown_until_round: u64) {
+        self.init_if_new(user, bump);
+        self.total_winnings = self.total_winnings.saturating_add(payout);
+        self.cooldown_until_round = self.cooldown_until_round.max(cooldown_until_round);
+    }
+
+    /// Fail if a recent prize claim still bars the wallet from `round_id`
+    pub fn require_off_cooldown(&self, round_id: u64) -> Result<()> {
+        require_detail!(
+            RecoveryRoomError::WalletOnCooldown,
+            "round_id",
+            round_id,
+            at_least self.cooldown_until_round + 1
+        );
+        Ok(())
+    }
+}
+
+/// Rounds entered that unlock a tiered milestone badge
+pub const BADGE_MILESTONES: [u32; 3] = [10, 50, 100];
+
+/// Number of recent VRF latencies kept for percentile stats
+pub const LATENCY_WINDOW: usize = 64;
+
+/// Operational metrics for the VRF pipeline
+#[account]
+#[derive(InitSpace)]
+pub struct Metrics {
+    /// Ring buffer of request -> consume latencies in seconds
+    pub vrf_latencies: [u32; LATENCY_WINDOW],
+    pub latency_head: u16,
+    pub latency_samples: u16,
+    pub total_vrf_fulfilled: u64,
+    pub max_vrf_latency: u32,
+    pub bump: u8,
+}
+
+impl Metrics {
+    pub const SIZE: usize = 8 + Self::INIT_SPACE;
+
+    pub fn record_vrf_latency(&mut self, latency: u32) {
+        self.vrf_latencies[self.latency_head as usize] = latency;
+        self.latency_head = ((self.latency_head as usize + 1) % LATENCY_WINDOW) as u16;
+        self.latency_samples = (self.latency_samples + 1).min(LATENCY_WINDOW as u16);
+        self.total_vrf_fulfilled = self.total_vrf_fulfilled.saturating_add(1);
+        self.max_vrf_latency = self.max_vrf_latency.max(latency);
+    }
+
+    /// Nearest-rank percentiles over the current window
+    pub fn vrf_latency_stats(&self) -> VrfLatencyStats {
+        let mut window = self.vrf_latencies[..self.latency_samples as usize].to_vec();
+        window.sort_unstable();
+
+        let percentile = |p: usize| -> u32 {
+            if window.is_empty() {
+                return 0;
+            }
+            let rank = (p * window.len()).div_ceil(100);
+            window[rank.max(1) - 1]
+        };
+
+        VrfLatencyStats {
+            samples: self.latency_samples,
+            p50: percentile(50),
+            p95: percentile(95),
+            window_max: window.last().copied().unwrap_or(0),
+            all_time_max: self.max_vrf_latency,
+            total_fulfilled: self.total_vrf_fulfilled,
+        }
+    }
+}
+
+/// Return data for get_vrf_latency (seconds)
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy)]
+pub struct VrfLatencyStats {
+    pub samples: u16,
+    pub p50: u32,
+    pub p95: u32,
+    pub window_max: u32,
+    pub all_time_max: u32,
+    pub total_fulfilled: u64,
+}
+
+/// Settings a room is created with; the rest start at their defaults
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy)]
+pub struct RoomConfig {
+    /// Round duration in seconds (3600 = 1 hour)
+    pub round_duration: i64,
+    /// Minimum loss % required (e.g., 80)
+    pub min_loss_percentage: u8,
+    /// Max tokens per participation (e.g., 3)
+    pub max_tokens_per_user: u8,
+    /// Fee taken from every payout (e.g., 250 = 2.5%)
+    pub protocol_fee_bps: u16,
+}
+
+/// Rooms of this deployment, the default room included. Rooms share the
+/// treasury, jackpot, keeper registry and other singleton accounts.
+#[account]
+#[derive(InitSpace)]
+pub struct RoomRegistry {
+    #[max_len(MAX_ROOMS)]
+    pub rooms: Vec<u32>,
+    /// Highest room id handed out so far
+    pub last_room_id: u32,
+    pub bump: u8,
+}
+
+impl RoomRegistry {
+    pub const SIZE: usize = 8 + Self::INIT_SPACE;
+}
+
+/// Max upcoming special rounds a room's schedule holds
+pub const MAX_SCHEDULED_ROUNDS: usize = 16;
+
+/// Max length of a scheduled round's theme
+#[constant]
+pub const MAX_SCHEDULE_THEME_LEN: usize = 32;
+
+/// Highest prize multiplier a scheduled round may carry, in bps (5x)
+#[constant]
+pub const MAX_PRIZE_MULTIPLIER_BPS: u16 = 50_000;
+
+/// Special round pre-programmed by the authority
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, Debug, InitSpace)]
+pub struct ScheduledRound {
+    /// Earliest time start_round picks the event up
+    pub start_time: i64,
+    pub duration: i64,
+    /// Prize multiplier in bps, paid out of the treasury at settlement
+    pub prize_multiplier_bps: u16,
+    #[max_len(MAX_SCHEDULE_THEME_LEN)]
+    pub theme: String,
+}
+
+/// A room's calendar of upcoming special rounds, ordered by start_time
+#[account]
+#[derive(InitSpace)]
+pub struct Schedule {
+    pub room_id: u32,
+    #[max_len(MAX_SCHEDULED_ROUNDS)]
+    pub rounds: Vec<ScheduledRound>,
+    pub bump: u8,
+}
+
+impl Schedule {
+    pub const SIZE: usize = 8 + Self::INIT_SPACE;
+
+    /// Remove and return the earliest round due at `now`; rounds whose
+    /// window ended before anyone started them are dropped
+    pub fn take_due(&mut self, now: i64) -> Option<ScheduledRound> {
+        self.rounds.retain(|r| r.start_time.saturating_add(r.duration) > now);
+        match self.rounds.first() {
+            Some(r) if r.start_time <= now => Some(self.rounds.remove(0)),
+            _ => None,
+        }
+    }
+}
+
+/// Return data for get_protocol_info
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy)]
+pub struct ProtocolInfo {
+    pub room_id: u32,
+    pub current_round: u64,
+    pub total_rounds_completed: u64,
+    pub round_duration: i64,
+    pub min_loss_percentage: u8,
+    pub max_tokens_per_user: u8,
+    pub protocol_fee_bps: u16,
+    pub entry_fee: EntryFee,
+    /// Season a round started now would count toward (0 = none)
+    pub current_season: u32,
+    /// Pass required to participate (None = open room)
+    pub access_pass: Option<AccessPass>,
+}
+
+/// Return data for get_round_info
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct RoundInfo {
+    pub room_id: u32,
+    pub round_id: u64,
+    pub status: RoundStatus,
+    pub start_time: i64,
+    pub end_time: i64,
+    /// Seconds until entries close (0 once the round is no longer active)
+    pub time_remaining: i64,
+    pub total_participants: u32,
+    pub total_token_entries: u32,
+    /// Lamports in the round's prize vault, or its settled prize pool
+    pub prize_pool: u64,
+    /// Fixed stablecoin prize, paid instead of the prize vault's SOL
+    pub stable_prize: Option<StablePrize>,
+    pub winner_token: Option<Pubkey>,
+    /// Sponsor the round is branded for, and its branding metadata
+    pub sponsor: Option<Pubkey>,
+    pub metadata_uri: String,
+    pub ticketed: bool,
+    pub ticket_supply: u64,
+    /// Last moment prizes can be claimed (0 = none)
+    pub claim_deadline: i64,
+    /// Participants the round admits (0 = unlimited)
+    pub max_participants: u32,
+    /// Pool shards settlement expects as remaining accounts
+    pub shard_count: u32,
+    /// Pool entries registered, and how many are tallied into the pool
+    pub pool_entry_count: u32,
+    pub pool_entries_tallied: u32,
+}
+
+/// Value carried over from skipped or cancelled rounds into the next round
+#[account]
+#[derive(InitSpace)]
+pub struct Jackpot {
+    pub balance: u64,
+    pub total_rolled_over: u64,
+    pub last_rolled_round: u64,
+    pub bump: u8,
+}
+
+impl Jackpot {
+    pub const SIZE: usize = 8 + Self::INIT_SPACE;
+}
+
+/// Max mints on the blacklist or whitelist
+pub const MAX_MINT_LIST_LEN: usize = 200;
+
+/// Max keepers in the registry
+pub const MAX_KEEPERS: usize = 10;
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default, PartialEq, Eq, Debug, InitSpace)]
+pub struct Keeper {
+    pub key: Pubkey,
+    /// CrankInstruction::permission bits this keeper may run
+    pub permissions: u8,
+    /// Last time this keeper ran a crank
+    pub last_active: i64,
+}
+
+
+/// Max users' entries in one participate_batch
+#[constant]
+pub const MAX_PARTICIPATE_BATCH: usize = 16;
+
+/// Remaining accounts per participate_batch item ahead of its per-entry
+/// accounts: user, participation, user stats
+pub const BATCH_ITEM_ACCOUNTS: usize = 3;
+
+/// Max partner programs in the integrator registry
+pub const MAX_INTEGRATORS: usize = 16;
+
+/// Seed, with the user's key, of the PDA an integrator program signs
+/// participate_for with
+#[constant]
+pub const PARTICIPATE_FOR_SEED: &[u8] = b"participate_for";
+
+/// Partner programs allowed to participate_for users through their PDA
+#[account]
+#[derive(InitSpace)]
+pub struct IntegratorRegistry {
+    #[max_len(MAX_INTEGRATORS)]
+    pub programs: Vec<Pubkey>,
+    pub bump: u8,
+}
+
+impl IntegratorRegistry {
+    pub const SIZE: usize = 8 + Self::INIT_SPACE;
+}
+
+/// Signer a user lets participate_for them
+#[account]
+#[derive(InitSpace)]
+pub struct ParticipationDelegate {
+    pub user: Pubkey,
+    pub delegate: Option<Pubkey>,
+    pub bump: u8,
+}
+
+impl ParticipationDelegate {
+    pub const SIZE: usize = 8 + Self::INIT_SPACE;
+}
+
+/// Keepers allowed to crank the round lifecycle, alongside the crank authority
+#[account]
+#[derive(InitSpace)]
+pub struct KeeperRegistry {
+    #[max_len(MAX_KEEPERS)]
+    pub keepers: Vec<Keeper>,
+    pub bump: u8,
+}
+
+impl KeeperRegistry {
+    pub const SIZE: usize = 8 + Self::INIT_SPACE;
+
+    /// If `key` is a keeper permitted to run `instruction`, stamp its activity
+    pub fn record_activity(&mut self, key: &Pubkey, instruction: CrankInstruction, now: i64) -> bool {
+        let Some(keeper) = self
+            .keepers
+            .iter_mut()
+            .find(|k| k.key == *key && k.permissions & instruction.permission() != 0)
+        else {
+            return false;
+        };
+        keeper.last_active = now;
+        true
+    }
+}
+
+/// Admin-managed set of mints; one account each for the blacklist and whitelist
+#[account]
+#[derive(InitSpace)]
+pub struct MintList {
+    #[max_len(MAX_MINT_LIST_LEN)]
+    pub mints: Vec<Pubkey>,
+    pub bump: u8,
+}
+
+impl MintList {
+    pub const SIZE: usize = 8 + Self::INIT_SPACE;
+
+    pub fn contains(&self, mint: &Pubkey) -> bool {
+        self.mints.contains(mint)
+    }
+}
+
+/// Max length of the receipt metadata URI
+pub const MAX_RECEIPT_URI_LEN: usize = 200;
+
+/// Symbol on participation receipt cNFTs
+pub const RECEIPT_SYMBOL: &str = "RROOM";
+
+/// Bubblegum tree receiving participation receipt cNFTs
+#[account]
+#[derive(InitSpace)]
+pub struct ReceiptConfig {
+    pub merkle_tree: Pubkey,
+    /// Metadata URI shared by every receipt
+    #[max_len(MAX_RECEIPT_URI_LEN)]
+    pub uri: String,
+    pub bump: u8,
+}
+
+impl ReceiptConfig {
+    pub const SIZE: usize = 8 + Self::INIT_SPACE;
+}
+
+/// Fee-funded pool paying fixed consolation rewards to non-winners;
+/// `balance` excludes the account's own rent
+#[account]
+#[derive(InitSpace)]
+pub struct ConsolationPool {
+    pub balance: u64,
+    pub total_funded: u64,
+    pub total_paid: u64,
+    pub bump: u8,
+}
+
+impl ConsolationPool {
+    pub const SIZE: usize = 8 + Self::INIT_SPACE;
+}
+
+/// Marker a compressed entry leaves at the wallet's participation address,
+/// so a wallet enters each round once whichever path it uses
+#[account]
+#[derive(InitSpace)]
+pub struct CompressedEntry {
+    pub user: Pubkey,
+    pub round_id: u64,
+    pub bump: u8,
+    /// Entry fee held in the prize vault, in the round's fee mint; refunded
+    /// by claim_void_refund_compressed if the round is voided
+    pub entry_fee_paid: u64,
+}
+
+impl CompressedEntry {
+    pub const SIZE: usize = 8 + Self::INIT_SPACE;
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct Participation {
+    pub user: Pubkey,
+    pub round_id: u64,
+    #[max_len(MAX_TOKENS_PER_USER)]
+    pub tokens: Vec<TokenEntry>,
+    pub timestamp: i64,
+    pub bump: u8,
+    /// Entrant's combined token weight at entry time (scaled by WEIGHT_SCALE)
+    pub effective_weight: u64,
+    /// Pool total weight at entry time (scaled by WEIGHT_SCALE)
+    pub pool_total_weight: u64,
+    pub claimed: bool,
+    pub badge_minted: bool,
+    /// Anti-spam deposit held by this account until settled
+    pub deposit: u64,
+    /// Set by the authority for an invalid entry; its deposit is forfeited
+    pub flagged: bool,
+    /// Layout version; see PARTICIPATION_VERSION
+    pub version: u8,
+    /// Recovery tokens for the losing entries have been minted
+    pub recovery_minted: bool,
+    /// Submissions each entry counted for, from the user's stake (0 before staking existed)
+    pub weight_multiplier: u8,
+    /// First of the bonus draw tickets this participation holds, one per entry
+    pub bonus_ticket_start: u32,
+    /// Each entry's cents of its token's attested total loss, for the wallet draw
+    #[max_len(MAX_TOKENS_PER_USER)]
+    pub wallet_tickets: Vec<WalletTickets>,
+    /// Entry losses were replaced by the price verifier's attested figures
+    pub losses_attested: bool,
+    /// Entry fee held in the prize vault, in the round's fee mint; refunded
+    /// by claim_void_refund if the round is voided
+    pub entry_fee_paid: u64,
+}
+
+/// The cents `[start, start + len)` of a token's round total loss that one
+/// entry counted for; empty for an entry the weight cap left out
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default, PartialEq, Eq, Debug, InitSpace)]
+pub struct WalletTickets {
+    pub start: u64,
+    pub len: u64,
+}
+
+impl WalletTickets {
+    pub fn contains(&self, ticket: u64) -> bool {
+        ticket >= self.start && ticket - self.start < self.len
+    }
+}
+
+impl Participation {
+    pub const SIZE: usize = 8 + Self::INIT_SPACE;
+}
+
+/// Upper bound on the bonus draw's share of a prize pool (20%)
+#[constant]
+pub const MAX_BONUS_PRIZE_BPS: u16 = 2_000;
+
+/// Upper bound on the referrer's share of an entry fee (20%)
+#[constant]
+pub const MAX_REFERRAL_FEE_BPS: u16 = 2_000;
+
+/// A referrer's fee balance and lifetime stats. Holds its claimable
+/// lamports on top of rent.
+#[account]
+#[derive(InitSpace)]
+pub struct Referral {
+    pub referrer: Pubkey,
+    /// Lamports earned and not yet claimed
+    pub claimable: u64,
+    pub total_earned: u64,
+    /// Participations that named this referrer
+    pub referred_entries: u64,
+    /// SOL entry fees those participations paid
+    pub referred_fees: u64,
+    pub registered_at: i64,
+    pub bump: u8,
+}
+
+impl Referral {
+    pub const SIZE: usize = 8 + Self::INIT_SPACE;
+}
+
+/// Highest weight multiplier staking can earn an entry
+#[constant]
+pub const MAX_STAKE_MULTIPLIER: u8 = 3;
+
+/// Recovery token staking parameters and totals; owns the stake vault
+#[account]
+#[derive(InitSpace)]
+pub struct StakePool {
+    /// Staked tokens per +1 of weight multiplier
+    pub tokens_per_boost: u64,
+    /// Seconds each stake locks the staker's balance for
+    pub lockup_secs: i64,
+    pub total_staked: u64,
+    pub bump: u8,
+}
+
+impl StakePool {
+    pub const SIZE: usize = 8 + Self::INIT_SPACE;
+
+    /// Submissions each entry of `stake`'s owner counts for in a round ending
+    /// at `round_end`. Only a stake locked through the round's end boosts.
+    pub fn weight_multiplier(&self, stake: &StakeAccount, round_end: i64) -> u8 {
+        if stake.locked_until < round_end {
+            return 1;
+        }
+        let boosts = (stake.amount / self.tokens_per_boost).min((MAX_STAKE_MULTIPLIER - 1) as u64);
+        1 + boosts as u8
+    }
+}
+
+/// One wallet's staked recovery tokens
+#[account]
+#[derive(InitSpace)]
+pub struct StakeAccount {
+    pub owner: Pubkey,
+    pub amount: u64,
+    pub locked_until: i64,
+    pub bump: u8,
+    /// RewardsVault::reward_per_token when rewards were last settled
+    pub reward_debt: u128,
+    /// Settled lamports not yet claimed
+    pub rewards_owed: u64,
+}
+
+impl StakeAccount {
+    pub const SIZE: usize = 8 + Self::INIT_SPACE;
+
+    /// Credit what the current stake earned since the last settlement. Must
+    /// run before `amount` changes.
+    pub fn settle_rewards(&mut self, reward_per_token: u128) -> Result<()> {
+        let earned = (self.amount as u128)
+            .checked_mul(reward_per_token - self.reward_debt)
+            .ok_or(RecoveryRoomError::ArithmeticOverflow)?
+            / REWARD_PRECISION;
+        self.rewards_owed = self
+            .rewards_owed
+            .checked_add(earned as u64)
+            .ok_or(RecoveryRoomError::ArithmeticOverflow)?;
+        self.reward_debt = reward_per_token;
+        Ok(())
+    }
+}
+
+/// Scale of RewardsVault::reward_per_token
+pub const REWARD_PRECISION: u128 = 1_000_000_000_000;
+
+/// Lamports of protocol fees owed to stakers. Each epoch's fees stream out
+/// evenly over the next epoch, pro rata by stake for the time it's staked,
+/// so staking just before a rollover earns no more than its share.
+#[account]
+#[derive(InitSpace)]
+pub struct RewardsVault {
+    pub epoch: u64,
+    pub epoch_secs: i64,
+    pub epoch_start: i64,
+    /// Fees received during the current epoch
+    pub pending: u64,
+    /// Lamports earned per staked token base unit, scaled by REWARD_PRECISION
+    pub reward_per_token: u128,
+    pub total_funded: u64,
+    pub total_claimed: u64,
+    pub bump: u8,
+    /// Previous epoch's fees, streaming out over the current one
+    pub streaming: u64,
+    /// Part of `streaming` already folded into reward_per_token
+    pub streamed: u64,
+}
+
+impl RewardsVault {
+    pub const SIZE: usize = 8 + Self::INIT_SPACE;
+
+    /// Fold what the current epoch has streamed by `now` into
+    /// reward_per_token over `total_staked`, then close the epoch once it has
+    /// run its length, queueing its fees to stream over the next. Must run
+    /// before the total stake changes. Returns the lamports queued, if it
+    /// closed; whatever streams while nothing is staked carries over.
+    pub fn accrue(&mut self, total_staked: u64, now: i64) -> Result<Option<u64>> {
+        let epoch_end = self.epoch_start.saturating_add(self.epoch_secs);
+        let elapsed = now.min(epoch_end).saturating_sub(self.epoch_start).max(0);
+        let due = mul_div(self.streaming, elapsed as u64, self.epoch_secs as u64)?;
+        let share = due.saturating_sub(self.streamed);
+        if share > 0 {
+            if total_staked == 0 {
+                self.pending = self.pending.checked_add(share).ok_or(RecoveryRoomError::ArithmeticOverflow)?;
+            } else {
+                self.reward_per_token = self
+                    .reward_per_token
+                    .checked_add(share as u128 * REWARD_PRECISION / total_staked as u128)
+                    .ok_or(RecoveryRoomError::ArithmeticOverflow)?;
+            }
+            self.streamed = due;
+        }
+        if now < epoch_end {
+            return Ok(None);
+        }
+
+        self.epoch += 1;
+        self.epoch_start = now;
+        self.streaming = self.pending;
+        self.streamed = 0;
+        self.pending = 0;
+        Ok(Some(self.streaming))
+    }
+}
+
+/// Decimals of the recovery token
+#[constant]
+pub const RECOVERY_TOKEN_DECIMALS: u8 = 6;
+
+/// Upper bound on protocol.max_tokens_per_user; sizes the Participation account
+#[constant]
+pub const MAX_TOKENS_PER_USER: u8 = 5;
+
+/// Most attested loss one participation mints recovery tokens for, in USD
+/// cents ($100k)
+#[constant]
+pub const MAX_RECOVERABLE_LOSS_USD_CENTS: u64 = 10_000_000;
+
+/// Largest loss one entry may claim, in USD cents ($10M)
+#[constant]
+pub const MAX_ENTRY_LOSS_USD_CENTS: u64 = 1_000_000_000;
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, InitSpace)]
+pub struct TokenEntry {
+    pub token_mint: Pubkey,
+    /// NUL-padded ASCII
+    pub ticker: [u8; 10],
+    pub loss_amount_usd: u64,    // In cents (e.g., 44076 = $440.76)
+    pub holdings: u64,
+    pub loss_percentage: u8,     // Drawdown from cost basis (e.g., 92 = 92%)
+}
+
+impl TokenEntry {
+    pub fn loss_usd(&self) -> UsdCents {
+        UsdCents(self.loss_amount_usd)
+    }
+
+    /// Enforce the room's minimum drawdown. With a price verifier set,
+    /// loss_percentage has already been replaced by the attested one.
+    pub fn require_min_loss(&self, min_loss_percentage: u8) -> Result<()> {
+        require_detail!(
+            RecoveryRoomError::InsufficientLoss,
+            "loss_percentage",
+            self.loss_percentage,
+            at_least min_loss_percentage
+        );
+        Ok(())
+    }
+
+    /// Reject entries whose fields cannot describe a real position
+    pub fn validate(&self) -> Result<()> {
+        require_keys_neq!(self.token_mint, Pubkey::default(), RecoveryRoomError::InvalidTokenMint);
+        self.ticker_str()?;
+        require!(self.holdings > 0, RecoveryRoomError::ZeroHoldings);
+        require_detail!(
+            RecoveryRoomError::LossOutOfRange,
+            "loss_amount_usd",
+            self.loss_amount_usd,
+            at_least 1
+        );
+        require_detail!(
+            RecoveryRoomError::LossOutOfRange,
+            "loss_amount_usd",
+            self.loss_amount_usd,
+            at_most MAX_ENTRY_LOSS_USD_CENTS
+        );
+        require_detail!(
+            RecoveryRoomError::InvalidLossPercentage,
+            "loss_percentage",
+            self.loss_percentage,
+            at_most 100
+        );
+        Ok(())
+    }
+
+    /// The ticker without padding; must be printable ASCII followed only by NULs
+    pub fn ticker_str(&self) -> Result<&str> {
+        let len = self.ticker.iter().position(|b| *b == 0).unwrap_or(self.ticker.len());
+        let (ticker, padding) = self.ticker.split_at(len);
+        require!(
+            len > 0 && ticker.iter().all(u8::is_ascii_graphic) && padding.iter().all(|b| *b == 0),
+            RecoveryRoomError::InvalidTicker
+        );
+        // Checked ASCII above, so this cannot fail
+        std::str::from_utf8(ticker).map_err(|_| error!(RecoveryRoomError::InvalidTicker))
+    }
+}
+
+/// Most entries a round's token pool can grow to
+pub const MAX_POOL_TOKENS: usize = 128;
+
+/// Entries a token pool is created with
+pub const INITIAL_POOL_TOKENS: usize = 16;
+
+/// Entries expand_token_pool adds at a time
+pub const POOL_GROWTH_TOKENS: usize = 16;
+
+/// Per-round weight table at ["token_pool", round_id], created by start_round.
+/// Zero-copy so settlement never deserializes the whole pool. This is the
+/// header; the account's allocated entries follow it and expand_token_pool
+/// grows their number. Only the first `len` entries are live.
+#[account(zero_copy)]
+pub struct TokenPool {
+    pub round_id: u64,
+    pub len: u32,
+    /// 1 once randomness is requested; the weight table is then read-only
+    pub locked: u8,
+    pub _padding: [u8; 3],
+}
+
+impl TokenPool {
+    const HEADER_LEN: usize = 8 + std::mem::size_of::<TokenPool>();
+    const ENTRY_LEN: usize = std::mem::size_of::<TokenPoolEntry>();
+
+    /// Account size for a pool with room for `capacity` entries
+    pub const fn space(capacity: usize) -> usize {
+        Self::HEADER_LEN + capacity * Self::ENTRY_LEN
+    }
+
+    /// Entries an account of `data_len` bytes has room for
+    pub fn capacity(data_len: usize) -> usize {
+        data_len.saturating_sub(Self::HEADER_LEN) / Self::ENTRY_LEN
+    }
+
+    pub fn require_unlocked(&self) -> Result<()> {
+        require!(self.locked == 0, RecoveryRoomError::TokenPoolLocked);
+        Ok(())
+    }
+}
+
+/// A token pool's header and allocated entries, borrowed from its account
+pub struct Pool<H, E> {
+    pub header: H,
+    slots: E,
+}
+
+pub type PoolRef<'a> = Pool<std::cell::Ref<'a, TokenPool>, std::cell::Ref<'a, [TokenPoolEntry]>>;
+pub type PoolRefMut<'a> = Pool<std::cell::RefMut<'a, TokenPool>, std::cell::RefMut<'a, [TokenPoolEntry]>>;
+
+impl<H, E> Pool<H, E>
+where
+    H: std::ops::Deref<Target = TokenPool>,
+    E: std::ops::Deref<Target = [TokenPoolEntry]>,
+{
+    pub fn entries(&self) -> &[TokenPoolEntry] {
+        &self.slots[..self.header.len as usize]
+    }
+
+    /// Merkle root of the live (mint, submission_count, total_loss_usd)
+    /// table in pool order, covering every input the weighting strategies
+    /// read. Leaves are keccak(mint || count_le || loss_le); an odd node is
+    /// carried up as is.
+    pub fn weight_root(&self) -> [u8; 32] {
+        let mut level: Vec<[u8; 32]> = self
+            .entries()
+            .iter()
+            .map(|e| {
+                keccak::hashv(&[
+                    e.token_mint.as_ref(),
+                    &e.submission_count.to_le_bytes(),
+                    &e.total_loss_usd.to_le_bytes(),
+                ])
+                .to_bytes()
+            })
+            .collect();
+        if level.is_empty() {
+            return [0; 32];
+        }
+        while level.len() > 1 {
+            level = level
+                .chunks(2)
+                .map(|pair| match pair {
+                    [left, right] => keccak::hashv(&[left, right]).to_bytes(),
+                    [single] => *single,
+                    _ => unreachable!("chunks(2) yields one or two nodes"),
+                })
+                .collect();
+        }
+        level[0]
+    }
+
+    pub fn require_unlocked(&self) -> Result<()> {
+        self.header.require_unlocked()
+    }
+}
+
+impl<H, E> Pool<H, E>
+where
+    H: std::ops::DerefMut<Target = TokenPool>,
+    E: std::ops::DerefMut<Target = [TokenPoolEntry]>,
+{
+    pub fn entries_mut(&mut self) -> &mut [TokenPoolEntry] {
+        let len = self.header.len as usize;
+        &mut self.slots[..len]
+    }
+
+    pub fn push(&mut self, entry: TokenPoolEntry) -> Result<()> {
+        let len = self.header.len as usize;
+        require!(len < self.slots.len(), RecoveryRoomError::TokenPoolFull);
+        self.slots[len] = entry;
+        self.header.len += 1;
+        Ok(())
+    }
+}
+
+/// Loading a token pool together with the entries after its header
+pub trait LoadTokenPool {
+    fn load_pool(&self) -> Result<PoolRef<'_>>;
+    fn load_pool_mut(&self) -> Result<PoolRefMut<'_>>;
+}
+
+impl<'info> LoadTokenPool for AccountLoader<'info, TokenPool> {
+    fn load_pool(&self) -> Result<PoolRef<'_>> {
+        // Checks the discriminator
+        drop(self.load()?);
+        let info: &AccountInfo<'info> = self.as_ref();
+        let (header, slots) = std::cell::Ref::map_split(info.try_borrow_data()?, |data| {
+            let (header, slots) = data.split_at(TokenPool::HEADER_LEN);
+            let slots = &slots[..TokenPool::capacity(data.len()) * TokenPool::ENTRY_LEN];
+            (bytemuck::from_bytes(&header[8..]), bytemuck::cast_slice(slots))
+        });
+        Ok(Pool { header, slots })
+    }
+
+    fn load_pool_mut(&self) -> Result<PoolRefMut<'_>> {
+        drop(self.load_mut()?);
+        let info: &AccountInfo<'info> = self.as_ref();
+        let (header, slots) = std::cell::RefMut::map_split(info.try_borrow_mut_data()?, |data| {
+            let capacity = TokenPool::capacity(data.len());
+            let (header, slots) = data.split_at_mut(TokenPool::HEADER_LEN);
+            let slots = &mut slots[..capacity * TokenPool::ENTRY_LEN];
+            (bytemuck::from_bytes_mut(&mut header[8..]), bytemuck::cast_slice_mut(slots))
+        });
+        Ok(Pool { header, slots })
+    }
+}
+
+/// Per-mint counters of one round at ["pool_entry", round_id, mint], created
+/// by register_token. Entries are counted here rather than in the shared
+/// TokenPool, so entries for different mints don't contend for one account;
+/// tally_pool_entries copies them into the pool's weight table once the
+/// round ends.
 #[account]
-pub struct RoundState {
+#[derive(InitSpace)]
+pub struct PoolEntry {
+    pub round_id: u64,
+    pub token_mint: Pubkey,
+    pub submission_count: u32,
+    /// Sum of loss_amount_usd (cents) across all submissions of this token
+    pub total_loss_usd: u64,
+    /// Copied into the token pool by tally_pool_entries
+    pub tallied: bool,
+    pub bump: u8,
+}
+
+impl PoolEntry {
+    pub const SIZE: usize = 8 + Self::INIT_SPACE;
+
+    /// The weight-table form of this entry
+    pub fn as_pool_entry(&self) -> TokenPoolEntry {
+        TokenPoolEntry {
+            total_loss_usd: self.total_loss_usd,
+            submission_count: self.submission_count,
+            token_mint: self.token_mint,
+            stats_synced: 0,
+            _padding: [0; 3],
+        }
+    }
+}
+
+/// Entries in one token pool shard
+pub const POOL_SHARD_TOKENS: usize = 128;
+
+/// Most shards a round's pool can chain
+pub const MAX_POOL_SHARDS: u32 = 32;
+
+/// Overflow page of a full token pool at ["token_pool_shard", round_id, index],
+/// for rounds with more distinct mints than MAX_POOL_TOKENS. Shards form a
+/// chain from index 0 through `next_shard`; the round's pool is the main
+/// TokenPool followed by each shard in chain order.
+#[account(zero_copy)]
+pub struct TokenPoolShard {
+    pub round_id: u64,
+    /// Shard after this one (default = this is the last)
+    pub next_shard: Pubkey,
+    pub index: u32,
+    pub len: u32,
+    pub bump: u8,
+    pub _padding: [u8; 7],
+    pub entries: [TokenPoolEntry; POOL_SHARD_TOKENS],
+}
+
+impl TokenPoolShard {
+    pub const SIZE: usize = 8 + std::mem::size_of::<TokenPoolShard>();
+
+    pub fn entries(&self) -> &[TokenPoolEntry] {
+        &self.entries[..self.len as usize]
+    }
+
+    pub fn entries_mut(&mut self) -> &mut [TokenPoolEntry] {
+        let len = self.len as usize;
+        &mut self.entries[..len]
+    }
+
+    pub fn push(&mut self, entry: TokenPoolEntry) -> Result<()> {
+        let len = self.len as usize;
+        require!(len < POOL_SHARD_TOKENS, RecoveryRoomError::TokenPoolFull);
+        self.entries[len] = entry;
+        self.len += 1;
+        Ok(())
+    }
+}
+
+/// Packed pool entry (48 bytes). Ticker and color live once per mint in its
+/// TokenRegistry, keyed by `token_mint`.
+#[zero_copy]
+pub struct TokenPoolEntry {
+    /// Sum of loss_amount_usd (cents) across all submissions of this token
+    pub total_loss_usd: u64,
+    pub submission_count: u32,
+    pub token_mint: Pubkey,
+    /// 1 once sync_token_stats folded this entry into the TokenRegistry
+    pub stats_synced: u8,
+    pub _padding: [u8; 3],
+}
+
+/// Per-mint metadata and lifetime stats, shared by every round's pool
+#[account]
+#[derive(InitSpace)]
+pub struct TokenRegistry {
+    pub mint: Pubkey,
+    /// NUL-padded upper-case ASCII
+    pub ticker: [u8; 10],
+    /// NUL-padded "#RRGGBB"
+    pub color: [u8; 7],
+    pub total_submissions: u64,
+    /// Rounds in which this token won a prize tier
+    pub total_wins: u32,
+    pub registered_at: i64,
+    pub bump: u8,
+    /// Latest round this token won a prize tier in, as of its last sync (0 = never)
+    pub last_won_round: u64,
+}
+
+impl TokenRegistry {
+    pub const SIZE: usize = 8 + Self::INIT_SPACE;
+
+    /// Whether a win within the last `cooldown_rounds` rounds bars this token from `round_id`
+    pub fn on_cooldown(&self, round_id: u64, cooldown_rounds: u32) -> bool {
+        self.last_won_round != 0 && round_id <= self.last_won_round.saturating_add(cooldown_rounds as u64)
+    }
+}
+
+/// Tokens ranked on the all-time leaderboard
+pub const LEADERBOARD_SIZE: usize = 25;
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default, PartialEq, Eq, InitSpace)]
+pub struct LeaderboardEntry {
+    pub mint: Pubkey,
+    pub submissions: u64,
+}
+
+
+/// Top LEADERBOARD_SIZE most-submitted tokens of all time, sorted descending.
+/// Kept with the space-saving algorithm: a new token displaces the last entry
+/// and inherits its count, so counts may overestimate by at most that count
+/// but no token with more submissions than the last entry is ever missing.
+#[account]
+#[derive(InitSpace)]
+pub struct Leaderboard {
+    #[max_len(LEADERBOARD_SIZE)]
+    pub entries: Vec<LeaderboardEntry>,
+    pub updated_at: i64,
+    pub bump: u8,
+}
+
+impl Leaderboard {
+    pub const SIZE: usize = 8 + Self::INIT_SPACE;
+
+    /// Count one submission of `mint`
+    pub fn record(&mut self, mint: Pubkey) {
+        rank_submission(&mut self.entries, mint);
+    }
+}
+
+/// Count one submission of `mint` on a sorted board of at most LEADERBOARD_SIZE
+fn rank_submission(entries: &mut Vec<LeaderboardEntry>, mint: Pubkey) {
+    let mut index = match entries.iter().position(|e| e.mint == mint) {
+        Some(index) => index,
+        None if entries.len() < LEADERBOARD_SIZE => {
+            entries.push(LeaderboardEntry { mint, submissions: 0 });
+            entries.len() - 1
+        }
+        None => {
+            let last = entries.len() - 1;
+            entries[last].mint = mint;
+            last
+        }
+    };
+    entries[index].submissions = entries[index].submissions.saturating_add(1);
+
+    // Bubble up to keep the board sorted
+    while index > 0 && entries[index].submissions > entries[index - 1].submissions {
+        entries.swap(index, index - 1);
+        index -= 1;
+    }
+}
+
+/// A competitive season: aggregate stats and a leaderboard that start from zero
+#[account]
+#[derive(InitSpace)]
+pub struct Season {
+    pub season_id: u32,
+    pub start_time: i64,
+    /// Scheduled end; rounds starting later are not stamped with the season
+    pub end_time: i64,
+    /// When end_season ran (0 = still open)
+    pub ended_at: i64,
+    /// Rounds of this season that drew at least one participant
+    pub rounds: u32,
+    pub participants: u64,
+    pub token_entries: u64,
+    /// Sum of submitted losses in USD cents
+    pub total_loss_usd: u64,
+    /// Most-submitted tokens this season, ranked like the all-time leaderboard
+    #[max_len(LEADERBOARD_SIZE)]
+    pub leaderboard: Vec<LeaderboardEntry>,
+    pub bump: u8,
+}
+
+impl Season {
+    pub const SIZE: usize = 8 + Self::INIT_SPACE;
+
+    pub fn record_entry(&mut self, first_in_round: bool, tokens: &[TokenEntry]) -> Result<()> {
+        let loss = tokens
+            .iter()
+            .try_fold(UsdCents::ZERO, |total, t| total.checked_add(t.loss_usd()))?;
+        self.total_loss_usd = UsdCents(self.total_loss_usd).checked_add(loss)?.cents();
+        if first_in_round {
+            self.rounds = self.rounds.saturating_add(1);
+        }
+        self.participants = self.participants.saturating_add(1);
+        self.token_entries = self.token_entries.saturating_add(tokens.len() as u64);
+        for token in tokens {
+            rank_submission(&mut self.leaderboard, token.token_mint);
+        }
+        Ok(())
+    }
+}
+
+/// A season's leaderboard, frozen at snapshot time
+#[account]
+#[derive(InitSpace)]
+pub struct LeaderboardSnapshot {
+    pub season_id: u32,
+    #[max_len(LEADERBOARD_SIZE)]
+    pub entries: Vec<LeaderboardEntry>,
+    pub taken_at: i64,
+    pub bump: u8,
+}
+
+impl LeaderboardSnapshot {
+    pub const SIZE: usize = 8 + Self::INIT_SPACE;
+}
+
+/// Max distinct tickers the global registry tracks
+pub const MAX_TICKER_RECORDS: usize = 200;
+
+/// Fee register_token_global charges into the treasury (0.1 SOL)
+#[constant]
+pub const TOKEN_REGISTRATION_FEE_LAMPORTS: u64 = 100_000_000;
+
+/// Global ticker -> canonical mint map, flagging tickers claimed by several mints
+#[account(zero_copy)]
+pub struct TickerRegistry {
+    pub len: u32,
+    pub _padding: [u8; 4],
+    pub records: [TickerRecord; MAX_TICKER_RECORDS],
+}
+
+impl TickerRegistry {
+    pub const SIZE: usize = 8 + std::mem::size_of::<TickerRegistry>();
+
+    pub fn find(&self, ticker: &[u8; 10]) -> Option<&TickerRecord> {
+        self.records[..self.len as usize].iter().find(|r| r.ticker == *ticker)
+    }
+
+    pub fn find_mut(&mut self, ticker: &[u8; 10]) -> Option<&mut TickerRecord> {
+        self.records[..self.len as usize].iter_mut().find(|r| r.ticker == *ticker)
+    }
+
+    /// Record `mint` under `ticker`. Returns the canonical mint if `mint`
+    /// collides with it.
+    pub fn register(&mut self, ticker: [u8; 10], mint: Pubkey) -> Result<Option<Pubkey>> {
+        if let Some(record) = self.find_mut(&ticker) {
+            if record.canonical_mint == mint {
+                return Ok(None);
+            }
+            record.collision = 1;
+            record.collision_count = record.collision_count.saturating_add(1);
+            return Ok(Some(record.canonical_mint));
+        }
+
+        require!(
+            (self.len as usize) < MAX_TICKER_RECORDS,
+            RecoveryRoomError::TickerRegistryFull
+        );
+        self.records[self.len as usize] = TickerRecord {
+            canonical_mint: mint,
+            ticker,
+            collision: 0,
+            _padding: 0,
+            collision_count: 0,
+        };
+        self.len += 1;
+        Ok(None)
+    }
+}
+
+/// One ticker's canonical mint (46 bytes)
+#[zero_copy]
+pub struct TickerRecord {
+    pub canonical_mint: Pubkey,
+    pub ticker: [u8; 10],
+    /// 1 once a second mint has used this ticker
+    pub collision: u8,
+    pub _padding: u8,
+    /// Non-canonical registrations seen under this ticker
+    pub collision_count: u16,
+}
+
+/// Loss credited per synthetic submission by seed_test_round ($100)
+#[cfg(feature = "test-harness")]
+pub const SYNTHETIC_LOSS_CENTS: u64 = 10_000;
+
+/// Upper-cased ticker as stored on-chain; 1-10 ASCII alphanumerics
+fn normalize_ticker(ticker: &str) -> Result<[u8; 10]> {
+    require!(
+        !ticker.is_empty() && ticker.len() <= 10 && ticker.chars().all(|c| c.is_ascii_alphanumeric()),
+        RecoveryRoomError::InvalidTicker
+    );
+    Ok(fixed_bytes(&ticker.to_ascii_uppercase()))
+}
+
+/// Enforce the mint blacklist and, in whitelist-only mode, the whitelist
+fn check_mint_lists(
+    protocol: &ProtocolState,
+    blacklist: Option<&MintList>,
+    whitelist: Option<&MintList>,
+    entries: &[TokenEntry],
+) -> Result<()> {
+    if protocol.blacklist_enabled {
+        let blacklist = blacklist.ok_or(RecoveryRoomError::MissingMintList)?;
+        for entry in entries {
+            if blacklist.contains(&entry.token_mint) {
+                msg!("Mint {} is blacklisted", entry.token_mint);
+                return err!(RecoveryRoomError::MintBlacklisted);
+            }
+        }
+    }
+    if protocol.whitelist_only {
+        let whitelist = whitelist.ok_or(RecoveryRoomError::MissingMintList)?;
+        for entry in entries {
+            if !whitelist.contains(&entry.token_mint) {
+                msg!("Mint {} is not whitelisted", entry.token_mint);
+                return err!(RecoveryRoomError::MintNotWhitelisted);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Require the protocol's rug verifier to have attested every entered mint
+fn check_rug_attestations(
+    protocol: &ProtocolState,
+    instructions: Option<&AccountInfo>,
+    entries: &[TokenEntry],
+) -> Result<()> {
+    let Some(verifier) = protocol.rug_verifier else {
+        return Ok(());
+    };
+    let instructions = instructions.ok_or(RecoveryRoomError::MissingInstructionsSysvar)?;
+    let mints: Vec<Pubkey> = entries.iter().map(|e| e.token_mint).collect();
+    attestation::require_rug_attestations(instructions, &verifier, &mints)
+}
+
+/// Fail unless the user's `pass_account` holds the room's access pass, if it has one
+fn check_access_pass(
+    protocol: &ProtocolState,
+    pass_account: Option<&TokenAccount>,
+    pass_metadata: Option<&AccountInfo>,
+) -> Result<()> {
+    let Some(pass) = protocol.access_pass else {
+        return Ok(());
+    };
+    let account = pass_account.ok_or(RecoveryRoomError::MissingAccessPass)?;
+    match pass {
+        AccessPass::Token { mint, min_amount } => {
+            require_keys_eq!(account.mint, mint, RecoveryRoomError::MissingAccessPass);
+            require_detail!(
+                RecoveryRoomError::MissingAccessPass,
+                "pass_amount",
+                account.amount,
+                at_least min_amount
+            );
+        }
+        AccessPass::Collection { collection } => {
+            require!(account.amount > 0, RecoveryRoomError::MissingAccessPass);
+            let metadata = pass_metadata.ok_or(RecoveryRoomError::MissingAccessPass)?;
+            require!(
+                metadata::read_verified_collection(metadata, &account.mint)? == Some(collection),
+                RecoveryRoomError::MissingAccessPass
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Replace each entry's loss_percentage and loss_amount_usd with the ones the
+/// protocol's price verifier attests to; the usual min_loss_percentage check
+/// then applies
+fn attest_losses(
+    protocol: &ProtocolState,
+    instructions: Option<&AccountInfo>,
+    user: &Pubkey,
+    entries: &mut [TokenEntry],
+) -> Result<()> {
+    let Some(verifier) = protocol.price_verifier else {
+        return Ok(());
+    };
+    let instructions = instructions.ok_or(RecoveryRoomError::MissingInstructionsSysvar)?;
+    let messages = attestation::signed_messages(instructions, &verifier)?;
+    let now = Clock::get()?.unix_timestamp;
+    for entry in entries {
+        entry.loss_percentage = attestation::attested_loss_percentage(&messages, user, &entry.token_mint, now)?;
+        entry.loss_amount_usd = attestation::attested_loss_usd(&messages, user, &entry.token_mint, now)?;
+    }
+    Ok(())
+}
+
+fn require_ticker_matches(ticker: &str, symbol: &str) -> Result<()> {
+    if !ticker.trim().eq_ignore_ascii_case(symbol) {
+        msg!("Ticker {} does not match metadata symbol {}", ticker, symbol);
+        return err!(RecoveryRoomError::TickerMismatch);
+    }
+    Ok(())
+}
+
+/// Check each entry's ticker against its mint's metadata symbol (one metadata
+/// account per entry, in order) and replace it with that symbol
+fn canonicalize_tickers(entries: &mut [TokenEntry], metadata_accounts: &[AccountInfo]) -> Result<()> {
+    require!(
+        metadata_accounts.len() == entries.len(),
+        RecoveryRoomError::MissingMetadataAccounts
+    );
+    for (entry, metadata) in entries.iter_mut().zip(metadata_accounts) {
+        let symbol = metadata::read_symbol(metadata, &entry.token_mint)?;
+        require_ticker_matches(entry.ticker_str()?, &symbol)?;
+        entry.ticker = fixed_bytes(&symbol);
+    }
+    Ok(())
+}
+
+/// An entry's remaining accounts split into its metadata, holding and pool
+/// entry accounts, `entries` of each; short groups are left for the
+/// consumer's own length check
+fn split_entry_accounts<'a, 'info>(
+    accounts: &'a [AccountInfo<'info>],
+    entries: usize,
+) -> (&'a [AccountInfo<'info>], &'a [AccountInfo<'info>], &'a [AccountInfo<'info>]) {
+    let (metadata, rest) = accounts.split_at(entries.min(accounts.len()));
+    let (holdings, pool_entries) = rest.split_at(entries.min(rest.len()));
+    (metadata, holdings, pool_entries)
+}
+
+/// Check the user's token account for each entry's mint (one per entry, in
+/// order) holds at least the entry's reported holdings
+fn verify_holdings(user: &Pubkey, entries: &[TokenEntry], token_accounts: &[AccountInfo]) -> Result<()> {
+    require!(
+        token_accounts.len() == entries.len(),
+        RecoveryRoomError::MissingHoldingAccounts
+    );
+    for (entry, info) in entries.iter().zip(token_accounts) {
+        require!(
+            *info.owner == token::ID || *info.owner == token_2022::ID,
+            RecoveryRoomError::InvalidHoldingAccount
+        );
+        let account = TokenAccount::try_deserialize(&mut &info.try_borrow_data()?[..])?;
+        require_keys_eq!(account.owner, *user, RecoveryRoomError::InvalidHoldingAccount);
+        require_keys_eq!(account.mint, entry.token_mint, RecoveryRoomError::InvalidHoldingAccount);
+        require_detail!(
+            RecoveryRoomError::InsufficientHoldings,
+            "holdings",
+            entry.holdings,
+            at_most account.amount
+        );
+    }
+    Ok(())
+}
+
+/// Emit TickerCollision for each entry whose ticker belongs to another mint
+fn warn_ticker_collisions(registry: &TickerRegistry, round_id: u64, entries: &[TokenEntry]) -> Result<()> {
+    for entry in entries {
+        let Ok(ticker) = entry.ticker_str().and_then(normalize_ticker) else {
+            continue;
+        };
+        if let Some(record) = registry.find(&ticker) {
+            if record.canonical_mint != entry.token_mint {
+                emit!(TickerCollision {
+                    round_id: Some(round_id),
+                    ticker,
+                    canonical_mint: record.canonical_mint,
+                    colliding_mint: entry.token_mint,
+                });
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Copy a string into a NUL-padded fixed array, truncating if too long
+fn fixed_bytes<const N: usize>(value: &str) -> [u8; N] {
+    let mut out = [0u8; N];
+    let len = value.len().min(N);
+    out[..len].copy_from_slice(&value.as_bytes()[..len]);
+    out
+}
+
+// ============ Context Structures ============
+
+#[derive(Accounts)]
+pub struct InitializeProtocol<'info> {
+    #[account(
+        init,
+        payer = authority,
+        space = ProtocolState::SIZE,
+        seeds = [PROTOCOL_SEED],
+        bump
+    )]
+    pub protocol_state: Account<'info, ProtocolState>,
+
+    #[account(
+        init,
+        payer = authority,
+        space = Treasury::SIZE,
+        seeds = [b"treasury"],
+        bump
+    )]
+    pub treasury: Account<'info, Treasury>,
+
+    #[account(
+        init,
+        payer = authority,
+        space = Metrics::SIZE,
+        seeds = [b"metrics"],
+        bump
+    )]
+    pub metrics: Account<'info, Metrics>,
+
+    #[account(
+        init,
+        payer = authority,
+        space = Jackpot::SIZE,
+        seeds = [b"jackpot"],
+        bump
+    )]
+    pub jackpot: Account<'info, Jackpot>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct CreateRoom<'info> {
+    #[account(
+        seeds = [PROTOCOL_SEED],
+        bump = default_room.bump,
+        has_one = authority
+    )]
+    pub default_room: Account<'info, ProtocolState>,
+
+    #[account(
+        init_if_needed,
+        payer = authority,
+        space = RoomRegistry::SIZE,
+        seeds = [b"room_registry"],
+        bump
+    )]
+    pub room_registry: Account<'info, RoomRegistry>,
+
+    #[account(
+        init,
+        payer = authority,
+        space = ProtocolState::SIZE,
+        seeds = [PROTOCOL_SEED, &room_seed(room_registry.last_room_id + 1)],
+        bump
+    )]
+    pub protocol_state: Account<'info, ProtocolState>,
+
+    #[account(
+        init,
+        payer = authority,
+        space = Treasury::SIZE,
+        seeds = [b"treasury".as_ref(), &room_seed(room_registry.last_room_id + 1)],
+        bump
+    )]
+    pub treasury: Account<'info, Treasury>,
+
+    #[account(
+        init,
+        payer = authority,
+        space = Jackpot::SIZE,
+        seeds = [b"jackpot".as_ref(), &room_seed(room_registry.last_room_id + 1)],
+        bump
+    )]
+    pub jackpot: Account<'info, Jackpot>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ScheduleRound<'info> {
+    #[account(
+        mut,
+        seeds = [PROTOCOL_SEED, &room_seed(protocol_state.room_id)],
+        bump = protocol_state.bump,
+        has_one = authority,
+        constraint = !protocol_state.finalized @ RecoveryRoomError::ConfigFinalized
+    )]
+    pub protocol_state: Account<'info, ProtocolState>,
+
+    #[account(
+        init_if_needed,
+        payer = authority,
+        space = Schedule::SIZE,
+        seeds = [b"schedule".as_ref(), &room_seed(protocol_state.room_id)],
+        bump
+    )]
+    pub schedule: Account<'info, Schedule>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UnscheduleRound<'info> {
+    #[account(
+        seeds = [PROTOCOL_SEED, &room_seed(protocol_state.room_id)],
+        bump = protocol_state.bump,
+        has_one = authority,
+        constraint = !protocol_state.finalized @ RecoveryRoomError::ConfigFinalized
+    )]
+    pub protocol_state: Account<'info, ProtocolState>,
+
+    #[account(
+        mut,
+        seeds = [b"schedule".as_ref(), &room_seed(protocol_state.room_id)],
+        bump = schedule.bump
+    )]
+    pub schedule: Account<'info, Schedule>,
+
+    pub authority: Signer<'info>,
+}
+
+#[cfg_attr(feature = "event-cpi", event_cpi)]
+#[derive(Accounts)]
+pub struct StartRound<'info> {
+    #[account(
+        mut,
+        seeds = [PROTOCOL_SEED, &room_seed(protocol_state.room_id)],
+        bump = protocol_state.bump
+    )]
+    pub protocol_state: Account<'info, ProtocolState>,
+
+    #[account(
+        init,
+        payer = payer,
+        space = RoundState::SIZE,
+        seeds = [ROUND_SEED, &room_seed(protocol_state.room_id), &(protocol_state.current_round + 1).to_le_bytes()],
+        bump
+    )]
+    pub round_state: Account<'info, RoundState>,
+
+    #[account(
+        init,
+        payer = payer,
+        space = PrizeVault::SIZE,
+        seeds = [b"prize_vault".as_ref(), &room_seed(protocol_state.room_id), &(protocol_state.current_round + 1).to_le_bytes()],
+        bump
+    )]
+    pub prize_vault: Account<'info, PrizeVault>,
+
+    #[account(
+        init,
+        payer = payer,
+        space = TokenPool::space(INITIAL_POOL_TOKENS),
+        seeds = [b"token_pool".as_ref(), &room_seed(protocol_state.room_id), &(protocol_state.current_round + 1).to_le_bytes()],
+        bump
+    )]
+    pub token_pool: AccountLoader<'info, TokenPool>,
+
+    #[account(
+        mut,
+        seeds = [b"jackpot", &room_seed(protocol_state.room_id)],
+        bump = jackpot.bump
+    )]
+    pub jackpot: Account<'info, Jackpot>,
+
+    #[account(
+        mut,
+        seeds = [b"treasury", &room_seed(protocol_state.room_id)],
+        bump = treasury.bump
+    )]
+    pub treasury: Account<'info, Treasury>,
+
+    /// Previous round (optional, for validation)
+    #[account(
+        seeds = [ROUND_SEED, &room_seed(protocol_state.room_id), &protocol_state.current_round.to_le_bytes()],
+        bump = previous_round.bump
+    )]
+    pub previous_round: Option<Account<'info, RoundState>>,
+
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    /// Automation thread, when the start is threaded
+    pub thread: Option<Signer<'info>>,
+
+    /// Required when the payer cranks as a registered keeper
+    #[account(
+        mut,
+        seeds = [b"keeper_registry"],
+        bump = keeper_registry.bump
+    )]
+    pub keeper_registry: Option<Account<'info, KeeperRegistry>>,
+
+    /// Room's schedule; required once schedule_round has created it
+    #[account(
+        mut,
+        seeds = [b"schedule".as_ref(), &room_seed(protocol_state.room_id)],
+        bump = schedule.bump
+    )]
+    pub schedule: Option<Account<'info, Schedule>>,
+
+    /// The new round's ticket mint, a PDA that is its own mint authority;
+    /// required exactly when the room is in ticket mode
+    #[account(
+        init,
+        payer = payer,
+        mint::decimals = 0,
+        mint::authority = ticket_mint,
+        mint::token_program = token_program,
+        seeds = [b"ticket_mint".as_ref(), &room_seed(protocol_state.room_id), &(protocol_state.current_round + 1).to_le_bytes()],
+        bump
+    )]
+    pub ticket_mint: Option<InterfaceAccount<'info, Mint>>,
+
+    /// Token program of the ticket mint (required with it)
+    pub token_program: Option<Interface<'info, TokenInterface>>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[cfg_attr(feature = "event-cpi", event_cpi)]
+#[derive(Accounts)]
+pub struct Participate<'info> {
+    #[account(
+        seeds = [PROTOCOL_SEED, &room_seed(protocol_state.room_id)],
+        bump = protocol_state.bump
+    )]
+    pub protocol_state: Account<'info, ProtocolState>,
+
+    #[account(
+        mut,
+        seeds = [ROUND_SEED, &room_seed(protocol_state.room_id), &round_state.round_id.to_le_bytes()],
+        bump = round_state.bump
+    )]
+    pub round_state: Account<'info, RoundState>,
+
+    #[account(
+        init,
+        payer = user,
+        space = Participation::SIZE,
+        seeds = [PARTICIPATION_SEED, round_state.key().as_ref(), user.key().as_ref()],
+        bump
+    )]
+    pub participation: Account<'info, Participation>,
+
+    #[account(
+        init_if_needed,
+        payer = user,
+        space = UserStats::SIZE,
+        seeds = [b"user_stats".as_ref(), &room_seed(protocol_state.room_id), user.key().as_ref()],
+        bump
+    )]
+    pub user_stats: Account<'info, UserStats>,
+
+    /// Global ticker registry; when passed, entries reusing another mint's ticker emit TickerCollision
+    #[account(seeds = [b"ticker_registry"], bump)]
+    pub ticker_registry: Option<AccountLoader<'info, TickerRegistry>>,
+
+    /// When passed, the entry counts toward the room's leaderboard
+    #[account(
+        mut,
+        seeds = [b"leaderboard", &room_seed(protocol_state.room_id)],
+        bump = leaderboard.bump
+    )]
+    pub leaderboard: Option<Account<'info, Leaderboard>>,
+
+    /// Required when the round belongs to a season
+    #[account(
+        mut,
+        seeds = [b"season".as_ref(), &round_state.season_id.to_le_bytes()],
+        bump = season.bump
+    )]
+    pub season: Option<Account<'info, Season>>,
+
+    /// Required while the protocol has a non-empty blacklist
+    #[account(seeds = [b"mint_blacklist"], bump = mint_blacklist.bump)]
+    pub mint_blacklist: Option<Account<'info, MintList>>,
+
+    /// Required in whitelist-only mode
+    #[account(seeds = [b"mint_whitelist"], bump = mint_whitelist.bump)]
+    pub mint_whitelist: Option<Account<'info, MintList>>,
+
+    /// CHECK: instructions sysvar, required while a rug or price verifier is set
+    #[account(address = sysvar::instructions::ID)]
+    pub instructions: Option<UncheckedAccount<'info>>,
+
+    /// User's token account for the room's access pass (required while the room is gated)
+    #[account(token::authority = user)]
+    pub pass_account: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    /// CHECK: Metaplex metadata of the pass NFT, required for collection passes; verified in the handler
+    pub pass_metadata: Option<UncheckedAccount<'info>>,
+
+    /// Staking pool and the user's stake, for a weight multiplier
+    #[account(seeds = [b"stake_pool"], bump = stake_pool.bump)]
+    pub stake_pool: Option<Account<'info, StakePool>>,
+
+    #[account(seeds = [b"stake", user.key().as_ref()], bump = stake_account.bump)]
+    pub stake_account: Option<Account<'info, StakeAccount>>,
+
+    /// Referrer to credit a share of the entry fee
+    #[account(
+        mut,
+        seeds = [b"referral", referral.referrer.as_ref()],
+        bump = referral.bump
+    )]
+    pub referral: Option<Account<'info, Referral>>,
+
+    /// Round's ticket mint and the user's account for it, created if needed
+    /// (required in ticketed rounds)
+    #[account(mut)]
+    pub ticket_mint: Option<InterfaceAccount<'info, Mint>>,
+
+    #[account(
+        init_if_needed,
+        payer = user,
+        associated_token::mint = ticket_mint,
+        associated_token::authority = user,
+        associated_token::token_program = token_program
+    )]
+    pub user_ticket_account: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    pub associated_token_program: Option<Program<'info, AssociatedToken>>,
+
+    #[account(
+        mut,
+        seeds = [b"prize_vault", &room_seed(round_state.room_id), &round_state.round_id.to_le_bytes()],
+        bump = prize_vault.bump
+    )]
+    pub prize_vault: Account<'info, PrizeVault>,
+
+    /// Source of an SPL entry fee (required when the round's fee has a mint)
+    #[account(mut, token::authority = user)]
+    pub user_fee_account: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    /// Prize vault token account for the fee mint, owned by the prize vault PDA
+    #[account(mut, token::authority = prize_vault)]
+    pub vault_fee_account: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    /// Fee mint (required when the round's fee has a mint)
+    #[account(mut)]
+    pub fee_mint: Option<InterfaceAccount<'info, Mint>>,
+
+    /// Receipt cNFT accounts: pass all to mint a receipt, or none to skip it
+    #[account(seeds = [b"receipt_config"], bump = receipt_config.bump)]
+    pub receipt_config: Option<Account<'info, ReceiptConfig>>,
+
+    /// CHECK: PDA that created and delegates the receipt tree
+    #[account(seeds = [b"receipt_authority"], bump)]
+    pub receipt_authority: Option<UncheckedAccount<'info>>,
+
+    /// CHECK: Bubblegum tree config; verified by Bubblegum
+    #[account(mut)]
+    pub receipt_tree_config: Option<UncheckedAccount<'info>>,
+
+    /// CHECK: Verified against the receipt config
+    #[account(mut)]
+    pub receipt_tree: Option<UncheckedAccount<'info>>,
+
+    /// CHECK: Metaplex Bubblegum program
+    #[account(address = bubblegum::BUBBLEGUM_PROGRAM_ID)]
+    pub bubblegum_program: Option<UncheckedAccount<'info>>,
+
+    /// CHECK: SPL Account Compression program
+    #[account(address = compression::SPL_ACCOUNT_COMPRESSION_ID)]
+    pub compression_program: Option<UncheckedAccount<'info>>,
+
+    /// CHECK: SPL Noop program
+    #[account(address = compression::SPL_NOOP_ID)]
+    pub noop_program: Option<UncheckedAccount<'info>>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+#[cfg_attr(feature = "event-cpi", event_cpi)]
+#[derive(Accounts)]
+pub struct ParticipateFor<'info> {
+    #[account(
+        seeds = [PROTOCOL_SEED, &room_seed(protocol_state.room_id)],
+        bump = protocol_state.bump
+    )]
+    pub protocol_state: Account<'info, ProtocolState>,
+
+    #[account(
+        mut,
+        seeds = [ROUND_SEED, &room_seed(protocol_state.room_id), &round_state.round_id.to_le_bytes()],
+        bump = round_state.bump
+    )]
+    pub round_state: Account<'info, RoundState>,
+
+    #[account(
+        init,
+        payer = delegate,
+        space = Participation::SIZE,
+        seeds = [PARTICIPATION_SEED, round_state.key().as_ref(), user.key().as_ref()],
+        bump
+    )]
+    pub participation: Account<'info, Participation>,
+
+    #[account(
+        init_if_needed,
+        payer = delegate,
+        space = UserStats::SIZE,
+        seeds = [b"user_stats".as_ref(), &room_seed(protocol_state.room_id), user.key().as_ref()],
+        bump
+    )]
+    pub user_stats: Account<'info, UserStats>,
+
+    /// Global ticker registry; when passed, entries reusing another mint's ticker emit TickerCollision
+    #[account(seeds = [b"ticker_registry"], bump)]
+    pub ticker_registry: Option<AccountLoader<'info, TickerRegistry>>,
+
+    /// When passed, the entry counts toward the room's leaderboard
+    #[account(
+        mut,
+        seeds = [b"leaderboard", &room_seed(protocol_state.room_id)],
+        bump = leaderboard.bump
+    )]
+    pub leaderboard: Option<Account<'info, Leaderboard>>,
+
+    /// Required when the round belongs to a season
+    #[account(
+        mut,
+        seeds = [b"season".as_ref(), &round_state.season_id.to_le_bytes()],
+        bump = season.bump
+    )]
+    pub season: Option<Account<'info, Season>>,
+
+    /// Required while the protocol has a non-empty blacklist
+    #[account(seeds = [b"mint_blacklist"], bump = mint_blacklist.bump)]
+    pub mint_blacklist: Option<Account<'info, MintList>>,
+
+    /// Required in whitelist-only mode
+    #[account(seeds = [b"mint_whitelist"], bump = mint_whitelist.bump)]
+    pub mint_whitelist: Option<Account<'info, MintList>>,
+
+    /// CHECK: instructions sysvar, required while a rug or price verifier is set
+    #[account(address = sysvar::instructions::ID)]
+    pub instructions: Option<UncheckedAccount<'info>>,
+
+    /// User's token account for the room's access pass (required while the room is gated)
+    #[account(token::authority = user)]
+    pub pass_account: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    /// CHECK: Metaplex metadata of the pass NFT, required for collection passes; verified in the handler
+    pub pass_metadata: Option<UncheckedAccount<'info>>,
+
+    /// Staking pool and the user's stake, for a weight multiplier
+    #[account(seeds = [b"stake_pool"], bump = stake_pool.bump)]
+    pub stake_pool: Option<Account<'info, StakePool>>,
+
+    #[account(seeds = [b"stake", user.key().as_ref()], bump = stake_account.bump)]
+    pub stake_account: Option<Account<'info, StakeAccount>>,
+
+    /// Referrer to credit a share of the entry fee
+    #[account(
+        mut,
+        seeds = [b"referral", referral.referrer.as_ref()],
+        bump = referral.bump
+    )]
+    pub referral: Option<Account<'info, Referral>>,
+
+    /// Round's ticket mint and the user's account for it, created if needed
+    /// (required in ticketed rounds)
+    #[account(mut)]
+    pub ticket_mint: Option<InterfaceAccount<'info, Mint>>,
+
+    #[account(
+        init_if_needed,
+        payer = delegate,
+        associated_token::mint = ticket_mint,
+        associated_token::authority = user,
+        associated_token::token_program = token_program
+    )]
+    pub user_ticket_account: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    pub associated_token_program: Option<Program<'info, AssociatedToken>>,
+
+    #[account(
+        mut,
+        seeds = [b"prize_vault", &room_seed(round_state.room_id), &round_state.round_id.to_le_bytes()],
+        bump = prize_vault.bump
+    )]
+    pub prize_vault: Account<'info, PrizeVault>,
+
+    /// Delegate's source of an SPL entry fee (required when the round's fee has a mint)
+    #[account(mut, token::authority = delegate)]
+    pub delegate_fee_account: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    /// Prize vault token account for the fee mint, owned by the prize vault PDA
+    #[account(mut, token::authority = prize_vault)]
+    pub vault_fee_account: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    /// Fee mint (required when the round's fee has a mint)
+    #[account(mut)]
+    pub fee_mint: Option<InterfaceAccount<'info, Mint>>,
+
+    /// Required with integrator_program
+    #[account(seeds = [b"integrator_registry"], bump = integrator_registry.bump)]
+    pub integrator_registry: Option<Account<'info, IntegratorRegistry>>,
+
+    /// CHECK: registered integrator whose PDA is the delegate; checked in the handler
+    pub integrator_program: Option<UncheckedAccount<'info>>,
+
+    /// User's approved delegate, when not acting through an integrator program
+    #[account(seeds = [b"delegation", user.key().as_ref()], bump = delegation.bump)]
+    pub delegation: Option<Account<'info, ParticipationDelegate>>,
+
+    /// CHECK: the entrant; participates through `delegate`, checked in the handler
+    pub user: UncheckedAccount<'info>,
+
+    /// Signs and pays for the user
+    #[account(mut)]
+    pub delegate: Signer<'info>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ParticipateBatch<'info> {
+    #[account(
+        seeds = [PROTOCOL_SEED, &room_seed(protocol_state.room_id)],
+        bump = protocol_state.bump
+    )]
+    pub protocol_state: Account<'info, ProtocolState>,
+
+    #[account(
+        mut,
+        seeds = [ROUND_SEED, &room_seed(protocol_state.room_id), &round_state.round_id.to_le_bytes()],
+        bump = round_state.bump
+    )]
+    pub round_state: Account<'info, RoundState>,
+
+    /// Global ticker registry; when passed, entries reusing another mint's ticker emit TickerCollision
+    #[account(seeds = [b"ticker_registry"], bump)]
+    pub ticker_registry: Option<AccountLoader<'info, TickerRegistry>>,
+
+    /// When passed, the entry counts toward the room's leaderboard
+    #[account(
+        mut,
+        seeds = [b"leaderboard", &room_seed(protocol_state.room_id)],
+        bump = leaderboard.bump
+    )]
+    pub leaderboard: Option<Account<'info, Leaderboard>>,
+
+    /// Required when the round belongs to a season
+    #[account(
+        mut,
+        seeds = [b"season".as_ref(), &round_state.season_id.to_le_bytes()],
+        bump = season.bump
+    )]
+    pub season: Option<Account<'info, Season>>,
+
+    /// Required while the protocol has a non-empty blacklist
+    #[account(seeds = [b"mint_blacklist"], bump = mint_blacklist.bump)]
+    pub mint_blacklist: Option<Account<'info, MintList>>,
+
+    /// Required in whitelist-only mode
+    #[account(seeds = [b"mint_whitelist"], bump = mint_whitelist.bump)]
+    pub mint_whitelist: Option<Account<'info, MintList>>,
+
+    /// CHECK: instructions sysvar, carrying the users' permits
+    #[account(address = sysvar::instructions::ID)]
+    pub instructions: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"prize_vault", &room_seed(round_state.room_id), &round_state.round_id.to_le_bytes()],
+        bump = prize_vault.bump
+    )]
+    pub prize_vault: Account<'info, PrizeVault>,
+
+    /// Submits the batch and pays for it
+    #[account(mut)]
+    pub relayer: Signer<'info>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SetParticipationDelegate<'info> {
+    #[account(
+        init_if_needed,
+        payer = user,
+        space = ParticipationDelegate::SIZE,
+        seeds = [b"delegation", user.key().as_ref()],
+        bump
+    )]
+    pub delegation: Account<'info, ParticipationDelegate>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ManageIntegrators<'info> {
+    #[account(
+        seeds = [PROTOCOL_SEED, &room_seed(protocol_state.room_id)],
+        bump = protocol_state.bump,
+        has_one = authority,
+        constraint = !protocol_state.finalized @ RecoveryRoomError::ConfigFinalized
+    )]
+    pub protocol_state: Account<'info, ProtocolState>,
+
+    #[account(
+        init_if_needed,
+        payer = authority,
+        space = IntegratorRegistry::SIZE,
+        seeds = [b"integrator_registry"],
+        bump
+    )]
+    pub integrator_registry: Account<'info, IntegratorRegistry>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ParticipateCompressed<'info> {
+    #[account(
+        mut,
+        seeds = [PROTOCOL_SEED, &room_seed(protocol_state.room_id)],
+        bump = protocol_state.bump
+    )]
+    pub protocol_state: Account<'info, ProtocolState>,
+
+    #[account(
+        mut,
+        seeds = [ROUND_SEED, &room_seed(protocol_state.room_id), &round_state.round_id.to_le_bytes()],
+        bump = round_state.bump
+    )]
+    pub round_state: Account<'info, RoundState>,
+
+    /// When passed, the entry counts toward the room's leaderboard
+    #[account(
+        mut,
+        seeds = [b"leaderboard", &room_seed(protocol_state.room_id)],
+        bump = leaderboard.bump
+    )]
+    pub leaderboard: Option<Account<'info, Leaderboard>>,
+
+    /// Required when the round belongs to a season
+    #[account(
+        mut,
+        seeds = [b"season".as_ref(), &round_state.season_id.to_le_bytes()],
+        bump = season.bump
+    )]
+    pub season: Option<Account<'info, Season>>,
+
+    /// Required while the protocol has a non-empty blacklist
+    #[account(seeds = [b"mint_blacklist"], bump = mint_blacklist.bump)]
+    pub mint_blacklist: Option<Account<'info, MintList>>,
+
+    /// Required in whitelist-only mode
+    #[account(seeds = [b"mint_whitelist"], bump = mint_whitelist.bump)]
+    pub mint_whitelist: Option<Account<'info, MintList>>,
+
+    /// CHECK: instructions sysvar, required while a rug or price verifier is set
+    #[account(address = sysvar::instructions::ID)]
+    pub instructions: Option<UncheckedAccount<'info>>,
+
+    /// User's token account for the room's access pass (required while the room is gated)
+    #[account(token::authority = user)]
+    pub pass_account: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    /// CHECK: Metaplex metadata of the pass NFT, required for collection passes; verified in the handler
+    pub pass_metadata: Option<UncheckedAccount<'info>>,
+
+    /// Staking pool and the user's stake, for a weight multiplier
+    #[account(seeds = [b"stake_pool"], bump = stake_pool.bump)]
+    pub stake_pool: Option<Account<'info, StakePool>>,
+
+    #[account(seeds = [b"stake", user.key().as_ref()], bump = stake_account.bump)]
+    pub stake_account: Option<Account<'info, StakeAccount>>,
+
+    /// Referrer to credit a share of the entry fee
+    #[account(
+        mut,
+        seeds = [b"referral", referral.referrer.as_ref()],
+        bump = referral.bump
+    )]
+    pub referral: Option<Account<'info, Referral>>,
+
+    #[account(
+        init_if_needed,
+        payer = user,
+        space = UserStats::SIZE,
+        seeds = [b"user_stats".as_ref(), &room_seed(protocol_state.room_id), user.key().as_ref()],
+        bump
+    )]
+    pub user_stats: Account<'info, UserStats>,
+
+    #[account(
+        mut,
+        seeds = [b"prize_vault", &room_seed(round_state.room_id), &round_state.round_id.to_le_bytes()],
+        bump = prize_vault.bump
+    )]
+    pub prize_vault: Account<'info, PrizeVault>,
+
+    /// Source of an SPL entry fee (required when the round's fee has a mint)
+    #[account(mut, token::authority = user)]
+    pub user_fee_account: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    /// Prize vault token account for the fee mint, owned by the prize vault PDA
+    #[account(mut, token::authority = prize_vault)]
+    pub vault_fee_account: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    /// Fee mint (required when the round's fee has a mint)
+    #[account(mut)]
+    pub fee_mint: Option<InterfaceAccount<'info, Mint>>,
+
+    /// CHECK: PDA signer acting as tree authority
+    #[account(seeds = [b"tree_authority"], bump)]
+    pub tree_authority: UncheckedAccount<'info>,
+
+    /// CHECK: Verified against protocol state; owned by the compression program
+    #[account(
+        mut,
+        address = protocol_state.participation_tree @ RecoveryRoomError::WrongParticipationTree
+    )]
+    pub merkle_tree: UncheckedAccount<'info>,
+
+    /// Takes the wallet's participation address, so it enters the round once
+    #[account(
+        init,
+        payer = user,
+        space = CompressedEntry::SIZE,
+        seeds = [PARTICIPATION_SEED, round_state.key().as_ref(), user.key().as_ref()],
+        bump
+    )]
+    pub compressed_entry: Account<'info, CompressedEntry>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    /// CHECK: SPL Account Compression program
+    #[account(address = compression::SPL_ACCOUNT_COMPRESSION_ID)]
+    pub compression_program: UncheckedAccount<'info>,
+
+    /// CHECK: SPL Noop program
+    #[account(address = compression::SPL_NOOP_ID)]
+    pub noop_program: UncheckedAccount<'info>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+#[cfg_attr(feature = "event-cpi", event_cpi)]
+#[derive(Accounts)]
+pub struct RequestRandomness<'info> {
+    #[account(
+        mut,
+        seeds = [PROTOCOL_SEED, &room_seed(protocol_state.room_id)],
+        bump = protocol_state.bump
+    )]
+    pub protocol_state: Account<'info, ProtocolState>,
+
+    #[account(
+        mut,
+        seeds = [ROUND_SEED, &room_seed(protocol_state.room_id), &round_state.round_id.to_le_bytes()],
+        bump = round_state.bump
+    )]
+    pub round_state: Account<'info, RoundState>,
+
+    /// Locked here so the weight table can't change while randomness is in flight
+    #[account(
+        mut,
+        seeds = [b"token_pool", &room_seed(round_state.room_id), &round_state.round_id.to_le_bytes()],
+        bump
+    )]
+    pub token_pool: AccountLoader<'info, TokenPool>,
+
+    // Switchboard VRF accounts
+    #[account(mut, address = protocol_state.vrf @ RecoveryRoomError::WrongVrfAccount)]
+    pub vrf: AccountLoader<'info, VrfAccountData>,
+
+    #[account(mut)]
+    pub oracle_queue: AccountLoader<'info, OracleQueueAccountData>,
+
+    /// CHECK: Queue authority
+    pub queue_authority: AccountInfo<'info>,
+
+    /// CHECK: Data buffer for VRF
+    #[account(mut)]
+    pub data_buffer: AccountInfo<'info>,
+
+    pub permission: AccountLoader<'info, PermissionAccountData>,
+
+    #[account(mut)]
+    pub escrow: Account<'info, token::TokenAccount>,
+
+    #[account(mut)]
+    pub payer_wallet: Account<'info, token::TokenAccount>,
+
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    /// CHECK: Recent blockhashes sysvar
+    pub recent_blockhashes: AccountInfo<'info>,
+
+    pub switchboard_program_state: AccountLoader<'info, SbState>,
+
+    /// CHECK: Switchboard program
+    pub switchboard_program: AccountInfo<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"treasury", &room_seed(protocol_state.room_id)],
+        bump = treasury.bump
+    )]
+    pub treasury: Account<'info, Treasury>,
+
+    /// Automation thread, when the request is threaded (also the payer)
+    pub thread: Option<Signer<'info>>,
+
+    /// Required when the payer cranks as a registered keeper
+    #[account(
+        mut,
+        seeds = [b"keeper_registry"],
+        bump = keeper_registry.bump
+    )]
+    pub keeper_registry: Option<Account<'info, KeeperRegistry>>,
+
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct CommitRandomness<'info> {
+    #[account(
+        seeds = [PROTOCOL_SEED, &room_seed(protocol_state.room_id)],
+        bump = protocol_state.bump
+    )]
+    pub protocol_state: Account<'info, ProtocolState>,
+
+    #[account(
+        mut,
+        seeds = [ROUND_SEED, &room_seed(protocol_state.room_id), &round_state.round_id.to_le_bytes()],
+        bump = round_state.bump
+    )]
+    pub round_state: Account<'info, RoundState>,
+
+    pub crank: Signer<'info>,
+
+    /// Required when the crank is a registered keeper
+    #[account(
+        mut,
+        seeds = [b"keeper_registry"],
+        bump = keeper_registry.bump
+    )]
+    pub keeper_registry: Option<Account<'info, KeeperRegistry>>,
+}
+
+#[derive(Accounts)]
+pub struct SealRandomness<'info> {
+    #[account(
+        seeds = [PROTOCOL_SEED, &room_seed(protocol_state.room_id)],
+        bump = protocol_state.bump
+    )]
+    pub protocol_state: Account<'info, ProtocolState>,
+
+    #[account(
+        mut,
+        seeds = [ROUND_SEED, &room_seed(protocol_state.room_id), &round_state.round_id.to_le_bytes()],
+        bump = round_state.bump
+    )]
+    pub round_state: Account<'info, RoundState>,
+}
+
+#[derive(Accounts)]
+pub struct RevealRandomness<'info> {
+    #[account(
+        seeds = [PROTOCOL_SEED, &room_seed(protocol_state.room_id)],
+        bump = protocol_state.bump
+    )]
+    pub protocol_state: Account<'info, ProtocolState>,
+
+    #[account(
+        mut,
+        seeds = [ROUND_SEED, &room_seed(protocol_state.room_id), &round_state.round_id.to_le_bytes()],
+        bump = round_state.bump
+    )]
+    pub round_state: Account<'info, RoundState>,
+
+    #[account(
+        mut,
+        seeds = [b"token_pool", &room_seed(round_state.room_id), &round_state.round_id.to_le_bytes()],
+        bump
+    )]
+    pub token_pool: AccountLoader<'info, TokenPool>,
+
+    /// CHECK: SlotHashes sysvar, read raw as it is too large to deserialize
+    #[account(address = sysvar::slot_hashes::ID)]
+    pub slot_hashes: UncheckedAccount<'info>,
+
+    pub crank: Signer<'info>,
+
+    /// Required when the crank is a registered keeper
+    #[account(
+        mut,
+        seeds = [b"keeper_registry"],
+        bump = keeper_registry.bump
+    )]
+    pub keeper_registry: Option<Account<'info, KeeperRegistry>>,
+}
+
+#[derive(Accounts)]
+pub struct SettleAndStart<'info> {
+    pub finalize: FinalizeRound<'info>,
+    pub start: StartRound<'info>,
+}
+
+#[cfg_attr(feature = "event-cpi", event_cpi)]
+#[derive(Accounts)]
+pub struct ConsumeRandomness<'info> {
+    #[account(
+        mut,
+        seeds = [PROTOCOL_SEED, &room_seed(protocol_state.room_id)],
+        bump = protocol_state.bump
+    )]
+    pub protocol_state: Account<'info, ProtocolState>,
+
+    #[account(
+        mut,
+        seeds = [ROUND_SEED, &room_seed(protocol_state.room_id), &round_state.round_id.to_le_bytes()],
+        bump = round_state.bump
+    )]
+    pub round_state: Account<'info, RoundState>,
+
+    #[account(address = protocol_state.vrf @ RecoveryRoomError::WrongVrfAccount)]
+    pub vrf: AccountLoader<'info, VrfAccountData>,
+
+    #[account(
+        mut,
+        seeds = [b"metrics"],
+        bump = metrics.bump
+    )]
+    pub metrics: Account<'info, Metrics>,
+
+    #[account(
+        mut,
+        seeds = [b"treasury", &room_seed(protocol_state.room_id)],
+        bump = treasury.bump
+    )]
+    pub treasury: Account<'info, Treasury>,
+
+    /// Crank caller to pay the consume bounty to (absent on oracle callbacks)
+    #[account(mut)]
+    pub keeper: Option<Signer<'info>>,
+
+    /// Required when the keeper cranks as a registered keeper
+    #[account(
+        mut,
+        seeds = [b"keeper_registry"],
+        bump = keeper_registry.bump
+    )]
+    pub keeper_registry: Option<Account<'info, KeeperRegistry>>,
+}
+
+#[derive(Accounts)]
+pub struct ResolveWithSlothash<'info> {
+    #[account(
+        mut,
+        seeds = [ROUND_SEED, &room_seed(round_state.room_id), &round_state.round_id.to_le_bytes()],
+        bump = round_state.bump
+    )]
+    pub round_state: Account<'info, RoundState>,
+
+    /// CHECK: SlotHashes sysvar, read raw as it is too large to deserialize
+    #[account(address = sysvar::slot_hashes::ID)]
+    pub slot_hashes: UncheckedAccount<'info>,
+}
+
+#[cfg_attr(feature = "event-cpi", event_cpi)]
+#[derive(Accounts)]
+pub struct FinalizeRound<'info> {
+    #[account(
+        mut,
+        seeds = [PROTOCOL_SEED, &room_seed(protocol_state.room_id)],
+        bump = protocol_state.bump
+    )]
+    pub protocol_state: Account<'info, ProtocolState>,
+
+    #[account(
+        mut,
+        seeds = [ROUND_SEED, &room_seed(protocol_state.room_id), &round_state.round_id.to_le_bytes()],
+        bump = round_state.bump
+    )]
+    pub round_state: Account<'info, RoundState>,
+
+    #[account(
+        seeds = [b"token_pool", &room_seed(round_state.room_id), &round_state.round_id.to_le_bytes()],
+        bump,
+        constraint = token_pool.load()?.round_id == round_state.round_id @ RecoveryRoomError::WrongTokenPool
+    )]
+    pub token_pool: AccountLoader<'info, TokenPool>,
+
+    #[account(
+        mut,
+        seeds = [b"prize_vault", &room_seed(round_state.room_id), &round_state.round_id.to_le_bytes()],
+        bump = prize_vault.bump
+    )]
+    pub prize_vault: Account<'info, PrizeVault>,
+
+    #[account(
+        mut,
+        seeds = [b"treasury", &room_seed(protocol_state.room_id)],
+        bump = treasury.bump
+    )]
+    pub treasury: Account<'info, Treasury>,
+
+    #[account(
+        mut,
+        seeds = [b"jackpot", &room_seed(protocol_state.room_id)],
+        bump = jackpot.bump
+    )]
+    pub jackpot: Account<'info, Jackpot>,
+}
+
+#[derive(Accounts)]
+pub struct MintBadge<'info> {
+    #[account(
+        seeds = [ROUND_SEED, &room_seed(round_state.room_id), &round_state.round_id.to_le_bytes()],
+        bump = round_state.bump
+    )]
+    pub round_state: Account<'info, RoundState>,
+
+    #[account(
+        mut,
+        seeds = [PARTICIPATION_SEED, round_state.key().as_ref(), user.key().as_ref()],
+        bump = participation.bump,
+        has_one = user
+    )]
+    pub participation: Account<'info, Participation>,
+
+    /// CHECK: Token-2022 badge mint for this round, created on first mint
+    #[account(
+        mut,
+        seeds = [b"round_badge", &room_seed(round_state.room_id), &round_state.round_id.to_le_bytes()],
+        bump
+    )]
+    pub badge_mint: UncheckedAccount<'info>,
+
+    /// CHECK: PDA holding mint authority over every badge mint
+    #[account(seeds = [b"badge_authority"], bump)]
+    pub badge_authority: UncheckedAccount<'info>,
+
+    /// CHECK: user's badge token account; created idempotently by the ATA program
+    #[account(mut)]
+    pub user_badge_account: UncheckedAccount<'info>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    pub token_program: Program<'info, Token2022>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(rounds: u32, room_id: u32)]
+pub struct MintMilestoneBadge<'info> {
+    #[account(
+        mut,
+        seeds = [b"user_stats", &room_seed(room_id), user.key().as_ref()],
+        bump = user_stats.bump,
+        has_one = user
+    )]
+    pub user_stats: Account<'info, UserStats>,
+
+    /// CHECK: Token-2022 badge mint for this milestone, created on first mint
+    #[account(
+        mut,
+        seeds = [b"milestone_badge".as_ref(), &room_seed(room_id), &rounds.to_le_bytes()],
+        bump
+    )]
+    pub badge_mint: UncheckedAccount<'info>,
+
+    /// CHECK: PDA holding mint authority over every badge mint
+    #[account(seeds = [b"badge_authority"], bump)]
+    pub badge_authority: UncheckedAccount<'info>,
+
+    /// CHECK: user's badge token account; created idempotently by the ATA program
+    #[account(mut)]
+    pub user_badge_account: UncheckedAccount<'info>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    pub token_program: Program<'info, Token2022>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct LiquidateEscrow<'info> {
+    #[account(
+        seeds = [PROTOCOL_SEED, &room_seed(round_state.room_id)],
+        bump = protocol_state.bump,
+        has_one = authority,
+        constraint = !protocol_state.finalized @ RecoveryRoomError::ConfigFinalized
+    )]
+    pub protocol_state: Account<'info, ProtocolState>,
+
+    #[account(
+        seeds = [ROUND_SEED, &room_seed(round_state.room_id), &round_state.round_id.to_le_bytes()],
+        bump = round_state.bump
+    )]
+    pub round_state: Account<'info, RoundState>,
+
+    #[account(
+        mut,
+        seeds = [b"prize_vault", &room_seed(round_state.room_id), &round_state.round_id.to_le_bytes()],
+        bump = prize_vault.bump
+    )]
+    pub prize_vault: Account<'info, PrizeVault>,
+
+    /// Prize vault token account being sold
+    #[account(mut, token::authority = prize_vault)]
+    pub source_token_account: InterfaceAccount<'info, TokenAccount>,
+
+    /// Receives SOL proceeds; closed into the prize vault after the swap,
+    /// its rent going on to the authority
+    #[account(
+        mut,
+        token::authority = prize_vault,
+        token::mint = token::spl_token::native_mint::ID
+    )]
+    pub wsol_account: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        seeds = [b"treasury", &room_seed(protocol_state.room_id)],
+        bump = treasury.bump
+    )]
+    pub treasury: Account<'info, Treasury>,
+
+    /// Receives stable prize mint proceeds, instead of wsol_account
+    #[account(mut, token::authority = treasury)]
+    pub treasury_stable_account: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        seeds = [b"jackpot", &room_seed(protocol_state.room_id)],
+        bump = jackpot.bump
+    )]
+    pub jackpot: Account<'info, Jackpot>,
+
+    /// CHECK: Jupiter aggregator program, the only swap program allowed
+    #[account(address = jupiter::JUPITER_PROGRAM_ID @ RecoveryRoomError::InvalidSwapProgram)]
+    pub jupiter_program: UncheckedAccount<'info>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+
+    /// Receives the wrapped-SOL account's rent when it is closed
+    #[account(mut)]
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct BurnEscrow<'info> {
+    #[account(
+        seeds = [ROUND_SEED, &room_seed(round_state.room_id), &round_state.round_id.to_le_bytes()],
+        bump = round_state.bump
+    )]
+    pub round_state: Account<'info, RoundState>,
+
+    #[account(
+        seeds = [b"prize_vault", &room_seed(round_state.room_id), &round_state.round_id.to_le_bytes()],
+        bump = prize_vault.bump
+    )]
+    pub prize_vault: Account<'info, PrizeVault>,
+
+    #[account(
+        mut,
+        constraint = round_state.winner_token == Some(winning_mint.key()) @ RecoveryRoomError::MintMismatch
+    )]
+    pub winning_mint: InterfaceAccount<'info, Mint>,
+
+    /// Prize vault's token account for the winning mint
+    #[account(mut, token::authority = prize_vault, token::mint = winning_mint)]
+    pub vault_token_account: InterfaceAccount<'info, TokenAccount>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+pub struct FinalizeVault<'info> {
+    #[account(
+        seeds = [ROUND_SEED, &room_seed(round_state.room_id), &round_state.round_id.to_le_bytes()],
+        bump = round_state.bump
+    )]
+    pub round_state: Account<'info, RoundState>,
+
+    #[account(
+        mut,
+        seeds = [b"prize_vault", &room_seed(round_state.room_id), &round_state.round_id.to_le_bytes()],
+        bump = prize_vault.bump,
+        close = treasury
+    )]
+    pub prize_vault: Account<'info, PrizeVault>,
+
+    #[account(
+        mut,
+        seeds = [b"treasury", &room_seed(round_state.room_id)],
+        bump = treasury.bump
+    )]
+    pub treasury: Account<'info, Treasury>,
+
+    pub token_program: Program<'info, Token>,
+    pub token_2022_program: Program<'info, Token2022>,
+}
+
+#[derive(Accounts)]
+pub struct CancelRound<'info> {
+    #[account(
+        mut,
+        seeds = [ROUND_SEED, &room_seed(round_state.room_id), &round_state.round_id.to_le_bytes()],
+        bump = round_state.bump
+    )]
+    pub round_state: Account<'info, RoundState>,
+
+    #[account(
+        mut,
+        seeds = [b"prize_vault", &room_seed(round_state.room_id), &round_state.round_id.to_le_bytes()],
+        bump = prize_vault.bump
+    )]
+    pub prize_vault: Account<'info, PrizeVault>,
+
+    #[account(
+        mut,
+        seeds = [b"jackpot", &room_seed(round_state.room_id)],
+        bump = jackpot.bump
+    )]
+    pub jackpot: Account<'info, Jackpot>,
+
+    /// Lets void_round void a round in which no token qualifies
+    #[account(
+        seeds = [b"token_pool", &room_seed(round_state.room_id), &round_state.round_id.to_le_bytes()],
+        bump
+    )]
+    pub token_pool: Option<AccountLoader<'info, TokenPool>>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimPrize<'info> {
+    #[account(
+        seeds = [PROTOCOL_SEED, &room_seed(protocol_state.room_id)],
+        bump = protocol_state.bump
+    )]
+    pub protocol_state: Account<'info, ProtocolState>,
+
+    #[account(
+        mut,
+        seeds = [ROUND_SEED, &room_seed(protocol_state.room_id), &round_state.round_id.to_le_bytes()],
+        bump = round_state.bump
+    )]
+    pub round_state: Account<'info, RoundState>,
+
+    #[account(
+        mut,
+        seeds = [b"prize_vault", &room_seed(round_state.room_id), &round_state.round_id.to_le_bytes()],
+        bump = prize_vault.bump
+    )]
+    pub prize_vault: Account<'info, PrizeVault>,
+
+    #[account(
+        mut,
+        seeds = [PARTICIPATION_SEED, round_state.key().as_ref(), user.key().as_ref()],
+        bump = participation.bump,
+        has_one = user
+    )]
+    pub participation: Account<'info, Participation>,
+
+    #[account(
+        mut,
+        seeds = [b"treasury", &room_seed(protocol_state.room_id)],
+        bump = treasury.bump
+    )]
+    pub treasury: Account<'info, Treasury>,
+
+    /// Required once a consolation fee is set
+    #[account(
+        mut,
+        seeds = [b"consolation_pool", &room_seed(protocol_state.room_id)],
+        bump = consolation_pool.bump
+    )]
+    pub consolation_pool: Option<Account<'info, ConsolationPool>>,
+
+    /// Required once a staking fee is set
+    #[account(
+        mut,
+        seeds = [b"rewards_vault"],
+        bump = rewards_vault.bump
+    )]
+    pub rewards_vault: Option<Account<'info, RewardsVault>>,
+
+    #[account(
+        init_if_needed,
+        payer = user,
+        space = PayoutLedger::space(0),
+        seeds = [b"payout_ledger", user.key().as_ref()],
+        bump
+    )]
+    pub payout_ledger: Account<'info, PayoutLedger>,
+
+    #[account(
+        init_if_needed,
+        payer = user,
+        space = UserStats::SIZE,
+        seeds = [b"user_stats".as_ref(), &room_seed(protocol_state.room_id), user.key().as_ref()],
+        bump
+    )]
+    pub user_stats: Account<'info, UserStats>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    /// Treasury's vault for the round's stable prize mint (stable-prize rounds only)
+    #[account(mut, token::authority = treasury)]
+    pub treasury_stable_account: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    /// Receives a stable prize
+    #[account(mut)]
+    pub user_stable_account: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    pub stable_mint: Option<InterfaceAccount<'info, Mint>>,
+
+    pub token_program: Option<Interface<'info, TokenInterface>>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimBonusPrize<'info> {
+    #[account(
+        seeds = [PROTOCOL_SEED, &room_seed(protocol_state.room_id)],
+        bump = protocol_state.bump
+    )]
+    pub protocol_state: Account<'info, ProtocolState>,
+
+    #[account(
+        mut,
+        seeds = [ROUND_SEED, &room_seed(protocol_state.room_id), &round_state.round_id.to_le_bytes()],
+        bump = round_state.bump
+    )]
+    pub round_state: Account<'info, RoundState>,
+
+    #[account(
+        mut,
+        seeds = [b"prize_vault", &room_seed(round_state.room_id), &round_state.round_id.to_le_bytes()],
+        bump = prize_vault.bump
+    )]
+    pub prize_vault: Account<'info, PrizeVault>,
+
+    #[account(
+        seeds = [PARTICIPATION_SEED, round_state.key().as_ref(), user.key().as_ref()],
+        bump = participation.bump,
+        has_one = user
+    )]
+    pub participation: Account<'info, Participation>,
+
+    #[account(
+        mut,
+        seeds = [b"treasury", &room_seed(protocol_state.room_id)],
+        bump = treasury.bump
+    )]
+    pub treasury: Account<'info, Treasury>,
+
+    /// Required once a consolation fee is set
+    #[account(
+        mut,
+        seeds = [b"consolation_pool", &room_seed(protocol_state.room_id)],
+        bump = consolation_pool.bump
+    )]
+    pub consolation_pool: Option<Account<'info, ConsolationPool>>,
+
+    /// Required once a staking fee is set
+    #[account(
+        mut,
+        seeds = [b"rewards_vault"],
+        bump = rewards_vault.bump
+    )]
+    pub rewards_vault: Option<Account<'info, RewardsVault>>,
+
+    #[account(
+        init_if_needed,
+        payer = user,
+        space = PayoutLedger::space(0),
+        seeds = [b"payout_ledger", user.key().as_ref()],
+        bump
+    )]
+    pub payout_ledger: Account<'info, PayoutLedger>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimMany<'info> {
+    #[account(
+        seeds = [PROTOCOL_SEED, &room_seed(protocol_state.room_id)],
+        bump = protocol_state.bump
+    )]
+    pub protocol_state: Account<'info, ProtocolState>,
+
+    #[account(
+        mut,
+        seeds = [b"treasury", &room_seed(protocol_state.room_id)],
+        bump = treasury.bump
+    )]
+    pub treasury: Account<'info, Treasury>,
+
+    /// Required once a consolation fee is set
+    #[account(
+        mut,
+        seeds = [b"consolation_pool", &room_seed(protocol_state.room_id)],
+        bump = consolation_pool.bump
+    )]
+    pub consolation_pool: Option<Account<'info, ConsolationPool>>,
+
+    /// Required once a staking fee is set
+    #[account(
+        mut,
+        seeds = [b"rewards_vault"],
+        bump = rewards_vault.bump
+    )]
+    pub rewards_vault: Option<Account<'info, RewardsVault>>,
+
+    #[account(
+        init_if_needed,
+        payer = user,
+        space = PayoutLedger::space(0),
+        seeds = [b"payout_ledger", user.key().as_ref()],
+        bump
+    )]
+    pub payout_ledger: Account<'info, PayoutLedger>,
+
+    #[account(
+        init_if_needed,
+        payer = user,
+        space = UserStats::SIZE,
+        seeds = [b"user_stats".as_ref(), &room_seed(protocol_state.room_id), user.key().as_ref()],
+        bump
+    )]
+    pub user_stats: Account<'info, UserStats>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SettleDeposit<'info> {
+    #[account(
+        seeds = [ROUND_SEED, &room_seed(round_state.room_id), &round_state.round_id.to_le_bytes()],
+        bump = round_state.bump
+    )]
+    pub round_state: Account<'info, RoundState>,
+
+    #[account(
+        mut,
+        seeds = [PARTICIPATION_SEED, round_state.key().as_ref(), participation.user.as_ref()],
+        bump = participation.bump
+    )]
+    pub participation: Account<'info, Participation>,
+
+    /// CHECK: the participant; receives the refund
+    #[account(mut, address = participation.user)]
+    pub user: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"treasury", &room_seed(round_state.room_id)],
+        bump = treasury.bump
+    )]
+    pub treasury: Account<'info, Treasury>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimVoidRefund<'info> {
+    #[account(
+        mut,
+        seeds = [ROUND_SEED, &room_seed(round_state.room_id), &round_state.round_id.to_le_bytes()],
+        bump = round_state.bump
+    )]
+    pub round_state: Account<'info, RoundState>,
+
+    #[account(
+        mut,
+        seeds = [b"prize_vault", &room_seed(round_state.room_id), &round_state.round_id.to_le_bytes()],
+        bump = prize_vault.bump
+    )]
+    pub prize_vault: Account<'info, PrizeVault>,
+
+    #[account(
+        mut,
+        seeds = [PARTICIPATION_SEED, round_state.key().as_ref(), participation.user.as_ref()],
+        bump = participation.bump
+    )]
+    pub participation: Account<'info, Participation>,
+
+    /// CHECK: the participant; receives the refund
+    #[account(mut, address = participation.user)]
+    pub user: UncheckedAccount<'info>,
+
+    /// The user's token account for an SPL fee (required when the round's
+    /// fee has a mint)
+    #[account(mut, token::authority = user)]
+    pub user_fee_account: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    /// Prize vault token account holding the fee
+    #[account(mut, token::authority = prize_vault)]
+    pub vault_fee_account: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    /// Fee mint (required when the round's fee has a mint)
+    pub fee_mint: Option<InterfaceAccount<'info, Mint>>,
+
+    /// Token program of the fee mint (required with it)
+    pub token_program: Option<Interface<'info, TokenInterface>>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimVoidRefundCompressed<'info> {
+    #[account(
+        mut,
+        seeds = [ROUND_SEED, &room_seed(round_state.room_id), &round_state.round_id.to_le_bytes()],
+        bump = round_state.bump
+    )]
+    pub round_state: Account<'info, RoundState>,
+
+    #[account(
+        mut,
+        seeds = [b"prize_vault", &room_seed(round_state.room_id), &round_state.round_id.to_le_bytes()],
+        bump = prize_vault.bump
+    )]
+    pub prize_vault: Account<'info, PrizeVault>,
+
+    #[account(
+        mut,
+        seeds = [PARTICIPATION_SEED, round_state.key().as_ref(), compressed_entry.user.as_ref()],
+        bump = compressed_entry.bump
+    )]
+    pub compressed_entry: Account<'info, CompressedEntry>,
+
+    /// CHECK: the participant; receives the refund
+    #[account(mut, address = compressed_entry.user)]
+    pub user: UncheckedAccount<'info>,
+
+    /// The user's token account for an SPL fee (required when the round's
+    /// fee has a mint)
+    #[account(mut, token::authority = user)]
+    pub user_fee_account: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    /// Prize vault token account holding the fee
+    #[account(mut, token::authority = prize_vault)]
+    pub vault_fee_account: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    /// Fee mint (required when the round's fee has a mint)
+    pub fee_mint: Option<InterfaceAccount<'info, Mint>>,
+
+    /// Token program of the fee mint (required with it)
+    pub token_program: Option<Interface<'info, TokenInterface>>,
+}
+
+#[derive(Accounts)]
+pub struct FlagEntry<'info> {
+    #[account(
+        seeds = [PROTOCOL_SEED, &room_seed(protocol_state.room_id)],
+        bump = protocol_state.bump,
+        has_one = authority,
+        constraint = !protocol_state.finalized @ RecoveryRoomError::ConfigFinalized
+    )]
+    pub protocol_state: Account<'info, ProtocolState>,
+
+    #[account(
+        seeds = [ROUND_SEED, &room_seed(protocol_state.room_id), &round_state.round_id.to_le_bytes()],
+        bump = round_state.bump
+    )]
+    pub round_state: Account<'info, RoundState>,
+
+    #[account(
+        mut,
+        seeds = [PARTICIPATION_SEED, round_state.key().as_ref(), participation.user.as_ref()],
+        bump = participation.bump
+    )]
+    pub participation: Account<'info, Participation>,
+
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimConsolation<'info> {
+    #[account(
+        seeds = [PROTOCOL_SEED, &room_seed(protocol_state.room_id)],
+        bump = protocol_state.bump
+    )]
+    pub protocol_state: Account<'info, ProtocolState>,
+
+    #[account(
+        seeds = [ROUND_SEED, &room_seed(protocol_state.room_id), &round_state.round_id.to_le_bytes()],
+        bump = round_state.bump
+    )]
+    pub round_state: Account<'info, RoundState>,
+
+    #[account(
+        mut,
+        seeds = [PARTICIPATION_SEED, round_state.key().as_ref(), user.key().as_ref()],
+        bump = participation.bump,
+        has_one = user
+    )]
+    pub participation: Account<'info, Participation>,
+
+    #[account(
+        mut,
+        seeds = [b"consolation_pool", &room_seed(protocol_state.room_id)],
+        bump = consolation_pool.bump
+    )]
+    pub consolation_pool: Account<'info, ConsolationPool>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct InitAutomation<'info> {
+    #[account(
+        mut,
+        seeds = [PROTOCOL_SEED, &room_seed(protocol_state.room_id)],
+        bump = protocol_state.bump,
+        has_one = authority
+    )]
+    pub protocol_state: Account<'info, ProtocolState>,
+
+    /// CHECK: PDA that owns the protocol's automation threads
+    #[account(seeds = [b"thread_authority"], bump)]
+    pub thread_authority: UncheckedAccount<'info>,
+
+    /// CHECK: Created by the thread program; address checked in the handler
+    #[account(mut)]
+    pub start_thread: UncheckedAccount<'info>,
+
+    /// CHECK: Created by the thread program; address checked in the handler
+    #[account(mut)]
+    pub request_thread: UncheckedAccount<'info>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    /// CHECK: Clockwork thread program
+    #[account(address = automation::CLOCKWORK_THREAD_PROGRAM_ID)]
+    pub thread_program: UncheckedAccount<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct GetVrfLatency<'info> {
+    #[account(
+        seeds = [b"metrics"],
+        bump = metrics.bump
+    )]
+    pub metrics: Account<'info, Metrics>,
+}
+
+#[derive(Accounts)]
+pub struct GetProtocolInfo<'info> {
+    #[account(
+        seeds = [PROTOCOL_SEED, &room_seed(protocol_state.room_id)],
+        bump = protocol_state.bump
+    )]
+    pub protocol_state: Account<'info, ProtocolState>,
+}
+
+#[derive(Accounts)]
+pub struct GetRoundInfo<'info> {
+    #[account(
+        seeds = [ROUND_SEED, &room_seed(round_state.room_id), &round_state.round_id.to_le_bytes()],
+        bump = round_state.bump
+    )]
+    pub round_state: Account<'info, RoundState>,
+
+    /// Omit once the vault is finalized; the settled prize pool is reported
+    #[account(
+        seeds = [b"prize_vault", &room_seed(round_state.room_id), &round_state.round_id.to_le_bytes()],
+        bump = prize_vault.bump
+    )]
+    pub prize_vault: Option<Account<'info, PrizeVault>>,
+}
+
+#[derive(Accounts)]
+pub struct DeriveShuffle<'info> {
+    #[account(
+        seeds = [ROUND_SEED, &room_seed(round_state.room_id), &round_state.round_id.to_le_bytes()],
+        bump = round_state.bump
+    )]
+    pub round_state: Account<'info, RoundState>,
+
+    #[account(
+        seeds = [b"token_pool", &room_seed(round_state.room_id), &round_state.round_id.to_le_bytes()],
+        bump,
+        constraint = token_pool.load()?.round_id == round_state.round_id @ RecoveryRoomError::WrongTokenPool
+    )]
+    pub token_pool: AccountLoader<'info, TokenPool>,
+}
+
+#[derive(Accounts)]
+pub struct VerifyWinner<'info> {
+    #[account(
+        seeds = [ROUND_SEED, &room_seed(round_state.room_id), &round_state.round_id.to_le_bytes()],
+        bump = round_state.bump
+    )]
+    pub round_state: Account<'info, RoundState>,
+
+    #[account(
+        seeds = [b"token_pool", &room_seed(round_state.room_id), &round_state.round_id.to_le_bytes()],
+        bump
+    )]
+    pub token_pool: AccountLoader<'info, TokenPool>,
+}
+
+#[derive(Accounts)]
+pub struct SimulateWinner<'info> {
+    #[account(
+        seeds = [PROTOCOL_SEED, &room_seed(protocol_state.room_id)],
+        bump = protocol_state.bump
+    )]
+    pub protocol_state: Account<'info, ProtocolState>,
+
+    #[account(
+        seeds = [ROUND_SEED, &room_seed(protocol_state.room_id), &round_state.round_id.to_le_bytes()],
+        bump = round_state.bump
+    )]
+    pub round_state: Account<'info, RoundState>,
+
+    #[account(
+        seeds = [b"token_pool", &room_seed(round_state.room_id), &round_state.round_id.to_le_bytes()],
+        bump
+    )]
+    pub token_pool: AccountLoader<'info, TokenPool>,
+}
+
+#[derive(Accounts)]
+pub struct GetPayoutTotals<'info> {
+    #[account(
+        seeds = [b"payout_ledger", payout_ledger.wallet.as_ref()],
+        bump = payout_ledger.bump
+    )]
+    pub payout_ledger: Account<'info, PayoutLedger>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimPrizeCompressed<'info> {
+    #[account(
+        seeds = [PROTOCOL_SEED, &room_seed(protocol_state.room_id)],
+        bump = protocol_state.bump
+    )]
+    pub protocol_state: Account<'info, ProtocolState>,
+
+    #[account(
+        mut,
+        seeds = [ROUND_SEED, &room_seed(protocol_state.room_id), &round_state.round_id.to_le_bytes()],
+        bump = round_state.bump
+    )]
+    pub round_state: Account<'info, RoundState>,
+
+    #[account(
+        mut,
+        seeds = [b"prize_vault", &room_seed(round_state.room_id), &round_state.round_id.to_le_bytes()],
+        bump = prize_vault.bump
+    )]
+    pub prize_vault: Account<'info, PrizeVault>,
+
+    #[account(
+        mut,
+        seeds = [b"treasury", &room_seed(protocol_state.room_id)],
+        bump = treasury.bump
+    )]
+    pub treasury: Account<'info, Treasury>,
+
+    /// Required once a consolation fee is set
+    #[account(
+        mut,
+        seeds = [b"consolation_pool", &room_seed(protocol_state.room_id)],
+        bump = consolation_pool.bump
+    )]
+    pub consolation_pool: Option<Account<'info, ConsolationPool>>,
+
+    /// Required once a staking fee is set
+    #[account(
+        mut,
+        seeds = [b"rewards_vault"],
+        bump = rewards_vault.bump
+    )]
+    pub rewards_vault: Option<Account<'info, RewardsVault>>,
+
+    #[account(
+        init_if_needed,
+        payer = user,
+        space = PayoutLedger::space(0),
+        seeds = [b"payout_ledger", user.key().as_ref()],
+        bump
+    )]
+    pub payout_ledger: Account<'info, PayoutLedger>,
+
+    #[account(
+        init_if_needed,
+        payer = user,
+        space = UserStats::SIZE,
+        seeds = [b"user_stats".as_ref(), &room_seed(protocol_state.room_id), user.key().as_ref()],
+        bump
+    )]
+    pub user_stats: Account<'info, UserStats>,
+
+    /// CHECK: PDA signer acting as tree authority
+    #[account(seeds = [b"tree_authority"], bump)]
+    pub tree_authority: UncheckedAccount<'info>,
+
+    /// CHECK: Verified against protocol state; owned by the compression program
+    #[account(
+        mut,
+        address = protocol_state.participation_tree @ RecoveryRoomError::WrongParticipationTree
+    )]
+    pub merkle_tree: UncheckedAccount<'info>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    /// CHECK: SPL Account Compression program
+    #[account(address = compression::SPL_ACCOUNT_COMPRESSION_ID)]
+    pub compression_program: UncheckedAccount<'info>,
+
+    /// CHECK: SPL Noop program
+    #[account(address = compression::SPL_NOOP_ID)]
+    pub noop_program: UncheckedAccount<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct InitParticipationTree<'info> {
+    #[account(
+        mut,
+        seeds = [PROTOCOL_SEED, &room_seed(protocol_state.room_id)],
+        bump = protocol_state.bump,
+        has_one = authority
+    )]
+    pub protocol_state: Account<'info, ProtocolState>,
+
+    /// CHECK: PDA signer acting as tree authority
+    #[account(seeds = [b"tree_authority"], bump)]
+    pub tree_authority: UncheckedAccount<'info>,
+
+    /// CHECK: Pre-allocated tree account, initialized by the compression program
+    #[account(mut)]
+    pub merkle_tree: UncheckedAccount<'info>,
+
+    pub authority: Signer<'info>,
+
+    /// CHECK: SPL Account Compression program
+    #[account(address = compression::SPL_ACCOUNT_COMPRESSION_ID)]
+    pub compression_program: UncheckedAccount<'info>,
+
+    /// CHECK: SPL Noop program
+    #[account(address = compression::SPL_NOOP_ID)]
+    pub noop_program: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct InitReceiptTree<'info> {
+    #[account(
+        seeds = [PROTOCOL_SEED, &room_seed(protocol_state.room_id)],
+        bump = protocol_state.bump,
+        has_one = authority
+    )]
+    pub protocol_state: Account<'info, ProtocolState>,
+
+    #[account(
+        init,
+        payer = authority,
+        space = ReceiptConfig::SIZE,
+        seeds = [b"receipt_config"],
+        bump
+    )]
+    pub receipt_config: Account<'info, ReceiptConfig>,
+
+    /// CHECK: PDA signer acting as tree creator and delegate
+    #[account(seeds = [b"receipt_authority"], bump)]
+    pub receipt_authority: UncheckedAccount<'info>,
+
+    /// CHECK: Bubblegum tree config PDA, created by Bubblegum
+    #[account(mut)]
+    pub tree_config: UncheckedAccount<'info>,
+
+    /// CHECK: Pre-allocated tree account, initialized through Bubblegum
+    #[account(mut)]
+    pub merkle_tree: UncheckedAccount<'info>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    /// CHECK: Metaplex Bubblegum program
+    #[account(address = bubblegum::BUBBLEGUM_PROGRAM_ID)]
+    pub bubblegum_program: UncheckedAccount<'info>,
+
+    /// CHECK: SPL Account Compression program
+    #[account(address = compression::SPL_ACCOUNT_COMPRESSION_ID)]
+    pub compression_program: UncheckedAccount<'info>,
+
+    /// CHECK: SPL Noop program
+    #[account(address = compression::SPL_NOOP_ID)]
+    pub noop_program: UncheckedAccount<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateProtocol<'info> {
+    #[account(
+        mut,
+        seeds = [PROTOCOL_SEED, &room_seed(protocol_state.room_id)],
+        bump = protocol_state.bump,
+        has_one = authority,
+        constraint = !protocol_state.finalized @ RecoveryRoomError::ConfigFinalized
+    )]
+    pub protocol_state: Account<'info, ProtocolState>,
+
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct QueueConfig<'info> {
+    #[account(
+        seeds = [PROTOCOL_SEED, &room_seed(protocol_state.room_id)],
+        bump = protocol_state.bump,
+        has_one = authority,
+        constraint = !protocol_state.finalized @ RecoveryRoomError::ConfigFinalized
+    )]
+    pub protocol_state: Account<'info, ProtocolState>,
+
+    #[account(
+        init,
+        payer = payer,
+        space = PendingConfig::SIZE,
+        seeds = [b"pending_config".as_ref(), &room_seed(protocol_state.room_id)],
+        bump
+    )]
+    pub pending_config: Account<'info, PendingConfig>,
+
+    /// Required to queue a nonzero StakingFee
+    #[account(seeds = [b"rewards_vault"], bump = rewards_vault.bump)]
+    pub rewards_vault: Option<Account<'info, RewardsVault>>,
+
+    pub authority: Signer<'info>,
+
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ApplyConfig<'info> {
+    #[account(
+        mut,
+        seeds = [PROTOCOL_SEED, &room_seed(protocol_state.room_id)],
+        bump = protocol_state.bump,
+        constraint = !protocol_state.finalized @ RecoveryRoomError::ConfigFinalized
+    )]
+    pub protocol_state: Account<'info, ProtocolState>,
+
+    #[account(
+        mut,
+        seeds = [b"pending_config".as_ref(), &room_seed(protocol_state.room_id)],
+        bump = pending_config.bump,
+        has_one = rent_payer,
+        close = rent_payer
+    )]
+    pub pending_config: Account<'info, PendingConfig>,
+
+    /// CHECK: refunded the pending account's rent; checked by has_one
+    #[account(mut)]
+    pub rent_payer: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct CancelPendingConfig<'info> {
+    #[account(
+        seeds = [PROTOCOL_SEED, &room_seed(protocol_state.room_id)],
+        bump = protocol_state.bump,
+        has_one = authority
+    )]
+    pub protocol_state: Account<'info, ProtocolState>,
+
+    #[account(
+        mut,
+        seeds = [b"pending_config".as_ref(), &room_seed(protocol_state.room_id)],
+        bump = pending_config.bump,
+        has_one = rent_payer,
+        close = rent_payer
+    )]
+    pub pending_config: Account<'info, PendingConfig>,
+
+    /// CHECK: refunded the pending account's rent; checked by has_one
+    #[account(mut)]
+    pub rent_payer: UncheckedAccount<'info>,
+
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetAdminSet<'info> {
+    #[account(
+        seeds = [PROTOCOL_SEED, &room_seed(protocol_state.room_id)],
+        bump = protocol_state.bump,
+        has_one = authority,
+        constraint = !protocol_state.finalized @ RecoveryRoomError::ConfigFinalized
+    )]
+    pub protocol_state: Account<'info, ProtocolState>,
+
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = AdminSet::SIZE,
+        seeds = [b"admin_set".as_ref(), &room_seed(protocol_state.room_id)],
+        bump
+    )]
+    pub admin_set: Account<'info, AdminSet>,
+
+    pub authority: Signer<'info>,
+
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct QueueEmergencyWithdraw<'info> {
+    #[account(
+        seeds = [PROTOCOL_SEED, &room_seed(round_state.room_id)],
+        bump = protocol_state.bump,
+        constraint = !protocol_state.finalized @ RecoveryRoomError::ConfigFinalized
+    )]
+    pub protocol_state: Account<'info, ProtocolState>,
+
+    #[account(
+        seeds = [b"admin_set".as_ref(), &room_seed(round_state.room_id)],
+        bump = admin_set.bump
+    )]
+    pub admin_set: Account<'info, AdminSet>,
+
+    #[account(
+        seeds = [ROUND_SEED, &room_seed(round_state.room_id), &round_state.round_id.to_le_bytes()],
+        bump = round_state.bump
+    )]
+    pub round_state: Account<'info, RoundState>,
+
+    #[account(
+        seeds = [b"prize_vault", &room_seed(round_state.room_id), &round_state.round_id.to_le_bytes()],
+        bump = prize_vault.bump
+    )]
+    pub prize_vault: Account<'info, PrizeVault>,
+
+    #[account(
+        init,
+        payer = payer,
+        space = EmergencyWithdrawal::SIZE,
+        seeds = [b"emergency_withdrawal".as_ref(), &room_seed(round_state.room_id), &round_state.round_id.to_le_bytes()],
+        bump
+    )]
+    pub emergency_withdrawal: Account<'info, EmergencyWithdrawal>,
+
+    /// CHECK: receives the withdrawn lamports; any account
+    pub destination: UncheckedAccount<'info>,
+
+    /// Prize vault token account to withdraw from, when token_amount > 0
+    #[account(token::authority = prize_vault)]
+    pub vault_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    /// Receives the withdrawn tokens
+    pub destination_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ExecuteEmergencyWithdraw<'info> {
+    #[account(
+        seeds = [PROTOCOL_SEED, &room_seed(round_state.room_id)],
+        bump = protocol_state.bump,
+        constraint = !protocol_state.finalized @ RecoveryRoomError::ConfigFinalized
+    )]
+    pub protocol_state: Account<'info, ProtocolState>,
+
+    #[account(
+        seeds = [ROUND_SEED, &room_seed(round_state.room_id), &round_state.round_id.to_le_bytes()],
+        bump = round_state.bump
+    )]
+    pub round_state: Account<'info, RoundState>,
+
+    #[account(
+        mut,
+        seeds = [b"prize_vault", &room_seed(round_state.room_id), &round_state.round_id.to_le_bytes()],
+        bump = prize_vault.bump
+    )]
+    pub prize_vault: Account<'info, PrizeVault>,
+
+    #[account(
+        mut,
+        seeds = [b"emergency_withdrawal".as_ref(), &room_seed(round_state.room_id), &round_state.round_id.to_le_bytes()],
+        bump = emergency_withdrawal.bump,
+        has_one = destination,
+        has_one = rent_payer,
+        close = rent_payer
+    )]
+    pub emergency_withdrawal: Account<'info, EmergencyWithdrawal>,
+
+    /// CHECK: the queued destination; checked by has_one
+    #[account(mut)]
+    pub destination: UncheckedAccount<'info>,
+
+    #[account(mut)]
+    pub vault_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(mut)]
+    pub destination_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    pub token_mint: Option<InterfaceAccount<'info, Mint>>,
+
+    pub token_program: Option<Interface<'info, TokenInterface>>,
+
+    /// CHECK: refunded the pending account's rent; checked by has_one
+    #[account(mut)]
+    pub rent_payer: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct CancelEmergencyWithdraw<'info> {
+    #[account(
+        seeds = [b"admin_set".as_ref(), &room_seed(round_state.room_id)],
+        bump = admin_set.bump,
+        constraint = admin_set.admins.contains(&admin.key()) @ RecoveryRoomError::NotAnAdmin
+    )]
+    pub admin_set: Account<'info, AdminSet>,
+
+    #[account(
+        seeds = [ROUND_SEED, &room_seed(round_state.room_id), &round_state.round_id.to_le_bytes()],
+        bump = round_state.bump
+    )]
+    pub round_state: Account<'info, RoundState>,
+
+    #[account(
+        mut,
+        seeds = [b"emergency_withdrawal".as_ref(), &room_seed(round_state.room_id), &round_state.round_id.to_le_bytes()],
+        bump = emergency_withdrawal.bump,
+        has_one = rent_payer,
+        close = rent_payer
+    )]
+    pub emergency_withdrawal: Account<'info, EmergencyWithdrawal>,
+
+    /// CHECK: refunded the pending account's rent; checked by has_one
+    #[account(mut)]
+    pub rent_payer: UncheckedAccount<'info>,
+
+    pub admin: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct AcceptAuthority<'info> {
+    #[account(
+        mut,
+        seeds = [PROTOCOL_SEED, &room_seed(protocol_state.room_id)],
+        bump = protocol_state.bump,
+        constraint = protocol_state.pending_authority == Some(new_authority.key())
+            @ RecoveryRoomError::NotPendingAuthority
+    )]
+    pub protocol_state: Account<'info, ProtocolState>,
+
+    pub new_authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct InitVrf<'info> {
+    #[account(
+        mut,
+        seeds = [PROTOCOL_SEED, &room_seed(protocol_state.room_id)],
+        bump = protocol_state.bump,
+        has_one = authority,
+        constraint = !protocol_state.finalized @ RecoveryRoomError::ConfigFinalized
+    )]
+    pub protocol_state: Account<'info, ProtocolState>,
+
+    pub vrf: AccountLoader<'info, VrfAccountData>,
+
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ManageKeepers<'info> {
+    #[account(
+        mut,
+        seeds = [PROTOCOL_SEED, &room_seed(protocol_state.room_id)],
+        bump = protocol_state.bump,
+        has_one = authority,
+        constraint = !protocol_state.finalized @ RecoveryRoomError::ConfigFinalized
+    )]
+    pub protocol_state: Account<'info, ProtocolState>,
+
+    #[account(
+        init_if_needed,
+        payer = authority,
+        space = KeeperRegistry::SIZE,
+        seeds = [b"keeper_registry"],
+        bump
+    )]
+    pub keeper_registry: Account<'info, KeeperRegistry>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SetMintBlacklist<'info> {
+    #[account(
+        mut,
+        seeds = [PROTOCOL_SEED, &room_seed(protocol_state.room_id)],
+        bump = protocol_state.bump,
+        has_one = authority,
+        constraint = !protocol_state.finalized @ RecoveryRoomError::ConfigFinalized
+    )]
+    pub protocol_state: Account<'info, ProtocolState>,
+
+    #[account(
+        init_if_needed,
+        payer = authority,
+        space = MintList::SIZE,
+        seeds = [b"mint_blacklist"],
+        bump
+    )]
+    pub mint_blacklist: Account<'info, MintList>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SetMintWhitelist<'info> {
+    #[account(
+        mut,
+        seeds = [PROTOCOL_SEED, &room_seed(protocol_state.room_id)],
+        bump = protocol_state.bump,
+        has_one = authority,
+        constraint = !protocol_state.finalized @ RecoveryRoomError::ConfigFinalized
+    )]
+    pub protocol_state: Account<'info, ProtocolState>,
+
+    #[account(
+        init_if_needed,
+        payer = authority,
+        space = MintList::SIZE,
+        seeds = [b"mint_whitelist"],
+        bump
+    )]
+    pub mint_whitelist: Account<'info, MintList>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SetRoundWeighting<'info> {
+    #[account(
+        seeds = [PROTOCOL_SEED, &room_seed(protocol_state.room_id)],
+        bump = protocol_state.bump,
+        has_one = authority,
+        constraint = !protocol_state.finalized @ RecoveryRoomError::ConfigFinalized
+    )]
+    pub protocol_state: Account<'info, ProtocolState>,
+
+    #[account(
+        mut,
+        seeds = [ROUND_SEED, &room_seed(protocol_state.room_id), &round_state.round_id.to_le_bytes()],
+        bump = round_state.bump
+    )]
+    pub round_state: Account<'info, RoundState>,
+
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct WithdrawTreasury<'info> {
+    #[account(
+        seeds = [PROTOCOL_SEED, &room_seed(protocol_state.room_id)],
+        bump = protocol_state.bump,
+        has_one = authority,
+        constraint = !protocol_state.finalized @ RecoveryRoomError::ConfigFinalized
+    )]
+    pub protocol_state: Account<'info, ProtocolState>,
+
+    #[account(
+        mut,
+        seeds = [b"treasury", &room_seed(protocol_state.room_id)],
+        bump = treasury.bump
+    )]
+    pub treasury: Account<'info, Treasury>,
+
+    /// Treasury token vault (required when token_amount > 0)
+    #[account(mut, token::authority = treasury)]
+    pub treasury_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(mut)]
+    pub destination_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    /// Mint of the withdrawn tokens (required when token_amount > 0)
+    pub token_mint: Option<InterfaceAccount<'info, Mint>>,
+
+    /// CHECK: Receives withdrawn lamports; chosen by the authority
+    #[account(mut)]
+    pub destination: AccountInfo<'info>,
+
+    pub authority: Signer<'info>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+pub struct SeedTestRound<'info> {
+    #[account(
+        seeds = [PROTOCOL_SEED, &room_seed(protocol_state.room_id)],
+        bump = protocol_state.bump,
+        has_one = authority,
+        constraint = !protocol_state.finalized @ RecoveryRoomError::ConfigFinalized
+    )]
+    pub protocol_state: Account<'info, ProtocolState>,
+
+    #[account(
+        mut,
+        seeds = [ROUND_SEED, &room_seed(protocol_state.room_id), &round_state.round_id.to_le_bytes()],
+        bump = round_state.bump
+    )]
+    pub round_state: Account<'info, RoundState>,
+
+    #[account(
+        mut,
+        seeds = [b"token_pool", &room_seed(round_state.room_id), &round_state.round_id.to_le_bytes()],
+        bump,
+        constraint = token_pool.load()?.round_id == round_state.round_id @ RecoveryRoomError::WrongTokenPool
+    )]
+    pub token_pool: AccountLoader<'info, TokenPool>,
+
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct InitRecoveryMint<'info> {
+    #[account(
+        seeds = [PROTOCOL_SEED, &room_seed(protocol_state.room_id)],
+        bump = protocol_state.bump,
+        has_one = authority
+    )]
+    pub protocol_state: Account<'info, ProtocolState>,
+
+    #[account(
+        init,
+        payer = authority,
+        mint::decimals = RECOVERY_TOKEN_DECIMALS,
+        mint::authority = recovery_mint,
+        seeds = [b"recovery_mint"],
+        bump
+    )]
+    pub recovery_mint: Account<'info, token::Mint>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct MintRecoveryTokens<'info> {
+    #[account(
+        seeds = [PROTOCOL_SEED, &room_seed(protocol_state.room_id)],
+        bump = protocol_state.bump
+    )]
+    pub protocol_state: Account<'info, ProtocolState>,
+
+    #[account(
+        seeds = [ROUND_SEED, &room_seed(protocol_state.room_id), &round_state.round_id.to_le_bytes()],
+        bump = round_state.bump
+    )]
+    pub round_state: Account<'info, RoundState>,
+
+    #[account(
+        mut,
+        seeds = [PARTICIPATION_SEED, round_state.key().as_ref(), user.key().as_ref()],
+        bump = participation.bump,
+        has_one = user
+    )]
+    pub participation: Account<'info, Participation>,
+
+    #[account(
+        mut,
+        seeds = [b"recovery_mint"],
+        bump
+    )]
+    pub recovery_mint: Account<'info, token::Mint>,
+
+    #[account(
+        init_if_needed,
+        payer = user,
+        associated_token::mint = recovery_mint,
+        associated_token::authority = user
+    )]
+    pub user_token_account: Account<'info, token::TokenAccount>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct RegisterReferrer<'info> {
+    #[account(
+        init,
+        payer = referrer,
+        space = Referral::SIZE,
+        seeds = [b"referral", referrer.key().as_ref()],
+        bump
+    )]
+    pub referral: Account<'info, Referral>,
+
+    #[account(mut)]
+    pub referrer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimReferralFees<'info> {
+    #[account(
+        mut,
+        seeds = [b"referral", referrer.key().as_ref()],
+        bump = referral.bump,
+        has_one = referrer
+    )]
+    pub referral: Account<'info, Referral>,
+
+    #[account(mut)]
+    pub referrer: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct InitStakePool<'info> {
+    #[account(
+        seeds = [PROTOCOL_SEED, &room_seed(protocol_state.room_id)],
+        bump = protocol_state.bump,
+        has_one = authority
+    )]
+    pub protocol_state: Account<'info, ProtocolState>,
+
+    #[account(
+        init,
+        payer = authority,
+        space = StakePool::SIZE,
+        seeds = [b"stake_pool"],
+        bump
+    )]
+    pub stake_pool: Account<'info, StakePool>,
+
+    #[account(seeds = [b"recovery_mint"], bump)]
+    pub recovery_mint: Account<'info, token::Mint>,
+
+    #[account(
+        init,
+        payer = authority,
+        token::mint = recovery_mint,
+        token::authority = stake_pool,
+        seeds = [b"stake_vault"],
+        bump
+    )]
+    pub stake_vault: Account<'info, token::TokenAccount>,
+
+    #[account(
+        init,
+        payer = authority,
+        space = RewardsVault::SIZE,
+        seeds = [b"rewards_vault"],
+        bump
+    )]
+    pub rewards_vault: Account<'info, RewardsVault>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct Stake<'info> {
+    #[account(
+        mut,
+        seeds = [b"stake_pool"],
+        bump = stake_pool.bump
+    )]
+    pub stake_pool: Account<'info, StakePool>,
+
+    #[account(
+        init_if_needed,
+        payer = user,
+        space = StakeAccount::SIZE,
+        seeds = [b"stake", user.key().as_ref()],
+        bump
+    )]
+    pub stake_account: Account<'info, StakeAccount>,
+
+    #[account(mut, seeds = [b"stake_vault"], bump)]
+    pub stake_vault: Account<'info, token::TokenAccount>,
+
+    #[account(
+        mut,
+        seeds = [b"rewards_vault"],
+        bump = rewards_vault.bump
+    )]
+    pub rewards_vault: Account<'info, RewardsVault>,
+
+    #[account(mut, token::authority = user, token::mint = stake_vault.mint)]
+    pub user_token_account: Account<'info, token::TokenAccount>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct Unstake<'info> {
+    #[account(
+        mut,
+        seeds = [b"stake_pool"],
+        bump = stake_pool.bump
+    )]
+    pub stake_pool: Account<'info, StakePool>,
+
+    #[account(
+        mut,
+        seeds = [b"stake", user.key().as_ref()],
+        bump = stake_account.bump
+    )]
+    pub stake_account: Account<'info, StakeAccount>,
+
+    #[account(mut, seeds = [b"stake_vault"], bump)]
+    pub stake_vault: Account<'info, token::TokenAccount>,
+
+    #[account(
+        mut,
+        seeds = [b"rewards_vault"],
+        bump = rewards_vault.bump
+    )]
+    pub rewards_vault: Account<'info, RewardsVault>,
+
+    #[account(mut, token::mint = stake_vault.mint)]
+    pub user_token_account: Account<'info, token::TokenAccount>,
+
+    pub user: Signer<'info>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct SetStakingFee<'info> {
+    #[account(
+        mut,
+        seeds = [PROTOCOL_SEED, &room_seed(protocol_state.room_id)],
+        bump = protocol_state.bump,
+        has_one = authority,
+        constraint = !protocol_state.finalized @ RecoveryRoomError::ConfigFinalized
+    )]
+    pub protocol_state: Account<'info, ProtocolState>,
+
+    /// Fees can only be routed to stakers once the vault exists
+    #[account(seeds = [b"rewards_vault"], bump = rewards_vault.bump)]
+    pub rewards_vault: Account<'info, RewardsVault>,
+
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimStakingRewards<'info> {
+    #[account(seeds = [b"stake_pool"], bump = stake_pool.bump)]
+    pub stake_pool: Account<'info, StakePool>,
+
+    #[account(
+        mut,
+        seeds = [b"rewards_vault"],
+        bump = rewards_vault.bump
+    )]
+    pub rewards_vault: Account<'info, RewardsVault>,
+
+    #[account(
+        mut,
+        seeds = [b"stake", user.key().as_ref()],
+        bump = stake_account.bump
+    )]
+    pub stake_account: Account<'info, StakeAccount>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct InitLeaderboard<'info> {
+    #[account(
+        seeds = [PROTOCOL_SEED, &room_seed(protocol_state.room_id)],
+        bump = protocol_state.bump,
+        has_one = authority
+    )]
+    pub protocol_state: Account<'info, ProtocolState>,
+
+    #[account(
+        init,
+        payer = authority,
+        space = Leaderboard::SIZE,
+        seeds = [b"leaderboard".as_ref(), &room_seed(protocol_state.room_id)],
+        bump
+    )]
+    pub leaderboard: Account<'info, Leaderboard>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct InitConsolationPool<'info> {
+    #[account(
+        seeds = [PROTOCOL_SEED, &room_seed(protocol_state.room_id)],
+        bump = protocol_state.bump,
+        has_one = authority
+    )]
+    pub protocol_state: Account<'info, ProtocolState>,
+
+    #[account(
+        init,
+        payer = authority,
+        space = ConsolationPool::SIZE,
+        seeds = [b"consolation_pool".as_ref(), &room_seed(protocol_state.room_id)],
+        bump
+    )]
+    pub consolation_pool: Account<'info, ConsolationPool>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(season_id: u32)]
+pub struct SnapshotLeaderboard<'info> {
+    #[account(
+        seeds = [PROTOCOL_SEED, &room_seed(protocol_state.room_id)],
+        bump = protocol_state.bump,
+        has_one = authority
+    )]
+    pub protocol_state: Account<'info, ProtocolState>,
+
+    #[account(
+        seeds = [b"leaderboard", &room_seed(protocol_state.room_id)],
+        bump = leaderboard.bump
+    )]
+    pub leaderboard: Account<'info, Leaderboard>,
+
+    #[account(
+        init,
+        payer = authority,
+        space = LeaderboardSnapshot::SIZE,
+        seeds = [b"leaderboard_snapshot".as_ref(), &season_id.to_le_bytes()],
+        bump
+    )]
+    pub snapshot: Account<'info, LeaderboardSnapshot>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct StartSeason<'info> {
+    #[account(
+        mut,
+        seeds = [PROTOCOL_SEED, &room_seed(protocol_state.room_id)],
+        bump = protocol_state.bump,
+        has_one = authority,
+        constraint = !protocol_state.finalized @ RecoveryRoomError::ConfigFinalized
+    )]
+    pub protocol_state: Account<'info, ProtocolState>,
+
+    #[account(
+        init,
+        payer = authority,
+        space = Season::SIZE,
+        seeds = [b"season".as_ref(), &(protocol_state.current_season + 1).to_le_bytes()],
+        bump
+    )]
+    pub season: Account<'info, Season>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct EndSeason<'info> {
+    #[account(
+        mut,
+        seeds = [PROTOCOL_SEED, &room_seed(protocol_state.room_id)],
+        bump = protocol_state.bump,
+        has_one = authority,
+        constraint = !protocol_state.finalized @ RecoveryRoomError::ConfigFinalized
+    )]
+    pub protocol_state: Account<'info, ProtocolState>,
+
+    #[account(
+        mut,
+        seeds = [b"season".as_ref(), &protocol_state.current_season.to_le_bytes()],
+        bump = season.bump
+    )]
+    pub season: Account<'info, Season>,
+
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct InitTickerRegistry<'info> {
+    #[account(
+        seeds = [PROTOCOL_SEED, &room_seed(protocol_state.room_id)],
+        bump = protocol_state.bump,
+        has_one = authority
+    )]
+    pub protocol_state: Account<'info, ProtocolState>,
+
+    #[account(
+        init,
+        payer = authority,
+        space = TickerRegistry::SIZE,
+        seeds = [b"ticker_registry"],
+        bump
+    )]
+    pub ticker_registry: AccountLoader<'info, TickerRegistry>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct RegisterTokenGlobal<'info> {
+    #[account(
+        init,
+        payer = registrant,
+        space = TokenRegistry::SIZE,
+        seeds = [b"token_registry", mint.key().as_ref()],
+        bump
+    )]
+    pub token_registry: Account<'info, TokenRegistry>,
+
+    #[account(
+        mut,
+        seeds = [b"ticker_registry"],
+        bump
+    )]
+    pub ticker_registry: AccountLoader<'info, TickerRegistry>,
+
+    #[account(
+        mut,
+        seeds = [b"treasury"],
+        bump = treasury.bump
+    )]
+    pub treasury: Account<'info, Treasury>,
+
+    pub mint: InterfaceAccount<'info, Mint>,
+
+    /// CHECK: Metaplex metadata PDA of `mint`, or `mint` itself if it is a
+    /// Token-2022 mint carrying its own metadata; verified in the handler
+    pub metadata: UncheckedAccount<'info>,
+
+    #[account(mut)]
+    pub registrant: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct RegisterToken<'info> {
+    #[account(
+        mut,
+        seeds = [ROUND_SEED, &room_seed(round_state.room_id), &round_state.round_id.to_le_bytes()],
+        bump = round_state.bump
+    )]
+    pub round_state: Account<'info, RoundState>,
+
+    #[account(
+        mut,
+        seeds = [b"token_pool", &room_seed(round_state.room_id), &round_state.round_id.to_le_bytes()],
+        bump,
+        constraint = token_pool.load()?.round_id == round_state.round_id @ RecoveryRoomError::WrongTokenPool
+    )]
+    pub token_pool: AccountLoader<'info, TokenPool>,
+
+    #[account(
+        seeds = [b"token_registry", token_registry.mint.as_ref()],
+        bump = token_registry.bump
+    )]
+    pub token_registry: Account<'info, TokenRegistry>,
+
+    #[account(
+        init,
+        payer = registrant,
+        space = PoolEntry::SIZE,
+        seeds = [
+            b"pool_entry".as_ref(),
+            &room_seed(round_state.room_id),
+            &round_state.round_id.to_le_bytes(),
+            token_registry.mint.as_ref(),
+        ],
+        bump
+    )]
+    pub pool_entry: Account<'info, PoolEntry>,
+
+    #[account(mut)]
+    pub registrant: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct AddTokenPoolShard<'info> {
+    #[account(
+        mut,
+        seeds = [ROUND_SEED, &room_seed(round_state.room_id), &round_state.round_id.to_le_bytes()],
+        bump = round_state.bump
+    )]
+    pub round_state: Account<'info, RoundState>,
+
+    #[account(
+        seeds = [b"token_pool", &room_seed(round_state.room_id), &round_state.round_id.to_le_bytes()],
+        bump,
+        constraint = token_pool.load()?.round_id == round_state.round_id @ RecoveryRoomError::WrongTokenPool
+    )]
+    pub token_pool: AccountLoader<'info, TokenPool>,
+
+    #[account(
+        init,
+        payer = payer,
+        space = TokenPoolShard::SIZE,
+        seeds = [
+            b"token_pool_shard".as_ref(),
+            &room_seed(round_state.room_id),
+            &round_state.round_id.to_le_bytes(),
+            &round_state.shard_count.to_le_bytes(),
+        ],
+        bump
+    )]
+    pub shard: AccountLoader<'info, TokenPoolShard>,
+
+    /// The round's last shard, linked to the new one; required once the round has shards
+    #[account(
+        mut,
+        seeds = [
+            b"token_pool_shard".as_ref(),
+            &room_seed(round_state.room_id),
+            &round_state.round_id.to_le_bytes(),
+            &round_state.shard_count.saturating_sub(1).to_le_bytes(),
+        ],
+        bump = tail_shard.load()?.bump
+    )]
+    pub tail_shard: Option<AccountLoader<'info, TokenPoolShard>>,
+
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ExpandTokenPool<'info> {
+    #[account(
+        seeds = [ROUND_SEED, &room_seed(round_state.room_id), &round_state.round_id.to_le_bytes()],
+        bump = round_state.bump
+    )]
+    pub round_state: Account<'info, RoundState>,
+
+    #[account(
+        mut,
+        seeds = [b"token_pool", &room_seed(round_state.room_id), &round_state.round_id.to_le_bytes()],
+        bump,
+        constraint = token_pool.load()?.round_id == round_state.round_id @ RecoveryRoomError::WrongTokenPool
+    )]
+    pub token_pool: AccountLoader<'info, TokenPool>,
+
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct TallyPoolEntries<'info> {
+    #[account(
+        mut,
+        seeds = [ROUND_SEED, &room_seed(round_state.room_id), &round_state.round_id.to_le_bytes()],
+        bump = round_state.bump
+    )]
+    pub round_state: Account<'info, RoundState>,
+
+    #[account(
+        mut,
+        seeds = [b"token_pool", &room_seed(round_state.room_id), &round_state.round_id.to_le_bytes()],
+        bump,
+        constraint = token_pool.load()?.round_id == round_state.round_id @ RecoveryRoomError::WrongTokenPool
+    )]
+    pub token_pool: AccountLoader<'info, TokenPool>,
+
+    /// Pool shard holding entered tokens the main pool doesn't; see add_token_pool_shard
+    #[account(
+        mut,
+        seeds = [
+            b"token_pool_shard".as_ref(),
+            &room_seed(round_state.room_id),
+            &round_state.round_id.to_le_bytes(),
+            &token_pool_shard.load()?.index.to_le_bytes(),
+        ],
+        bump = token_pool_shard.load()?.bump
+    )]
+    pub token_pool_shard: Option<AccountLoader<'info, TokenPoolShard>>,
+}
+
+#[derive(Accounts)]
+pub struct SyncTokenStats<'info> {
+    #[account(
+        seeds = [ROUND_SEED, &room_seed(round_state.room_id), &round_state.round_id.to_le_bytes()],
+        bump = round_state.bump
+    )]
+    pub round_state: Account<'info, RoundState>,
+
+    #[account(
+        mut,
+        seeds = [b"token_pool", &room_seed(round_state.room_id), &round_state.round_id.to_le_bytes()],
+        bump,
+        constraint = token_pool.load()?.round_id == round_state.round_id @ RecoveryRoomError::WrongTokenPool
+    )]
+    pub token_pool: AccountLoader<'info, TokenPool>,
+
+    /// Pool shard holding entered tokens the main pool doesn't; see add_token_pool_shard
+    #[account(
+        mut,
+        seeds = [
+            b"token_pool_shard".as_ref(),
+            &room_seed(round_state.room_id),
+            &round_state.round_id.to_le_bytes(),
+            &token_pool_shard.load()?.index.to_le_bytes(),
+        ],
+        bump = token_pool_shard.load()?.bump
+    )]
+    pub token_pool_shard: Option<AccountLoader<'info, TokenPoolShard>>,
+}
+
+#[derive(Accounts)]
+pub struct SetCanonicalMint<'info> {
+    #[account(
+        seeds = [PROTOCOL_SEED, &room_seed(protocol_state.room_id)],
+        bump = protocol_state.bump,
+        has_one = authority,
+        constraint = !protocol_state.finalized @ RecoveryRoomError::ConfigFinalized
+    )]
+    pub protocol_state: Account<'info, ProtocolState>,
+
+    #[account(
+        mut,
+        seeds = [b"ticker_registry"],
+        bump
+    )]
+    pub ticker_registry: AccountLoader<'info, TickerRegistry>,
+
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct GcRounds<'info> {
+    #[account(
+        seeds = [PROTOCOL_SEED, &room_seed(protocol_state.room_id)],
+        bump = protocol_state.bump
+    )]
+    pub protocol_state: Account<'info, ProtocolState>,
+
+    #[account(
+        mut,
+        seeds = [b"jackpot", &room_seed(protocol_state.room_id)],
+        bump = jackpot.bump
+    )]
+    pub jackpot: Account<'info, Jackpot>,
+
+    #[account(mut)]
+    pub caller: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct GcRound<'info> {
+    #[account(
+        seeds = [ROUND_SEED, &room_seed(round_state.room_id), &round_state.round_id.to_le_bytes()],
+        bump = round_state.bump
+    )]
+    pub round_state: Account<'info, RoundState>,
+
+    /// The round's token pool; omitted once an earlier gc_round closed it
+    #[account(
+        mut,
+        seeds = [b"token_pool", &room_seed(round_state.room_id), &round_state.round_id.to_le_bytes()],
+        bump,
+        constraint = token_pool.load()?.round_id == round_state.round_id @ RecoveryRoomError::WrongTokenPool
+    )]
+    pub token_pool: Option<AccountLoader<'info, TokenPool>>,
+
+    #[account(
+        mut,
+        seeds = [b"jackpot", &room_seed(round_state.room_id)],
+        bump = jackpot.bump
+    )]
+    pub jackpot: Account<'info, Jackpot>,
+
+    #[account(mut)]
+    pub caller: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ArchiveRound<'info> {
+    #[account(
+        seeds = [PROTOCOL_SEED, &room_seed(protocol_state.room_id)],
+        bump = protocol_state.bump
+    )]
+    pub protocol_state: Account<'info, ProtocolState>,
+
+    /// CHECK: one of this room's rounds, decoded and checked by collectable_round
+    #[account(mut)]
+    pub round_state: UncheckedAccount<'info>,
+
+    /// CHECK: the round's prize vault, which collectable_round requires to be
+    /// empty or closed
+    pub prize_vault: UncheckedAccount<'info>,
+
+    #[account(
+        init_if_needed,
+        payer = caller,
+        space = RoundArchive::space(0),
+        seeds = [b"round_archive".as_ref(), &room_seed(protocol_state.room_id)],
+        bump
+    )]
+    pub archive: Account<'info, RoundArchive>,
+
+    #[account(
+        mut,
+        seeds = [b"jackpot", &room_seed(protocol_state.room_id)],
+        bump = jackpot.bump
+    )]
+    pub jackpot: Account<'info, Jackpot>,
+
+    #[account(mut)]
+    pub caller: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// Migrations take the account unchecked: an older layout may be too short
+/// for Account<T> to decode until it has been grown.
+#[derive(Accounts)]
+#[instruction(room_id: u32)]
+pub struct MigrateProtocol<'info> {
+    /// CHECK: protocol PDA, decoded by realloc::upgrade after growing
+    #[account(mut, seeds = [PROTOCOL_SEED, &room_seed(room_id)], bump)]
+    pub protocol_state: UncheckedAccount<'info>,
+
+    pub authority: Signer<'info>,
+
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(room_id: u32, round_id: u64)]
+pub struct MigrateRound<'info> {
+    /// CHECK: round PDA, decoded by realloc::upgrade after growing
+    #[account(mut, seeds = [ROUND_SEED, &room_seed(room_id), &round_id.to_le_bytes()], bump)]
+    pub round_state: UncheckedAccount<'info>,
+
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(room_id: u32, round_id: u64, user: Pubkey)]
+pub struct MigrateParticipation<'info> {
+    /// CHECK: only its address is used, to derive the participation PDA
+    #[account(seeds = [ROUND_SEED, &room_seed(room_id), &round_id.to_le_bytes()], bump)]
+    pub round_state: UncheckedAccount<'info>,
+
+    /// CHECK: participation PDA, decoded by realloc::upgrade after growing
+    #[account(
+        mut,
+        seeds = [PARTICIPATION_SEED, round_state.key().as_ref(), user.as_ref()],
+        bump
+    )]
+    pub participation: UncheckedAccount<'info>,
+
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct FundPrizePool<'info> {
+    #[account(
+        seeds = [ROUND_SEED, &room_seed(round_state.room_id), &round_state.round_id.to_le_bytes()],
+        bump = round_state.bump
+    )]
+    pub round_state: Account<'info, RoundState>,
+
+    #[account(
+        mut,
+        seeds = [b"prize_vault", &room_seed(round_state.room_id), &round_state.round_id.to_le_bytes()],
+        bump = prize_vault.bump
+    )]
+    pub prize_vault: Account<'info, PrizeVault>,
+
+    #[account(
+        init_if_needed,
+        payer = sponsor,
+        space = Sponsor::SIZE,
+        seeds = [b"sponsor".as_ref(), &room_seed(round_state.room_id), &round_state.round_id.to_le_bytes(), sponsor.key().as_ref()],
+        bump
+    )]
+    pub sponsor_record: Account<'info, Sponsor>,
+
+    /// Source of SPL funding (required when token_amount > 0)
+    #[account(mut, token::authority = sponsor)]
+    pub sponsor_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    /// Prize vault token account for the funded mint, owned by the prize vault PDA
+    #[account(mut, token::authority = prize_vault)]
+    pub vault_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    /// Mint of the funded tokens (required when token_amount > 0)
+    pub token_mint: Option<InterfaceAccount<'info, Mint>>,
+
+    #[account(mut)]
+    pub sponsor: Signer<'info>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct BrandRound<'info> {
+    #[account(
+        seeds = [PROTOCOL_SEED, &room_seed(round_state.room_id)],
+        bump = protocol_state.bump,
+        has_one = authority,
+        constraint = !protocol_state.finalized @ RecoveryRoomError::ConfigFinalized
+    )]
+    pub protocol_state: Account<'info, ProtocolState>,
+
+    #[account(
+        mut,
+        seeds = [ROUND_SEED, &room_seed(round_state.room_id), &round_state.round_id.to_le_bytes()],
+        bump = round_state.bump
+    )]
+    pub round_state: Account<'info, RoundState>,
+
+    /// Funding record of the sponsor the round is branded for
+    #[account(
+        seeds = [b"sponsor".as_ref(), &room_seed(round_state.room_id), &round_state.round_id.to_le_bytes(), sponsor_record.sponsor.as_ref()],
+        bump = sponsor_record.bump
+    )]
+    pub sponsor_record: Account<'info, Sponsor>,
+
+    pub authority: Signer<'info>,
+}
+
+// ============ Events ============
+
+#[event]
+pub struct RoomCreated {
+    pub room_id: u32,
+    pub protocol_state: Pubkey,
+    pub authority: Pubkey,
+}
+
+#[event]
+pub struct RoundStarted {
+    pub round_id: u64,
+    pub start_time: i64,
+    pub end_time: i64,
+}
+
+#[event]
+pub struct UserParticipated {
+    pub round_id: u64,
+    pub user: Pubkey,
+    pub token_count: u8,
+}
+
+#[event]
+pub struct XpAwarded {
+    pub user: Pubkey,
+    pub round_id: u64,
+    pub points: u64,
+    pub total_xp: u64,
+}
+
+#[event]
+pub struct SeasonStarted {
+    pub season_id: u32,
+    pub start_time: i64,
+    pub end_time: i64,
+}
+
+#[event]
+pub struct SeasonEnded {
+    pub season_id: u32,
+    pub ended_at: i64,
+    pub rounds: u32,
+    pub participants: u64,
+    pub total_loss_usd: u64,
+}
+
+#[event]
+pub struct TickerCollision {
+    /// Round being entered, or None when raised at global registration
+    pub round_id: Option<u64>,
+    pub ticker: [u8; 10],
+    pub canonical_mint: Pubkey,
+    pub colliding_mint: Pubkey,
+}
+
+#[event]
+pub struct EscrowLiquidated {
+    pub round_id: u64,
+    pub input_mint: Pubkey,
+    pub amount_in: u64,
+    pub output_mint: Pubkey,
+    /// Proceeds credited: the stable vault's gain, or the whole unwrapped
+    /// balance of the wrapped-SOL account
+    pub amount_out: u64,
+    pub to_stable_vault: bool,
+    pub to_jackpot: bool,
+}
+
+#[event]
+pub struct TicketsMinted {
+    pub round_id: u64,
+    pub user: Pubkey,
+    pub tickets: u64,
+}
+
+#[event]
+pub struct ParticipatedFor {
+    pub round_id: u64,
+    pub user: Pubkey,
+    pub delegate: Pubkey,
+    pub integrator_program: Option<Pubkey>,
+}
+
+#[event]
+pub struct ReferrerRegistered {
+    pub referrer: Pubkey,
+}
+
+#[event]
+pub struct ReferralFeePaid {
+    pub referrer: Pubkey,
+    pub user: Pubkey,
+    pub round_id: u64,
+    pub amount: u64,
+}
+
+#[event]
+pub struct ReferralFeesClaimed {
+    pub referrer: Pubkey,
+    pub amount: u64,
+}
+
+#[event]
+pub struct Staked {
+    pub user: Pubkey,
+    pub amount: u64,
+    pub total: u64,
+    pub locked_until: i64,
+}
+
+#[event]
+pub struct Unstaked {
+    pub user: Pubkey,
+    pub amount: u64,
+    pub total: u64,
+}
+
+#[event]
+pub struct RewardEpochAdvanced {
+    pub epoch: u64,
+    /// Fees of the closed epoch, streaming out over the new one
+    pub streaming: u64,
+    pub total_staked: u64,
+}
+
+#[event]
+pub struct StakingRewardsClaimed {
+    pub user: Pubkey,
+    pub amount: u64,
+    pub epoch: u64,
+}
+
+#[event]
+pub struct RecoveryTokensMinted {
+    pub round_id: u64,
+    pub user: Pubkey,
+    pub losses_usd_cents: u64,
+    pub amount: u64,
+}
+
+#[event]
+pub struct TokensBurned {
+    pub round_id: u64,
+    pub mint: Pubkey,
+    pub amount: u64,
+}
+
+#[event]
+pub struct RoundFinalized {
+    pub round_id: u64,
+    pub lamports_returned: u64,
+    pub token_accounts_closed: u8,
+}
+
+#[event]
+pub struct KeeperBountyPaid {
+    pub keeper: Pubkey,
+    pub crank: CrankInstruction,
+    pub round_id: u64,
+    pub amount: u64,
+}
+
+#[event]
+pub struct ConfigQueued {
+    pub room_id: u32,
+    pub change: ConfigChange,
+    pub eta: i64,
+}
+
+#[event]
+pub struct ConfigApplied {
+    pub room_id: u32,
+    pub change: ConfigChange,
+}
+
+#[event]
+pub struct ConfigCancelled {
+    pub room_id: u32,
+    pub change: ConfigChange,
+}
+
+#[event]
+pub struct ProtocolFinalized {
+    pub room_id: u32,
+    pub finalized_at: i64,
+}
+
+#[event]
+pub struct AdminSetUpdated {
+    pub room_id: u32,
+    pub admins: Vec<Pubkey>,
+    pub threshold: u8,
+}
+
+#[event]
+pub struct EmergencyWithdrawQueued {
+    pub room_id: u32,
+    pub round_id: u64,
+    pub destination: Pubkey,
+    pub lamports: u64,
+    pub token_account: Option<Pubkey>,
+    pub token_amount: u64,
+    pub approvals: u8,
+    pub eta: i64,
+}
+
+#[event]
+pub struct EmergencyWithdrawExecuted {
+    pub room_id: u32,
+    pub round_id: u64,
+    pub destination: Pubkey,
+    pub lamports: u64,
+    pub token_account: Option<Pubkey>,
+    /// Tokens the destination received, net of any transfer fee
+    pub token_amount: u64,
+}
+
+#[event]
+pub struct EmergencyWithdrawCancelled {
+    pub room_id: u32,
+    pub round_id: u64,
+    pub cancelled_by: Pubkey,
+}
+
+#[event]
+pub struct AuthorityTransferred {
+    pub room_id: u32,
+    pub previous: Pubkey,
+    pub new_authority: Pubkey,
+}
+
+#[event]
+pub struct AccountMigrated {
+    pub account: Pubkey,
+    pub from_version: u8,
+    pub to_version: u8,
+}
+
+#[event]
+pub struct Resized {
+    pub account: Pubkey,
+    pub old_len: u32,
+    pub new_len: u32,
+    pub payer: Pubkey,
+}
+
+#[event]
+pub struct EntryFeePaid {
+    pub round_id: u64,
+    pub user: Pubkey,
+    pub mint: Pubkey,
+    pub amount: u64,
+    pub burned: bool,
+}
+
+#[event]
+pub struct CompressedParticipation {
+    pub round_id: u64,
+    pub user: Pubkey,
+    pub leaf: [u8; 32],
+    pub leaf_index: u64,
+    pub effective_weight: u64,
+    pub pool_total_weight: u64,
+}
+
+#[event]
+pub struct WinnerVerified {
+    pub round_id: u64,
+    pub winner_token: Pubkey,
+    pub tier_winners: Vec<Pubkey>,
+    pub pool_root: [u8; 32],
+    pub vrf_result: [u8; 32],
+}
+
+#[event]
+pub struct VrfRequested {
+    pub round_id: u64,
+    pub timestamp: i64,
+    /// pool_root of the table the draw will use
+    pub pool_root: [u8; 32],
+}
+
+#[event]
+pub struct OracleFailover {
+    pub round_id: u64,
+    pub oracle_queue: Pubkey,
+    pub primary_failures: u8,
+}
+
+#[event]
+pub struct RandomnessConsumed {
+    pub round_id: u64,
+    pub vrf_result: [u8; 32],
+}
+
+#[event]
+pub struct RandomnessCommitted {
+    pub round_id: u64,
+    pub commitment: [u8; 32],
+}
+
+#[event]
+pub struct RandomnessSealed {
+    pub round_id: u64,
+    pub salt_slot: u64,
+}
+
+#[event]
+pub struct RandomnessRevealed {
+    pub round_id: u64,
+    pub preimage: [u8; 32],
+    /// First slot at or after the sealed salt slot, and its hash
+    pub salt_slot: u64,
+    pub slot_hash: [u8; 32],
+    /// keccak(preimage || slot_hash), stored as the round's vrf_result
+    pub result: [u8; 32],
+    pub pool_root: [u8; 32],
+}
+
+#[event]
+pub struct SlotHashFallbackResolved {
+    pub round_id: u64,
+    /// Slot whose hash was used
+    pub slot: u64,
+    pub slot_hash: [u8; 32],
+    /// Stored as the round's vrf_result
+    pub result: [u8; 32],
+}
+
+#[event]
+pub struct RoundComplete {
+    pub round_id: u64,
+    pub winner_token: Pubkey,
+    pub vrf_result: [u8; 32],
+    pub tier_winners: Vec<Pubkey>,
+}
+
+#[event]
+pub struct UnclaimedPrizesSwept {
+    pub round_id: u64,
+    pub claimed_count: u32,
+    pub rolled_to_jackpot: u64,
+}
+
+#[event]
+pub struct RoundFilled {
+    pub round_id: u64,
+    pub participants: u32,
+}
+
+#[event]
+pub struct RoundCancelled {
+    pub round_id: u64,
+    pub rolled_to_jackpot: u64,
+}
+
+#[event]
+pub struct RoundExtended {
     pub round_id: u64,
-    pub start_time: i64,
     pub end_time: i64,
-    pub total_participants: u32,
-    pub total_token_entries: u32,
-    pub status: RoundStatus,
-    pub vrf_result: Option<[u8; 32]>,
-    pub winner_token: Option<Pubkey>,
-    pub bump: u8,
+    pub total_extension: u32,
+}
+
+#[event]
+pub struct RoundVoided {
+    pub round_id: u64,
+    pub participants: u32,
+    pub rolled_to_jackpot: u64,
+    /// SOL entry fees held back for claim_void_refund
+    pub refundable: u64,
+}
+
+#[event]
+pub struct EntryFeeRefunded {
+    pub round_id: u64,
+    pub user: Pubkey,
+    /// The fee's mint; the native mint for SOL
+    pub mint: Pubkey,
+    pub amount: u64,
+}
+
+#[event]
+pub struct RoundBranded {
+    pub round_id: u64,
+    pub sponsor: Pubkey,
+    pub metadata_uri: String,
+}
+
+#[event]
+pub struct PrizePoolFunded {
+    pub round_id: u64,
+    pub sponsor: Pubkey,
+    pub lamports: u64,
+    pub token_mint: Option<Pubkey>,
+    pub token_amount: u64,
+}
+
+#[event]
+pub struct PrizeClaimed {
+    pub round_id: u64,
+    pub user: Pubkey,
+    pub amount: u64,
+    pub fee: u64,
+}
+
+/// A full payout ledger dropped its oldest bucket; these are its final totals
+#[event]
+pub struct PayoutBucketEvicted {
+    pub wallet: Pubkey,
+    pub year: u16,
+    pub mint: Pubkey,
+    pub amount: u64,
+    pub fees: u64,
+    pub claims: u32,
+}
+
+#[event]
+pub struct WalletTicketDrawn {
+    pub round_id: u64,
+    pub winner_token: Pubkey,
+    /// Cent of the token's total loss; its entry's wallet wins the prize
+    pub ticket: u64,
+    pub total_loss_usd: u64,
 }
 
-#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq)]
-pub enum RoundStatus {
-    Active,
-    VrfRequested,
-    Complete,
-}
+#[event]
+pub struct BonusPrizeClaimed {
+    pub round_id: u64,
+    pub user: Pubkey,
+    pub ticket: u32,
+    pub amount: u64,
+    pub fee: u64,
+}
+
+#[event]
+pub struct BadgeMinted {
+    pub user: Pubkey,
+    pub mint: Pubkey,
+    /// Set for a per-round badge
+    pub round_id: Option<u64>,
+    /// Set for a milestone badge (rounds entered)
+    pub milestone: Option<u32>,
+}
+
+#[event]
+pub struct DepositSettled {
+    pub round_id: u64,
+    pub user: Pubkey,
+    pub amount: u64,
+    pub forfeited: bool,
+}
+
+#[event]
+pub struct ConsolationClaimed {
+    pub round_id: u64,
+    pub user: Pubkey,
+    pub amount: u64,
+}
+
+#[event]
+pub struct PrizeSplitPaid {
+    pub round_id: u64,
+    pub user: Pubkey,
+    pub recipient: Pubkey,
+    pub amount: u64,
+}
+
+#[event]
+pub struct TreasuryWithdrawn {
+    pub destination: Pubkey,
+    pub lamports: u64,
+    pub token_amount: u64,
+}
+
+#[event]
+pub struct RoundsCollected {
+    pub rounds_closed: u8,
+    pub rent_reclaimed: u64,
+    pub caller_reward: u64,
+}
+
+#[event]
+pub struct RoundAccountsCollected {
+    pub round_id: u64,
+    pub accounts_closed: u32,
+    pub rent_reclaimed: u64,
+    pub caller_reward: u64,
+}
+
+#[event]
+pub struct RoundArchived {
+    pub round_id: u64,
+    /// Record's position in the room's RoundArchive
+    pub index: u32,
+    pub rent_reclaimed: u64,
+    pub caller_reward: u64,
+}
+
+#[event]
+pub struct RoundScheduled {
+    pub room_id: u32,
+    pub start_time: i64,
+    pub duration: i64,
+    pub prize_multiplier_bps: u16,
+    pub theme: String,
+}
+
+#[event]
+pub struct PrizeBoosted {
+    pub round_id: u64,
+    pub multiplier_bps: u16,
+    pub amount: u64,
+}
+
+#[event]
+pub struct JackpotRolled {
+    pub round_id: u64,
+    pub amount: u64,
+}
+
+// ============ Errors ============
+
+/// Required-vs-provided context logged alongside a failing error code
+pub struct ErrorDetail {
+    pub field: &'static str,
+    /// Comparison the provided value had to satisfy (">=" or "<=")
+    pub op: &'static str,
+    pub required: i128,
+    pub provided: i128,
+}
+
+impl ErrorDetail {
+    /// Prefix clients scan failed-transaction logs for
+    pub const LOG_PREFIX: &'static str = "RecoveryRoomErrorDetail:";
+
+    pub fn log(&self, error: RecoveryRoomError) {
+        msg!(
+            "{} {{\"code\":{},\"name\":\"{}\",\"field\":\"{}\",\"op\":\"{}\",\"required\":{},\"provided\":{}}}",
+            Self::LOG_PREFIX,
+            u32::from(error),
+            error.name(),
+            self.field,
+            self.op,
+            self.required,
+            self.provided
+        );
+    }
+}
+
+#[error_code]
+pub enum RecoveryRoomError {
+    #[msg("Round is not active")]
+    RoundNotActive,
+
+    #[msg("Round has already ended")]
+    RoundEnded,
+
+    #[msg("Round has not ended yet")]
+    RoundNotEnded,
+
+    #[msg("Invalid token count (must be 1-3)")]
+    InvalidTokenCount,
+
+    #[msg("Previous round not complete")]
+    PreviousRoundNotComplete,
+
+    #[msg("Invalid round status")]
+    InvalidRoundStatus,
+
+    #[msg("No participants in round")]
+    NoParticipants,
+
+    #[msg("VRF result not yet resolved")]
+    VrfNotResolved,
+
+    #[msg("User already participated in this round")]
+    AlreadyParticipated,
+
+    #[msg("Token does not meet minimum loss requirement")]
+    InsufficientLoss,
+
+    #[msg("Token or USD amount overflowed")]
+    AmountOverflow,
+
+    #[msg("Token amounts are denominated in different mints")]
+    MintMismatch,
+
+    #[msg("Mint decimals out of range")]
+    InvalidDecimals,
+
+    #[msg("Price must be non-zero")]
+    InvalidPrice,
+
+    #[msg("Round has participants and cannot be cancelled")]
+    RoundHasParticipants,
+
+    #[msg("Vault balance too low for transfer")]
+    InsufficientVaultBalance,
+
+    #[msg("Funding amount must be non-zero")]
+    ZeroFundingAmount,
+
+    #[msg("Sponsor memo too long")]
+    MemoTooLong,
+
+    #[msg("Round metadata URI too long")]
+    MetadataUriTooLong,
+
+    #[msg("Invalid scheduled round")]
+    InvalidScheduledRound,
+
+    #[msg("Round schedule is full")]
+    ScheduleFull,
+
+    #[msg("Token accounts required for SPL transfer")]
+    MissingTokenAccounts,
+
+    #[msg("Invalid number of rounds to collect")]
+    InvalidGcBatch,
+
+    #[msg("Account is not a round PDA owned by this program")]
+    InvalidGcAccount,
+
+    #[msg("Invalid number of claim accounts")]
+    InvalidClaimBatch,
+
+    #[msg("Stable prize amount must be non-zero")]
+    InvalidStablePrize,
+
+    #[msg("Stable-prize rounds are claimed with claim_prize")]
+    StablePrizeRound,
+
+    #[msg("Claim account is not the expected writable PDA")]
+    InvalidClaimAccount,
+
+    #[msg("Round is not settled or still within retention")]
+    RoundNotCollectable,
+
+    #[msg("Protocol fee exceeds the maximum")]
+    FeeTooHigh,
+
+    #[msg("Prize already claimed")]
+    AlreadyClaimed,
+
+    #[msg("Participation did not submit the winning token")]
+    NotAWinner,
+
+    #[msg("Tier split must sum to 10000 bps with contiguous non-zero tiers")]
+    InvalidTierSplit,
+
+    #[msg("Claim split must cover 10000 bps across writable recipients")]
+    InvalidClaimSplit,
+
+    #[msg("Invalid weighting strategy parameters")]
+    InvalidWeightingStrategy,
+
+    #[msg("Test harness instructions are disabled in this build")]
+    TestHarnessDisabled,
+
+    #[msg("Oracle queue is not the configured primary or an approved fallback")]
+    OracleQueueNotAllowed,
+
+    #[msg("Token pool is full; expand_token_pool grows it up to MAX_POOL_TOKENS")]
+    TokenPoolFull,
+
+    #[msg("Merkle tree is not the protocol's participation tree")]
+    WrongParticipationTree,
+
+    #[msg("Invalid entry fee configuration")]
+    InvalidEntryFee,
+
+    #[msg("Account cannot grow past its maximum size")]
+    AccountSizeExceeded,
+
+    #[msg("Keeper bounty exceeds the maximum")]
+    BountyTooHigh,
+
+    #[msg("Signer is not the protocol's automation thread")]
+    UnauthorizedThread,
+
+    #[msg("Signer is neither the crank authority nor a permitted keeper")]
+    UnauthorizedCrank,
+
+    #[msg("Keeper registry is full")]
+    KeeperRegistryFull,
+
+    #[msg("Keeper is not registered")]
+    KeeperNotFound,
+
+    #[msg("Keeper permissions must be a non-empty set of crank instructions")]
+    InvalidKeeperPermissions,
+
+    #[msg("Missing Switchboard accounts for the request thread")]
+    MissingAutomationAccounts,
+
+    #[msg("Round still has unclaimed prize shares")]
+    PrizesOutstanding,
+
+    #[msg("Token account is not owned by the expected vault")]
+    InvalidVaultTokenAccount,
+
+    #[msg("Swap route is not an exact-in Jupiter route")]
+    InvalidSwapRoute,
+
+    #[msg("Swap exceeded its slippage limits")]
+    SlippageExceeded,
+
+    #[msg("Vault holds none of the winning token")]
+    NothingToBurn,
+
+    #[msg("Recovery token minting is off in this room")]
+    RecoveryMintingDisabled,
+
+    #[msg("Recovery tokens already minted for this participation")]
+    RecoveryAlreadyMinted,
+
+    #[msg("Participation was flagged as invalid")]
+    ParticipationFlagged,
+
+    #[msg("Invalid staking configuration")]
+    InvalidStakeConfig,
+
+    #[msg("Stake amount must be non-zero")]
+    ZeroStakeAmount,
+
+    #[msg("Stake is still locked")]
+    StakeLocked,
+
+    #[msg("Not enough staked")]
+    InsufficientStake,
+
+    #[msg("A staking fee is set, so the rewards vault is required")]
+    MissingRewardsVault,
+
+    #[msg("No staking rewards to claim")]
+    NoStakingRewards,
+
+    #[msg("Signer is not the nominated authority")]
+    NotPendingAuthority,
+
+    #[msg("This room's parameter changes go through queue_config")]
+    ConfigTimelocked,
+
+    #[msg("Queued config change is not due yet")]
+    ConfigNotReady,
+
+    #[msg("Invalid config delay")]
+    InvalidConfigDelay,
+
+    #[msg("Users can't refer themselves")]
+    SelfReferral,
+
+    #[msg("No referral fees to claim")]
+    NoReferralFees,
+
+    #[msg("Program is not a registered integrator")]
+    UnknownIntegrator,
+
+    #[msg("Integrator registry is full")]
+    IntegratorRegistryFull,
+
+    #[msg("Signer may not participate for this user")]
+    UnauthorizedDelegate,
+
+    #[msg("No participation permit signed by the user for these entries")]
+    MissingPermit,
+
+    #[msg("Participation permit has expired")]
+    PermitExpired,
+
+    #[msg("Invalid participate batch or account count")]
+    InvalidParticipateBatch,
+
+    #[msg("Batch account is not the expected PDA")]
+    InvalidBatchAccount,
+
+    #[msg("Rooms with an access pass or SPL entry fee can't batch participations")]
+    BatchUnsupported,
+
+    #[msg("Pass the round's ticket mint and the user's ticket account")]
+    MissingTicketAccounts,
+
+    #[msg("Ticketed rounds need a participation account per entrant")]
+    TicketsUnsupported,
+
+    #[msg("Round is not ticketed")]
+    RoundNotTicketed,
+
+    #[msg("Ticketed rounds always draw with Linear weighting")]
+    TicketedRoundWeighting,
+
+    #[msg("Round's claim deadline has passed")]
+    ClaimDeadlinePassed,
+
+    #[msg("Round's claim window is still open, or has no deadline")]
+    ClaimWindowOpen,
+
+    #[msg("Round's unclaimed prizes were already swept")]
+    AlreadySwept,
+
+    #[msg("Claim window must be 0 or at least MIN_CLAIM_WINDOW_SECS")]
+    InvalidClaimWindow,
+
+    #[msg("Admin set must list at most MAX_ADMINS distinct admins, with a threshold of MIN_ADMIN_THRESHOLD to their count")]
+    InvalidAdminSet,
+
+    #[msg("Not enough admins signed")]
+    InsufficientAdminApprovals,
+
+    #[msg("Signer is not in the room's admin set")]
+    NotAnAdmin,
+
+    #[msg("Emergency withdrawal's delay hasn't passed")]
+    EmergencyWithdrawNotReady,
+
+    #[msg("Protocol is finalized; its config can no longer change")]
+    ConfigFinalized,
+
+    #[msg("Round has reached its maximum participants")]
+    RoundFull,
+
+    #[msg("Maximum participants must be 0 or at least the round's minimum and current participants")]
+    InvalidMaxParticipants,
+
+    #[msg("Pool shards must be the round's shard chain, in order")]
+    InvalidPoolShard,
+
+    #[msg("A pool shard is only added once the pool and its last shard are full")]
+    PoolShardNotNeeded,
+
+    #[msg("Token is not in the round's pool or the passed pool shard")]
+    TokenNotInPool,
+
+    #[msg("Each entry needs its mint's pool entry account")]
+    MissingPoolEntryAccounts,
+
+    #[msg("Account is not the round's pool entry for this mint")]
+    InvalidPoolEntry,
+
+    #[msg("Every pool entry must be tallied before randomness is requested")]
+    PoolEntriesUntallied,
+
+    #[msg("Pool token stats must be synced to the token registries first")]
+    TokenStatsNotSynced,
+
+    #[msg("The round's fallback slot has not passed yet")]
+    FallbackSlotNotReached,
+
+    #[msg("The fallback slot's hash is no longer in SlotHashes; retry the VRF request")]
+    FallbackSlotExpired,
+
+    #[msg("Instruction does not match the room's randomness mode")]
+    WrongRandomnessMode,
+
+    #[msg("Randomness is already committed for this round")]
+    RandomnessAlreadyCommitted,
+
+    #[msg("Reveal is not yet allowed: the round must end and the reveal delay pass")]
+    RevealTooEarly,
+
+    #[msg("Preimage does not match the committed randomness")]
+    InvalidReveal,
+
+    #[msg("No token has enough submissions to qualify for the draw")]
+    NoQualifyingTokens,
+
+    #[msg("Bonus prize share exceeds the maximum")]
+    BonusPrizeTooHigh,
+
+    #[msg("Participation does not hold the round's bonus ticket")]
+    NotBonusWinner,
+
+    #[msg("Wallet-draw rounds need a participation account per entrant")]
+    WalletDrawUnsupported,
+
+    #[msg("Entry lacks a loss attestation from the price verifier")]
+    MissingLossAttestation,
+
+    #[msg("Losses were not attested by a price verifier")]
+    LossNotAttested,
+
+    #[msg("The round's fallback slot has passed; resolve it with resolve_with_slothash")]
+    FallbackWindowOpen,
+
+    #[msg("Wallet is bound to a different referrer")]
+    ReferrerMismatch,
 
-#[account]
-pub struct Participation {
-    pub user: Pubkey,
-    pub round_id: u64,
-    pub tokens: Vec<TokenEntry>,
-    pub timestamp: i64,
-    pub bump: u8,
-}
+    #[msg("A consolation fee is set, so the consolation pool is required")]
+    MissingConsolationPool,
 
-#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
-pub struct TokenEntry {
-    pub token_mint: Pubkey,
-    pub ticker: String,
-    pub loss_amount_usd: u64,    // In cents (e.g., 44076 = $440.76)
-    pub holdings: u64,
-}
+    #[msg("The room has a schedule, so start_round requires it")]
+    MissingSchedule,
 
-#[account]
-pub struct TokenPool {
-    pub round_id: u64,
-    pub entries: Vec<TokenPoolEntry>,
-}
+    #[msg("No entry fee is owed back for this entry")]
+    NoRefundOwed,
 
-#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
-pub struct TokenPoolEntry {
-    pub token_mint: Pubkey,
-    pub ticker: String,
-    pub submission_count: u32,
-    pub color: String,
-}
+    #[msg("Entrants of the voided round still have entry fees to reclaim")]
+    RefundsOutstanding,
 
-// ============ Context Structures ============
+    #[msg("Swaps only run through the Jupiter v6 program")]
+    InvalidSwapProgram,
 
-#[derive(Accounts)]
-pub struct InitializeProtocol<'info> {
-    #[account(
-        init,
-        payer = authority,
-        space = 8 + 32 + 8 + 1 + 1 + 8 + 8 + 1,
-        seeds = [b"protocol"],
-        bump
-    )]
-    pub protocol_state: Account<'info, ProtocolState>,
+    #[msg("Participation has no losing entries to mint recovery tokens for")]
+    NoRecoveryTokensOwed,
 
-    #[account(mut)]
-    pub authority: Signer<'info>,
+    #[msg("Ticker must be 1-10 ASCII letters or digits")]
+    InvalidTicker,
 
-    pub system_program: Program<'info, System>,
-}
+    #[msg("Color must be a #RRGGBB hex string")]
+    InvalidColor,
 
-#[derive(Accounts)]
-pub struct StartRound<'info> {
-    #[account(
-        mut,
-        seeds = [b"protocol"],
-        bump = protocol_state.bump
-    )]
-    pub protocol_state: Account<'info, ProtocolState>,
+    #[msg("Token is already in this round's pool")]
+    TokenAlreadyRegistered,
 
-    #[account(
-        init,
-        payer = payer,
-        space = 8 + 8 + 8 + 8 + 4 + 4 + 1 + 33 + 33 + 1,
-        seeds = [b"round", &(protocol_state.current_round + 1).to_le_bytes()],
-        bump
-    )]
-    pub round_state: Account<'info, RoundState>,
+    #[msg("Ticker registry is at capacity")]
+    TickerRegistryFull,
 
-    /// Previous round (optional, for validation)
-    pub previous_round: Option<Account<'info, RoundState>>,
+    #[msg("Ticker is not registered")]
+    TickerNotRegistered,
 
-    #[account(mut)]
-    pub payer: Signer<'info>,
+    #[msg("A season is already active")]
+    SeasonActive,
 
-    pub system_program: Program<'info, System>,
-}
+    #[msg("Consolation rewards are disabled")]
+    ConsolationDisabled,
 
-#[derive(Accounts)]
-pub struct Participate<'info> {
-    #[account(
-        seeds = [b"protocol"],
-        bump = protocol_state.bump
-    )]
-    pub protocol_state: Account<'info, ProtocolState>,
+    #[msg("Winning participations claim via claim_prize")]
+    NotConsolationEligible,
 
-    #[account(
-        mut,
-        seeds = [b"round", &round_state.round_id.to_le_bytes()],
-        bump = round_state.bump
-    )]
-    pub round_state: Account<'info, RoundState>,
+    #[msg("Consolation pool cannot cover the reward")]
+    ConsolationPoolDepleted,
 
-    #[account(
-        init,
-        payer = user,
-        space = 8 + 32 + 8 + 4 + (32 + 32 + 8 + 8) * 3 + 8 + 1, // Max 3 tokens
-        seeds = [b"participation", round_state.key().as_ref(), user.key().as_ref()],
-        bump
-    )]
-    pub participation: Account<'info, Participation>,
+    #[msg("Badge already minted")]
+    BadgeAlreadyMinted,
 
-    #[account(mut)]
-    pub token_pool_entries: Account<'info, TokenPool>,
+    #[msg("Not a badge milestone")]
+    InvalidBadgeMilestone,
 
-    #[account(mut)]
-    pub user: Signer<'info>,
+    #[msg("Not enough rounds entered for this badge")]
+    BadgeMilestoneNotReached,
 
-    pub system_program: Program<'info, System>,
-}
+    #[msg("Receipt URI is too long")]
+    ReceiptUriTooLong,
 
-#[derive(Accounts)]
-pub struct RequestRandomness<'info> {
-    #[account(
-        mut,
-        seeds = [b"protocol"],
-        bump = protocol_state.bump
-    )]
-    pub protocol_state: Account<'info, ProtocolState>,
+    #[msg("Missing receipt cNFT accounts")]
+    MissingReceiptAccounts,
 
-    #[account(
-        mut,
-        seeds = [b"round", &round_state.round_id.to_le_bytes()],
-        bump = round_state.bump
-    )]
-    pub round_state: Account<'info, RoundState>,
+    #[msg("Merkle tree is not the protocol's receipt tree")]
+    WrongReceiptTree,
 
-    // Switchboard VRF accounts
-    #[account(mut)]
-    pub vrf: AccountLoader<'info, VrfAccountData>,
+    #[msg("Account is neither the mint's Metaplex metadata nor a Token-2022 mint holding its own metadata")]
+    InvalidMetadataAccount,
 
-    #[account(mut)]
-    pub oracle_queue: AccountLoader<'info, OracleQueueAccountData>,
+    #[msg("Pass one metadata account per token entry")]
+    MissingMetadataAccounts,
 
-    /// CHECK: Queue authority
-    pub queue_authority: AccountInfo<'info>,
+    #[msg("Ticker does not match the mint's metadata symbol")]
+    TickerMismatch,
 
-    /// CHECK: Data buffer for VRF
-    #[account(mut)]
-    pub data_buffer: AccountInfo<'info>,
+    #[msg("Token mint must not be the default pubkey")]
+    InvalidTokenMint,
 
-    pub permission: AccountLoader<'info, PermissionAccountData>,
+    #[msg("Token entry must report non-zero holdings")]
+    ZeroHoldings,
 
-    #[account(mut)]
-    pub escrow: Account<'info, TokenAccount>,
+    #[msg("Loss amount is outside the accepted range")]
+    LossOutOfRange,
 
-    #[account(mut)]
-    pub payer_wallet: Account<'info, TokenAccount>,
+    #[msg("Loss percentage cannot exceed 100")]
+    InvalidLossPercentage,
 
-    #[account(mut)]
-    pub payer: Signer<'info>,
+    #[msg("Mint list is at capacity")]
+    MintListFull,
 
-    /// CHECK: Recent blockhashes sysvar
-    pub recent_blockhashes: AccountInfo<'info>,
+    #[msg("Pass the protocol's mint blacklist/whitelist")]
+    MissingMintList,
 
-    pub switchboard_program_state: AccountLoader<'info, SbState>,
+    #[msg("Mint is blacklisted")]
+    MintBlacklisted,
 
-    /// CHECK: Switchboard program
-    pub switchboard_program: AccountInfo<'info>,
+    #[msg("Mint is not on the whitelist")]
+    MintNotWhitelisted,
 
-    pub token_program: Program<'info, Token>,
-    pub system_program: Program<'info, System>,
-}
+    #[msg("Round has too few participants to be drawn")]
+    BelowMinParticipants,
 
-#[derive(Accounts)]
-pub struct ConsumeRandomness<'info> {
-    #[account(
-        mut,
-        seeds = [b"protocol"],
-        bump = protocol_state.bump
-    )]
-    pub protocol_state: Account<'info, ProtocolState>,
+    #[msg("Round reached its minimum participants")]
+    MinParticipantsReached,
 
-    #[account(
-        mut,
-        seeds = [b"round", &round_state.round_id.to_le_bytes()],
-        bump = round_state.bump
-    )]
-    pub round_state: Account<'info, RoundState>,
+    #[msg("Anti-snipe extension exceeds its maximum")]
+    InvalidAntiSnipe,
 
-    pub vrf: AccountLoader<'info, VrfAccountData>,
+    #[msg("Token won too recently to enter this round")]
+    TokenOnCooldown,
 
-    #[account(mut)]
-    pub token_pool: Account<'info, TokenPool>,
-}
+    #[msg("VRF account is not the one bound to the protocol")]
+    WrongVrfAccount,
 
-// ============ Events ============
+    #[msg("VRF account's authority must be the protocol PDA")]
+    VrfAuthorityMismatch,
 
-#[event]
-pub struct RoundStarted {
-    pub round_id: u64,
-    pub start_time: i64,
-    pub end_time: i64,
-}
+    #[msg("VRF result does not answer this round's request")]
+    StaleVrfResult,
 
-#[event]
-pub struct UserParticipated {
-    pub round_id: u64,
-    pub user: Pubkey,
-    pub token_count: u8,
-}
+    #[msg("Token pool is locked while randomness is in flight")]
+    TokenPoolLocked,
 
-#[event]
-pub struct VrfRequested {
-    pub round_id: u64,
-    pub timestamp: i64,
-}
+    #[msg("Token pool no longer matches the root committed at the VRF request")]
+    PoolRootMismatch,
 
-#[event]
-pub struct RoundComplete {
-    pub round_id: u64,
-    pub winner_token: Pubkey,
-    pub vrf_result: [u8; 32],
+    #[msg("Re-drawn winners do not match the recorded winners")]
+    WinnerMismatch,
+
+    #[msg("Participation rate limit exceeded; try again later")]
+    RateLimited,
+
+    #[msg("Rate-limit window must be non-zero when capped")]
+    InvalidRateLimit,
+
+    #[msg("Entry deposit exceeds the maximum")]
+    DepositTooHigh,
+
+    #[msg("Participation holds no deposit")]
+    NoDeposit,
+
+    #[msg("Compressed participation is unavailable while entry deposits are on")]
+    DepositNeedsParticipationAccount,
+
+    #[msg("Malformed ed25519 attestation instruction")]
+    InvalidAttestation,
+
+    #[msg("Pass the instructions sysvar to verify attestations")]
+    MissingInstructionsSysvar,
+
+    #[msg("Mint lacks a rug attestation from the protocol's verifier")]
+    MissingRugAttestation,
+
+    #[msg("Entry lacks a cost-basis attestation from the price verifier")]
+    MissingCostBasisAttestation,
+
+    #[msg("Entry lacks a fresh price attestation from the price verifier")]
+    MissingPriceAttestation,
+
+    #[msg("Pass the user's token account for each entry")]
+    MissingHoldingAccounts,
+
+    #[msg("Account is not the user's token account for the entry's mint")]
+    InvalidHoldingAccount,
+
+    #[msg("Token account holds less than the entry's reported holdings")]
+    InsufficientHoldings,
+
+    #[msg("Wallet claimed a prize too recently to enter this round")]
+    WalletOnCooldown,
+
+    #[msg("Round duration is outside the protocol's bounds")]
+    InvalidRoundDuration,
+
+    #[msg("Only the authority may override a round's settings")]
+    UnauthorizedRoundOverride,
+
+    #[msg("No season is active")]
+    NoActiveSeason,
+
+    #[msg("Season must end in the future")]
+    InvalidSeasonEnd,
+
+    #[msg("Round belongs to a season; pass its Season account")]
+    SeasonAccountMissing,
+
+    #[msg("Account is not a migratable account of this program")]
+    InvalidMigrationAccount,
+
+    #[msg("Account is already on the current layout")]
+    AlreadyMigrated,
+
+    #[msg("Token pool does not belong to this round")]
+    WrongTokenPool,
+
+    #[msg("Counter or timestamp arithmetic overflowed")]
+    ArithmeticOverflow,
+
+    #[msg("Room registry is full")]
+    RoomRegistryFull,
+
+    #[msg("This room requires holding its access pass")]
+    MissingAccessPass,
+
+    #[msg("Access pass must require a non-zero amount")]
+    InvalidAccessPass,
+
+    #[msg("Commit-reveal needs a crank authority or registered keepers")]
+    CommitRevealNeedsCrank,
+
+    #[msg("Round randomness has not been committed")]
+    RandomnessNotCommitted,
+
+    #[msg("The round's reveal salt slot is already sealed")]
+    RandomnessAlreadySealed,
+
+    #[msg("The salt slot's hash is no longer in SlotHashes; the round can only be voided")]
+    SaltSlotExpired,
+
+    #[msg("Payout ledger is full of this year's buckets")]
+    PayoutLedgerFull,
 }
 
-// ============ Errors ============
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-#[error_code]
-pub enum RecoveryRoomError {
-    #[msg("Round is not active")]
-    RoundNotActive,
+    /// An account with every field at its zero value, for tests to fill in.
+    /// Enum fields hold their first variant, which may be smaller than the
+    /// largest InitSpace reserves for.
+    fn zeroed<T: AnchorDeserialize>() -> T {
+        T::deserialize(&mut &[0u8; 4096][..]).unwrap()
+    }
 
-    #[msg("Round has already ended")]
-    RoundEnded,
+    /// Serialized length of an account, discriminator included
+    fn stored_len<T: AnchorSerialize>(account: &T) -> usize {
+        8 + account.try_to_vec().unwrap().len()
+    }
 
-    #[msg("Round has not ended yet")]
-    RoundNotEnded,
+    fn token_entry() -> TokenEntry {
+        TokenEntry {
+            token_mint: Pubkey::new_unique(),
+            ticker: *b"RUGGEDCOIN",
+            loss_amount_usd: u64::MAX,
+            holdings: u64::MAX,
+            loss_percentage: 100,
+        }
+    }
 
-    #[msg("Invalid token count (must be 1-3)")]
-    InvalidTokenCount,
+    #[test]
+    fn protocol_state_fits_with_every_option_set() {
+        let mut protocol: ProtocolState = zeroed();
+        protocol.fallback_oracle_queue = Some(Pubkey::new_unique());
+        protocol.rug_verifier = Some(Pubkey::new_unique());
+        protocol.price_verifier = Some(Pubkey::new_unique());
+        protocol.crank_authority = Some(Pubkey::new_unique());
+        protocol.access_pass = Some(AccessPass::Token { mint: Pubkey::new_unique(), min_amount: 1 });
+        protocol.stable_prize = Some(StablePrize { mint: Pubkey::new_unique(), amount: 1 });
+        protocol.pending_authority = Some(Pubkey::new_unique());
+        assert!(stored_len(&protocol) <= ProtocolState::SIZE);
+    }
 
-    #[msg("Previous round not complete")]
-    PreviousRoundNotComplete,
+    #[test]
+    fn round_state_fits_a_drawn_sponsored_round() {
+        let mut round: RoundState = zeroed();
+        round.vrf_result = Some([7; 32]);
+        round.winner_token = Some(Pubkey::new_unique());
+        round.sponsor = Some(Pubkey::new_unique());
+        round.metadata_uri = "u".repeat(MAX_ROUND_METADATA_URI_LEN);
+        round.theme = "t".repeat(MAX_SCHEDULE_THEME_LEN);
+        round.stable_prize = Some(StablePrize { mint: Pubkey::new_unique(), amount: 1 });
+        assert!(stored_len(&round) <= RoundState::SIZE);
+    }
 
-    #[msg("Invalid round status")]
-    InvalidRoundStatus,
+    #[test]
+    fn participation_fits_max_tokens() {
+        let mut participation: Participation = zeroed();
+        participation.tokens = vec![token_entry(); MAX_TOKENS_PER_USER as usize];
+        participation.wallet_tickets = vec![WalletTickets { start: u64::MAX, len: u64::MAX }; MAX_TOKENS_PER_USER as usize];
+        assert_eq!(stored_len(&participation), Participation::SIZE);
+    }
 
-    #[msg("No participants in round")]
-    NoParticipants,
+    #[test]
+    fn full_schedule_fits() {
+        let mut schedule: Schedule = zeroed();
+        schedule.rounds = vec![
+            ScheduledRound {
+                start_time: i64::MAX,
+                duration: i64::MAX,
+                prize_multiplier_bps: u16::MAX,
+                theme: "t".repeat(MAX_SCHEDULE_THEME_LEN),
+            };
+            MAX_SCHEDULED_ROUNDS
+        ];
+        assert_eq!(stored_len(&schedule), Schedule::SIZE);
+    }
 
-    #[msg("VRF result not yet resolved")]
-    VrfNotResolved,
+    #[test]
+    fn payout_ledger_grows_one_bucket_at_a_time() {
+        let mut ledger: PayoutLedger = zeroed();
+        assert_eq!(stored_len(&ledger), PayoutLedger::space(0));
+        for year in 0..MAX_LEDGER_ENTRIES as u16 {
+            ledger.record(2024 + year, TokenAmount::lamports(1), 0).unwrap();
+            assert_eq!(stored_len(&ledger), PayoutLedger::space(ledger.entries.len()));
+        }
+        assert_eq!(stored_len(&ledger), PayoutLedger::SIZE);
+    }
 
-    #[msg("User already participated in this round")]
-    AlreadyParticipated,
+    #[test]
+    fn payout_ledger_only_evicts_earlier_years() {
+        let new_mint = || TokenAmount::new(Pubkey::new_unique(), 6, 1).unwrap();
+        let mut ledger: PayoutLedger = zeroed();
+        ledger.record(2024, TokenAmount::lamports(1), 0).unwrap();
+        for _ in 1..MAX_LEDGER_ENTRIES {
+            ledger.record(2025, new_mint(), 0).unwrap();
+        }
+        ledger.record(2025, TokenAmount::lamports(1), 0).unwrap();
+        assert!(ledger.entries.iter().all(|e| e.year == 2025));
+        assert!(ledger.record(2025, new_mint(), 0).is_err());
+        // An existing bucket still takes payouts once the ledger is full
+        ledger.record(2025, TokenAmount::lamports(2), 0).unwrap();
+    }
 
-    #[msg("Token does not meet minimum loss requirement")]
-    InsufficientLoss,
+    #[test]
+    fn year_from_unix_handles_leap_years_and_pre_epoch() {
+        assert_eq!(year_from_unix(0), 1970);
+        assert_eq!(year_from_unix(-1), 1969);
+        assert_eq!(year_from_unix(-31_536_000), 1969);
+        assert_eq!(year_from_unix(-31_536_001), 1968);
+        // 2000-02-29 and 2000-12-31 23:59:59 (leap century), 2001-01-01
+        assert_eq!(year_from_unix(951_782_400), 2000);
+        assert_eq!(year_from_unix(978_307_199), 2000);
+        assert_eq!(year_from_unix(978_307_200), 2001);
+        // 2024-12-31 23:59:59 and 2025-01-01
+        assert_eq!(year_from_unix(1_735_689_599), 2024);
+        assert_eq!(year_from_unix(1_735_689_600), 2025);
+        // 1900-03-01, after a non-leap century's February
+        assert_eq!(year_from_unix(-2_203_891_200), 1900);
+    }
+
+    #[test]
+    fn user_stats_fits_with_a_bound_referrer() {
+        let mut stats: UserStats = zeroed();
+        stats.referrer = Some(Pubkey::new_unique());
+        assert_eq!(stored_len(&stats), UserStats::SIZE);
+    }
+
+    fn pool_entry(token_mint: Pubkey, submission_count: u32) -> TokenPoolEntry {
+        TokenPoolEntry {
+            total_loss_usd: 0,
+            submission_count,
+            token_mint,
+            stats_synced: 0,
+            _padding: [0; 3],
+        }
+    }
+
+    #[test]
+    fn select_winner_walks_cumulative_weight() {
+        let (a, b) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let entries = [pool_entry(a, 1), pool_entry(b, 3)];
+        let pages: [&[TokenPoolEntry]; 1] = [&entries];
+        let linear = WeightingStrategy::Linear;
+
+        assert_eq!(select_winner(&pages, linear, 0, 0, &[]).unwrap(), a);
+        assert_eq!(select_winner(&pages, linear, 0, u128::MAX / 2, &[]).unwrap(), b);
+        assert_eq!(select_winner(&pages, linear, 0, u128::MAX, &[]).unwrap(), b);
+        // Excluded and unqualified tokens carry no weight
+        assert_eq!(select_winner(&pages, linear, 0, u128::MAX, &[b]).unwrap(), a);
+        assert_eq!(select_winner(&pages, linear, 2, 0, &[]).unwrap(), b);
+        assert!(select_winner(&pages, linear, 4, 0, &[]).is_err());
+    }
+
+    #[test]
+    fn draw_winners_stops_at_the_qualified_tokens() {
+        let (a, b, c) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+        let entries = [pool_entry(a, 5), pool_entry(b, 1), pool_entry(c, 9)];
+        let pages: [&[TokenPoolEntry]; 1] = [&entries];
+        let tier_bps = [5_000, 3_000, 2_000];
+
+        let winners = draw_winners(&pages, WeightingStrategy::Sqrt, 2, &tier_bps, &[7; 32]).unwrap();
+        assert_eq!(winners.len(), 2);
+        assert_ne!(winners[0], winners[1]);
+        assert!(!winners.contains(&b));
+
+        // A zero tier ends the draw
+        let winners = draw_winners(&pages, WeightingStrategy::Sqrt, 0, &[10_000, 0, 0], &[7; 32]).unwrap();
+        assert_eq!(winners.len(), 1);
+    }
+
+    #[test]
+    fn prize_share_splits_each_tier_by_submissions() {
+        let (a, b) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let mut round: RoundState = zeroed();
+        round.winner_count = 2;
+        round.winner_tokens = [a, b, Pubkey::default()];
+        round.tier_prizes = [600, 400, 0];
+        round.tier_submissions = [6, 4, 0];
+        let entry = |token_mint| TokenEntry { token_mint, ..token_entry() };
+        let tokens = [entry(a), entry(a), entry(b), entry(Pubkey::new_unique())];
+
+        let (gross, tier_entries) = prize_share(&round, &tokens, &[], 1).unwrap();
+        assert_eq!(gross.amount, 200 + 100);
+        assert_eq!(tier_entries, [2, 1, 0]);
+
+        // Staked entries count weight_multiplier times, after the weight cap
+        let (gross, _) = prize_share(&round, &tokens, &[], 2).unwrap();
+        assert_eq!(gross.amount, 400 + 200);
+        round.weight_cap = 1;
+        let (gross, tier_entries) = prize_share(&round, &tokens, &[], 2).unwrap();
+        assert_eq!(gross.amount, 200 + 200);
+        assert_eq!(tier_entries, [2, 2, 0]);
+
+        assert!(prize_share(&round, &[entry(Pubkey::new_unique())], &[], 1).is_err());
+    }
+
+    #[test]
+    fn prize_share_pays_a_wallet_draw_whole_to_the_ticket_holder() {
+        let a = Pubkey::new_unique();
+        let mut round: RoundState = zeroed();
+        round.wallet_draw = true;
+        round.winner_token = Some(a);
+        round.winner_count = 1;
+        round.winner_tokens[0] = a;
+        round.tier_prizes[0] = 1_000;
+        round.tier_submissions[0] = 6;
+        round.wallet_ticket = 150;
+        let tokens = [TokenEntry { token_mint: a, ..token_entry() }];
+
+        let holder = [WalletTickets { start: 100, len: 100 }];
+        let (gross, tier_entries) = prize_share(&round, &tokens, &holder, 1).unwrap();
+        assert_eq!(gross.amount, 1_000);
+        assert_eq!(tier_entries[0], 6);
+
+        let other = [WalletTickets { start: 0, len: 150 }];
+        assert!(prize_share(&round, &tokens, &other, 1).is_err());
+    }
+
+    #[test]
+    fn shuffle_indices_are_a_deterministic_permutation() {
+        let order = derive_shuffle_indices(&[3; 32], 50);
+        let mut sorted = order.clone();
+        sorted.sort_unstable();
+        assert_eq!(sorted, (0..50).collect::<Vec<u16>>());
+        assert_eq!(order, derive_shuffle_indices(&[3; 32], 50));
+        assert_ne!(order, derive_shuffle_indices(&[4; 32], 50));
+        assert!(derive_shuffle_indices(&[3; 32], 0).is_empty());
+    }
+
+    #[test]
+    fn first_slot_hash_from_skips_missing_slots() {
+        // SlotHashes layout: entry count, then (slot, hash) pairs newest first
+        let mut data = 3u64.to_le_bytes().to_vec();
+        for slot in [105u64, 103, 100] {
+            data.extend_from_slice(&slot.to_le_bytes());
+            data.extend_from_slice(&[slot as u8; 32]);
+        }
+        let (key, owner) = (sysvar::slot_hashes::ID, Pubkey::default());
+        let mut lamports = 0;
+        let info = AccountInfo::new(&key, false, false, &mut lamports, &mut data, &owner, false, 0);
+
+        assert_eq!(first_slot_hash_from(&info, 103).unwrap(), Some((103, [103; 32])));
+        assert_eq!(first_slot_hash_from(&info, 101).unwrap(), Some((103, [103; 32])));
+        assert_eq!(first_slot_hash_from(&info, 100).unwrap(), Some((100, [100; 32])));
+        // Not yet hashed, or older than the sysvar reaches back
+        assert_eq!(first_slot_hash_from(&info, 106).unwrap(), None);
+        assert_eq!(first_slot_hash_from(&info, 99).unwrap(), None);
+    }
+
+    #[test]
+    fn anti_snipe_extends_inside_the_window_up_to_the_cap() {
+        let anti_snipe = AntiSnipe { window_secs: 60, extension_secs: 30, max_extension_secs: 100 };
+        assert_eq!(anti_snipe.extension(1_000, 1_100, 0), 0);
+        assert_eq!(anti_snipe.extension(1_040, 1_100, 0), 30);
+        assert_eq!(anti_snipe.extension(1_040, 1_100, 90), 10);
+        assert_eq!(anti_snipe.extension(1_040, 1_100, 100), 0);
+        assert_eq!(AntiSnipe::default().extension(1_100, 1_100, 0), 0);
+    }
+
+    #[test]
+    fn rate_limit_caps_slots_and_windows() {
+        let limit = RateLimit { window_secs: 60, max_per_window: 2, max_per_slot: 1 };
+        let mut round: RoundState = zeroed();
+        let mut stats: UserStats = zeroed();
+        let clock = |slot, unix_timestamp| Clock { slot, unix_timestamp, ..Clock::default() };
+
+        assert!(enforce_rate_limit(&limit, &mut round, &mut stats, &clock(5, 1_000)).is_ok());
+        assert!(enforce_rate_limit(&limit, &mut round, &mut stats, &clock(5, 1_000)).is_err());
+        assert!(enforce_rate_limit(&limit, &mut round, &mut stats, &clock(6, 1_010)).is_ok());
+        assert!(enforce_rate_limit(&limit, &mut round, &mut stats, &clock(7, 1_020)).is_err());
+        // A new window starts once window_secs have passed
+        assert!(enforce_rate_limit(&limit, &mut round, &mut stats, &clock(8, 1_060)).is_ok());
+    }
 }
@@ -0,0 +1,128 @@
+//! Read-only access to Metaplex Token Metadata.
+//!
+//! Only the symbol and collection are needed, so rather than depend on the
+//! Metaplex crate we check the account is the mint's metadata PDA and
+//! Borsh-decode its layout up to the field we want: key, update_authority,
+//! mint, name, symbol, uri, seller_fee_basis_points, creators,
+//! primary_sale_happened, is_mutable, edition_nonce, token_standard,
+//! collection.
+//!
+//! A Token-2022 mint may instead carry its metadata in its own
+//! TokenMetadata extension, with a metadata pointer back to itself; the
+//! mint is then passed as its metadata account. Mints pointing at metadata
+//! held anywhere else are rejected.
+
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::pubkey;
+use anchor_spl::token_2022::{self, spl_token_2022};
+use spl_token_2022::extension::{
+    metadata_pointer::MetadataPointer, BaseStateWithExtensions, ExtensionType, StateWithExtensions,
+};
+
+use crate::RecoveryRoomError;
+
+/// Metaplex Token Metadata program
+pub const TOKEN_METADATA_PROGRAM_ID: Pubkey = pubkey!("metaqbxxUerdq28cj1RbAWkYQm3ybzjb6a8bt518x1s");
+
+/// `Key::MetadataV1` discriminant
+const METADATA_V1_KEY: u8 = 4;
+
+/// Address of `mint`'s metadata account
+pub fn metadata_pda(mint: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(
+        &[b"metadata", TOKEN_METADATA_PROGRAM_ID.as_ref(), mint.as_ref()],
+        &TOKEN_METADATA_PROGRAM_ID,
+    )
+    .0
+}
+
+/// Symbol in `mint`'s metadata, with Metaplex's NUL padding trimmed
+pub fn read_symbol(metadata: &AccountInfo, mint: &Pubkey) -> Result<String> {
+    if *metadata.owner == token_2022::ID {
+        return read_token_2022_symbol(metadata, mint);
+    }
+    let data = metadata_data(metadata, mint)?;
+    let mut fields = &data[65..];
+    let _name = String::deserialize(&mut fields)?;
+    let symbol = String::deserialize(&mut fields)?;
+
+    Ok(symbol.trim_end_matches('\0').trim().to_string())
+}
+
+/// Collection `mint` is a verified member of, if any. Metadata written before
+/// collections existed ends early and has none.
+pub fn read_verified_collection(metadata: &AccountInfo, mint: &Pubkey) -> Result<Option<Pubkey>> {
+    let data = metadata_data(metadata, mint)?;
+    let mut fields = &data[65..];
+    let _name = String::deserialize(&mut fields)?;
+    let _symbol = String::deserialize(&mut fields)?;
+    let _uri = String::deserialize(&mut fields)?;
+    let _seller_fee_basis_points = u16::deserialize(&mut fields)?;
+    // Creator: address, verified, share
+    let _creators = Option::<Vec<(Pubkey, bool, u8)>>::deserialize(&mut fields)?;
+    let _primary_sale_happened = bool::deserialize(&mut fields)?;
+    let _is_mutable = bool::deserialize(&mut fields)?;
+    let Ok(_edition_nonce) = Option::<u8>::deserialize(&mut fields) else {
+        return Ok(None);
+    };
+    let Ok(_token_standard) = Option::<u8>::deserialize(&mut fields) else {
+        return Ok(None);
+    };
+    // Collection: verified, key
+    let Ok(collection) = Option::<(bool, Pubkey)>::deserialize(&mut fields) else {
+        return Ok(None);
+    };
+    Ok(collection.filter(|(verified, _)| *verified).map(|(_, key)| key))
+}
+
+/// Symbol in the TokenMetadata extension of the Token-2022 mint `metadata`,
+/// which must be `mint` itself and point to its own metadata
+fn read_token_2022_symbol(metadata: &AccountInfo, mint: &Pubkey) -> Result<String> {
+    require_keys_eq!(metadata.key(), *mint, RecoveryRoomError::InvalidMetadataAccount);
+
+    let data = metadata.try_borrow_data()?;
+    let state = StateWithExtensions::<spl_token_2022::state::Mint>::unpack(&data)?;
+    let pointer = state
+        .get_extension::<MetadataPointer>()
+        .map_err(|_| error!(RecoveryRoomError::InvalidMetadataAccount))?;
+    require!(
+        Option::<Pubkey>::from(pointer.metadata_address) == Some(*mint),
+        RecoveryRoomError::InvalidMetadataAccount
+    );
+
+    // TLV entries: type (u16), length (u16), value
+    let mut tlv = state.get_tlv_data();
+    let mut value = None;
+    while tlv.len() >= 4 {
+        let kind = u16::from_le_bytes([tlv[0], tlv[1]]);
+        let len = u16::from_le_bytes([tlv[2], tlv[3]]) as usize;
+        let entry = tlv.get(4..4 + len).ok_or(RecoveryRoomError::InvalidMetadataAccount)?;
+        if kind == ExtensionType::TokenMetadata as u16 {
+            value = Some(entry);
+            break;
+        }
+        tlv = &tlv[4 + len..];
+    }
+    let mut fields = value.ok_or(RecoveryRoomError::InvalidMetadataAccount)?;
+
+    let _update_authority = Pubkey::deserialize(&mut fields)?;
+    let metadata_mint = Pubkey::deserialize(&mut fields)?;
+    require_keys_eq!(metadata_mint, *mint, RecoveryRoomError::InvalidMetadataAccount);
+    let _name = String::deserialize(&mut fields)?;
+    let symbol = String::deserialize(&mut fields)?;
+
+    Ok(symbol.trim().to_string())
+}
+
+/// Data of `mint`'s metadata account, after checking it is that account
+fn metadata_data<'a>(metadata: &'a AccountInfo, mint: &Pubkey) -> Result<std::cell::Ref<'a, &'a mut [u8]>> {
+    require_keys_eq!(*metadata.owner, TOKEN_METADATA_PROGRAM_ID, RecoveryRoomError::InvalidMetadataAccount);
+    require_keys_eq!(metadata.key(), metadata_pda(mint), RecoveryRoomError::InvalidMetadataAccount);
+
+    let data = metadata.try_borrow_data()?;
+    require!(
+        data.len() > 65 && data[0] == METADATA_V1_KEY,
+        RecoveryRoomError::InvalidMetadataAccount
+    );
+    Ok(data)
+}
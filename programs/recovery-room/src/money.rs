@@ -0,0 +1,176 @@
+//! Decimals-aware money types.
+//!
+//! Losses are tracked in USD cents while prizes and fees move as raw token
+//! base units (6 decimals for USDC, 9 for SOL, anything for SPL mints).
+//! Every conversion between the two goes through here so instructions never
+//! hand-roll `10^decimals` math.
+
+use anchor_lang::prelude::*;
+use anchor_spl::token::spl_token::native_mint;
+
+use crate::RecoveryRoomError;
+
+/// Basis points in 100%
+pub const BPS_DENOMINATOR: u64 = 10_000;
+
+/// Cents in one US dollar
+pub const CENTS_PER_DOLLAR: u64 = 100;
+
+/// Decimals of native SOL (lamports)
+pub const SOL_DECIMALS: u8 = 9;
+
+/// Largest decimals value we accept; 10^19 no longer fits in a u64
+pub const MAX_DECIMALS: u8 = 18;
+
+/// A USD value in whole cents (e.g. 44076 = $440.76)
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub struct UsdCents(pub u64);
+
+impl UsdCents {
+    pub const ZERO: Self = Self(0);
+
+    pub fn from_dollars(dollars: u64) -> Result<Self> {
+        dollars
+            .checked_mul(CENTS_PER_DOLLAR)
+            .map(Self)
+            .ok_or_else(|| error!(RecoveryRoomError::AmountOverflow))
+    }
+
+    pub fn cents(self) -> u64 {
+        self.0
+    }
+
+    pub fn checked_add(self, other: Self) -> Result<Self> {
+        self.0
+            .checked_add(other.0)
+            .map(Self)
+            .ok_or_else(|| error!(RecoveryRoomError::AmountOverflow))
+    }
+
+    pub fn checked_sub(self, other: Self) -> Result<Self> {
+        self.0
+            .checked_sub(other.0)
+            .map(Self)
+            .ok_or_else(|| error!(RecoveryRoomError::AmountOverflow))
+    }
+
+    /// `bps` share of this value, rounded down
+    pub fn bps(self, bps: u16) -> Result<Self> {
+        mul_div(self.0, bps as u64, BPS_DENOMINATOR).map(Self)
+    }
+}
+
+impl From<u64> for UsdCents {
+    fn from(cents: u64) -> Self {
+        Self(cents)
+    }
+}
+
+/// A raw token amount tagged with the mint and decimals it is denominated in
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TokenAmount {
+    pub mint: Pubkey,
+    pub decimals: u8,
+    pub amount: u64,
+}
+
+impl TokenAmount {
+    pub fn new(mint: Pubkey, decimals: u8, amount: u64) -> Result<Self> {
+        require!(decimals <= MAX_DECIMALS, RecoveryRoomError::InvalidDecimals);
+        Ok(Self { mint, decimals, amount })
+    }
+
+    /// Native SOL, denominated in lamports
+    pub fn lamports(amount: u64) -> Self {
+        Self {
+            mint: native_mint::ID,
+            decimals: SOL_DECIMALS,
+            amount,
+        }
+    }
+
+    pub fn zero(mint: Pubkey, decimals: u8) -> Result<Self> {
+        Self::new(mint, decimals, 0)
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.amount == 0
+    }
+
+    /// Base units in one whole token (10^decimals)
+    pub fn one_token(&self) -> u64 {
+        10u64.pow(self.decimals as u32)
+    }
+
+    fn with_amount(&self, amount: u64) -> Self {
+        Self { amount, ..*self }
+    }
+
+    pub fn checked_add(self, other: Self) -> Result<Self> {
+        self.require_same_mint(&other)?;
+        self.amount
+            .checked_add(other.amount)
+            .map(|amount| self.with_amount(amount))
+            .ok_or_else(|| error!(RecoveryRoomError::AmountOverflow))
+    }
+
+    pub fn checked_sub(self, other: Self) -> Result<Self> {
+        self.require_same_mint(&other)?;
+        self.amount
+            .checked_sub(other.amount)
+            .map(|amount| self.with_amount(amount))
+            .ok_or_else(|| error!(RecoveryRoomError::AmountOverflow))
+    }
+
+    /// `bps` share of this amount, rounded down
+    pub fn bps(self, bps: u16) -> Result<Self> {
+        mul_div(self.amount, bps as u64, BPS_DENOMINATOR).map(|amount| self.with_amount(amount))
+    }
+
+    /// Split off a `bps` cut, returning (cut, remainder); the two always sum to `self`
+    pub fn split_bps(self, bps: u16) -> Result<(Self, Self)> {
+        let cut = self.bps(bps)?;
+        let rest = self.checked_sub(cut)?;
+        Ok((cut, rest))
+    }
+
+    /// USD value given the price of one whole token in cents
+    pub fn to_usd(self, price_per_token: UsdCents) -> Result<UsdCents> {
+        mul_div(self.amount, price_per_token.0, self.one_token()).map(UsdCents)
+    }
+
+    /// Base units worth `usd` given the price of one whole token in cents
+    pub fn from_usd(mint: Pubkey, decimals: u8, usd: UsdCents, price_per_token: UsdCents) -> Result<Self> {
+        require!(price_per_token.0 > 0, RecoveryRoomError::InvalidPrice);
+        let unit = Self::zero(mint, decimals)?;
+        mul_div(usd.0, unit.one_token(), price_per_token.0).map(|amount| unit.with_amount(amount))
+    }
+
+    /// The same value expressed in `decimals` base units (rounded down when narrowing)
+    pub fn rescale(self, decimals: u8) -> Result<u64> {
+        require!(decimals <= MAX_DECIMALS, RecoveryRoomError::InvalidDecimals);
+        if decimals >= self.decimals {
+            let factor = 10u64.pow((decimals - self.decimals) as u32);
+            self.amount
+                .checked_mul(factor)
+                .ok_or_else(|| error!(RecoveryRoomError::AmountOverflow))
+        } else {
+            Ok(self.amount / 10u64.pow((self.decimals - decimals) as u32))
+        }
+    }
+
+    fn require_same_mint(&self, other: &Self) -> Result<()> {
+        require!(
+            self.mint == other.mint && self.decimals == other.decimals,
+            RecoveryRoomError::MintMismatch
+        );
+        Ok(())
+    }
+}
+
+/// `value * numerator / denominator` with a u128 intermediate, rounded down
+pub fn mul_div(value: u64, numerator: u64, denominator: u64) -> Result<u64> {
+    require!(denominator > 0, RecoveryRoomError::AmountOverflow);
+    let result = (value as u128) * (numerator as u128) / (denominator as u128);
+    u64::try_from(result).map_err(|_| error!(RecoveryRoomError::AmountOverflow))
+}
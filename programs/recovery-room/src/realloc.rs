@@ -0,0 +1,81 @@
+//! Guarded account growth.
+//!
+//! Every account that grows after creation goes through `grow` so the same
+//! rules apply everywhere: a hard size ceiling per account type, the runtime's
+//! per-instruction growth limit, zeroed new space, rent topped up by the
+//! caller-chosen payer, and a `Resized` event for indexers.
+
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::entrypoint::MAX_PERMITTED_DATA_INCREASE;
+use anchor_lang::system_program;
+
+use crate::{RecoveryRoomError, Resized};
+
+/// Grow `account` to `new_len` bytes (never shrinks), topping up rent from `payer`
+pub fn grow<'info>(
+    account: &AccountInfo<'info>,
+    new_len: usize,
+    max_len: usize,
+    payer: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+) -> Result<()> {
+    let old_len = account.data_len();
+    if new_len <= old_len {
+        return Ok(());
+    }
+    require!(new_len <= max_len, RecoveryRoomError::AccountSizeExceeded);
+    require!(
+        new_len - old_len <= MAX_PERMITTED_DATA_INCREASE,
+        RecoveryRoomError::AccountSizeExceeded
+    );
+
+    let shortfall = Rent::get()?
+        .minimum_balance(new_len)
+        .saturating_sub(account.lamports());
+    if shortfall > 0 {
+        system_program::transfer(
+            CpiContext::new(
+                system_program.clone(),
+                system_program::Transfer {
+                    from: payer.clone(),
+                    to: account.clone(),
+                },
+            ),
+            shortfall,
+        )?;
+    }
+
+    account.realloc(new_len, true)?;
+
+    emit!(Resized {
+        account: account.key(),
+        old_len: old_len as u32,
+        new_len: new_len as u32,
+        payer: payer.key(),
+    });
+    Ok(())
+}
+
+/// Grow an account written under an older `T` layout to `len` bytes and
+/// decode it. Fields are only ever appended, so the new zeroed tail decodes
+/// as their defaults and the layout version reads back as 0 until stamped.
+pub fn upgrade<'info, T>(
+    account: &AccountInfo<'info>,
+    len: usize,
+    payer: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+) -> Result<T>
+where
+    T: AccountDeserialize + Owner,
+{
+    require_keys_eq!(*account.owner, T::owner(), RecoveryRoomError::InvalidMigrationAccount);
+    grow(account, len, len, payer, system_program)?;
+    let data = account.try_borrow_data()?;
+    T::try_deserialize(&mut &data[..])
+}
+
+/// Write `value` back over an account loaded by `upgrade`
+pub fn store<T: AccountSerialize>(account: &AccountInfo, value: &T) -> Result<()> {
+    let mut data = account.try_borrow_mut_data()?;
+    value.try_serialize(&mut &mut data[..])
+}
@@ -0,0 +1,91 @@
+//! SPL token transfers that hold up against Token-2022 extensions.
+//!
+//! Rugged tokens are often Token-2022 mints with a transfer fee, so the
+//! destination receives less than was sent, or a transfer hook, whose
+//! program wants extra accounts on every transfer. Transfers here resolve
+//! the hook's accounts from the ones the caller passes in and report what
+//! the destination actually received, read from its balance, so vault
+//! accounting never counts tokens that didn't arrive.
+
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::program::invoke;
+use anchor_spl::token_2022::{self, spl_token_2022};
+use anchor_spl::token_interface::TokenAccount;
+use spl_token_2022::extension::{
+    transfer_fee::{self, TransferFeeConfig},
+    transfer_hook, BaseStateWithExtensions, StateWithExtensions,
+};
+
+/// Extensions of a mint that change how its tokens move
+#[derive(Clone, Copy, Default, Debug)]
+pub struct MintExtensions {
+    pub transfer_fee: bool,
+    pub transfer_hook: bool,
+}
+
+/// Transfer extensions `mint` carries; none for SPL Token mints
+pub fn mint_extensions(mint: &AccountInfo) -> Result<MintExtensions> {
+    if *mint.owner != token_2022::ID {
+        return Ok(MintExtensions::default());
+    }
+    let data = mint.try_borrow_data()?;
+    let state = StateWithExtensions::<spl_token_2022::state::Mint>::unpack(&data)?;
+    Ok(MintExtensions {
+        transfer_fee: state.get_extension::<TransferFeeConfig>().is_ok(),
+        transfer_hook: transfer_hook::get_program_id(&state).is_some(),
+    })
+}
+
+/// Token balance of `account`
+pub fn balance(account: &AccountInfo) -> Result<u64> {
+    let data = account.try_borrow_data()?;
+    Ok(TokenAccount::try_deserialize(&mut &data[..])?.amount)
+}
+
+/// `transfer_checked` of `amount` from `from` to `to`, returning what `to`
+/// received net of any transfer fee
+///
+/// A transfer hook's extra accounts, its validation account and program
+/// included, are looked up in `hook_accounts`; signers among them are
+/// passed on as multisig signers, so it must not hold the authority.
+#[allow(clippy::too_many_arguments)]
+pub fn transfer_checked<'info>(
+    token_program: &AccountInfo<'info>,
+    from: &AccountInfo<'info>,
+    mint: &AccountInfo<'info>,
+    to: &AccountInfo<'info>,
+    authority: &AccountInfo<'info>,
+    hook_accounts: &[AccountInfo<'info>],
+    amount: u64,
+    decimals: u8,
+    signer_seeds: &[&[&[u8]]],
+) -> Result<u64> {
+    let before = balance(to)?;
+    spl_token_2022::onchain::invoke_transfer_checked(
+        token_program.key,
+        from.clone(),
+        mint.clone(),
+        to.clone(),
+        authority.clone(),
+        hook_accounts,
+        amount,
+        decimals,
+        signer_seeds,
+    )?;
+    Ok(balance(to)?.saturating_sub(before))
+}
+
+/// Sweep transfer fees withheld in `token_account` into its mint, which
+/// Token-2022 requires before the account can be closed (permissionless)
+pub fn harvest_withheld<'info>(
+    token_program: &AccountInfo<'info>,
+    mint: &AccountInfo<'info>,
+    token_account: &AccountInfo<'info>,
+) -> Result<()> {
+    let ix = transfer_fee::instruction::harvest_withheld_tokens_to_mint(
+        token_program.key,
+        mint.key,
+        &[token_account.key],
+    )?;
+    invoke(&ix, &[mint.clone(), token_account.clone(), token_program.clone()]).map_err(Into::into)
+}
@@ -39,6 +39,7 @@ const IDL = {
       accounts: [
         { name: "protocolState", isMut: true, isSigner: false },
         { name: "roundState", isMut: true, isSigner: false },
+        { name: "tokenPool", isMut: true, isSigner: false },
         { name: "vrf", isMut: true, isSigner: false },
         { name: "oracleQueue", isMut: true, isSigner: false },
         { name: "queueAuthority", isMut: false, isSigner: false },
@@ -64,7 +65,10 @@ const IDL = {
         { name: "payer", isMut: true, isSigner: true },
         { name: "systemProgram", isMut: false, isSigner: false },
       ],
-      args: [],
+      args: [
+        { name: "duration", type: { option: "i64" } },
+        { name: "maxTokens", type: { option: "u8" } },
+      ],
     },
   ],
   accounts: [],
@@ -6,7 +6,7 @@
  * This version provides a simplified interface for demo/simulation mode
  */
 
-export type RoundStatus = 'Active' | 'VrfRequested' | 'Complete';
+export type RoundStatus = 'Active' | 'VrfRequested' | 'Settling' | 'Complete';
 
 export interface OnChainRoundState {
   roundId: number;
@@ -24,8 +24,65 @@ export interface TokenEntry {
   ticker: string;
   lossAmountUsd: number;
   holdings: number;
+  lossPercentage: number;
 }
 
+/**
+ * Required-vs-provided context the program logs next to a failing error code
+ */
+export interface RecoveryRoomErrorDetail {
+  code: number;
+  name: string;
+  field: string;
+  op: '>=' | '<=';
+  required: number;
+  provided: number;
+}
+
+const ERROR_DETAIL_PREFIX = 'RecoveryRoomErrorDetail:';
+
+// How each detail field reads in a user-facing message
+const ERROR_DETAIL_FIELDS: Record<string, { label: string; unit: string }> = {
+  loss_percentage: { label: 'loss', unit: '%' },
+  loss_amount_usd: { label: 'loss', unit: '¢' },
+  token_count: { label: 'tokens', unit: '' },
+  protocol_fee_bps: { label: 'fee', unit: ' bps' },
+  memo_len: { label: 'memo length', unit: ' chars' },
+  theme_len: { label: 'theme length', unit: ' chars' },
+  prize_multiplier_bps: { label: 'prize multiplier', unit: ' bps' },
+  metadata_uri_len: { label: 'metadata URI length', unit: ' chars' },
+  lamports: { label: 'lamports', unit: '' },
+  round_id: { label: 'round', unit: '' },
+  holdings: { label: 'holdings', unit: '' },
+  slot_entries: { label: 'entries this slot', unit: '' },
+  window_entries: { label: 'entries this window', unit: '' },
+  swap_in: { label: 'tokens sold', unit: '' },
+  swap_out: { label: 'swap proceeds', unit: '' },
+  pass_amount: { label: 'pass balance', unit: '' },
+};
+
+/**
+ * Extract the error detail from a failed transaction's logs, if the program logged one
+ */
+export const parseErrorDetail = (logs: string[] | null | undefined): RecoveryRoomErrorDetail | null => {
+  const line = logs?.find(l => l.includes(ERROR_DETAIL_PREFIX));
+  if (!line) return null;
+  try {
+    return JSON.parse(line.slice(line.indexOf(ERROR_DETAIL_PREFIX) + ERROR_DETAIL_PREFIX.length));
+  } catch {
+    return null;
+  }
+};
+
+/**
+ * Turn an error detail into a message like "need ≥80% loss, you provided 42%"
+ */
+export const formatErrorDetail = (detail: RecoveryRoomErrorDetail): string => {
+  const { label, unit } = ERROR_DETAIL_FIELDS[detail.field] || { label: detail.field, unit: '' };
+  const op = detail.op === '>=' ? '≥' : '≤';
+  return `need ${op}${detail.required}${unit} ${label}, you provided ${detail.provided}${unit}`;
+};
+
 export interface VrfServiceConfig {
   rpcEndpoint: string;
   programId?: string;